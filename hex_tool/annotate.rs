@@ -0,0 +1,56 @@
+use crate::number::parse_offset;
+
+/// A named byte range from an annotations file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub start: u64,
+    pub end: u64,
+    pub name: String,
+}
+
+impl Region {
+    /// The part of the region inside `start..end`, if any.
+    pub fn clip(&self, start: u64, end: u64) -> Option<(u64, u64)> {
+        let (from, to) = (self.start.max(start), self.end.min(end));
+        (from < to).then_some((from, to))
+    }
+}
+
+/// Parses `START..END NAME` lines (offsets in decimal or 0x hex, END
+/// exclusive, the name the rest of the line). Blank lines and `#` comments
+/// are ignored. Regions must be listed in ascending order without
+/// overlapping, so a layout typo is caught before anything is shown.
+pub fn parse_annotations(text: &str) -> Result<Vec<Region>, String> {
+    let mut regions: Vec<Region> = Vec::new();
+    for (index, raw_line) in text.lines().enumerate() {
+        let line_no = index + 1;
+        let line = raw_line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (span, name) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| format!("line {}: expected 'START..END NAME', found '{}'", line_no, line))?;
+        let (start, end) = span
+            .split_once("..")
+            .ok_or_else(|| format!("line {}: expected START..END, found '{}'", line_no, span))?;
+        let start = parse_offset(start).map_err(|e| format!("line {}: invalid start: {}", line_no, e))?;
+        let end = parse_offset(end).map_err(|e| format!("line {}: invalid end: {}", line_no, e))?;
+        if end <= start {
+            return Err(format!("line {}: region {:#x}..{:#x} is empty", line_no, start, end));
+        }
+        if let Some(last) = regions.last().filter(|last| start < last.end) {
+            return Err(format!(
+                "line {}: region {:#x}..{:#x} overlaps or comes before '{}' ({:#x}..{:#x})",
+                line_no, start, end, last.name, last.start, last.end
+            ));
+        }
+        regions.push(Region {
+            start,
+            end,
+            name: name.trim().to_string(),
+        });
+    }
+    Ok(regions)
+}
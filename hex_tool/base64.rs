@@ -0,0 +1,69 @@
+/// Value of one base64 digit, and whether it belongs to the URL-safe
+/// alphabet (`Some(false)` for standard-only digits, `None` for the shared
+/// ones).
+fn digit(c: char) -> Option<(u8, Option<bool>)> {
+    let value = match c {
+        'A'..='Z' => c as u8 - b'A',
+        'a'..='z' => c as u8 - b'a' + 26,
+        '0'..='9' => c as u8 - b'0' + 52,
+        '+' => return Some((62, Some(false))),
+        '/' => return Some((63, Some(false))),
+        '-' => return Some((62, Some(true))),
+        '_' => return Some((63, Some(true))),
+        _ => return None,
+    };
+    Some((value, None))
+}
+
+/// Decodes standard or URL-safe base64, with or without `=` padding.
+/// Whitespace is ignored, so wrapped input works. Errors give the
+/// character position (from 0) of the problem.
+pub fn decode_base64(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+    let mut url_safe: Option<bool> = None;
+    let mut buffer = 0u32;
+    let mut digits = 0usize;
+    let mut padding = 0usize;
+    let mut end = 0;
+    for (pos, c) in text.chars().enumerate() {
+        end = pos + 1;
+        if c.is_ascii_whitespace() {
+            continue;
+        }
+        if c == '=' {
+            padding += 1;
+            if digits % 4 < 2 || (digits + padding) % 4 == 1 || padding > 2 {
+                return Err(format!("unexpected padding at position {}", pos));
+            }
+            continue;
+        }
+        let Some((value, alphabet)) = digit(c) else {
+            return Err(format!("invalid base64 character '{}' at position {}", c, pos));
+        };
+        if padding > 0 {
+            return Err(format!("data after padding at position {}", pos));
+        }
+        if let Some(alphabet) = alphabet {
+            if url_safe.is_some_and(|seen| seen != alphabet) {
+                return Err(format!("'{}' at position {} mixes the standard and URL-safe alphabets", c, pos));
+            }
+            url_safe = Some(alphabet);
+        }
+
+        buffer = buffer << 6 | u32::from(value);
+        digits += 1;
+        if digits.is_multiple_of(4) {
+            bytes.extend_from_slice(&buffer.to_be_bytes()[1..]);
+            buffer = 0;
+        }
+    }
+
+    match digits % 4 {
+        0 if padding == 0 => {}
+        1 => return Err(format!("input ends mid-byte at position {}", end)),
+        2 if padding == 0 || padding == 2 => bytes.push((buffer >> 4) as u8),
+        3 if padding <= 1 => bytes.extend_from_slice(&((buffer >> 2) as u16).to_be_bytes()),
+        _ => return Err(format!("incomplete padding at position {}", end)),
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,92 @@
+use super::resize::extend_file;
+use super::{create_output, invalid_input, same_file};
+use hex_tool::hexstr::parse_byte;
+use hex_tool::parse_byte_quantity;
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(clap::Args, Debug)]
+pub struct CatArgs {
+    /// Files to concatenate, in order
+    #[arg(long, value_name = "FILE", num_args = 1.., required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// File to create
+    #[arg(long, value_name = "OUT")]
+    output: PathBuf,
+
+    /// Pad each piece up to a multiple of N bytes (suffixes K, M, G allowed)
+    #[arg(long, value_name = "N", value_parser = parse_byte_quantity)]
+    align: Option<u64>,
+
+    /// Byte used for the padding (hex)
+    #[arg(long, value_name = "BYTE", default_value = "00", value_parser = parse_byte, requires = "align")]
+    fill: u8,
+
+    /// Print the layout as JSON instead of a table
+    #[arg(long)]
+    pub json: bool,
+
+    /// Overwrite OUT if it already exists
+    #[arg(long)]
+    force: bool,
+}
+
+/// Streams the inputs one after another into a new file, padding after each
+/// so the next one starts on an --align boundary, then prints where each
+/// piece landed.
+pub fn run(args: CatArgs) -> io::Result<()> {
+    let align = args.align.unwrap_or(1);
+    if align == 0 {
+        return Err(invalid_input("--align must be greater than zero"));
+    }
+    // Checked before the output is created, which would truncate the input
+    for input in &args.inputs {
+        if same_file(input, &args.output)? {
+            return Err(invalid_input(format!(
+                "input {} is also the output file",
+                input.display()
+            )));
+        }
+    }
+
+    let mut output = create_output(&args.output, args.force)?;
+    let mut layout = Vec::new();
+    let mut pos = 0u64;
+    for input in &args.inputs {
+        let size = io::copy(&mut File::open(input)?, &mut output)?;
+        let padded = size.div_ceil(align) * align;
+        extend_file(&mut output, pos + size, pos + padded, args.fill, false)?;
+        layout.push((input, pos, size, padded));
+        pos += padded;
+    }
+
+    if args.json {
+        let pieces: Vec<serde_json::Value> = layout
+            .iter()
+            .map(|(input, start, size, padded)| {
+                serde_json::json!({
+                    "file": input.display().to_string(),
+                    "offset": start,
+                    "size": size,
+                    "padded_size": padded,
+                })
+            })
+            .collect();
+        let record = serde_json::json!({
+            "output": args.output.display().to_string(),
+            "size": pos,
+            "pieces": pieces,
+        });
+        println!("{}", record);
+        return Ok(());
+    }
+
+    println!("{:<10} {:>10} {:>10}  FILE", "OFFSET", "SIZE", "PADDED");
+    for (input, start, size, padded) in &layout {
+        println!("{:#010x} {:>10} {:>10}  {}", start, size, padded, input.display());
+    }
+    println!("Wrote {} bytes to {}.", pos, args.output.display());
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use super::{
+    invalid_input, prepare_write, print_plan, same_file, target_len, Blocks, WriteOptions, WriteTarget,
+    DRY_RUN_PREVIEW,
+};
+use hex_tool::number::parse_offset;
+use hex_tool::progress::Progress;
+use hex_tool::{file_len_or_zero, parse_byte_quantity, read_existing, CHUNK_SIZE};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+#[derive(clap::Args, Debug)]
+pub struct CopyArgs {
+    /// Source file
+    #[arg(short, long = "file", value_name = "SRC")]
+    file: PathBuf,
+
+    /// Offset in the source file (decimal or 0x hex)
+    #[arg(short = 'o', long, value_name = "OFF", default_value = "0", value_parser = parse_offset)]
+    offset: u64,
+
+    /// Number of bytes to copy (suffixes K, M, G allowed)
+    #[arg(short, long, value_name = "N", value_parser = parse_byte_quantity, required_unless_present = "count")]
+    size: Option<u64>,
+
+    #[command(flatten)]
+    blocks: Blocks,
+
+    /// Destination file (created if missing, patched in place otherwise)
+    #[arg(long, value_name = "DEST")]
+    to: PathBuf,
+
+    /// Offset in the destination file (decimal or 0x hex)
+    #[arg(long, value_name = "OFF", default_value = "0", value_parser = parse_offset)]
+    to_offset: u64,
+
+    /// Allow overlapping ranges when SRC and DEST are the same file
+    #[arg(long)]
+    allow_overlap: bool,
+
+    /// Show a progress bar on stderr (on by default for large copies)
+    #[arg(long)]
+    progress: bool,
+
+    #[command(flatten)]
+    opts: WriteOptions,
+}
+
+/// Copies of at least this many bytes show progress even without --progress.
+const PROGRESS_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+pub fn run(args: CopyArgs) -> io::Result<()> {
+    let (offset, requested) = match args.blocks.range()? {
+        Some((offset, size)) => (offset, size.expect("clap requires --count without --size")),
+        None => (args.offset, args.size.expect("clap requires --size without --count")),
+    };
+    let src_len = target_len(&args.file)?;
+    let size = requested.min(src_len.saturating_sub(offset));
+
+    // The source range lies within the file, so only the destination can
+    // run past the largest offset; every position below is inside the two
+    let src_end = offset + size;
+    let dest_end = args.to_offset.checked_add(size).ok_or_else(|| {
+        invalid_input(format!(
+            "{} bytes at destination offset {:#x} would end past the largest 64-bit offset",
+            size, args.to_offset
+        ))
+    })?;
+
+    let same = same_file(&args.file, &args.to)?;
+    let overlaps = offset < dest_end && args.to_offset < src_end;
+    if same && overlaps && !args.allow_overlap {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "source and destination ranges overlap in the same file (use --allow-overlap)",
+        ));
+    }
+
+    let mut src = File::open(&args.file)?;
+    if args.opts.dry_run {
+        let preview = size.min(DRY_RUN_PREVIEW as u64) as usize;
+        let old = read_existing(&args.to, args.to_offset, preview)?;
+        let new = read_existing(&args.file, offset, preview)?;
+        print_plan(&args.to, args.to_offset, size, &old, &new);
+        return Ok(());
+    }
+    if size > 0 {
+        let size_after = file_len_or_zero(&args.to)?.max(dest_end);
+        prepare_write(&args.to, &args.opts, &[(args.to_offset, size)], size_after)?;
+    }
+    let mut target = WriteTarget::open(
+        &args.to,
+        &args.opts,
+        OpenOptions::new().write(true).create(true).truncate(false),
+    )?;
+    let dest = target.file();
+
+    // When moving data towards the end of the same file, copy the last chunk
+    // first so no source byte is overwritten before it has been read.
+    let backward = same && args.to_offset > offset;
+    let mut progress = Progress::new(size, args.progress || size >= PROGRESS_THRESHOLD);
+
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut copied = 0u64;
+    while copied < size {
+        let n = (size - copied).min(CHUNK_SIZE as u64) as usize;
+        let pos = if backward { size - copied - n as u64 } else { copied };
+
+        src.seek(SeekFrom::Start(offset + pos))?;
+        src.read_exact(&mut buffer[..n])?;
+        dest.seek(SeekFrom::Start(args.to_offset + pos))?;
+        dest.write_all(&buffer[..n])?;
+        copied += n as u64;
+
+        progress.update(copied);
+    }
+    target.commit()?;
+    progress.finish();
+
+    println!(
+        "Copied {} bytes from {} to {} at offset {:#x}.",
+        copied,
+        args.file.display(),
+        args.to.display(),
+        args.to_offset
+    );
+    if copied < requested {
+        eprintln!(
+            "Note: source ended early; copied {} of {} requested bytes.",
+            copied, requested
+        );
+    }
+    Ok(())
+}
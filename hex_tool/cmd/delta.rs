@@ -0,0 +1,134 @@
+use super::{invalid_input, prepare_write, print_plan, WriteOptions, WriteTarget};
+use hex_tool::delta::{checksum, diff, Delta};
+use hex_tool::{read_range, write_at};
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::PathBuf;
+
+#[derive(clap::Args, Debug)]
+pub struct MkpatchArgs {
+    /// The original file
+    #[arg(long, value_name = "FILE")]
+    old: PathBuf,
+
+    /// The file the patch should produce
+    #[arg(long, value_name = "FILE")]
+    new: PathBuf,
+
+    /// Where to write the patch
+    #[arg(long, value_name = "PATCH")]
+    output: PathBuf,
+}
+
+pub fn run_mkpatch(args: MkpatchArgs) -> io::Result<()> {
+    let delta = diff(&mut File::open(&args.old)?, &mut File::open(&args.new)?)?;
+    fs::write(&args.output, delta.to_text())?;
+
+    let changed: usize = delta.records.iter().map(|r| r.new.len()).sum();
+    println!(
+        "Wrote {} changes ({} bytes) turning {} ({} bytes) into {} ({} bytes) to {}.",
+        delta.records.len(),
+        changed,
+        args.old.display(),
+        delta.old_size,
+        args.new.display(),
+        delta.new_size,
+        args.output.display()
+    );
+    Ok(())
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ApplypatchArgs {
+    /// File to patch in place
+    #[arg(short, long = "file", value_name = "FILE")]
+    file: PathBuf,
+
+    /// Patch written by mkpatch
+    #[arg(long, value_name = "PATCH")]
+    patch: PathBuf,
+
+    #[command(flatten)]
+    opts: WriteOptions,
+}
+
+/// Checks that the file is exactly the patch's original, byte ranges and
+/// whole-file checksum, before writing anything; then applies the changes,
+/// resizes, and checks the result against the recorded checksum.
+pub fn run_applypatch(args: ApplypatchArgs) -> io::Result<()> {
+    let text = fs::read_to_string(&args.patch)?;
+    let delta = Delta::parse(&text).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", args.patch.display(), e),
+        )
+    })?;
+
+    let mut file = File::open(&args.file)?;
+    let (len, crc) = checksum(&mut file)?;
+    if len != delta.old_size || crc != delta.old_crc {
+        return Err(invalid_input(format!(
+            "{} ({} bytes, crc32 {:08x}) is not the file this patch was made from ({} bytes, crc32 {:08x})",
+            args.file.display(),
+            len,
+            crc,
+            delta.old_size,
+            delta.old_crc
+        )));
+    }
+    for record in &delta.records {
+        if read_range(&mut file, record.offset, record.old.len() as u64)? != record.old {
+            return Err(invalid_input(format!(
+                "bytes at {:#x} do not match the patch's original bytes",
+                record.offset
+            )));
+        }
+    }
+
+    if args.opts.dry_run {
+        for record in &delta.records {
+            print_plan(&args.file, record.offset, record.new.len() as u64, &record.old, &record.new);
+        }
+        if delta.new_size < delta.old_size {
+            println!("Dry run: would truncate to {} bytes", delta.new_size);
+        }
+        return Ok(());
+    }
+
+    let spans: Vec<(u64, u64)> = delta.records.iter().map(|r| (r.offset, r.new.len() as u64)).collect();
+    prepare_write(&args.file, &args.opts, &spans, delta.new_size)?;
+
+    let mut target = WriteTarget::open(&args.file, &args.opts, OpenOptions::new().read(true).write(true))?;
+    let file = target.file();
+    for record in &delta.records {
+        write_at(file, record.offset, &record.new)?;
+    }
+    file.set_len(delta.new_size)?;
+    file.sync_all()?;
+
+    // With --atomic this checks the copy, so a bad result never replaces the original
+    let (len, crc) = checksum(&mut File::open(target.path())?)?;
+    if len != delta.new_size || crc != delta.new_crc {
+        let advice = if args.opts.atomic {
+            "the original was left unchanged"
+        } else {
+            "restore it from a backup or journal"
+        };
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "patched file has crc32 {:08x}, but the patch expects {:08x}; {}",
+                crc, delta.new_crc, advice
+            ),
+        ));
+    }
+    target.commit()?;
+    println!(
+        "Applied {} changes to {}; it is now {} bytes (crc32 {:08x}).",
+        delta.records.len(),
+        args.file.display(),
+        len,
+        crc
+    );
+    Ok(())
+}
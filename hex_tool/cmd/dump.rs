@@ -0,0 +1,820 @@
+use super::{check_raw_output, file_kind, invalid_input, is_stdio, open_input, target_len, Blocks, FileKind, OutputArgs, Target};
+use clap::ValueEnum;
+use hex_tool::annotate::{parse_annotations, Region};
+use hex_tool::dump::{format_compare_line, write_dump, Charset, ColorChoice, DumpStyle, Highlight, OffsetRadix, Radix, BYTES_PER_LINE};
+use hex_tool::hexstr::decode_hex;
+use hex_tool::histogram::Histogram;
+use hex_tool::number::parse_offset;
+use hex_tool::output::Output;
+use hex_tool::range::{parse_range, sort_ranges, ByteRange};
+use hex_tool::search::find_matches;
+use hex_tool::template::parse_template;
+use hex_tool::value::{Endian, ValueType};
+use hex_tool::{parse_byte_quantity, read_existing, read_range, CHUNK_SIZE};
+use std::fs::{self, File};
+use std::io::{self, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+/// The --max-bytes cap on a hexdump, or a comparison, without --size.
+const DEFAULT_MAX_BYTES: u64 = 64 * 1024;
+
+#[derive(clap::Args, Debug)]
+pub struct DumpArgs {
+    #[command(flatten)]
+    pub target: Target,
+
+    /// Number of bytes to read (suffixes K, M, G allowed)
+    #[arg(short, long = "size", value_name = "N", value_parser = parse_byte_quantity)]
+    pub size: Option<u64>,
+
+    #[command(flatten)]
+    blocks: Blocks,
+
+    /// Dump everything when no --size is given, lifting the --max-bytes cap
+    #[arg(long, conflicts_with = "size")]
+    all: bool,
+
+    /// Most bytes a hexdump shows, or --compare compares, when no --size is
+    /// given [default: 65536]; raw output is only capped when this is given
+    #[arg(long, value_name = "N", value_parser = parse_byte_quantity)]
+    max_bytes: Option<u64>,
+
+    /// Dump OFFSET:SIZE instead of --offset/--size (repeatable)
+    #[arg(
+        long = "range",
+        value_name = "OFFSET:SIZE",
+        value_parser = parse_range,
+        conflicts_with_all = ["offset", "size", "as_types", "template", "compare"]
+    )]
+    ranges: Vec<ByteRange>,
+
+    /// Decode the bytes at the offset as consecutive typed fields
+    /// (repeat or comma-separate for several)
+    #[arg(long = "as", value_name = "TYPE", value_enum, value_delimiter = ',')]
+    as_types: Vec<ValueType>,
+
+    /// Decode a record laid out by a template file of `name: type` lines
+    #[arg(long, value_name = "FILE", conflicts_with = "as_types")]
+    template: Option<PathBuf>,
+
+    /// Byte order for --as, and for template fields without a suffix
+    #[arg(long, value_enum, default_value_t = Endian::Le)]
+    endian: Endian,
+
+    /// Emit machine-readable JSON instead of text: one record per dump line,
+    /// per --range, per decoded field, or per differing run with --compare
+    #[arg(long)]
+    pub json: bool,
+
+    /// Number base for the bytes in the dump
+    #[arg(long, value_enum, default_value_t = Radix::Hex)]
+    radix: Radix,
+
+    /// Number base for the offset column
+    #[arg(long, value_enum, default_value_t = OffsetRadix::Hex, conflicts_with_all = ["compare", "as_types", "template"])]
+    offset_radix: OffsetRadix,
+
+    /// Number the dump's offsets from 0 instead of from --offset
+    #[arg(long, conflicts_with_all = ["compare", "as_types", "template"])]
+    relative: bool,
+
+    /// How the text gutter shows the bytes
+    #[arg(long, value_enum, default_value_t = Charset::Ascii)]
+    charset: Charset,
+
+    /// Print every line, even runs of identical ones
+    #[arg(long)]
+    no_squeeze: bool,
+
+    /// Print a header row labeling the byte and gutter columns
+    #[arg(long)]
+    ruler: bool,
+
+    /// Repeat the --ruler header every N lines (implies --ruler)
+    #[arg(long, value_name = "N")]
+    ruler_every: Option<usize>,
+
+    /// Count each byte value over the range instead of dumping it (the whole
+    /// range is read, regardless of --max-bytes)
+    #[arg(long, conflicts_with_all = ["ranges", "as_types", "template", "compare", "compare_self", "follow"])]
+    histogram: bool,
+
+    /// With --histogram, list only the N most frequent values
+    #[arg(long, value_name = "N", requires = "histogram")]
+    top: Option<usize>,
+
+    /// Mark every occurrence of HEX in the dump (repeatable; each pattern
+    /// gets its own color, or brackets without color)
+    #[arg(long = "highlight", value_name = "HEX", value_parser = parse_pattern)]
+    highlights: Vec<Vec<u8>>,
+
+    /// Label the dump with named regions from FILE, one `START..END NAME`
+    /// per line (regions are also colored when colors are on)
+    #[arg(long, value_name = "FILE")]
+    annotations: Option<PathBuf>,
+
+    /// Dump the range side by side with the same offsets of a reference file,
+    /// highlighting differing bytes
+    #[arg(long, value_name = "REF", conflicts_with_all = ["as_types", "template"])]
+    compare: Option<PathBuf>,
+
+    /// Compare SIZE bytes at A with SIZE bytes at B of the same file, side
+    /// by side; exits 1 if they differ
+    #[arg(
+        long,
+        value_name = "A:B:SIZE",
+        value_parser = parse_self_compare,
+        conflicts_with_all = ["compare", "ranges", "as_types", "template"]
+    )]
+    compare_self: Option<SelfCompare>,
+
+    /// When to use colors in the output
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// After reaching the end of the file, keep waiting for and dumping new
+    /// bytes, like `tail -f` (stop with Ctrl-C)
+    #[arg(long, conflicts_with_all = ["size", "ranges", "as_types", "template", "compare", "out", "json"])]
+    follow: bool,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Hex)]
+    format: Format,
+
+    /// Allow writing raw bytes to an interactive terminal
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Classic hexdump with offsets and an ASCII column
+    Hex,
+    /// Raw bytes, unformatted
+    Raw,
+}
+
+/// Settings for the plain dump.
+#[derive(Debug)]
+struct ReadOptions {
+    format: Format,
+    style: DumpStyle,
+    force: bool,
+    json: bool,
+    /// Cap on bytes dumped when no size was given; `None` with `--all`,
+    /// and for raw output without `--max-bytes`
+    max_bytes: Option<u64>,
+    /// Show each range's offsets starting from 0
+    relative: bool,
+    /// Byte sequences to mark wherever they occur in a dump
+    highlights: Vec<Vec<u8>>,
+    /// Named regions from --annotations, by file offset
+    regions: Vec<Region>,
+}
+
+/// Two regions of one file for --compare-self.
+#[derive(Clone, Copy, Debug)]
+struct SelfCompare {
+    a: u64,
+    b: u64,
+    size: u64,
+}
+
+fn parse_self_compare(text: &str) -> Result<SelfCompare, String> {
+    let [a, b, size] = text.split(':').collect::<Vec<_>>()[..] else {
+        return Err(format!("expected A_OFFSET:B_OFFSET:SIZE, found '{}'", text));
+    };
+    Ok(SelfCompare {
+        a: parse_offset(a).map_err(|e| format!("invalid first offset: {}", e))?,
+        b: parse_offset(b).map_err(|e| format!("invalid second offset: {}", e))?,
+        size: parse_byte_quantity(size).map_err(|e| format!("invalid size: {}", e))?,
+    })
+}
+
+fn parse_pattern(text: &str) -> Result<Vec<u8>, String> {
+    match decode_hex(text).map_err(|e| e.to_string())? {
+        bytes if bytes.is_empty() => Err("pattern must not be empty".to_string()),
+        bytes => Ok(bytes),
+    }
+}
+
+impl ReadOptions {
+    /// The dump style for `buffer`, read from `file_offset` and shown from
+    /// `shown_offset`: every occurrence of each --highlight pattern marked
+    /// in that pattern's color, and each annotated region labeled where it
+    /// enters the dump (and colored, with color on).
+    fn style_for(&self, buffer: &[u8], file_offset: u64, shown_offset: u64) -> io::Result<DumpStyle> {
+        let mut style = self.style.clone();
+        for (color, pattern) in self.highlights.iter().enumerate() {
+            for start in find_matches(&mut &buffer[..], shown_offset, pattern, None)? {
+                style.highlights.push(Highlight {
+                    start,
+                    end: start + pattern.len() as u64,
+                    color,
+                });
+            }
+        }
+        let end = file_offset + buffer.len() as u64;
+        for (i, region) in self.regions.iter().enumerate() {
+            let Some((from, to)) = region.clip(file_offset, end) else {
+                continue;
+            };
+            let shown = |offset: u64| offset - file_offset + shown_offset;
+            style.labels.push((shown(from), region.name.clone()));
+            if style.color {
+                style.highlights.push(Highlight {
+                    start: shown(from),
+                    end: shown(to),
+                    color: self.highlights.len() + i,
+                });
+            }
+        }
+        style.highlights.sort_by_key(|h| h.start);
+        Ok(style)
+    }
+}
+
+/// Runs the dump. Returns false only when --compare-self found differences.
+pub fn run(args: DumpArgs) -> io::Result<bool> {
+    let target_offset = args.target.resolve_offset()?;
+    let (offset, size) = match args.blocks.range()? {
+        Some(range) => range,
+        None => (target_offset, args.size),
+    };
+    let file = args.target.file().to_path_buf();
+    let open_ended = size.is_none()
+        && args.ranges.is_empty()
+        && args.as_types.is_empty()
+        && args.template.is_none()
+        && args.compare_self.is_none();
+    if open_ended && !is_stdio(&file) {
+        check_device_read(&file, args.all || args.histogram)?;
+    }
+
+    let regions = match &args.annotations {
+        Some(path) => parse_annotations(&fs::read_to_string(path)?).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e))
+        })?,
+        None => Vec::new(),
+    };
+
+    let mut read_opts = ReadOptions {
+        format: args.format,
+        style: DumpStyle {
+            radix: args.radix,
+            offset_radix: args.offset_radix,
+            charset: args.charset,
+            squeeze: !args.no_squeeze,
+            ruler: args.ruler || args.ruler_every.is_some(),
+            ruler_every: args.ruler_every.unwrap_or(0),
+            ..DumpStyle::default()
+        },
+        force: args.force,
+        json: args.json,
+        max_bytes: match args.max_bytes {
+            _ if args.all => None,
+            // Raw bytes are usually piped on, so are only capped on request
+            None if args.format == Format::Raw && !args.json && args.compare.is_none() => None,
+            max_bytes => Some(max_bytes.unwrap_or(DEFAULT_MAX_BYTES)),
+        },
+        relative: args.relative,
+        highlights: args.highlights,
+        regions,
+    };
+
+    // Without --range, --offset/--size describe the single range
+    let labeled = !args.ranges.is_empty();
+    let mut ranges = args.ranges;
+    if ranges.is_empty() {
+        ranges.push(ByteRange {
+            offset,
+            size: size.unwrap_or(u64::MAX),
+        });
+    }
+    // Overlapping ranges are fine to read; they are just shown twice
+    sort_ranges(&mut ranges);
+
+    let mut out = args.output.create()?;
+    read_opts.style.color = out.color(args.color)?;
+    let mut same = true;
+    if let Some(regions) = args.compare_self {
+        same = handle_compare_self(&mut out, &file, regions, read_opts.style.color, args.json)?;
+    } else if args.histogram {
+        handle_histogram(&mut out, &file, offset, size, args.top, args.json)?;
+    } else if let Some(template_path) = &args.template {
+        handle_template(&mut out, file, offset, template_path, args.endian, args.json)?;
+    } else if let Some(reference) = &args.compare {
+        handle_compare(&mut out, file, offset, size, reference, &read_opts)?;
+    } else if !args.as_types.is_empty() {
+        handle_decode(&mut out, file, offset, &args.as_types, args.endian)?;
+    } else if args.follow {
+        handle_follow(&mut out, &file, offset, &read_opts)?;
+    } else {
+        handle_read(&mut out, file, &ranges, labeled, &read_opts)?;
+    }
+    if let Some(path) = out.finish()? {
+        println!("Output saved to: {}", path.display());
+    }
+    Ok(same)
+}
+
+/// Guards reads without a --size against devices: a block device needs
+/// --size, or --all confirmed on a terminal, and a character device needs
+/// --size whatever the format, since it may never end.
+fn check_device_read(file_path: &Path, whole: bool) -> io::Result<()> {
+    match file_kind(file_path)? {
+        FileKind::BlockDevice if !whole => Err(invalid_input(format!(
+            "{} is a block device; give --size (or --all to read all of it)",
+            file_path.display()
+        ))),
+        FileKind::BlockDevice => {
+            let len = target_len(file_path)?;
+            if !io::stdin().is_terminal() {
+                return Err(invalid_input(format!(
+                    "reading all {} bytes of block device {} needs confirmation on a terminal; give --size instead",
+                    len,
+                    file_path.display()
+                )));
+            }
+            eprint!("Read all {} bytes of block device {}? [y/N] ", len, file_path.display());
+            let mut answer = String::new();
+            io::stdin().read_line(&mut answer)?;
+            match answer.trim() {
+                "y" | "Y" | "yes" => Ok(()),
+                _ => Err(invalid_input("not confirmed")),
+            }
+        }
+        FileKind::CharDevice => Err(invalid_input(format!(
+            "{} is a character device and may never end; give --size",
+            file_path.display()
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Dumps each range in ascending order through a single handle. Ranges from
+/// `--range` are labeled; stdin supports only one range since it can only
+/// be read forwards once.
+fn handle_read(
+    out: &mut Output,
+    file_path: PathBuf,
+    ranges: &[ByteRange],
+    labeled: bool,
+    opts: &ReadOptions,
+) -> std::io::Result<()> {
+    if is_stdio(&file_path) && ranges.len() > 1 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "multiple ranges need a seekable file, not stdin",
+        ));
+    }
+    if opts.format == Format::Raw {
+        check_raw_output(out.is_terminal(), opts.force)?;
+    }
+
+    let mut file = if is_stdio(&file_path) {
+        None
+    } else {
+        Some(File::open(&file_path)?)
+    };
+    let mut records = Vec::new();
+    let mut truncated = false;
+    for range in ranges {
+        let cap = opts.max_bytes.filter(|_| range.size == u64::MAX);
+        let mut reader: Box<dyn Read + '_> = match file.as_mut() {
+            Some(file) => {
+                file.seek(SeekFrom::Start(range.offset))?;
+                Box::new(file)
+            }
+            None => open_input(&file_path, range.offset)?,
+        };
+
+        // Raw bytes are copied straight through, never held all at once
+        if opts.format == Format::Raw && !opts.json {
+            let limit = cap.unwrap_or(range.size);
+            let copied = io::copy(&mut (&mut reader).take(limit), out)?;
+            if cap.is_some_and(|c| copied == c) && reader.read(&mut [0])? > 0 {
+                truncated = true;
+            }
+            continue;
+        }
+
+        // An open-ended dump reads one byte past the cap to tell whether
+        // anything was cut off
+        let limit = cap.map_or(range.size, |c| c.saturating_add(1));
+        let mut buffer = Vec::new();
+        reader.take(limit).read_to_end(&mut buffer)?;
+        if let Some(cap) = cap.filter(|&c| buffer.len() as u64 > c) {
+            buffer.truncate(cap as usize);
+            truncated = true;
+        }
+
+        match opts.format {
+            _ if opts.json && labeled => records.push(serde_json::json!({
+                "offset": range.offset,
+                "size": buffer.len(),
+                "bytes": hex::encode(&buffer),
+            })),
+            _ if opts.json => {
+                let shown_offset = if opts.relative { 0 } else { range.offset };
+                for (i, chunk) in buffer.chunks(BYTES_PER_LINE).enumerate() {
+                    let mut record = serde_json::json!({
+                        "offset": shown_offset + (i * BYTES_PER_LINE) as u64,
+                        "bytes": hex::encode(chunk),
+                        "ascii": opts.style.charset.text(chunk),
+                    });
+                    if !opts.regions.is_empty() {
+                        let line_start = range.offset + (i * BYTES_PER_LINE) as u64;
+                        let line_end = line_start + chunk.len() as u64;
+                        let regions: Vec<serde_json::Value> = opts
+                            .regions
+                            .iter()
+                            .filter_map(|region| {
+                                let (from, to) = region.clip(line_start, line_end)?;
+                                Some(serde_json::json!({
+                                    "name": region.name,
+                                    "offset": from - range.offset + shown_offset,
+                                    "size": to - from,
+                                }))
+                            })
+                            .collect();
+                        record["regions"] = serde_json::Value::Array(regions);
+                    }
+                    records.push(record);
+                }
+            }
+            Format::Hex => {
+                if labeled {
+                    writeln!(
+                        out,
+                        "Range {:#x}..{:#x} ({} bytes):",
+                        range.offset,
+                        range.offset + buffer.len() as u64,
+                        buffer.len()
+                    )?;
+                }
+                let shown_offset = if opts.relative { 0 } else { range.offset };
+                write_dump(out, &buffer, shown_offset, &opts.style_for(&buffer, range.offset, shown_offset)?)?;
+            }
+            Format::Raw => unreachable!("raw output is copied above"),
+        }
+    }
+
+    if opts.json {
+        writeln!(out, "{}", serde_json::Value::Array(records))?;
+    }
+    if truncated {
+        eprintln!(
+            "Note: output truncated to {} bytes; use --all to dump everything, or --size/--max-bytes to set a limit.",
+            opts.max_bytes.unwrap_or(0)
+        );
+    }
+    Ok(())
+}
+
+/// How often `--follow` checks the file for new bytes.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Dumps from `offset` to the end of the file, then polls for appended bytes
+/// and dumps each batch as it arrives, labeled with its real offset. Only
+/// returns on error. If the file shrinks below what was already shown, that
+/// is reported and following resumes from the new end.
+fn handle_follow(out: &mut Output, file_path: &Path, offset: u64, opts: &ReadOptions) -> io::Result<()> {
+    if is_stdio(file_path) {
+        return Err(invalid_input("--follow needs a regular file, not stdin"));
+    }
+    let mut file = File::open(file_path)?;
+    if !file.metadata()?.is_file() {
+        return Err(invalid_input(format!(
+            "--follow needs a regular file; {} is not one",
+            file_path.display()
+        )));
+    }
+    if opts.format == Format::Raw {
+        check_raw_output(out.is_terminal(), opts.force)?;
+    }
+
+    let mut pos = offset;
+    let mut last_len = file.metadata()?.len();
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        let len = file.metadata()?.len();
+        if len < last_len && len < pos {
+            eprintln!(
+                "Note: {} was truncated to {} bytes; following from the new end.",
+                file_path.display(),
+                len
+            );
+            pos = len;
+        }
+        last_len = len;
+        if len <= pos {
+            out.flush()?;
+            thread::sleep(FOLLOW_INTERVAL);
+            continue;
+        }
+
+        file.seek(SeekFrom::Start(pos))?;
+        let want = (len - pos).min(CHUNK_SIZE as u64) as usize;
+        let n = file.read(&mut buffer[..want])?;
+        match opts.format {
+            Format::Hex => {
+                let shown = if opts.relative { pos.saturating_sub(offset) } else { pos };
+                write_dump(out, &buffer[..n], shown, &opts.style_for(&buffer[..n], pos, shown)?)?;
+            }
+            Format::Raw => out.write_all(&buffer[..n])?,
+        }
+        pos += n as u64;
+    }
+}
+
+/// Dumps a range next to the same range of `reference` and reports how many
+/// bytes differ in the displayed window. With `json`, prints the differing
+/// runs instead of the dump. Without `size`, at most `--max-bytes` are
+/// compared, as an open-ended hexdump shows.
+fn handle_compare(
+    out: &mut Output,
+    file_path: PathBuf,
+    offset: u64,
+    size: Option<u64>,
+    reference: &Path,
+    opts: &ReadOptions,
+) -> std::io::Result<()> {
+    let input = open_input(&file_path, offset)?;
+    let cap = opts.max_bytes.filter(|_| size.is_none());
+    // One byte past the cap tells whether anything was cut off
+    let limit = size.or(cap.map(|c| c.saturating_add(1))).unwrap_or(u64::MAX);
+    let mut target = Vec::new();
+    input.take(limit).read_to_end(&mut target)?;
+    let truncated = cap.filter(|&c| target.len() as u64 > c);
+    if let Some(cap) = truncated {
+        target.truncate(cap as usize);
+    }
+
+    // Read as much of the reference as the window can show
+    let window = size.map_or(target.len(), |s| s as usize);
+    let ref_bytes = read_existing(reference, offset, window)?;
+    print_comparison(out, offset, None, &target, &ref_bytes, opts.style.color, opts.json)?;
+    if let Some(cap) = truncated {
+        eprintln!(
+            "Note: comparison truncated to {} bytes; use --all to compare everything, or --size/--max-bytes to set a limit.",
+            cap
+        );
+    }
+    Ok(())
+}
+
+/// Dumps `size` bytes at `regions.a` next to those at `regions.b` of
+/// the same file, returning whether they are equal.
+fn handle_compare_self(
+    out: &mut Output,
+    file_path: &Path,
+    regions: SelfCompare,
+    color: bool,
+    json: bool,
+) -> io::Result<bool> {
+    if is_stdio(file_path) {
+        return Err(invalid_input("--compare-self needs a file it can seek in, not stdin"));
+    }
+    let mut file = File::open(file_path)?;
+    let first = read_range(&mut file, regions.a, regions.size)?;
+    let second = read_range(&mut file, regions.b, regions.size)?;
+    if regions.a.abs_diff(regions.b) < regions.size {
+        eprintln!("Note: the regions overlap; they are only read, so this is fine.");
+    }
+    if !json {
+        writeln!(
+            out,
+            "Comparing {:#x}..{:#x} (left) with {:#x}..{:#x} (right):",
+            regions.a,
+            regions.a + first.len() as u64,
+            regions.b,
+            regions.b + second.len() as u64
+        )?;
+    }
+    print_comparison(out, regions.a, Some(regions.b), &first, &second, color, json)
+}
+
+/// Prints `target` (from `offset`) side by side with `reference`, or with
+/// `json` just the differing runs, and returns whether they are equal. With
+/// `other_offset` the reference comes from elsewhere in the same file and
+/// JSON runs carry both offsets.
+fn print_comparison(
+    out: &mut Output,
+    offset: u64,
+    other_offset: Option<u64>,
+    target: &[u8],
+    ref_bytes: &[u8],
+    color: bool,
+    json: bool,
+) -> io::Result<bool> {
+    let window = target.len().max(ref_bytes.len());
+
+    if json {
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        for i in (0..window).filter(|&i| target.get(i) != ref_bytes.get(i)) {
+            match runs.last_mut() {
+                Some((start, len)) if *start + *len == i => *len += 1,
+                _ => runs.push((i, 1)),
+            }
+        }
+        let ranges: Vec<serde_json::Value> = runs
+            .iter()
+            .map(|&(start, len)| {
+                let mut run = serde_json::json!({ "offset": offset + start as u64, "size": len });
+                if let Some(other) = other_offset {
+                    run["other_offset"] = serde_json::json!(other + start as u64);
+                }
+                run
+            })
+            .collect();
+        let mut record = serde_json::json!({
+            "offset": offset,
+            "size": window,
+            "differing": runs.iter().map(|&(_, len)| len).sum::<usize>(),
+            "ranges": ranges,
+        });
+        if let Some(other) = other_offset {
+            record["other_offset"] = serde_json::json!(other);
+        }
+        writeln!(out, "{}", record)?;
+        return Ok(runs.is_empty());
+    }
+
+    let mut differing = 0;
+    for start in (0..window).step_by(BYTES_PER_LINE) {
+        let end = (start + BYTES_PER_LINE).min(window);
+        let t = &target[start.min(target.len())..end.min(target.len())];
+        let r = &ref_bytes[start.min(ref_bytes.len())..end.min(ref_bytes.len())];
+        differing += (start..end).filter(|&i| target.get(i) != ref_bytes.get(i)).count();
+        writeln!(out, "{}", format_compare_line(offset + start as u64, t, r, color))?;
+    }
+    println!(
+        "{} of {} bytes differ in {:#x}..{:#x}.",
+        differing,
+        window,
+        offset,
+        offset + window as u64
+    );
+    Ok(differing == 0)
+}
+
+/// Counts the byte values of the range and prints them as a 16x16 table
+/// (row: high nibble, column: low nibble) or the --top values, plus totals.
+fn handle_histogram(
+    out: &mut Output,
+    file_path: &Path,
+    offset: u64,
+    size: Option<u64>,
+    top: Option<usize>,
+    json: bool,
+) -> io::Result<()> {
+    let input = open_input(file_path, offset)?;
+    let histogram = Histogram::from_reader(&mut input.take(size.unwrap_or(u64::MAX)))?;
+    let total = histogram.total();
+    let (nulls, printable) = (histogram.counts[0], histogram.printable());
+
+    if json {
+        let record = serde_json::json!({
+            "offset": offset,
+            "size": total,
+            "counts": histogram.counts.to_vec(),
+            "null": nulls,
+            "printable": printable,
+        });
+        writeln!(out, "{}", record)?;
+        return Ok(());
+    }
+
+    let percent = |count: u64| if total == 0 { 0.0 } else { count as f64 * 100.0 / total as f64 };
+    match top {
+        Some(n) => {
+            for (byte, count) in histogram.top(n) {
+                let shown = Charset::Ascii.text(&[byte]);
+                writeln!(out, "{:#04x} {} {:>12} {:>6.2}%", byte, shown, count, percent(count))?;
+            }
+        }
+        None => {
+            let width = histogram.counts.iter().max().map_or(1, |c| c.to_string().len()).max(2);
+            let header: Vec<String> = (0..16).map(|col| format!("{:>width$}", format!("_{:x}", col))).collect();
+            writeln!(out, "    {}", header.join(" "))?;
+            for (row, counts) in histogram.counts.chunks(16).enumerate() {
+                let cells: Vec<String> = counts.iter().map(|c| format!("{:>width$}", c)).collect();
+                writeln!(out, "{:x}_: {}", row, cells.join(" "))?;
+            }
+        }
+    }
+    writeln!(
+        out,
+        "{} bytes: {} null ({:.2}%), {} printable ASCII ({:.2}%).",
+        total,
+        nulls,
+        percent(nulls),
+        printable,
+        percent(printable)
+    )?;
+    Ok(())
+}
+
+/// Decodes consecutive fields of the given types starting at `offset`.
+fn handle_decode(
+    out: &mut Output,
+    file_path: PathBuf,
+    offset: u64,
+    types: &[ValueType],
+    endian: Endian,
+) -> std::io::Result<()> {
+    let needed: usize = types.iter().map(|t| t.width()).sum();
+    let mut buffer = Vec::with_capacity(needed);
+    open_input(&file_path, offset)?
+        .take(needed as u64)
+        .read_to_end(&mut buffer)?;
+
+    let mut pos = 0;
+    for &ty in types {
+        let field_offset = offset + pos as u64;
+        let Some(bytes) = buffer.get(pos..pos + ty.width()) else {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "{} at offset {:#x} needs {} bytes, only {} available",
+                    ty.name(),
+                    field_offset,
+                    ty.width(),
+                    buffer.len() - pos
+                ),
+            ));
+        };
+        writeln!(out, "{:08x}: {:<4} {}", field_offset, ty.name(), ty.format(bytes, endian))?;
+        pos += ty.width();
+    }
+    Ok(())
+}
+
+/// Decodes the record described by a template file starting at `offset`.
+fn handle_template(
+    out: &mut Output,
+    file_path: PathBuf,
+    offset: u64,
+    template_path: &Path,
+    endian: Endian,
+    json: bool,
+) -> std::io::Result<()> {
+    let text = fs::read_to_string(template_path)?;
+    let fields = parse_template(&text, endian).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", template_path.display(), e),
+        )
+    })?;
+
+    let needed: usize = fields.iter().map(|f| f.size()).sum();
+    let mut buffer = Vec::with_capacity(needed);
+    open_input(&file_path, offset)?
+        .take(needed as u64)
+        .read_to_end(&mut buffer)?;
+    if buffer.len() < needed {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("template needs {} bytes, only {} available", needed, buffer.len()),
+        ));
+    }
+
+    let name_width = fields.iter().map(|f| f.name.len()).max().unwrap_or(0);
+    let mut records = Vec::new();
+    let mut pos = 0;
+    for field in &fields {
+        let bytes = &buffer[pos..pos + field.size()];
+        let field_offset = offset + pos as u64;
+        if json {
+            records.push(serde_json::json!({
+                "name": field.name,
+                "offset": field_offset,
+                "raw": hex::encode(bytes),
+                "value": field.to_json(bytes),
+            }));
+        } else {
+            writeln!(
+                out,
+                "{:08x}: {:<w$}  {}  {}",
+                field_offset,
+                field.name,
+                hex::encode(bytes),
+                field.format(bytes),
+                w = name_width
+            )?;
+        }
+        pos += field.size();
+    }
+
+    if json {
+        let record = serde_json::json!({ "offset": offset, "size": needed, "fields": records });
+        writeln!(out, "{}", record)?;
+    }
+    Ok(())
+}
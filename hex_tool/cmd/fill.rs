@@ -0,0 +1,92 @@
+use super::{invalid_input, is_stdio, prepare_write, print_plan, Target, WriteOptions, WriteTarget, DRY_RUN_PREVIEW};
+use hex_tool::number::parse_offset;
+use hex_tool::rng::SplitMix64;
+use hex_tool::{file_len_or_zero, parse_byte_quantity, read_existing, CHUNK_SIZE};
+use std::fs::OpenOptions;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(clap::Args, Debug)]
+pub struct FillRandomArgs {
+    #[command(flatten)]
+    pub target: Target,
+
+    /// Number of bytes to write (suffixes K, M, G allowed)
+    #[arg(short, long = "size", value_name = "N", value_parser = parse_byte_quantity)]
+    size: u64,
+
+    /// Seed for the generator; the same seed always produces the same bytes
+    #[arg(long, value_name = "SEED", value_parser = parse_offset)]
+    seed: Option<u64>,
+
+    #[command(flatten)]
+    opts: WriteOptions,
+}
+
+/// A seed that differs between runs, for when none was given.
+fn fresh_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+    nanos ^ (u64::from(std::process::id()) << 32)
+}
+
+/// Overwrites `--size` bytes at the offset with seeded pseudo-random data,
+/// generated and written a chunk at a time. The file is created or extended
+/// as needed but never truncated.
+pub fn run(args: FillRandomArgs) -> io::Result<()> {
+    let offset = args.target.resolve_offset()?;
+    let file_path = args.target.file().to_path_buf();
+    if is_stdio(&file_path) {
+        return Err(invalid_input("fill-random needs a file, not stdout"));
+    }
+    let end = offset
+        .checked_add(args.size)
+        .ok_or_else(|| invalid_input("offset plus size overflows"))?;
+
+    let seed = match args.seed {
+        Some(seed) => seed,
+        None => {
+            let seed = fresh_seed();
+            println!("Using seed {} (pass --seed {} to reproduce).", seed, seed);
+            seed
+        }
+    };
+    let mut rng = SplitMix64::new(seed);
+
+    if args.opts.dry_run {
+        let preview_len = args.size.min(DRY_RUN_PREVIEW as u64) as usize;
+        let old = read_existing(&file_path, offset, preview_len)?;
+        let mut new = vec![0; preview_len];
+        rng.fill(&mut new);
+        print_plan(&file_path, offset, args.size, &old, &new);
+        return Ok(());
+    }
+
+    if args.size > 0 {
+        let size_after = file_len_or_zero(&file_path)?.max(end);
+        prepare_write(&file_path, &args.opts, &[(offset, args.size)], size_after)?;
+    }
+
+    let mut target = WriteTarget::open(
+        &file_path,
+        &args.opts,
+        OpenOptions::new().write(true).create(true).truncate(false),
+    )?;
+    let file = target.file();
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut written = 0u64;
+    while written < args.size {
+        let n = (args.size - written).min(CHUNK_SIZE as u64) as usize;
+        rng.fill(&mut buffer[..n]);
+        file.write_all(&buffer[..n])?;
+        written += n as u64;
+    }
+    target.commit()?;
+    println!(
+        "Wrote {} random bytes at offset {:#x} (seed {}).",
+        written, offset, seed
+    );
+    Ok(())
+}
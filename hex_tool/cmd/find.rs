@@ -0,0 +1,132 @@
+use super::{invalid_input, is_stdio, open_input, OutputArgs, Target};
+use hex_tool::dump::{format_hexdump, ColorChoice, DumpStyle, Highlight, BYTES_PER_LINE};
+use hex_tool::hexstr::decode_hex;
+use hex_tool::output::Output;
+use hex_tool::search::find_matches;
+use hex_tool::{parse_byte_quantity, read_range};
+use std::fs::File;
+use std::io::{self, Read, Write};
+
+#[derive(clap::Args, Debug)]
+pub struct FindArgs {
+    #[command(flatten)]
+    pub target: Target,
+
+    /// Byte sequence to look for, as hex
+    #[arg(value_name = "HEX")]
+    pattern: String,
+
+    /// Only search this many bytes from --offset (suffixes K, M, G allowed)
+    #[arg(short, long = "size", value_name = "N", value_parser = parse_byte_quantity)]
+    size: Option<u64>,
+
+    /// Stop after N matches
+    #[arg(long, value_name = "N")]
+    max: Option<usize>,
+
+    /// Also dump N bytes around each match, with the match highlighted;
+    /// windows that touch are merged
+    #[arg(short = 'C', long, value_name = "N", value_parser = parse_byte_quantity)]
+    context: Option<u64>,
+
+    /// Print the match offsets as a JSON array instead
+    #[arg(long, conflicts_with = "context")]
+    pub json: bool,
+
+    /// When to color the matches in --context dumps
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+/// Prints the offset of every non-overlapping occurrence, one per line.
+pub fn run(args: FindArgs) -> io::Result<()> {
+    let offset = args.target.resolve_offset()?;
+    let pattern = decode_hex(&args.pattern).map_err(|e| invalid_input(format!("search bytes: {}", e)))?;
+    if pattern.is_empty() {
+        return Err(invalid_input("search bytes must not be empty"));
+    }
+
+    let input = open_input(args.target.file(), offset)?;
+    let mut input = input.take(args.size.unwrap_or(u64::MAX));
+    let matches = find_matches(&mut input, offset, &pattern, args.max)?;
+    if args.context.is_some() && is_stdio(args.target.file()) {
+        return Err(invalid_input("--context needs a file it can seek in, not stdin"));
+    }
+
+    let mut out = args.output.create()?;
+    if args.json {
+        writeln!(out, "{}", serde_json::json!(matches))?;
+    } else {
+        match args.context {
+            Some(context) => {
+                let color = out.color(args.color)?;
+                let mut file = File::open(args.target.file())?;
+                print_contexts(&mut out, &mut file, &matches, pattern.len() as u64, context, color)?;
+            }
+            None => {
+                for m in &matches {
+                    writeln!(out, "{:#010x}", m)?;
+                }
+            }
+        }
+        writeln!(out, "{} matches found.", matches.len())?;
+    }
+    if let Some(path) = out.finish()? {
+        println!("Output saved to: {}", path.display());
+    }
+    Ok(())
+}
+
+/// Dumps the bytes around each match, widened to whole dump lines. Matches
+/// whose windows overlap or touch share one dump; dumps are separated by
+/// `--` like grep's context groups.
+fn print_contexts(
+    out: &mut Output,
+    file: &mut File,
+    matches: &[u64],
+    len: u64,
+    context: u64,
+    color: bool,
+) -> io::Result<()> {
+    let line = BYTES_PER_LINE as u64;
+    let mut windows: Vec<(u64, u64, Vec<u64>)> = Vec::new();
+    for &m in matches {
+        let start = m.saturating_sub(context) / line * line;
+        let end = (m + len).saturating_add(context).div_ceil(line).saturating_mul(line);
+        match windows.last_mut() {
+            Some((_, last_end, group)) if start <= *last_end => {
+                *last_end = end;
+                group.push(m);
+            }
+            _ => windows.push((start, end, vec![m])),
+        }
+    }
+
+    for (i, (start, end, group)) in windows.iter().enumerate() {
+        if i > 0 {
+            writeln!(out, "--")?;
+        }
+        let offsets: Vec<String> = group.iter().map(|m| format!("{:#x}", m)).collect();
+        writeln!(out, "Match at {}:", offsets.join(", "))?;
+        let style = DumpStyle {
+            squeeze: true,
+            highlights: group
+                .iter()
+                .map(|&m| Highlight {
+                    start: m,
+                    end: m + len,
+                    color: 0,
+                })
+                .collect(),
+            color,
+            ..DumpStyle::default()
+        };
+        // read_range stops at the end of the file
+        let bytes = read_range(file, *start, end - start)?;
+        write!(out, "{}", format_hexdump(&bytes, *start, &style))?;
+    }
+    Ok(())
+}
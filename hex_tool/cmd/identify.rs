@@ -0,0 +1,49 @@
+use hex_tool::magic;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+#[derive(clap::Args, Debug)]
+pub struct IdentifyArgs {
+    /// File to identify
+    #[arg(short, long = "file", value_name = "FILE")]
+    file: PathBuf,
+
+    /// Scan the whole file for embedded signatures at any offset (signatures
+    /// shorter than 3 bytes are skipped)
+    #[arg(long)]
+    all: bool,
+}
+
+pub fn run(args: IdentifyArgs) -> io::Result<()> {
+    let mut file = File::open(&args.file)?;
+
+    if args.all {
+        let found = magic::scan(&mut file)?;
+        for (offset, m) in &found {
+            println!("{:08x}: {} (magic {} at {:#x})", offset, m.name, hex::encode(m.bytes), offset + m.offset);
+        }
+        println!("{} embedded signatures found.", found.len());
+        return Ok(());
+    }
+
+    let mut header = Vec::new();
+    file.take(magic::header_len() as u64).read_to_end(&mut header)?;
+    let matches = magic::identify(&header);
+    match matches.split_first() {
+        Some((best, others)) => {
+            println!(
+                "{}: {} (magic {} at offset {:#x})",
+                args.file.display(),
+                best.name,
+                hex::encode(best.bytes),
+                best.offset
+            );
+            for m in others {
+                println!("  also matches: {} (magic {} at offset {:#x})", m.name, hex::encode(m.bytes), m.offset);
+            }
+        }
+        None => println!("{}: unknown file type", args.file.display()),
+    }
+    Ok(())
+}
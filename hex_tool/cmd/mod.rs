@@ -0,0 +1,564 @@
+//! One module per subcommand, plus the arguments and helpers they share.
+
+use hex_tool::dump::print_hexdump;
+use hex_tool::journal::write_journal;
+use hex_tool::number::{parse_position, Position};
+use hex_tool::output::{temp_sibling, Output};
+use hex_tool::parse_byte_quantity;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, IsTerminal, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+pub mod cat;
+pub mod copy;
+pub mod delta;
+pub mod dump;
+pub mod extract;
+pub mod fill;
+pub mod find;
+pub mod identify;
+pub mod replace;
+pub mod resize;
+pub mod seal;
+pub mod shell;
+pub mod split;
+pub mod transform;
+pub mod undo;
+pub mod verify;
+pub mod write;
+
+/// The file an operation works on and where in it to start.
+#[derive(clap::Args, Debug)]
+pub struct Target {
+    /// Target file ("-" for stdin/stdout; commands that only read default
+    /// to stdin when it is piped)
+    #[arg(short, long = "file", value_name = "FILE")]
+    file: Option<PathBuf>,
+
+    /// Offset in bytes (decimal, 0x hex, 0o octal or 0b binary; a leading
+    /// '-' counts back from the end of the file)
+    #[arg(
+        short = 'o',
+        long = "offset",
+        value_name = "OFF",
+        default_value = "0",
+        allow_hyphen_values = true,
+        value_parser = parse_position
+    )]
+    pub offset: Position,
+}
+
+impl Target {
+    /// The file named by --file. `main` fills in a missing one (or exits with
+    /// a usage error) before any subcommand runs.
+    pub fn file(&self) -> &Path {
+        self.file.as_deref().expect("--file is resolved before dispatch")
+    }
+
+    /// Fills in a missing --file with stdin for commands that only read, as
+    /// long as stdin is not a terminal nobody is typing into. Returns whether
+    /// a file is set.
+    pub fn default_to_stdin(&mut self, reads_only: bool) -> bool {
+        if self.file.is_none() && reads_only && !io::stdin().is_terminal() {
+            self.file = Some(PathBuf::from("-"));
+        }
+        self.file.is_some()
+    }
+
+    /// The absolute offset, looking up the file's length for offsets that
+    /// count back from the end.
+    pub fn resolve_offset(&self) -> io::Result<u64> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+        if !is_stdio(self.file()) && file_kind(self.file())? == FileKind::Directory {
+            return Err(invalid(format!("{} is a directory, not a file", self.file().display())));
+        }
+        match self.offset {
+            Position::Start(offset) => Ok(offset),
+            Position::FromEnd(_) if is_stdio(self.file()) => Err(invalid(
+                "offsets from the end of the file need a seekable file, not stdin".to_string(),
+            )),
+            Position::FromEnd(_) => {
+                let len = target_len(self.file())?;
+                self.offset
+                    .resolve(len)
+                    .map_err(|e| invalid(format!("invalid offset value: {}", e)))
+            }
+        }
+    }
+}
+
+/// Where a subcommand that prints results writes them.
+#[derive(clap::Args, Debug)]
+pub struct OutputArgs {
+    /// Write the formatted output to FILE (created atomically)
+    #[arg(long, value_name = "FILE")]
+    pub out: Option<PathBuf>,
+}
+
+impl OutputArgs {
+    pub fn create(&self) -> io::Result<Output> {
+        Output::create(self.out.as_deref())
+    }
+}
+
+/// What kind of filesystem object a path names.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileKind {
+    Regular,
+    Directory,
+    BlockDevice,
+    /// Character devices such as /dev/zero, which may never end
+    CharDevice,
+    /// FIFOs, sockets and anything else
+    Other,
+    Missing,
+}
+
+pub fn file_kind(path: &Path) -> io::Result<FileKind> {
+    let file_type = match fs::metadata(path) {
+        Ok(metadata) => metadata.file_type(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(FileKind::Missing),
+        Err(e) => return Err(e),
+    };
+    Ok(if file_type.is_file() {
+        FileKind::Regular
+    } else if file_type.is_dir() {
+        FileKind::Directory
+    } else {
+        special_kind(file_type)
+    })
+}
+
+#[cfg(unix)]
+fn special_kind(file_type: fs::FileType) -> FileKind {
+    use std::os::unix::fs::FileTypeExt;
+    if file_type.is_block_device() {
+        FileKind::BlockDevice
+    } else if file_type.is_char_device() {
+        FileKind::CharDevice
+    } else {
+        FileKind::Other
+    }
+}
+
+#[cfg(not(unix))]
+fn special_kind(_file_type: fs::FileType) -> FileKind {
+    FileKind::Other
+}
+
+/// Size of a file or block device. Block devices report 0 in their
+/// metadata, so they are measured by seeking to the end instead.
+pub fn target_len(path: &Path) -> io::Result<u64> {
+    let mut file = File::open(path)?;
+    let metadata = file.metadata()?;
+    if metadata.is_file() {
+        return Ok(metadata.len());
+    }
+    file.seek(SeekFrom::End(0))
+}
+
+/// dd-style addressing: `--skip` and `--count` whole blocks of `--bs` bytes,
+/// as an alternative to --offset/--size.
+#[derive(clap::Args, Debug)]
+pub struct Blocks {
+    /// Block size for --skip/--count (suffixes K, M, G allowed; default 512)
+    #[arg(long, value_name = "N", value_parser = parse_byte_quantity, conflicts_with_all = ["offset", "size"])]
+    bs: Option<u64>,
+
+    /// Start this many blocks into the file
+    #[arg(long, value_name = "BLOCKS", conflicts_with_all = ["offset", "size"])]
+    skip: Option<u64>,
+
+    /// Cover this many blocks (default: to the end of the file)
+    #[arg(long, value_name = "BLOCKS", conflicts_with_all = ["offset", "size"])]
+    count: Option<u64>,
+}
+
+impl Blocks {
+    /// The byte offset and size (`None`: to the end) the block options
+    /// describe, or `None` if none were given. The range is echoed on stderr.
+    pub fn range(&self) -> io::Result<Option<(u64, Option<u64>)>> {
+        if self.bs.is_none() && self.skip.is_none() && self.count.is_none() {
+            return Ok(None);
+        }
+        let bs = self.bs.unwrap_or(512);
+        if bs == 0 {
+            return Err(invalid_input("--bs must be greater than zero"));
+        }
+        let bytes = |blocks: u64, what: &str| {
+            blocks.checked_mul(bs).ok_or_else(|| {
+                invalid_input(format!("{} {} blocks of {} bytes overflows a 64-bit offset", what, blocks, bs))
+            })
+        };
+        let offset = bytes(self.skip.unwrap_or(0), "--skip")?;
+        let size = self.count.map(|count| bytes(count, "--count")).transpose()?;
+        match size {
+            Some(size) => {
+                let end = offset
+                    .checked_add(size)
+                    .ok_or_else(|| invalid_input("--skip plus --count overflows a 64-bit offset"))?;
+                eprintln!("Blocks: bytes {:#x}..{:#x} ({} bytes)", offset, end, size);
+            }
+            None => eprintln!("Blocks: bytes {:#x} to the end of the file", offset),
+        }
+        Ok(Some((offset, size)))
+    }
+}
+
+/// Settings shared by every subcommand that modifies the target file.
+#[derive(clap::Args, Debug)]
+pub struct WriteOptions {
+    /// Copy the target to FILE+SUFFIX before modifying it
+    #[arg(long, value_name = "SUFFIX", num_args = 0..=1, require_equals = true, default_missing_value = ".bak")]
+    backup: Option<String>,
+
+    /// Refuse to overwrite an existing backup file
+    #[arg(long, requires = "backup")]
+    no_clobber_backup: bool,
+
+    /// Show what would change without modifying anything
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Record the bytes the operation overwrites to JOURNAL so `undo` can restore them
+    #[arg(long, value_name = "JOURNAL")]
+    pub journal: Option<PathBuf>,
+
+    /// Allow writing to a block device (and, for `write -f -`, raw bytes to
+    /// a terminal, or for `transform --output`, replacing an existing file)
+    #[arg(long)]
+    pub force: bool,
+
+    /// Apply the change to a copy in the same directory, then rename it over
+    /// the target, so it is never left half-written. A run that is killed
+    /// leaves its copy behind as .NAME.tmpPID, which is safe to delete
+    #[arg(long)]
+    pub atomic: bool,
+}
+
+impl WriteOptions {
+    pub fn backup_policy(&self) -> Option<BackupPolicy> {
+        self.backup.clone().map(|suffix| BackupPolicy {
+            suffix,
+            no_clobber: self.no_clobber_backup,
+        })
+    }
+}
+
+/// Where to save a copy of the target before it is modified.
+#[derive(Debug)]
+pub struct BackupPolicy {
+    pub suffix: String,
+    pub no_clobber: bool,
+}
+
+/// At most this many bytes of old and new content are shown by `--dry-run`.
+pub const DRY_RUN_PREVIEW: usize = 256;
+
+pub fn is_stdio(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+pub fn invalid_input(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg.into())
+}
+
+/// Refuses to dump binary data to a terminal unless explicitly forced.
+pub fn check_raw_output(is_terminal: bool, force: bool) -> io::Result<()> {
+    if !force && is_terminal {
+        return Err(invalid_input(
+            "refusing to write raw bytes to a terminal (use --force to override)",
+        ));
+    }
+    Ok(())
+}
+
+/// Whether both paths name the same existing file.
+pub fn same_file(a: &Path, b: &Path) -> io::Result<bool> {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => Ok(a == b),
+        (Err(e), _) => Err(e),
+        // A destination that does not exist yet cannot be the source
+        (Ok(_), Err(_)) => Ok(false),
+    }
+}
+
+/// Creates a fresh output file, refusing to replace an existing one unless
+/// `force` is set.
+pub fn create_output(path: &Path, force: bool) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    options.open(path).map_err(|e| match e.kind() {
+        io::ErrorKind::AlreadyExists => io::Error::new(
+            e.kind(),
+            format!("{} already exists (use --force to overwrite)", path.display()),
+        ),
+        _ => e,
+    })
+}
+
+/// The file a mutating command writes to: the target itself, or with
+/// `--atomic` a copy of it that `commit` renames into place. An atomic copy
+/// that is never committed is removed when this is dropped, which a killed
+/// process never gets to do; the target is untouched either way.
+pub struct WriteTarget {
+    file: File,
+    path: PathBuf,
+    dest: Option<PathBuf>,
+}
+
+impl WriteTarget {
+    /// Opens `file_path` with `options`, or with `--atomic` copies it to a
+    /// temporary sibling (keeping its permissions) and opens that instead.
+    pub fn open(file_path: &Path, opts: &WriteOptions, options: &OpenOptions) -> io::Result<WriteTarget> {
+        if !opts.atomic {
+            return Ok(WriteTarget {
+                file: options.open(file_path)?,
+                path: file_path.to_path_buf(),
+                dest: None,
+            });
+        }
+        if !matches!(file_kind(file_path)?, FileKind::Regular | FileKind::Missing) {
+            return Err(invalid_input(format!(
+                "--atomic needs a regular file, and {} is not one",
+                file_path.display()
+            )));
+        }
+
+        let temp = temp_sibling(file_path)?;
+        let copied = if file_path.exists() {
+            fs::copy(file_path, &temp).map(|_| ())
+        } else {
+            // The command is about to create the file; start from nothing
+            File::create(&temp).map(|_| ())
+        };
+        let target = copied.and_then(|()| {
+            Ok(WriteTarget {
+                file: options.clone().create(false).open(&temp)?,
+                path: temp.clone(),
+                dest: Some(file_path.to_path_buf()),
+            })
+        });
+        target.map_err(|e| {
+            let _ = fs::remove_file(&temp);
+            io::Error::new(
+                e.kind(),
+                format!("cannot create temporary copy {} for --atomic: {}", temp.display(), e),
+            )
+        })
+    }
+
+    pub fn file(&mut self) -> &mut File {
+        &mut self.file
+    }
+
+    /// The path being written, which is the temporary copy in atomic mode.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Flushes the change to disk and, in atomic mode, renames the copy over
+    /// the target.
+    pub fn commit(mut self) -> io::Result<()> {
+        let Some(dest) = self.dest.take() else {
+            return Ok(());
+        };
+        let renamed = self.file.sync_all().and_then(|()| fs::rename(&self.path, &dest));
+        if renamed.is_err() {
+            let _ = fs::remove_file(&self.path);
+        }
+        renamed
+    }
+}
+
+impl Drop for WriteTarget {
+    fn drop(&mut self) {
+        if self.dest.is_some() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Opens the input positioned at `offset`. Stdin cannot seek, so the
+/// leading bytes are read and discarded instead.
+pub fn open_input(file_path: &Path, offset: u64) -> io::Result<Box<dyn Read>> {
+    if is_stdio(file_path) {
+        let mut stdin = io::stdin().lock();
+        let skipped = io::copy(&mut (&mut stdin).take(offset), &mut io::sink())?;
+        if skipped < offset {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("input ended after {} bytes, before offset {}", skipped, offset),
+            ));
+        }
+        Ok(Box::new(stdin))
+    } else {
+        let mut file = File::open(file_path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        Ok(Box::new(file))
+    }
+}
+
+/// Copies `file_path` next to itself before a destructive operation. Called
+/// only once the operation is known to modify bytes, and before the target is
+/// opened for writing, so a failed backup leaves the original untouched.
+pub fn create_backup(file_path: &Path, policy: Option<&BackupPolicy>) -> io::Result<()> {
+    let Some(policy) = policy else {
+        return Ok(());
+    };
+    if !file_path.exists() {
+        // Nothing to preserve; the operation is about to create the file
+        return Ok(());
+    }
+
+    let mut backup_path = file_path.as_os_str().to_owned();
+    backup_path.push(&policy.suffix);
+    let backup_path = PathBuf::from(backup_path);
+
+    if policy.no_clobber && backup_path.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("backup {} already exists", backup_path.display()),
+        ));
+    }
+    fs::copy(file_path, &backup_path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("failed to create backup {}: {}", backup_path.display(), e),
+        )
+    })?;
+    println!("Backup saved to: {}", backup_path.display());
+    Ok(())
+}
+
+/// Refuses to write to a block device without --force, then preserves what
+/// a mutating operation is about to change: a backup copy and/or a journal
+/// of the `(offset, length)` ranges it overwrites along with the size the
+/// file will have afterwards.
+pub fn prepare_write(file_path: &Path, opts: &WriteOptions, ranges: &[(u64, u64)], size_after: u64) -> io::Result<()> {
+    if file_kind(file_path)? == FileKind::BlockDevice && !opts.force {
+        return Err(invalid_input(format!(
+            "{} is a block device; use --force if you really mean to write to it",
+            file_path.display()
+        )));
+    }
+    create_backup(file_path, opts.backup_policy().as_ref())?;
+    if let Some(journal) = &opts.journal {
+        write_journal(journal, file_path, ranges, size_after)?;
+        println!("Journal saved to: {}", journal.display());
+    }
+    Ok(())
+}
+
+/// Describes a pending modification for `--dry-run`. `old` and `new` may be
+/// a leading excerpt of a longer range of `total` bytes.
+pub fn print_plan(file_path: &Path, offset: u64, total: u64, old: &[u8], new: &[u8]) {
+    println!(
+        "Dry run: would modify {} bytes at {:#x}..{:#x} in {}",
+        total,
+        offset,
+        offset + total,
+        file_path.display()
+    );
+    if (old.len() as u64) < total.min(new.len() as u64) {
+        println!(
+            "Note: {} bytes would be written past the current end of file",
+            new.len() - old.len()
+        );
+    }
+    println!("Old bytes:");
+    print_hexdump(old, offset);
+    println!("New bytes:");
+    print_hexdump(new, offset);
+    if total > new.len() as u64 {
+        println!("... ({} more bytes not shown)", total - new.len() as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_tool::write_at;
+    use std::panic;
+
+    fn atomic() -> WriteOptions {
+        WriteOptions {
+            backup: None,
+            no_clobber_backup: false,
+            dry_run: false,
+            journal: None,
+            force: false,
+            atomic: true,
+        }
+    }
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hex_tool-cmd-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn entries(dir: &Path) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir).unwrap().map(|e| e.unwrap().path()).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn interrupted_atomic_write_keeps_the_original_and_removes_the_copy() {
+        let dir = scratch("atomic-interrupted");
+        let path = dir.join("data.bin");
+        fs::write(&path, b"original contents").unwrap();
+
+        // A panic part way through the write unwinds past the target
+        // without committing it, as an error returned with `?` would
+        let interrupted = panic::catch_unwind(|| {
+            let mut target = WriteTarget::open(&path, &atomic(), OpenOptions::new().write(true)).unwrap();
+            assert_ne!(target.path(), path);
+            assert!(target.path().exists());
+            write_at(target.file(), 0, b"half-writ").unwrap();
+            panic!("interrupted");
+        });
+        assert!(interrupted.is_err());
+
+        assert_eq!(fs::read(&path).unwrap(), b"original contents");
+        assert_eq!(entries(&dir), [path]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn committed_atomic_write_replaces_the_original() {
+        let dir = scratch("atomic-committed");
+        let path = dir.join("data.bin");
+        fs::write(&path, b"original contents").unwrap();
+
+        let mut target = WriteTarget::open(&path, &atomic(), OpenOptions::new().write(true)).unwrap();
+        write_at(target.file(), 0, b"ORIGINAL").unwrap();
+        // Until the commit the original is untouched
+        assert_eq!(fs::read(&path).unwrap(), b"original contents");
+        target.commit().unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"ORIGINAL contents");
+        assert_eq!(entries(&dir), [path]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn atomic_write_to_a_missing_file_leaves_nothing_behind_when_dropped() {
+        let dir = scratch("atomic-missing");
+        let path = dir.join("new.bin");
+
+        let mut target = WriteTarget::open(&path, &atomic(), OpenOptions::new().write(true)).unwrap();
+        write_at(target.file(), 0, b"new").unwrap();
+        drop(target);
+
+        assert!(entries(&dir).is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,130 @@
+use super::{invalid_input, prepare_write, WriteOptions, WriteTarget};
+use hex_tool::file_len_or_zero;
+use hex_tool::hexstr::decode_hex;
+use hex_tool::output::temp_sibling;
+use hex_tool::search::find_matches;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(clap::Args, Debug)]
+pub struct ReplaceArgs {
+    /// File to modify
+    #[arg(short, long = "file", value_name = "FILE")]
+    file: PathBuf,
+
+    /// Byte sequences to swap, as hex
+    #[arg(value_name = "OLDHEX=NEWHEX")]
+    spec: String,
+
+    /// Replace only the Nth occurrence (1-based)
+    #[arg(long, value_name = "N", conflicts_with = "max")]
+    nth: Option<usize>,
+
+    /// Replace at most N occurrences
+    #[arg(long, value_name = "N")]
+    max: Option<usize>,
+
+    /// Allow a replacement of a different length, rewriting the file
+    #[arg(long)]
+    allow_resize: bool,
+
+    #[command(flatten)]
+    opts: WriteOptions,
+}
+
+/// Replaces occurrences of one byte sequence with another, non-overlapping
+/// and left to right. Equal lengths are patched in place; otherwise, with
+/// `--allow-resize`, the file is rewritten through a temporary sibling that
+/// is renamed over the original.
+pub fn run(args: ReplaceArgs) -> io::Result<()> {
+    let file_path = args.file;
+    let opts = &args.opts;
+    let spec = &args.spec;
+    let (old_hex, new_hex) = spec
+        .split_once('=')
+        .ok_or_else(|| invalid_input(format!("expected OLDHEX=NEWHEX, found '{}'", spec)))?;
+    let old = decode_hex(old_hex).map_err(|e| invalid_input(format!("search bytes: {}", e)))?;
+    let new = decode_hex(new_hex).map_err(|e| invalid_input(format!("replacement bytes: {}", e)))?;
+    if old.is_empty() {
+        return Err(invalid_input("search bytes must not be empty"));
+    }
+    if args.nth == Some(0) {
+        return Err(invalid_input("--nth counts from 1"));
+    }
+    if old.len() != new.len() && opts.journal.is_some() {
+        return Err(invalid_input(
+            "--journal cannot record a replacement that changes the file size; use --backup",
+        ));
+    }
+    if old.len() != new.len() && !args.allow_resize {
+        return Err(invalid_input(format!(
+            "replacement is {} bytes but the search is {} bytes (use --allow-resize)",
+            new.len(),
+            old.len()
+        )));
+    }
+
+    let limit = args.nth.or(args.max);
+    let mut matches = find_matches(&mut File::open(&file_path)?, 0, &old, limit)?;
+    if let Some(n) = args.nth {
+        matches = matches.into_iter().skip(n - 1).collect();
+    }
+
+    if opts.dry_run {
+        for &offset in &matches {
+            println!("Dry run: would replace {} bytes at {:#x}", old.len(), offset);
+        }
+        println!("{} replacements would be made.", matches.len());
+        return Ok(());
+    }
+    if matches.is_empty() {
+        println!("No occurrences found; nothing replaced.");
+        return Ok(());
+    }
+
+    let spans: Vec<(u64, u64)> = matches.iter().map(|&m| (m, old.len() as u64)).collect();
+    prepare_write(&file_path, opts, &spans, file_len_or_zero(&file_path)?)?;
+
+    if old.len() == new.len() {
+        let mut target = WriteTarget::open(&file_path, opts, OpenOptions::new().write(true))?;
+        let file = target.file();
+        for &offset in &matches {
+            file.seek(SeekFrom::Start(offset))?;
+            file.write_all(&new)?;
+            println!("Replaced {} bytes at {:#x}", new.len(), offset);
+        }
+        target.commit()?;
+    } else {
+        let temp = temp_sibling(&file_path)?;
+        let result = rewrite_with_replacements(&file_path, &temp, &matches, old.len() as u64, &new);
+        if let Err(e) = result.and_then(|()| fs::rename(&temp, &file_path)) {
+            let _ = fs::remove_file(&temp);
+            return Err(e);
+        }
+        // Report offsets in the original file, where the user found them
+        for &offset in &matches {
+            println!("Replaced {} bytes with {} at {:#x}", old.len(), new.len(), offset);
+        }
+    }
+    println!("{} replacements made.", matches.len());
+    Ok(())
+}
+
+/// Streams `src` into `dest`, substituting `new` for the `old_len` bytes at
+/// each offset in `matches` (ascending, non-overlapping).
+fn rewrite_with_replacements(src: &Path, dest: &Path, matches: &[u64], old_len: u64, new: &[u8]) -> io::Result<()> {
+    let mut input = File::open(src)?;
+    let permissions = input.metadata()?.permissions();
+    let mut output = File::create(dest)?;
+    let mut pos = 0;
+    for &offset in matches {
+        io::copy(&mut (&mut input).take(offset - pos), &mut output)?;
+        output.write_all(new)?;
+        input.seek(SeekFrom::Start(offset + old_len))?;
+        pos = offset + old_len;
+    }
+    io::copy(&mut input, &mut output)?;
+    output.sync_all()?;
+    fs::set_permissions(dest, permissions)
+}
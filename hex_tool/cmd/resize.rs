@@ -0,0 +1,178 @@
+use super::{create_backup, BackupPolicy};
+use hex_tool::dump::print_hexdump;
+use hex_tool::hexstr::parse_byte;
+use hex_tool::{parse_byte_quantity, read_existing, CHUNK_SIZE};
+use clap::ArgGroup;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+#[derive(clap::Args, Debug)]
+#[command(group(ArgGroup::new("target").args(["to", "align"]).required(true)))]
+pub struct PadArgs {
+    /// File to extend
+    #[arg(short, long = "file", value_name = "FILE")]
+    file: PathBuf,
+
+    /// Target size in bytes (decimal or 0x hex, suffixes K, M, G allowed)
+    #[arg(long, value_name = "SIZE", value_parser = parse_byte_quantity)]
+    to: Option<u64>,
+
+    /// Round the current size up to a multiple of N bytes
+    #[arg(long, value_name = "N", value_parser = parse_byte_quantity)]
+    align: Option<u64>,
+
+    /// Byte used for the new region (hex)
+    #[arg(long, value_name = "BYTE", default_value = "00", value_parser = parse_byte)]
+    fill: u8,
+
+    /// Shrink the file if it is already larger than --to
+    #[arg(long)]
+    truncate_ok: bool,
+
+    /// Extend with a hole (set_len) instead of writing fill bytes; zero fill only
+    #[arg(long)]
+    sparse: bool,
+}
+
+/// Grows `file` from `from` to `to` bytes, either by writing `fill` bytes
+/// in chunks or, if `sparse`, by just moving the end of file.
+pub fn extend_file(file: &mut File, from: u64, to: u64, fill: u8, sparse: bool) -> io::Result<()> {
+    if sparse {
+        return file.set_len(to);
+    }
+
+    let buffer = vec![fill; CHUNK_SIZE];
+    file.seek(SeekFrom::Start(from))?;
+    let mut pos = from;
+    while pos < to {
+        let n = (to - pos).min(CHUNK_SIZE as u64) as usize;
+        file.write_all(&buffer[..n])?;
+        pos += n as u64;
+    }
+    Ok(())
+}
+
+pub fn run_pad(args: PadArgs) -> io::Result<()> {
+    if args.sparse && args.fill != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--sparse can only extend with zero bytes",
+        ));
+    }
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&args.file)?;
+    let len = file.metadata()?.len();
+
+    let target = match (args.to, args.align) {
+        (Some(to), _) => to,
+        (None, Some(0)) => {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "--align must be positive"));
+        }
+        (None, Some(align)) => len.div_ceil(align).checked_mul(align).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "aligned size overflows")
+        })?,
+        (None, None) => unreachable!("clap requires --to or --align"),
+    };
+
+    if target < len {
+        if !args.truncate_ok {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} is already {} bytes, larger than {} (use --truncate-ok to shrink)",
+                    args.file.display(),
+                    len,
+                    target
+                ),
+            ));
+        }
+        file.set_len(target)?;
+        println!("Truncated {} from {} to {} bytes.", args.file.display(), len, target);
+        return Ok(());
+    }
+    if target == len {
+        println!("{} is already {} bytes.", args.file.display(), len);
+        return Ok(());
+    }
+
+    extend_file(&mut file, len, target, args.fill, args.sparse)?;
+    println!(
+        "Padded {} from {} to {} bytes with {:02x}{}.",
+        args.file.display(),
+        len,
+        target,
+        args.fill,
+        if args.sparse { " (sparse)" } else { "" }
+    );
+    Ok(())
+}
+
+#[derive(clap::Args, Debug)]
+pub struct TruncateArgs {
+    /// File to resize
+    #[arg(short, long = "file", value_name = "FILE")]
+    file: PathBuf,
+
+    /// New size in bytes (decimal or 0x hex, suffixes K, M, G allowed)
+    #[arg(long, value_name = "SIZE", value_parser = parse_byte_quantity)]
+    to: u64,
+
+    /// Allow the file to shrink, discarding everything past --to
+    #[arg(long)]
+    shrink: bool,
+
+    /// Hexdump the first 64 bytes that would be discarded
+    #[arg(long)]
+    preview: bool,
+
+    /// Copy the file to FILE+SUFFIX before resizing it
+    #[arg(long, value_name = "SUFFIX", num_args = 0..=1, require_equals = true, default_missing_value = ".bak")]
+    backup: Option<String>,
+}
+
+/// Number of discarded bytes shown by `truncate --preview`.
+const TRUNCATE_PREVIEW: u64 = 64;
+
+pub fn run_truncate(args: TruncateArgs) -> io::Result<()> {
+    let len = fs::metadata(&args.file)?.len();
+
+    if args.to < len {
+        let discarded = len - args.to;
+        println!("{} bytes past offset {:#x} would be discarded.", discarded, args.to);
+        if args.preview {
+            let preview = read_existing(&args.file, args.to, TRUNCATE_PREVIEW.min(discarded) as usize)?;
+            print_hexdump(&preview, args.to);
+        }
+        if !args.shrink {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "refusing to shrink the file without --shrink",
+            ));
+        }
+    } else if args.to == len {
+        println!("{} is already {} bytes.", args.file.display(), len);
+        return Ok(());
+    }
+
+    let backup = args.backup.map(|suffix| BackupPolicy {
+        suffix,
+        no_clobber: false,
+    });
+    create_backup(&args.file, backup.as_ref())?;
+
+    let mut file = OpenOptions::new().write(true).open(&args.file)?;
+    if args.to < len {
+        file.set_len(args.to)?;
+        println!("Truncated {} from {} to {} bytes.", args.file.display(), len, args.to);
+    } else {
+        // Growing behaves exactly like `pad --fill 00`
+        extend_file(&mut file, len, args.to, 0, false)?;
+        println!("Padded {} from {} to {} bytes with 00.", args.file.display(), len, args.to);
+    }
+    Ok(())
+}
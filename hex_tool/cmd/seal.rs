@@ -0,0 +1,123 @@
+use super::{invalid_input, prepare_write, print_plan, WriteOptions, WriteTarget};
+use hex_tool::digest::Algorithm;
+use hex_tool::number::parse_offset;
+use hex_tool::range::{parse_range, ByteRange};
+use hex_tool::value::Endian;
+use hex_tool::{read_range, write_at, CHUNK_SIZE};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+#[derive(clap::Args, Debug)]
+pub struct SealArgs {
+    /// File to seal in place
+    #[arg(short, long = "file", value_name = "FILE")]
+    file: PathBuf,
+
+    /// Bytes to checksum, as OFFSET:SIZE
+    #[arg(long, value_name = "OFFSET:SIZE", value_parser = parse_range)]
+    range: ByteRange,
+
+    /// Checksum or hash to compute
+    #[arg(long, value_enum)]
+    algo: Algorithm,
+
+    /// Offset to store the digest at
+    #[arg(long, value_name = "OFF", value_parser = parse_offset)]
+    write_to: u64,
+
+    /// Byte order of a stored CRC (hashes are stored as-is)
+    #[arg(long, value_enum, default_value_t = Endian::Le)]
+    endian: Endian,
+
+    /// Store only the leading N bytes of a sha256 digest
+    #[arg(long, value_name = "N")]
+    truncate: Option<usize>,
+
+    /// Allow the digest field inside the range; it is hashed as zeros
+    #[arg(long)]
+    zero_field: bool,
+
+    #[command(flatten)]
+    opts: WriteOptions,
+}
+
+/// Hashes the range (with the digest field zeroed if it lies inside) and
+/// stores the digest at --write-to.
+pub fn run(args: SealArgs) -> io::Result<()> {
+    let digest_len = match (args.algo, args.truncate) {
+        (_, None) => args.algo.digest_len(),
+        (Algorithm::Sha256, Some(n)) if (1..=args.algo.digest_len()).contains(&n) => n,
+        (Algorithm::Sha256, Some(n)) => {
+            return Err(invalid_input(format!("--truncate must be between 1 and 32, got {}", n)));
+        }
+        (_, Some(_)) => return Err(invalid_input("--truncate only applies to sha256")),
+    };
+    let field = ByteRange {
+        offset: args.write_to,
+        size: digest_len as u64,
+    };
+    if field.overlaps(&args.range) && !args.zero_field {
+        return Err(invalid_input(format!(
+            "the digest field {:#x}..{:#x} lies inside the hashed range (use --zero-field to hash it as zeros)",
+            field.offset,
+            field.end()
+        )));
+    }
+    let len = fs::metadata(&args.file)?.len();
+    if args.range.end() > len {
+        return Err(invalid_input(format!(
+            "range {:#x}..{:#x} goes past the end of the file ({} bytes)",
+            args.range.offset,
+            args.range.end(),
+            len
+        )));
+    }
+
+    let mut file = File::open(&args.file)?;
+    file.seek(SeekFrom::Start(args.range.offset))?;
+    let mut hasher = args.algo.hasher();
+    let mut chunk = vec![0; CHUNK_SIZE];
+    let mut pos = args.range.offset;
+    while pos < args.range.end() {
+        let n = (args.range.end() - pos).min(CHUNK_SIZE as u64) as usize;
+        file.read_exact(&mut chunk[..n])?;
+        // Zero whatever part of the digest field falls in this chunk
+        let start = field.offset.clamp(pos, pos + n as u64);
+        let end = field.end().clamp(pos, pos + n as u64);
+        chunk[(start - pos) as usize..(end - pos) as usize].fill(0);
+        hasher.update(&chunk[..n]);
+        pos += n as u64;
+    }
+    let mut digest = hasher.finish(args.endian);
+    digest.truncate(digest_len);
+
+    let stored = hex::encode(&digest);
+    let shown = match (args.algo, args.endian) {
+        (Algorithm::Sha256, _) => stored.clone(),
+        (_, Endian::Be) => format!("0x{} (stored as {})", stored, stored),
+        (_, Endian::Le) => {
+            let value: Vec<u8> = digest.iter().rev().copied().collect();
+            format!("0x{} (stored as {})", hex::encode(value), stored)
+        }
+    };
+    println!(
+        "{} of {:#x}..{:#x}: {}",
+        args.algo.name(),
+        args.range.offset,
+        args.range.end(),
+        shown
+    );
+
+    if args.opts.dry_run {
+        let old = read_range(&mut file, field.offset, field.size)?;
+        print_plan(&args.file, field.offset, field.size, &old, &digest);
+        return Ok(());
+    }
+    prepare_write(&args.file, &args.opts, &[(field.offset, field.size)], len.max(field.end()))?;
+    let mut target = WriteTarget::open(&args.file, &args.opts, OpenOptions::new().write(true))?;
+    write_at(target.file(), field.offset, &digest)?;
+    target.commit()?;
+    println!("Wrote {} bytes at {:#x}.", digest.len(), field.offset);
+    Ok(())
+}
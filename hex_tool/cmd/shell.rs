@@ -0,0 +1,210 @@
+use super::invalid_input;
+use hex_tool::dump::print_hexdump;
+use hex_tool::hexstr::decode_hex;
+use hex_tool::number::parse_offset;
+use hex_tool::search::find_matches;
+use hex_tool::value::{Endian, ValueType};
+use hex_tool::{parse_byte_quantity, read_range, write_at};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, IsTerminal, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+#[derive(clap::Args, Debug)]
+pub struct ShellArgs {
+    /// File to inspect (opened read-only if it cannot be written)
+    #[arg(short, long = "file", value_name = "FILE")]
+    file: PathBuf,
+}
+
+/// Bytes shown by `d` when no size is given.
+const DEFAULT_DUMP: u64 = 256;
+
+const HELP: &str = "\
+d [OFFSET [SIZE]]    dump SIZE bytes (default 256) from OFFSET (default: where the last dump ended)
+f HEX                list the offsets of HEX and move to the first one
+w OFFSET HEX         write HEX at OFFSET, after confirmation
+as OFFSET TYPE [le|be]  decode a value (u8..u64, i8..i64, f32, f64)
+len                  print the file size
+undo                 revert the last write
+quit                 leave the shell";
+
+/// A confirmed write, with what it replaced so `undo` can put it back.
+struct Edit {
+    offset: u64,
+    old: Vec<u8>,
+    old_len: u64,
+}
+
+struct Session {
+    file: File,
+    writable: bool,
+    /// Where `d` without an offset starts
+    cursor: u64,
+    history: Vec<Edit>,
+}
+
+/// Reads commands from stdin until `quit` or end of input. A failing
+/// command prints an error to stderr and the shell carries on.
+pub fn run(args: ShellArgs) -> io::Result<()> {
+    let (file, writable) = match OpenOptions::new().read(true).write(true).open(&args.file) {
+        Ok(file) => (file, true),
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => (File::open(&args.file)?, false),
+        Err(e) => return Err(e),
+    };
+    let mut session = Session {
+        file,
+        writable,
+        cursor: 0,
+        history: Vec::new(),
+    };
+
+    let interactive = io::stdin().is_terminal();
+    if interactive {
+        println!("{} ({} bytes). Type 'help' for commands.", args.file.display(), session.len()?);
+    }
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        if interactive {
+            print!("hex_tool> ");
+            io::stdout().flush()?;
+        }
+        let Some(line) = lines.next().transpose()? else {
+            break;
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let result = match words.as_slice() {
+            [] => Ok(()),
+            ["quit" | "q" | "exit"] => break,
+            ["help" | "?"] => {
+                println!("{}", HELP);
+                Ok(())
+            }
+            ["d", rest @ ..] if rest.len() <= 2 => session.dump(rest),
+            ["f", pattern] => session.find(pattern),
+            ["w", offset, hex_string] => session.write(offset, hex_string, &mut lines),
+            ["as", offset, ty] => session.decode(offset, ty, "le"),
+            ["as", offset, ty, endian] => session.decode(offset, ty, endian),
+            ["len"] => session.len().map(|len| println!("{} bytes", len)),
+            ["undo"] => session.undo(),
+            [command, ..] => Err(invalid_input(format!(
+                "unknown command or wrong arguments: '{}' (try 'help')",
+                command
+            ))),
+        };
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+        }
+    }
+    Ok(())
+}
+
+fn offset_arg(text: &str) -> io::Result<u64> {
+    parse_offset(text).map_err(|e| invalid_input(format!("invalid offset: {}", e)))
+}
+
+impl Session {
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.file.metadata()?.len())
+    }
+
+    fn dump(&mut self, args: &[&str]) -> io::Result<()> {
+        let offset = match args.first() {
+            Some(text) => offset_arg(text)?,
+            None => self.cursor,
+        };
+        let size = match args.get(1) {
+            Some(text) => parse_byte_quantity(text).map_err(|e| invalid_input(format!("invalid size: {}", e)))?,
+            None => DEFAULT_DUMP,
+        };
+        let bytes = read_range(&mut self.file, offset, size)?;
+        if bytes.is_empty() {
+            println!("(end of file at {:#x})", self.len()?);
+        }
+        print_hexdump(&bytes, offset);
+        self.cursor = offset + bytes.len() as u64;
+        Ok(())
+    }
+
+    fn find(&mut self, pattern: &str) -> io::Result<()> {
+        let pattern = decode_hex(pattern).map_err(|e| invalid_input(format!("search bytes: {}", e)))?;
+        if pattern.is_empty() {
+            return Err(invalid_input("search bytes must not be empty"));
+        }
+        self.file.seek(SeekFrom::Start(0))?;
+        let matches = find_matches(&mut self.file, 0, &pattern, None)?;
+        for m in &matches {
+            println!("{:#010x}", m);
+        }
+        println!("{} matches found.", matches.len());
+        if let Some(&first) = matches.first() {
+            self.cursor = first;
+        }
+        Ok(())
+    }
+
+    fn write(
+        &mut self,
+        offset: &str,
+        hex_string: &str,
+        lines: &mut impl Iterator<Item = io::Result<String>>,
+    ) -> io::Result<()> {
+        if !self.writable {
+            return Err(invalid_input("the file was opened read-only"));
+        }
+        let offset = offset_arg(offset)?;
+        let bytes = decode_hex(hex_string).map_err(|e| invalid_input(format!("invalid hex string: {}", e)))?;
+        let old = read_range(&mut self.file, offset, bytes.len() as u64)?;
+        println!("Old bytes:");
+        print_hexdump(&old, offset);
+        println!("New bytes:");
+        print_hexdump(&bytes, offset);
+        print!("Write {} bytes at {:#x}? [y/N] ", bytes.len(), offset);
+        io::stdout().flush()?;
+        let answer = lines.next().transpose()?.unwrap_or_default();
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            println!("Not written.");
+            return Ok(());
+        }
+
+        let old_len = self.len()?;
+        write_at(&mut self.file, offset, &bytes)?;
+        self.history.push(Edit { offset, old, old_len });
+        println!("Successfully written {} bytes.", bytes.len());
+        Ok(())
+    }
+
+    fn decode(&mut self, offset: &str, ty: &str, endian: &str) -> io::Result<()> {
+        let offset = offset_arg(offset)?;
+        let ty = ValueType::from_name(ty).ok_or_else(|| invalid_input(format!("unknown type '{}'", ty)))?;
+        let endian = match endian {
+            "le" => Endian::Le,
+            "be" => Endian::Be,
+            _ => return Err(invalid_input(format!("byte order must be le or be, not '{}'", endian))),
+        };
+        let bytes = read_range(&mut self.file, offset, ty.width() as u64)?;
+        if bytes.len() < ty.width() {
+            return Err(invalid_input(format!(
+                "{} at offset {:#x} needs {} bytes, only {} available",
+                ty.name(),
+                offset,
+                ty.width(),
+                bytes.len()
+            )));
+        }
+        println!("{:08x}: {:<4} {}", offset, ty.name(), ty.format(&bytes, endian));
+        Ok(())
+    }
+
+    fn undo(&mut self) -> io::Result<()> {
+        let Some(edit) = self.history.pop() else {
+            return Err(invalid_input("nothing to undo"));
+        };
+        write_at(&mut self.file, edit.offset, &edit.old)?;
+        // A write past the old end extended the file; shrink it back
+        if self.len()? > edit.old_len {
+            self.file.set_len(edit.old_len)?;
+        }
+        println!("Restored {} bytes at {:#x}.", edit.old.len(), edit.offset);
+        Ok(())
+    }
+}
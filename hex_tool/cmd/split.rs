@@ -0,0 +1,116 @@
+use super::{create_output, invalid_input, open_input};
+use hex_tool::{parse_byte_quantity, CHUNK_SIZE};
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(clap::Args, Debug)]
+pub struct SplitArgs {
+    /// File to split ("-" for stdin)
+    #[arg(short, long = "file", value_name = "FILE")]
+    file: PathBuf,
+
+    /// Size of every chunk but the last (suffixes K, M, G allowed)
+    #[arg(long, value_name = "N", value_parser = parse_byte_quantity)]
+    chunk_size: u64,
+
+    /// Chunks are written to PREFIX.000, PREFIX.001, ...
+    #[arg(long, value_name = "PREFIX")]
+    output_prefix: PathBuf,
+
+    /// Also write a manifest listing each chunk's offset, size and SHA-256
+    #[arg(long, value_name = "FILE")]
+    manifest: Option<PathBuf>,
+
+    /// Overwrite chunk files that already exist
+    #[arg(long)]
+    force: bool,
+}
+
+/// First line of every manifest; bump the number when the format changes.
+const MANIFEST_HEADER: &str = "hex_tool-split 1";
+
+struct Chunk {
+    path: PathBuf,
+    offset: u64,
+    size: u64,
+    sha256: String,
+}
+
+/// Streams the input into consecutive chunk files, hashing each one (and
+/// the whole input) on the way.
+pub fn run(args: SplitArgs) -> io::Result<()> {
+    if args.chunk_size == 0 {
+        return Err(invalid_input("--chunk-size must be greater than zero"));
+    }
+
+    let mut input = open_input(&args.file, 0)?;
+    let mut total_hasher = Sha256::new();
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut chunks: Vec<Chunk> = Vec::new();
+    // The chunk being filled, created once its first byte arrives so an
+    // input that ends on a chunk boundary leaves no empty file behind
+    let mut current: Option<(File, Sha256)> = None;
+    let mut filled = 0u64;
+    let mut offset = 0u64;
+    loop {
+        let want = (args.chunk_size - filled).min(CHUNK_SIZE as u64) as usize;
+        let n = input.read(&mut buffer[..want])?;
+        if n == 0 {
+            break;
+        }
+        if current.is_none() {
+            let path = chunk_path(&args.output_prefix, chunks.len());
+            current = Some((create_output(&path, args.force)?, Sha256::new()));
+            chunks.push(Chunk {
+                path,
+                offset,
+                size: 0,
+                sha256: String::new(),
+            });
+        }
+        let (file, hasher) = current.as_mut().expect("chunk was just created");
+        file.write_all(&buffer[..n])?;
+        hasher.update(&buffer[..n]);
+        total_hasher.update(&buffer[..n]);
+        filled += n as u64;
+        offset += n as u64;
+
+        if filled == args.chunk_size {
+            finish_chunk(&mut chunks, current.take(), filled);
+            filled = 0;
+        }
+    }
+    finish_chunk(&mut chunks, current.take(), filled);
+
+    for chunk in &chunks {
+        println!("{:#010x} {:>10} {}", chunk.offset, chunk.size, chunk.path.display());
+    }
+    println!("Split {} bytes into {} chunks.", offset, chunks.len());
+
+    if let Some(manifest) = &args.manifest {
+        let mut text = format!("{}\n", MANIFEST_HEADER);
+        let _ = writeln!(text, "source {} {}", offset, hex::encode(total_hasher.finalize()));
+        for chunk in &chunks {
+            let _ = writeln!(text, "chunk {:#x} {} {} {}", chunk.offset, chunk.size, chunk.sha256, chunk.path.display());
+        }
+        fs::write(manifest, text)?;
+        println!("Manifest saved to: {}", manifest.display());
+    }
+    Ok(())
+}
+
+fn chunk_path(prefix: &Path, index: usize) -> PathBuf {
+    let mut name = prefix.as_os_str().to_owned();
+    name.push(format!(".{:03}", index));
+    PathBuf::from(name)
+}
+
+fn finish_chunk(chunks: &mut [Chunk], current: Option<(File, Sha256)>, size: u64) {
+    if let (Some((_, hasher)), Some(chunk)) = (current, chunks.last_mut()) {
+        chunk.size = size;
+        chunk.sha256 = hex::encode(hasher.finalize());
+    }
+}
@@ -0,0 +1,261 @@
+use super::{
+    create_output, invalid_input, is_stdio, prepare_write, print_plan, same_file, target_len, Target, WriteOptions,
+    WriteTarget, DRY_RUN_PREVIEW,
+};
+use clap::ArgGroup;
+use hex_tool::hexstr::decode_hex;
+use hex_tool::progress::Progress;
+use hex_tool::range::{parse_range, sort_ranges, ByteRange};
+use hex_tool::{parse_byte_quantity, read_existing, CHUNK_SIZE};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+#[derive(clap::Args, Debug)]
+#[command(group(ArgGroup::new("op").args(["xor", "and", "or", "swap"]).required(true)))]
+pub struct TransformArgs {
+    #[command(flatten)]
+    pub target: Target,
+
+    /// Number of bytes to transform (default: to the end of the file)
+    #[arg(short, long = "size", value_name = "N", value_parser = parse_byte_quantity)]
+    size: Option<u64>,
+
+    /// Transform OFFSET:SIZE instead of --offset/--size (repeatable)
+    #[arg(
+        long = "range",
+        value_name = "OFFSET:SIZE",
+        value_parser = parse_range,
+        conflicts_with_all = ["offset", "size"]
+    )]
+    ranges: Vec<ByteRange>,
+
+    /// XOR the range with a key (hex), cycling the key across the range
+    #[arg(long, value_name = "KEYHEX")]
+    xor: Option<String>,
+
+    /// AND the range with a key (hex), cycling the key across the range
+    #[arg(long, value_name = "KEYHEX")]
+    and: Option<String>,
+
+    /// OR the range with a key (hex), cycling the key across the range
+    #[arg(long, value_name = "KEYHEX")]
+    or: Option<String>,
+
+    /// Reverse the bytes within each WIDTH-byte word (2, 4 or 8) to switch
+    /// endianness; every range must be a whole number of words
+    #[arg(long, value_name = "WIDTH", value_parser = parse_swap_width)]
+    swap: Option<usize>,
+
+    /// Write the transformed file to a new path instead of modifying it in
+    /// place; an existing FILE is only replaced with --force
+    #[arg(long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Show a progress bar on stderr
+    #[arg(long)]
+    progress: bool,
+
+    #[command(flatten)]
+    opts: WriteOptions,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BitOp {
+    Xor,
+    And,
+    Or,
+}
+
+impl BitOp {
+    fn apply(self, byte: u8, key: u8) -> u8 {
+        match self {
+            BitOp::Xor => byte ^ key,
+            BitOp::And => byte & key,
+            BitOp::Or => byte | key,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            BitOp::Xor => "xor",
+            BitOp::And => "and",
+            BitOp::Or => "or",
+        }
+    }
+}
+
+fn parse_swap_width(s: &str) -> Result<usize, String> {
+    match s {
+        "2" => Ok(2),
+        "4" => Ok(4),
+        "8" => Ok(8),
+        _ => Err(format!("word width must be 2, 4 or 8, found '{}'", s)),
+    }
+}
+
+fn parse_key(op: BitOp, key_hex: &str) -> io::Result<Op> {
+    let key = decode_hex(key_hex).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    if key.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "key must not be empty"));
+    }
+    Ok(Op::Bits(op, key))
+}
+
+/// What happens to each byte of a range.
+enum Op {
+    /// A bitwise operation with a key that cycles across the range
+    Bits(BitOp, Vec<u8>),
+    /// Byte order reversal within words of this many bytes
+    Swap(usize),
+}
+
+impl Op {
+    /// Transforms `chunk`, which starts `start` bytes into its range. Chunks
+    /// of a swap always start on a word boundary.
+    fn apply(&self, chunk: &mut [u8], start: u64) {
+        match self {
+            Op::Bits(op, key) => {
+                for (i, byte) in chunk.iter_mut().enumerate() {
+                    // Index by position within the whole range so the key
+                    // keeps cycling correctly across chunk boundaries
+                    let k = key[((start + i as u64) % key.len() as u64) as usize];
+                    *byte = op.apply(*byte, k);
+                }
+            }
+            Op::Swap(width) => chunk.chunks_exact_mut(*width).for_each(|word| word.reverse()),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Op::Bits(op, key) => format!("Applied {} with a {}-byte key to", op.name(), key.len()),
+            Op::Swap(width) => format!("Swapped the byte order of {}-byte words in", width),
+        }
+    }
+}
+
+/// Applies the operation over each range, streaming in chunks. With
+/// `--output`, the file is first copied there and the copy is transformed.
+pub fn run(args: TransformArgs) -> io::Result<()> {
+    let op = match (args.xor, args.and, args.or, args.swap) {
+        (_, _, _, Some(width)) => Op::Swap(width),
+        (Some(k), _, _, _) => parse_key(BitOp::Xor, &k)?,
+        (_, Some(k), _, _) => parse_key(BitOp::And, &k)?,
+        (_, _, Some(k), _) => parse_key(BitOp::Or, &k)?,
+        _ => unreachable!("clap requires --xor, --and, --or or --swap"),
+    };
+    let file_path = args.target.file().to_path_buf();
+    if is_stdio(&file_path) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "cannot transform stdin in place",
+        ));
+    }
+
+    let mut ranges = args.ranges;
+    if ranges.is_empty() {
+        ranges.push(ByteRange {
+            offset: args.target.resolve_offset()?,
+            size: args.size.unwrap_or(u64::MAX),
+        });
+    }
+    if let Some((a, b)) = sort_ranges(&mut ranges) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "ranges {:#x}:{} and {:#x}:{} overlap, which is not allowed when writing",
+                a.offset, a.size, b.offset, b.size
+            ),
+        ));
+    }
+
+    // Clamp every range to the end of the file
+    let len = target_len(&file_path)?;
+    let ranges: Vec<ByteRange> = ranges
+        .iter()
+        .map(|r| ByteRange {
+            offset: r.offset,
+            size: r.size.min(len.saturating_sub(r.offset)),
+        })
+        .collect();
+    if let Op::Swap(width) = op {
+        if let Some(r) = ranges.iter().find(|r| r.size % width as u64 != 0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} bytes at offset {:#x} is not a whole number of {}-byte words",
+                    r.size, r.offset, width
+                ),
+            ));
+        }
+    }
+    let total: u64 = ranges.iter().map(|r| r.size).sum();
+
+    let opts = &args.opts;
+    if opts.dry_run {
+        for range in &ranges {
+            let old = read_existing(&file_path, range.offset, range.size.min(DRY_RUN_PREVIEW as u64) as usize)?;
+            let mut new = old.clone();
+            op.apply(&mut new, 0);
+            print_plan(args.output.as_deref().unwrap_or(&file_path), range.offset, range.size, &old, &new);
+        }
+        return Ok(());
+    }
+
+    let target = match args.output {
+        Some(output) => {
+            // Checked before the output is created, which would truncate the input
+            if same_file(&file_path, &output)? {
+                return Err(invalid_input(format!(
+                    "{} is also the output file; leave out --output to transform it in place",
+                    output.display()
+                )));
+            }
+            let mut copy = create_output(&output, opts.force)?;
+            io::copy(&mut File::open(&file_path)?, &mut copy)?;
+            output
+        }
+        None => {
+            if total > 0 {
+                let spans: Vec<(u64, u64)> = ranges.iter().map(|r| (r.offset, r.size)).collect();
+                prepare_write(&file_path, opts, &spans, len)?;
+            }
+            file_path
+        }
+    };
+
+    let mut writer = WriteTarget::open(&target, opts, OpenOptions::new().read(true).write(true))?;
+    let file = writer.file();
+
+    let mut progress = Progress::new(total, args.progress);
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut processed = 0u64;
+    for range in &ranges {
+        let mut done = 0u64;
+        while done < range.size {
+            let n = (range.size - done).min(CHUNK_SIZE as u64) as usize;
+            file.seek(SeekFrom::Start(range.offset + done))?;
+            file.read_exact(&mut buffer[..n])?;
+            op.apply(&mut buffer[..n], done);
+            file.seek(SeekFrom::Start(range.offset + done))?;
+            file.write_all(&buffer[..n])?;
+            done += n as u64;
+            progress.update(processed + done);
+        }
+        processed += done;
+    }
+    writer.commit()?;
+    progress.finish();
+
+    for range in &ranges {
+        println!(
+            "{} {} bytes at offset {:#x} in {}.",
+            op.describe(),
+            range.size,
+            range.offset,
+            target.display()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use hex_tool::journal::read_journal;
+use hex_tool::write_at;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::PathBuf;
+
+#[derive(clap::Args, Debug)]
+pub struct UndoArgs {
+    /// File to restore
+    #[arg(short, long = "file", value_name = "FILE")]
+    file: PathBuf,
+
+    /// Journal written by an earlier --journal run
+    #[arg(long, value_name = "JOURNAL")]
+    journal: PathBuf,
+}
+
+/// Restores the journaled bytes a line at a time, after a first pass over
+/// the journal has checked it is complete and matches its checksum.
+pub fn run(args: UndoArgs) -> io::Result<()> {
+    let journal = read_journal(&args.journal, |_, _| Ok(()))?;
+    let len = fs::metadata(&args.file)?.len();
+    if len != journal.size_after {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} is {} bytes but the journal expects {}; it was resized after the journaled operation",
+                args.file.display(),
+                len,
+                journal.size_after
+            ),
+        ));
+    }
+
+    let mut file = OpenOptions::new().write(true).open(&args.file)?;
+    read_journal(&args.journal, |offset, bytes| write_at(&mut file, offset, bytes))?;
+    if journal.size_before != len {
+        file.set_len(journal.size_before)?;
+    }
+    file.sync_all()?;
+
+    println!(
+        "Restored {} bytes in {} ranges of {} ({} bytes).",
+        journal.bytes,
+        journal.ranges,
+        args.file.display(),
+        journal.size_before
+    );
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use super::{open_input, Target};
+use hex_tool::hexstr::decode_hex;
+use std::io::{self, Read};
+
+#[derive(clap::Args, Debug)]
+pub struct VerifyArgs {
+    #[command(flatten)]
+    pub target: Target,
+
+    /// Bytes expected at the offset, as hex
+    #[arg(value_name = "HEX")]
+    hex: String,
+
+    /// Suppress output and rely on the exit code only
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+/// Returns whether the bytes at the offset match. Running out of input
+/// before all expected bytes are read counts as a mismatch.
+pub fn run(args: VerifyArgs) -> io::Result<bool> {
+    let offset = args.target.resolve_offset()?;
+    let quiet = args.quiet;
+    let expected = decode_hex(&args.hex)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    let mut actual = Vec::with_capacity(expected.len());
+    open_input(args.target.file(), offset)?
+        .take(expected.len() as u64)
+        .read_to_end(&mut actual)?;
+
+    for (i, &want) in expected.iter().enumerate() {
+        let position = offset + i as u64;
+        match actual.get(i) {
+            Some(&got) if got == want => continue,
+            Some(&got) => {
+                if !quiet {
+                    println!(
+                        "Mismatch at offset {:#x}: expected {:02x}, found {:02x}",
+                        position, want, got
+                    );
+                }
+            }
+            None => {
+                if !quiet {
+                    println!(
+                        "Mismatch at offset {:#x}: expected {:02x}, found end of file",
+                        position, want
+                    );
+                }
+            }
+        }
+        return Ok(false);
+    }
+
+    if !quiet {
+        println!("Verified {} bytes at offset {:#x}.", expected.len(), offset);
+    }
+    Ok(true)
+}
@@ -0,0 +1,323 @@
+use super::{
+    check_raw_output, invalid_input, is_stdio, prepare_write, print_plan, Target, WriteOptions, WriteTarget,
+    DRY_RUN_PREVIEW,
+};
+use clap::ArgGroup;
+use hex_tool::base64::decode_base64;
+use hex_tool::hexstr::decode_hex;
+use hex_tool::patch::{find_overlaps, parse_patch_file, parse_poke, Patch};
+use hex_tool::value::{Endian, ValueType};
+use hex_tool::{file_len_or_zero, read_existing, read_range, write_at};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, IsTerminal, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(clap::Args, Debug)]
+#[command(group(ArgGroup::new("data").args(["hex", "int", "b64", "b64_file", "pokes"]).required(true)))]
+pub struct WriteArgs {
+    #[command(flatten)]
+    pub target: Target,
+
+    /// Bytes to write, as hex
+    #[arg(value_name = "HEX")]
+    pub hex: Option<String>,
+
+    /// Write a number encoded as --type instead (e.g. 3735928559, 0xdeadbeef,
+    /// -1, 1.5)
+    #[arg(long, value_name = "VALUE", requires = "ty", allow_hyphen_values = true)]
+    int: Option<String>,
+
+    /// Type of the --int value
+    #[arg(long = "type", value_name = "TYPE", value_enum, requires = "int")]
+    ty: Option<ValueType>,
+
+    /// Byte order of the --int value
+    #[arg(long, value_enum, default_value_t = Endian::Le)]
+    endian: Endian,
+
+    /// Write bytes given as standard or URL-safe base64 instead
+    #[arg(long, value_name = "DATA", allow_hyphen_values = true)]
+    b64: Option<String>,
+
+    /// Write the bytes of a base64 text file instead (line breaks allowed)
+    #[arg(long, value_name = "FILE")]
+    b64_file: Option<PathBuf>,
+
+    /// Write HEX at OFFSET instead (repeatable); all pokes are checked
+    /// before any is written
+    #[arg(long = "poke", value_name = "OFFSET=HEX", value_parser = parse_poke, conflicts_with = "offset")]
+    pokes: Vec<Patch>,
+
+    #[command(flatten)]
+    opts: WriteOptions,
+}
+
+/// Writes the bytes in place without truncating, creating the file if
+/// needed. With FILE `-` they go to stdout instead.
+pub fn run(args: WriteArgs) -> io::Result<()> {
+    let offset = args.target.resolve_offset()?;
+    let file_path = args.target.file().to_path_buf();
+    if !args.pokes.is_empty() {
+        return run_pokes(&file_path, args.pokes, &args.opts);
+    }
+    let bytes = match (&args.hex, &args.int, args.ty) {
+        (Some(hex_string), _, _) => {
+            decode_hex(hex_string).map_err(|e| invalid_input(format!("invalid hex string: {}", e)))?
+        }
+        (None, Some(value), Some(ty)) => ty.encode(value, args.endian).map_err(invalid_input)?,
+        _ => match (&args.b64, &args.b64_file) {
+            (Some(text), _) => decode_base64(text).map_err(|e| invalid_input(format!("--b64: {}", e)))?,
+            (None, Some(path)) => decode_base64(&fs::read_to_string(path)?)
+                .map_err(|e| invalid_input(format!("{}: {}", path.display(), e)))?,
+            (None, None) => unreachable!("clap requires one source of bytes"),
+        },
+    };
+
+    if is_stdio(&file_path) {
+        check_raw_output(io::stdout().is_terminal(), args.opts.force)?;
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(&bytes)?;
+        stdout.flush()?;
+        eprintln!("Successfully written {} bytes.", bytes.len());
+        return Ok(());
+    }
+
+    let end = offset.checked_add(bytes.len() as u64).ok_or_else(|| {
+        invalid_input(format!(
+            "{} bytes at {:#x} would end past the largest 64-bit offset",
+            bytes.len(),
+            offset
+        ))
+    })?;
+
+    if args.opts.dry_run {
+        let old = read_existing(&file_path, offset, bytes.len())?;
+        print_plan(&file_path, offset, bytes.len() as u64, &old, &bytes);
+        return Ok(());
+    }
+
+    if !bytes.is_empty() {
+        let size_after = file_len_or_zero(&file_path)?.max(end);
+        prepare_write(&file_path, &args.opts, &[(offset, bytes.len() as u64)], size_after)?;
+    }
+
+    let mut target = WriteTarget::open(
+        &file_path,
+        &args.opts,
+        OpenOptions::new().write(true).create(true).truncate(false),
+    )?;
+    write_at(target.file(), offset, &bytes)?;
+    target.commit()?;
+    println!("Successfully written {} bytes.", bytes.len());
+    Ok(())
+}
+
+/// Applies the --poke patches in ascending offset order through one handle,
+/// once they have all been checked to lie inside the file without
+/// overlapping.
+fn run_pokes(file_path: &Path, mut pokes: Vec<Patch>, opts: &WriteOptions) -> io::Result<()> {
+    if is_stdio(file_path) {
+        return Err(invalid_input("--poke needs a file to patch, not stdout"));
+    }
+    for (i, poke) in pokes.iter_mut().enumerate() {
+        poke.line = i + 1;
+    }
+    if let Some(&(a, b)) = find_overlaps(&pokes).first() {
+        return Err(invalid_input(format!("pokes #{} and #{} overlap", a, b)));
+    }
+    let len = fs::metadata(file_path)?.len();
+    if let Some(poke) = pokes.iter().find(|p| p.end() > len) {
+        return Err(invalid_input(format!(
+            "poke #{} ({} bytes at {:#x}) goes past the end of the file ({} bytes)",
+            poke.line,
+            poke.bytes.len(),
+            poke.offset,
+            len
+        )));
+    }
+    pokes.sort_by_key(|p| p.offset);
+
+    if opts.dry_run {
+        for poke in &pokes {
+            let old = read_existing(file_path, poke.offset, poke.bytes.len())?;
+            print_plan(file_path, poke.offset, poke.bytes.len() as u64, &old, &poke.bytes);
+        }
+        return Ok(());
+    }
+
+    let spans: Vec<(u64, u64)> = pokes.iter().map(|p| (p.offset, p.bytes.len() as u64)).collect();
+    prepare_write(file_path, opts, &spans, len)?;
+
+    let mut target = WriteTarget::open(file_path, opts, OpenOptions::new().read(true).write(true))?;
+    let file = target.file();
+    let mut total_changed = 0;
+    for poke in &pokes {
+        let old = read_range(file, poke.offset, poke.bytes.len() as u64)?;
+        let changed = old.iter().zip(&poke.bytes).filter(|(a, b)| a != b).count();
+        write_at(file, poke.offset, &poke.bytes)?;
+        println!(
+            "Poke #{}: wrote {} bytes at {:#x} ({} changed)",
+            poke.line,
+            poke.bytes.len(),
+            poke.offset,
+            changed
+        );
+        total_changed += changed;
+    }
+    target.commit()?;
+    println!("Applied {} pokes, {} bytes changed.", pokes.len(), total_changed);
+    Ok(())
+}
+
+#[derive(clap::Args, Debug)]
+#[command(group(ArgGroup::new("source").args(["hex", "from"]).required(true)))]
+pub struct AppendArgs {
+    /// File to extend (created if missing)
+    #[arg(short, long = "file", value_name = "FILE")]
+    file: PathBuf,
+
+    /// Bytes to append, as hex
+    #[arg(value_name = "HEX")]
+    hex: Option<String>,
+
+    /// Append the contents of another file instead
+    #[arg(long, value_name = "FILE")]
+    from: Option<PathBuf>,
+
+    #[command(flatten)]
+    opts: WriteOptions,
+}
+
+/// Appends data at the end of the file (creating it if needed) and reports
+/// the offset where it landed.
+pub fn run_append(args: AppendArgs) -> io::Result<()> {
+    let file_path = args.file;
+    let (bytes, mut reader, len): (Vec<u8>, Option<File>, u64) = match (args.hex, args.from) {
+        (Some(hex_string), _) => {
+            let bytes = decode_hex(&hex_string).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            let len = bytes.len() as u64;
+            (bytes, None, len)
+        }
+        (None, Some(path)) => {
+            let file = File::open(&path)?;
+            let len = file.metadata()?.len();
+            (Vec::new(), Some(file), len)
+        }
+        (None, None) => unreachable!("clap requires HEX or --from"),
+    };
+
+    if args.opts.dry_run {
+        let end = file_len_or_zero(&file_path)?;
+        let mut preview = bytes;
+        if let Some(reader) = reader.as_mut() {
+            reader.take(DRY_RUN_PREVIEW as u64).read_to_end(&mut preview)?;
+        }
+        preview.truncate(DRY_RUN_PREVIEW);
+        print_plan(&file_path, end, len, &[], &preview);
+        return Ok(());
+    }
+
+    if len > 0 {
+        prepare_write(&file_path, &args.opts, &[], file_len_or_zero(&file_path)? + len)?;
+    }
+
+    let mut target = WriteTarget::open(&file_path, &args.opts, OpenOptions::new().append(true).create(true))?;
+    let file = target.file();
+    let written = match reader.as_mut() {
+        Some(reader) => io::copy(reader, file)?,
+        None => {
+            file.write_all(&bytes)?;
+            bytes.len() as u64
+        }
+    };
+    // In append mode the position after writing is the end of our data, even
+    // if another process extended the file in the meantime
+    let landed = file.stream_position()? - written;
+    target.commit()?;
+    println!("Appended {} bytes at offset {:#x}.", written, landed);
+    Ok(())
+}
+
+#[derive(clap::Args, Debug)]
+pub struct PatchArgs {
+    /// File to patch
+    #[arg(short, long = "file", value_name = "FILE")]
+    file: PathBuf,
+
+    /// Patch file of `OFFSET: HEX` lines
+    #[arg(value_name = "PATCH_FILE")]
+    patch_file: PathBuf,
+
+    /// Let later patch lines overwrite earlier overlapping ones (with a warning)
+    #[arg(long)]
+    allow_overlap: bool,
+
+    #[command(flatten)]
+    opts: WriteOptions,
+}
+
+/// Validates a whole patch file, then applies it through a single handle in
+/// file order, so with `--allow-overlap` the last line wins.
+pub fn run_patch(args: PatchArgs) -> io::Result<()> {
+    let file_path = args.file;
+    let patch_path = args.patch_file;
+    let text = fs::read_to_string(&patch_path)?;
+    let patches = parse_patch_file(&text).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", patch_path.display(), e),
+        )
+    })?;
+
+    let overlaps = find_overlaps(&patches);
+    for &(a, b) in &overlaps {
+        if !args.allow_overlap {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("patches on lines {} and {} overlap (use --allow-overlap)", a, b),
+            ));
+        }
+        eprintln!("Warning: patch on line {} overrides overlapping bytes from line {}", b, a);
+    }
+
+    if args.opts.dry_run {
+        for patch in &patches {
+            println!("Line {}:", patch.line);
+            let old = read_existing(&file_path, patch.offset, patch.bytes.len())?;
+            print_plan(&file_path, patch.offset, patch.bytes.len() as u64, &old, &patch.bytes);
+        }
+        return Ok(());
+    }
+
+    if !patches.is_empty() {
+        let spans: Vec<(u64, u64)> = patches.iter().map(|p| (p.offset, p.bytes.len() as u64)).collect();
+        let end = patches.iter().map(|p| p.end()).max().unwrap_or(0);
+        prepare_write(&file_path, &args.opts, &spans, file_len_or_zero(&file_path)?.max(end))?;
+    }
+
+    let mut target = WriteTarget::open(&file_path, &args.opts, OpenOptions::new().read(true).write(true))?;
+    let file = target.file();
+    let mut total_changed = 0;
+    for patch in &patches {
+        let old = read_range(file, patch.offset, patch.bytes.len() as u64)?;
+        let unchanged = old.iter().zip(&patch.bytes).filter(|(a, b)| a == b).count();
+        let changed = patch.bytes.len() - unchanged;
+
+        write_at(file, patch.offset, &patch.bytes)?;
+        println!(
+            "Line {}: wrote {} bytes at {:#x} ({} changed)",
+            patch.line,
+            patch.bytes.len(),
+            patch.offset,
+            changed
+        );
+        total_changed += changed;
+    }
+    target.commit()?;
+    println!(
+        "Applied {} patches, {} bytes changed.",
+        patches.len(),
+        total_changed
+    );
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use crate::hexstr::decode_hex;
+use crate::number::parse_offset;
+use crate::CHUNK_SIZE;
+use std::fmt::Write as _;
+use std::io::{self, Read};
+
+/// First line of every binary patch; bump the number when the format changes.
+const HEADER: &str = "hex_tool-patch 1";
+
+/// Differences separated by at most this many equal bytes share a record,
+/// which is shorter than starting a new one.
+const MERGE_GAP: usize = 8;
+
+/// One changed run: the bytes at `offset` in the old file, and what replaces
+/// them. `old` is shorter than `new` where the run extends past the old end.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeltaRecord {
+    pub offset: u64,
+    pub old: Vec<u8>,
+    pub new: Vec<u8>,
+}
+
+/// Everything needed to turn one file into another, and to check that the
+/// patch is applied to the right file and produced the right result.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Delta {
+    pub old_size: u64,
+    pub old_crc: u32,
+    pub new_size: u64,
+    pub new_crc: u32,
+    pub records: Vec<DeltaRecord>,
+}
+
+/// Length and CRC-32 of everything `input` yields.
+pub fn checksum(input: &mut dyn Read) -> io::Result<(u64, u32)> {
+    let mut hasher = crc32fast::Hasher::new();
+    let mut chunk = vec![0; CHUNK_SIZE];
+    let mut len = 0u64;
+    loop {
+        let n = input.read(&mut chunk)?;
+        if n == 0 {
+            return Ok((len, hasher.finalize()));
+        }
+        hasher.update(&chunk[..n]);
+        len += n as u64;
+    }
+}
+
+/// Reads until `buffer` is full or the input ends.
+fn read_full(input: &mut dyn Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match input.read(&mut buffer[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+/// Compares two streams offset by offset and collects the changed runs.
+/// Both are read a chunk at a time; only the differences are kept.
+pub fn diff(old: &mut dyn Read, new: &mut dyn Read) -> io::Result<Delta> {
+    let mut old_hasher = crc32fast::Hasher::new();
+    let mut new_hasher = crc32fast::Hasher::new();
+    let mut old_chunk = vec![0; CHUNK_SIZE];
+    let mut new_chunk = vec![0; CHUNK_SIZE];
+    let (mut old_size, mut new_size) = (0u64, 0u64);
+
+    let mut records = Vec::new();
+    let mut current: Option<DeltaRecord> = None;
+    // Equal bytes seen since the last difference of `current`
+    let mut gap = Vec::new();
+    loop {
+        let old_n = read_full(old, &mut old_chunk)?;
+        let new_n = read_full(new, &mut new_chunk)?;
+        if old_n == 0 && new_n == 0 {
+            break;
+        }
+        old_hasher.update(&old_chunk[..old_n]);
+        new_hasher.update(&new_chunk[..new_n]);
+
+        // Bytes past the end of the new file need no record; the size in
+        // the header truncates them
+        for (i, &after) in new_chunk[..new_n].iter().enumerate() {
+            let pos = new_size + i as u64;
+            let before = old_chunk[..old_n].get(i).copied();
+            if before == Some(after) {
+                if current.is_some() {
+                    gap.push(after);
+                    if gap.len() > MERGE_GAP {
+                        records.extend(current.take());
+                        gap.clear();
+                    }
+                }
+                continue;
+            }
+            let record = current.get_or_insert_with(|| DeltaRecord {
+                offset: pos,
+                old: Vec::new(),
+                new: Vec::new(),
+            });
+            record.old.extend_from_slice(&gap);
+            record.new.extend_from_slice(&gap);
+            gap.clear();
+            record.old.extend(before);
+            record.new.push(after);
+        }
+        old_size += old_n as u64;
+        new_size += new_n as u64;
+    }
+    records.extend(current);
+
+    Ok(Delta {
+        old_size,
+        old_crc: old_hasher.finalize(),
+        new_size,
+        new_crc: new_hasher.finalize(),
+        records,
+    })
+}
+
+impl Delta {
+    /// Renders the patch as text: a header with both files' sizes and
+    /// checksums, then one `change OFFSET OLDHEX NEWHEX` line per record
+    /// (`-` for no old bytes).
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{}\n", HEADER);
+        let _ = writeln!(text, "old {} {:08x}", self.old_size, self.old_crc);
+        let _ = writeln!(text, "new {} {:08x}", self.new_size, self.new_crc);
+        for record in &self.records {
+            let old = if record.old.is_empty() {
+                "-".to_string()
+            } else {
+                hex::encode(&record.old)
+            };
+            let _ = writeln!(text, "change {:#x} {} {}", record.offset, old, hex::encode(&record.new));
+        }
+        text
+    }
+
+    /// Parses text written by `to_text`. Errors name the offending line.
+    pub fn parse(text: &str) -> Result<Delta, String> {
+        let mut lines = text.lines().enumerate();
+        match lines.next() {
+            Some((_, HEADER)) => {}
+            _ => return Err(format!("line 1: not a patch of a supported version (expected '{}')", HEADER)),
+        }
+
+        let mut sizes = [None, None];
+        let mut records: Vec<DeltaRecord> = Vec::new();
+        for (index, line) in lines {
+            let bad = |what: String| format!("line {}: {}", index + 1, what);
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                [] => continue,
+                [side @ ("old" | "new"), size, crc] => {
+                    let size = parse_offset(size).map_err(|e| bad(e.to_string()))?;
+                    let crc = u32::from_str_radix(crc, 16).map_err(|_| bad(format!("invalid checksum '{}'", crc)))?;
+                    sizes[usize::from(*side == "new")] = Some((size, crc));
+                }
+                ["change", offset, old, new] => {
+                    let offset = parse_offset(offset).map_err(|e| bad(format!("invalid offset: {}", e)))?;
+                    let old = match *old {
+                        "-" => Vec::new(),
+                        hex_str => decode_hex(hex_str).map_err(|e| bad(format!("old bytes: {}", e)))?,
+                    };
+                    let new = decode_hex(new).map_err(|e| bad(format!("new bytes: {}", e)))?;
+                    if new.is_empty() || old.len() > new.len() {
+                        return Err(bad("a change needs new bytes, at least as many as old ones".to_string()));
+                    }
+                    if records.last().is_some_and(|r| offset < r.offset + r.new.len() as u64) {
+                        return Err(bad("changes must be in ascending order without overlaps".to_string()));
+                    }
+                    records.push(DeltaRecord { offset, old, new });
+                }
+                _ => return Err(bad(format!("unrecognized entry '{}'", line))),
+            }
+        }
+
+        let [Some((old_size, old_crc)), Some((new_size, new_crc))] = sizes else {
+            return Err("patch is missing its 'old' or 'new' line".to_string());
+        };
+        Ok(Delta {
+            old_size,
+            old_crc,
+            new_size,
+            new_crc,
+            records,
+        })
+    }
+}
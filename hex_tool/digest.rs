@@ -0,0 +1,77 @@
+use crate::value::Endian;
+use clap::ValueEnum;
+use sha2::{Digest as _, Sha256};
+
+/// Checksum or hash algorithm for `seal`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Algorithm {
+    /// CRC-32 (IEEE, as used by zip and PNG), 4 bytes
+    Crc32,
+    /// CRC-16/CCITT-FALSE (poly 0x1021, init 0xffff), 2 bytes
+    Crc16,
+    /// SHA-256, 32 bytes, optionally truncated to its leading bytes
+    Sha256,
+}
+
+impl Algorithm {
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Crc32 => "crc32",
+            Algorithm::Crc16 => "crc16",
+            Algorithm::Sha256 => "sha256",
+        }
+    }
+
+    /// Length of the full digest in bytes.
+    pub fn digest_len(self) -> usize {
+        match self {
+            Algorithm::Crc32 => 4,
+            Algorithm::Crc16 => 2,
+            Algorithm::Sha256 => 32,
+        }
+    }
+
+    pub fn hasher(self) -> Hasher {
+        match self {
+            Algorithm::Crc32 => Hasher::Crc32(crc32fast::Hasher::new()),
+            Algorithm::Crc16 => Hasher::Crc16(0xffff),
+            Algorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+        }
+    }
+}
+
+/// A running digest of one of the supported algorithms.
+pub enum Hasher {
+    Crc32(crc32fast::Hasher),
+    Crc16(u16),
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    pub fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Hasher::Crc32(hasher) => hasher.update(bytes),
+            Hasher::Crc16(crc) => {
+                for &byte in bytes {
+                    *crc ^= u16::from(byte) << 8;
+                    for _ in 0..8 {
+                        *crc = if *crc & 0x8000 != 0 { *crc << 1 ^ 0x1021 } else { *crc << 1 };
+                    }
+                }
+            }
+            Hasher::Sha256(hasher) => hasher.update(bytes),
+        }
+    }
+
+    /// The digest as it should be stored: CRCs in the given byte order,
+    /// hashes in their usual byte sequence.
+    pub fn finish(self, endian: Endian) -> Vec<u8> {
+        match (self, endian) {
+            (Hasher::Crc32(hasher), Endian::Le) => hasher.finalize().to_le_bytes().to_vec(),
+            (Hasher::Crc32(hasher), Endian::Be) => hasher.finalize().to_be_bytes().to_vec(),
+            (Hasher::Crc16(crc), Endian::Le) => crc.to_le_bytes().to_vec(),
+            (Hasher::Crc16(crc), Endian::Be) => crc.to_be_bytes().to_vec(),
+            (Hasher::Sha256(hasher), _) => hasher.finalize().to_vec(),
+        }
+    }
+}
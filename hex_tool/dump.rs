@@ -0,0 +1,354 @@
+use clap::ValueEnum;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+/// Bytes shown on each dump line.
+pub const BYTES_PER_LINE: usize = 16;
+
+/// Number base used for the bytes in the dump body.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Radix {
+    /// Two hex digits per byte
+    #[default]
+    Hex,
+    /// Three octal digits per byte, like od
+    Oct,
+    /// Zero-padded three-digit decimal
+    Dec,
+    /// Eight binary digits per byte
+    Bin,
+}
+
+impl Radix {
+    /// Column width of one rendered byte.
+    pub fn width(self) -> usize {
+        match self {
+            Radix::Hex => 2,
+            Radix::Oct | Radix::Dec => 3,
+            Radix::Bin => 8,
+        }
+    }
+
+    fn render(self, out: &mut String, byte: u8) {
+        let _ = match self {
+            Radix::Hex => write!(out, "{:02x}", byte),
+            Radix::Oct => write!(out, "{:03o}", byte),
+            Radix::Dec => write!(out, "{:03}", byte),
+            Radix::Bin => write!(out, "{:08b}", byte),
+        };
+    }
+}
+
+/// Number base for the offset column.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OffsetRadix {
+    /// Eight hex digits
+    #[default]
+    Hex,
+    /// Eight zero-padded decimal digits
+    Dec,
+}
+
+impl OffsetRadix {
+    pub fn render(self, offset: u64) -> String {
+        match self {
+            OffsetRadix::Hex => format!("{:08x}", offset),
+            OffsetRadix::Dec => format!("{:08}", offset),
+        }
+    }
+}
+
+/// How the right-hand gutter renders bytes as text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Charset {
+    /// One column per byte; printable ASCII as itself, anything else as `.`
+    #[default]
+    Ascii,
+    /// Like ascii, plus the printable ISO-8859-1 characters 0xa1-0xff
+    Latin1,
+    /// One column per byte pair, decoded as a UTF-16LE code unit. Surrogates
+    /// and non-printable units show as `.`, as does a trailing odd byte
+    Utf16le,
+}
+
+impl Charset {
+    fn render(self, out: &mut String, chunk: &[u8]) {
+        out.extend(chunk.chunks(self.unit_len()).map(|unit| self.render_unit(unit)));
+    }
+
+    /// The gutter text for `chunk`.
+    pub fn text(self, chunk: &[u8]) -> String {
+        let mut text = String::new();
+        self.render(&mut text, chunk);
+        text
+    }
+
+    /// Bytes shown by one gutter column.
+    fn unit_len(self) -> usize {
+        match self {
+            Charset::Utf16le => 2,
+            Charset::Ascii | Charset::Latin1 => 1,
+        }
+    }
+
+    fn render_unit(self, unit: &[u8]) -> char {
+        match (self, unit) {
+            (Charset::Ascii, &[b]) => ascii_char(b),
+            // 0xad is a soft hyphen, invisible in most terminals
+            (Charset::Latin1, &[b @ 0xa1..=0xff]) if b != 0xad => char::from(b),
+            (Charset::Latin1, &[b]) => ascii_char(b),
+            (Charset::Utf16le, &[lo, hi]) => char::from_u32(u32::from(u16::from_le_bytes([lo, hi])))
+                .filter(|c| !c.is_control() && (*c == ' ' || !c.is_whitespace()))
+                .unwrap_or('.'),
+            _ => '.',
+        }
+    }
+}
+
+fn ascii_char(byte: u8) -> char {
+    if (32..=126).contains(&byte) {
+        byte as char
+    } else {
+        '.'
+    }
+}
+
+/// When to emit ANSI colors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when writing to a terminal
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Auto => is_terminal,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+const RED: &str = "\x1b[1;31m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// Colors for highlights, picked by `Highlight::color` (cycling).
+const HIGHLIGHT_COLORS: [&str; 6] = [RED, "\x1b[1;32m", "\x1b[1;33m", "\x1b[1;34m", "\x1b[1;35m", "\x1b[1;36m"];
+
+/// Bytes `start..end`, by the offsets shown in the dump, to call out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Highlight {
+    pub start: u64,
+    pub end: u64,
+    /// Index into the highlight palette
+    pub color: usize,
+}
+
+/// Presentation settings for the hexdump.
+#[derive(Clone, Debug, Default)]
+pub struct DumpStyle {
+    pub radix: Radix,
+    pub offset_radix: OffsetRadix,
+    pub charset: Charset,
+    /// Collapse runs of identical lines into a single `*`
+    pub squeeze: bool,
+    /// Ranges to call out, sorted by start. With color they are colored in
+    /// both columns; without, runs are bracketed in the byte columns
+    pub highlights: Vec<Highlight>,
+    pub color: bool,
+    /// Print a header labeling the columns above the dump
+    pub ruler: bool,
+    /// Repeat the header after this many lines (0: only at the top)
+    pub ruler_every: usize,
+    /// Names printed after the gutter of the line holding each offset,
+    /// sorted by offset
+    pub labels: Vec<(u64, String)>,
+}
+
+impl DumpStyle {
+    /// The labels placed on the line of `len` bytes starting at `line_offset`.
+    fn labels_in(&self, line_offset: u64, len: usize) -> &[(u64, String)] {
+        let from = self.labels.partition_point(|(offset, _)| *offset < line_offset);
+        let to = self.labels.partition_point(|(offset, _)| *offset < line_offset + len as u64);
+        &self.labels[from..to]
+    }
+
+    /// The highlight covering `offset`, if any.
+    fn highlight_at(&self, offset: u64) -> Option<&Highlight> {
+        let after = self.highlights.partition_point(|h| h.start <= offset);
+        self.highlights[..after].last().filter(|h| offset < h.end)
+    }
+
+    fn paint(&self, out: &mut String, highlight: Option<&Highlight>, text: &str) {
+        match highlight.filter(|_| self.color) {
+            Some(h) => {
+                let _ = write!(out, "{}{}{}", HIGHLIGHT_COLORS[h.color % HIGHLIGHT_COLORS.len()], text, RESET);
+            }
+            None => out.push_str(text),
+        }
+    }
+}
+
+/// Renders one dump line: offset, byte columns padded to a full line so the
+/// gutter stays aligned, and the ASCII gutter.
+pub fn format_line(chunk: &[u8], line_offset: u64, style: &DumpStyle) -> String {
+    let width = style.radix.width();
+    let marks: Vec<Option<&Highlight>> = (0..chunk.len())
+        .map(|i| style.highlight_at(line_offset + i as u64))
+        .collect();
+    // Without color, the space before a highlighted run becomes `[` and the
+    // one after it `]`, which keeps every column in place
+    let bracket = |before: bool, after: bool| match (style.color, before, after) {
+        (false, false, true) => '[',
+        (false, true, false) => ']',
+        _ => ' ',
+    };
+
+    let mut line = format!("{}:", style.offset_radix.render(line_offset));
+    let mut previous = false;
+    let mut text = String::new();
+    for (&byte, mark) in chunk.iter().zip(&marks) {
+        line.push(bracket(previous, mark.is_some()));
+        text.clear();
+        style.radix.render(&mut text, byte);
+        style.paint(&mut line, *mark, &text);
+        previous = mark.is_some();
+    }
+    line.push(bracket(previous, false));
+    // Add padding for the last line
+    for _ in chunk.len()..BYTES_PER_LINE {
+        line.push_str(&" ".repeat(width + 1));
+    }
+    line.push('|');
+    let unit_len = style.charset.unit_len();
+    for (i, unit) in chunk.chunks(unit_len).enumerate() {
+        let mark = marks[i * unit_len..i * unit_len + unit.len()].iter().find_map(|m| *m);
+        style.paint(&mut line, mark, style.charset.render_unit(unit).encode_utf8(&mut [0; 4]));
+    }
+    line.push('|');
+    let labels: Vec<&str> = style.labels_in(line_offset, chunk.len()).iter().map(|(_, name)| name.as_str()).collect();
+    if !labels.is_empty() {
+        let _ = write!(line, "  <- {}", labels.join(", "));
+    }
+    line
+}
+
+/// Renders the column header: each byte column labeled with its position in
+/// the line, in the offset column's radix, and the gutter labeled with the
+/// last digit of each column's position.
+pub fn format_ruler(style: &DumpStyle) -> String {
+    let width = style.radix.width();
+    let offset_width = style.offset_radix.render(0).len();
+    let mut line = " ".repeat(offset_width + 1);
+    for i in 0..BYTES_PER_LINE {
+        let label = match style.offset_radix {
+            OffsetRadix::Hex => format!("{:02x}", i),
+            OffsetRadix::Dec => format!("{:02}", i),
+        };
+        let _ = write!(line, " {:>width$}", label, width = width);
+    }
+    line.push_str("  ");
+    for i in (0..BYTES_PER_LINE).step_by(style.charset.unit_len()) {
+        let digit = match style.offset_radix {
+            OffsetRadix::Hex => char::from_digit(i as u32 % 16, 16),
+            OffsetRadix::Dec => char::from_digit(i as u32 % 10, 10),
+        };
+        line.push(digit.expect("digit is below the radix"));
+    }
+    line
+}
+
+/// Renders a whole buffer, labeling its first byte with `offset`. With squeezing, a line identical to the one
+/// before it is replaced by `*` (once per run); a run that reaches the end
+/// of the buffer is followed by the end offset so the length stays visible.
+pub fn format_dump(buffer: &[u8], offset: u64, style: &DumpStyle) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut previous: Option<&[u8]> = None;
+    let mut squeezing = false;
+    for (i, chunk) in buffer.chunks(BYTES_PER_LINE).enumerate() {
+        let line_offset = offset + (i * BYTES_PER_LINE) as u64;
+        let highlighted = (0..chunk.len() as u64).any(|k| style.highlight_at(line_offset + k).is_some())
+            || !style.labels_in(line_offset, chunk.len()).is_empty();
+        if style.squeeze && previous == Some(chunk) && !highlighted {
+            if !squeezing {
+                lines.push("*".to_string());
+                squeezing = true;
+            }
+            continue;
+        }
+        squeezing = false;
+        previous = Some(chunk);
+        lines.push(format_line(chunk, line_offset, style));
+    }
+    if squeezing {
+        lines.push(style.offset_radix.render(offset + buffer.len() as u64));
+    }
+    if style.ruler {
+        let ruler = format_ruler(style);
+        let every = if style.ruler_every == 0 { usize::MAX } else { style.ruler_every };
+        let mut with_ruler = Vec::with_capacity(lines.len() + lines.len() / every.max(1) + 1);
+        for (i, line) in lines.into_iter().enumerate() {
+            if i % every == 0 {
+                with_ruler.push(ruler.clone());
+            }
+            with_ruler.push(line);
+        }
+        return with_ruler;
+    }
+    lines
+}
+
+/// Renders a whole buffer as text, one newline-terminated line per entry of
+/// `format_dump`.
+pub fn format_hexdump(buffer: &[u8], offset: u64, style: &DumpStyle) -> String {
+    let mut text = String::new();
+    for line in format_dump(buffer, offset, style) {
+        text.push_str(&line);
+        text.push('\n');
+    }
+    text
+}
+
+pub fn write_dump(out: &mut dyn Write, buffer: &[u8], offset: u64, style: &DumpStyle) -> io::Result<()> {
+    out.write_all(format_hexdump(buffer, offset, style).as_bytes())
+}
+
+/// Prints a dump in the default style.
+pub fn print_hexdump(buffer: &[u8], offset: u64) {
+    print!("{}", format_hexdump(buffer, offset, &DumpStyle::default()));
+}
+
+/// Renders one line of a comparison dump: the target bytes, then the
+/// reference bytes at the same offsets, then the target's ASCII gutter.
+/// Bytes missing on either side show as `--`. Differing bytes are red (or
+/// marked with `!` without color) and matching bytes are dimmed.
+pub fn format_compare_line(line_offset: u64, target: &[u8], reference: &[u8], color: bool) -> String {
+    let columns = target.len().max(reference.len());
+    let mut line = format!("{:08x}: ", line_offset);
+    for side in [target, reference] {
+        for i in 0..BYTES_PER_LINE {
+            if i >= columns {
+                line.push_str("   ");
+                continue;
+            }
+            let differs = target.get(i) != reference.get(i);
+            let text = side.get(i).map_or_else(|| "--".to_string(), |b| format!("{:02x}", b));
+            match (color, differs) {
+                (true, true) => line.push_str(&format!("{}{}{} ", RED, text, RESET)),
+                (true, false) => line.push_str(&format!("{}{}{} ", DIM, text, RESET)),
+                (false, true) => line.push_str(&format!("{}!", text)),
+                (false, false) => line.push_str(&format!("{} ", text)),
+            }
+        }
+        line.push(' ');
+    }
+    line.push('|');
+    Charset::Ascii.render(&mut line, target);
+    line.push('|');
+    line
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+
+/// Why a user-supplied hex string was rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HexError {
+    /// A character that is neither a hex digit nor a separator, with its
+    /// position in the cleaned-up string
+    InvalidChar { ch: char, position: usize },
+    /// The digits do not pair up into whole bytes
+    OddLength(usize),
+}
+
+impl fmt::Display for HexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HexError::InvalidChar { ch, position } => write!(
+                f,
+                "invalid hex character {:?} at position {} (after removing separators)",
+                ch, position
+            ),
+            HexError::OddLength(n) => write!(f, "odd number of hex digits ({})", n),
+        }
+    }
+}
+
+impl std::error::Error for HexError {}
+
+/// Decodes hex the way people paste it: `DE AD BE EF`, `de:ad:be:ef`,
+/// `de,ad`, `0xDEADBEEF` or dumps with line breaks. Whitespace, colons and
+/// commas are dropped and each group may carry a `0x` prefix.
+pub fn decode_hex(input: &str) -> Result<Vec<u8>, HexError> {
+    let mut digits = String::with_capacity(input.len());
+    for group in input.split(|c: char| c.is_whitespace() || c == ':' || c == ',') {
+        let group = group
+            .strip_prefix("0x")
+            .or_else(|| group.strip_prefix("0X"))
+            .unwrap_or(group);
+        digits.push_str(group);
+    }
+
+    if let Some((position, ch)) = digits.chars().enumerate().find(|(_, c)| !c.is_ascii_hexdigit()) {
+        return Err(HexError::InvalidChar { ch, position });
+    }
+    if !digits.len().is_multiple_of(2) {
+        return Err(HexError::OddLength(digits.len()));
+    }
+
+    Ok(digits
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            // Both characters were validated as ASCII hex digits above
+            let text = std::str::from_utf8(pair).expect("hex digits are ASCII");
+            u8::from_str_radix(text, 16).expect("valid hex pair")
+        })
+        .collect())
+}
+
+/// Parses a single hex byte such as `ff` or `0xff`.
+pub fn parse_byte(byte_str: &str) -> Result<u8, String> {
+    match decode_hex(byte_str).map_err(|e| e.to_string())?.as_slice() {
+        [byte] => Ok(*byte),
+        bytes => Err(format!("expected exactly one byte, found {}", bytes.len())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pasted_forms_decode_the_same() {
+        let expected = vec![0xde, 0xad, 0xbe, 0xef];
+        for input in [
+            "deadbeef",
+            "DEADBEEF",
+            "DE AD BE EF",
+            "de:ad:be:ef",
+            "de,ad,be,ef",
+            "de, ad, be, ef",
+            "0xDEADBEEF",
+            "0Xdeadbeef",
+            "0xde 0xad 0xbe 0xef",
+            "0xde,0xad,0xbe,0xef",
+            "  dead\n\tbeef\r\n",
+            "de\nad:be, 0xef",
+        ] {
+            assert_eq!(decode_hex(input), Ok(expected.clone()), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn empty_input_is_no_bytes() {
+        assert_eq!(decode_hex(""), Ok(Vec::new()));
+        assert_eq!(decode_hex(" : , \n"), Ok(Vec::new()));
+        assert_eq!(decode_hex("0x"), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn invalid_characters_are_reported_at_their_position_in_the_digits() {
+        assert_eq!(decode_hex("zz"), Err(HexError::InvalidChar { ch: 'z', position: 0 }));
+        // Separators and prefixes do not count towards the position
+        assert_eq!(decode_hex("de ad 0xbg"), Err(HexError::InvalidChar { ch: 'g', position: 5 }));
+        assert_eq!(decode_hex("de-ad"), Err(HexError::InvalidChar { ch: '-', position: 2 }));
+        // A 0x prefix is only recognized at the start of a group
+        assert_eq!(decode_hex("de0xad"), Err(HexError::InvalidChar { ch: 'x', position: 3 }));
+        assert_eq!(decode_hex("dé"), Err(HexError::InvalidChar { ch: 'é', position: 1 }));
+        assert_eq!(
+            decode_hex("0xde 0xag").unwrap_err().to_string(),
+            "invalid hex character 'g' at position 3 (after removing separators)"
+        );
+    }
+
+    #[test]
+    fn digits_must_pair_up_across_groups() {
+        assert_eq!(decode_hex("abc"), Err(HexError::OddLength(3)));
+        assert_eq!(decode_hex("a b c"), Err(HexError::OddLength(3)));
+        // Groups need not be whole bytes as long as the total is even
+        assert_eq!(decode_hex("a bc d"), Ok(vec![0xab, 0xcd]));
+        assert_eq!(decode_hex("0xf").unwrap_err().to_string(), "odd number of hex digits (1)");
+    }
+
+    #[test]
+    fn parse_byte_takes_exactly_one() {
+        assert_eq!(parse_byte("ff"), Ok(0xff));
+        assert_eq!(parse_byte("0x0A"), Ok(0x0a));
+        assert_eq!(parse_byte("ff00"), Err("expected exactly one byte, found 2".to_string()));
+        assert_eq!(parse_byte(""), Err("expected exactly one byte, found 0".to_string()));
+        assert!(parse_byte("f").is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use crate::CHUNK_SIZE;
+use std::io::{self, Read};
+
+/// How often each byte value occurs in a stream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Histogram {
+    pub counts: [u64; 256],
+}
+
+impl Histogram {
+    /// Counts every byte `input` yields, a chunk at a time.
+    pub fn from_reader(input: &mut dyn Read) -> io::Result<Histogram> {
+        let mut counts = [0u64; 256];
+        let mut chunk = vec![0; CHUNK_SIZE];
+        loop {
+            let n = input.read(&mut chunk)?;
+            if n == 0 {
+                return Ok(Histogram { counts });
+            }
+            for &byte in &chunk[..n] {
+                counts[usize::from(byte)] += 1;
+            }
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Bytes in the printable ASCII range 0x20-0x7e.
+    pub fn printable(&self) -> u64 {
+        self.counts[0x20..=0x7e].iter().sum()
+    }
+
+    /// Shannon entropy in bits per byte: 0 when one value makes up the
+    /// whole stream (or it is empty), 8 when all 256 are equally common.
+    pub fn entropy(&self) -> f64 {
+        let total = self.total() as f64;
+        self.counts
+            .iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f64 / total;
+                -p * p.log2()
+            })
+            .sum()
+    }
+
+    /// The `n` most frequent byte values that occur at all, most frequent
+    /// first; ties go to the lower value.
+    pub fn top(&self, n: usize) -> Vec<(u8, u64)> {
+        let mut values: Vec<(u8, u64)> = (0..=255u8)
+            .map(|b| (b, self.counts[usize::from(b)]))
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        values.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        values.truncate(n);
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn histogram_of(bytes: &[u8]) -> Histogram {
+        Histogram::from_reader(&mut &bytes[..]).unwrap()
+    }
+
+    #[test]
+    fn one_of_each_byte_value() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let histogram = histogram_of(&bytes);
+        assert_eq!(histogram.counts, [1; 256]);
+        assert_eq!(histogram.total(), 256);
+        assert_eq!(histogram.printable(), 0x7f - 0x20);
+        assert_eq!(histogram.entropy(), 8.0);
+        assert_eq!(histogram.top(3), [(0, 1), (1, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn counts_span_chunks() {
+        let mut bytes = vec![b'a'; CHUNK_SIZE * 2 + 3];
+        bytes.extend_from_slice(b"bbc");
+        let histogram = histogram_of(&bytes);
+        assert_eq!(histogram.counts[usize::from(b'a')], (CHUNK_SIZE * 2 + 3) as u64);
+        assert_eq!(histogram.top(5), [(b'a', (CHUNK_SIZE * 2 + 3) as u64), (b'b', 2), (b'c', 1)]);
+    }
+
+    #[test]
+    fn entropy_of_uniform_and_skewed_streams() {
+        assert_eq!(histogram_of(b"").entropy(), 0.0);
+        assert_eq!(histogram_of(b"aaaa").entropy(), 0.0);
+        assert_eq!(histogram_of(b"abab").entropy(), 1.0);
+        assert_eq!(histogram_of(b"abcd").entropy(), 2.0);
+        // p = 3/4 and 1/4
+        let expected = -(0.75f64 * 0.75f64.log2() + 0.25 * 0.25f64.log2());
+        assert!((histogram_of(b"aaab").entropy() - expected).abs() < 1e-12);
+    }
+}
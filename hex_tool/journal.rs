@@ -0,0 +1,221 @@
+use crate::hexstr::decode_hex;
+use crate::number::parse_offset;
+use crate::{file_len_or_zero, CHUNK_SIZE};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// First line of every journal; bump the number when the format changes.
+const HEADER: &str = "hex_tool-journal 1";
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Writes journal lines while keeping a running CRC-32 of everything
+/// written, which becomes the final `crc32` line.
+struct JournalWriter {
+    out: BufWriter<File>,
+    crc: crc32fast::Hasher,
+}
+
+impl JournalWriter {
+    fn line(&mut self, text: &str) -> io::Result<()> {
+        self.crc.update(text.as_bytes());
+        self.crc.update(b"\n");
+        writeln!(self.out, "{}", text)
+    }
+}
+
+/// Records the current contents of `ranges` (offset, length) of `target`,
+/// plus its size before and after the pending operation, so `undo` can put
+/// them back. The journal is synced to disk before returning, which callers
+/// do before modifying the target. An existing journal is never overwritten.
+pub fn write_journal(journal: &Path, target: &Path, ranges: &[(u64, u64)], size_after: u64) -> io::Result<()> {
+    let size_before = file_len_or_zero(target)?;
+
+    let file = OpenOptions::new().write(true).create_new(true).open(journal).map_err(|e| {
+        io::Error::new(e.kind(), format!("cannot create journal {}: {}", journal.display(), e))
+    })?;
+    let mut writer = JournalWriter {
+        out: BufWriter::new(file),
+        crc: crc32fast::Hasher::new(),
+    };
+    writer.line(HEADER)?;
+    writer.line(&format!("size-before {}", size_before))?;
+    writer.line(&format!("size-after {}", size_after))?;
+
+    let mut source = if size_before > 0 { Some(File::open(target)?) } else { None };
+    let mut chunk = vec![0; CHUNK_SIZE];
+    for &(offset, len) in ranges {
+        // Only bytes that exist today need restoring; anything past the
+        // current end is removed by truncating back to size-before
+        let len = len.min(size_before.saturating_sub(offset));
+        let Some(source) = source.as_mut().filter(|_| len > 0) else {
+            continue;
+        };
+        // One line per chunk, so neither side holds more than a chunk
+        source.seek(SeekFrom::Start(offset))?;
+        let mut done = 0;
+        while done < len {
+            let n = (len - done).min(CHUNK_SIZE as u64) as usize;
+            source.read_exact(&mut chunk[..n])?;
+            writer.line(&format!("range {:#x} {}", offset + done, hex::encode(&chunk[..n])))?;
+            done += n as u64;
+        }
+    }
+
+    let crc = writer.crc.clone().finalize();
+    writeln!(writer.out, "crc32 {:08x}", crc)?;
+    writer.out.into_inner().map_err(|e| e.into_error())?.sync_all()
+}
+
+/// What a journal records about the target: its size around the journaled
+/// operation and how much of it the original bytes cover.
+pub struct Journal {
+    pub size_before: u64,
+    pub size_after: u64,
+    /// Runs of adjacent `range` lines
+    pub ranges: usize,
+    pub bytes: u64,
+}
+
+/// Parses and checksums a journal written by `write_journal`, passing the
+/// original bytes of each `range` line to `restore` as it is read. Nothing
+/// is held beyond one line, so callers that apply the bytes should first
+/// make a pass that restores nothing, to check the whole journal is intact.
+pub fn read_journal(
+    path: &Path,
+    mut restore: impl FnMut(u64, &[u8]) -> io::Result<()>,
+) -> io::Result<Journal> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut crc = crc32fast::Hasher::new();
+    let mut journal = Journal {
+        size_before: 0,
+        size_after: 0,
+        ranges: 0,
+        bytes: 0,
+    };
+    let mut run_end = None;
+    let mut verified = false;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_no = index + 1;
+        let bad = |what: &str| invalid_data(format!("{} line {}: {}", path.display(), line_no, what));
+
+        if verified {
+            return Err(bad("unexpected data after checksum"));
+        }
+        if index == 0 {
+            if line != HEADER {
+                return Err(bad(&format!("not a journal of a supported version (expected '{}')", HEADER)));
+            }
+        } else if let Some(expected) = line.strip_prefix("crc32 ") {
+            let actual = format!("{:08x}", crc.clone().finalize());
+            if expected != actual {
+                return Err(invalid_data(format!(
+                    "{} is corrupt: checksum {} does not match contents ({})",
+                    path.display(),
+                    expected,
+                    actual
+                )));
+            }
+            verified = true;
+            continue;
+        } else if let Some(value) = line.strip_prefix("size-before ") {
+            journal.size_before = parse_offset(value).map_err(|e| bad(&e.to_string()))?;
+        } else if let Some(value) = line.strip_prefix("size-after ") {
+            journal.size_after = parse_offset(value).map_err(|e| bad(&e.to_string()))?;
+        } else if let Some(rest) = line.strip_prefix("range ") {
+            let (offset, bytes) = rest.split_once(' ').ok_or_else(|| bad("expected 'range OFFSET HEX'"))?;
+            let offset = parse_offset(offset).map_err(|e| bad(&e.to_string()))?;
+            let bytes = decode_hex(bytes).map_err(|e| bad(&e.to_string()))?;
+            let end = offset
+                .checked_add(bytes.len() as u64)
+                .ok_or_else(|| bad("range ends past the largest 64-bit offset"))?;
+            if run_end != Some(offset) {
+                journal.ranges += 1;
+            }
+            run_end = Some(end);
+            journal.bytes += bytes.len() as u64;
+            restore(offset, &bytes)?;
+        } else {
+            return Err(bad("unrecognized entry"));
+        }
+        crc.update(line.as_bytes());
+        crc.update(b"\n");
+    }
+
+    if !verified {
+        return Err(invalid_data(format!("{} is truncated: missing checksum", path.display())));
+    }
+    Ok(journal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hex_tool-journal-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn large_ranges_are_written_a_chunk_per_line() {
+        let dir = scratch("chunks");
+        let target = dir.join("target.bin");
+        let contents: Vec<u8> = (0..3 * CHUNK_SIZE as u32 + 100).map(|i| (i % 251) as u8).collect();
+        fs::write(&target, &contents).unwrap();
+        let journal = dir.join("journal");
+        let ranges = [(10, 2 * CHUNK_SIZE as u64 + 5), (3 * CHUNK_SIZE as u64, 1000)];
+        write_journal(&journal, &target, &ranges, contents.len() as u64).unwrap();
+
+        let text = fs::read_to_string(&journal).unwrap();
+        let lines: Vec<&str> = text.lines().filter(|l| l.starts_with("range ")).collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines.iter().all(|l| l.rsplit(' ').next().unwrap().len() <= 2 * CHUNK_SIZE));
+
+        let mut seen = Vec::new();
+        let read = read_journal(&journal, |offset, bytes| {
+            seen.push((offset, bytes.to_vec()));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!((read.ranges, read.bytes), (2, 2 * CHUNK_SIZE as u64 + 5 + 100));
+        assert_eq!(read.size_before, contents.len() as u64);
+        let offsets: Vec<u64> = seen.iter().map(|(offset, _)| *offset).collect();
+        assert_eq!(offsets, [10, 10 + CHUNK_SIZE as u64, 10 + 2 * CHUNK_SIZE as u64, 3 * CHUNK_SIZE as u64]);
+        for (offset, bytes) in &seen {
+            let start = *offset as usize;
+            assert_eq!(bytes[..], contents[start..start + bytes.len()]);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_and_truncated_journals_are_rejected() {
+        let dir = scratch("corrupt");
+        let target = dir.join("target.bin");
+        fs::write(&target, b"hello world").unwrap();
+        let journal = dir.join("journal");
+        write_journal(&journal, &target, &[(6, 5)], 11).unwrap();
+        let text = fs::read_to_string(&journal).unwrap();
+        assert!(read_journal(&journal, |_, _| Ok(())).is_ok());
+
+        fs::write(&journal, text.replace("776f726c64", "776f726c65")).unwrap();
+        let err = read_journal(&journal, |_, _| Ok(())).err().unwrap();
+        assert!(err.to_string().contains("is corrupt"), "{}", err);
+
+        let truncated = &text[..text.find("crc32").unwrap()];
+        fs::write(&journal, truncated).unwrap();
+        let err = read_journal(&journal, |_, _| Ok(())).err().unwrap();
+        assert!(err.to_string().contains("missing checksum"), "{}", err);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,68 @@
+//! Byte-level operations behind the `hex_tool` command line: reading and
+//! writing ranges, formatting hexdumps and parsing the numbers users type.
+//! The range functions work on any `Read + Seek` or `Write + Seek` value, so
+//! an `io::Cursor` over a `Vec<u8>` can stand in for a file.
+
+pub mod annotate;
+pub mod base64;
+pub mod delta;
+pub mod digest;
+pub mod dump;
+pub mod hexstr;
+pub mod histogram;
+pub mod journal;
+pub mod magic;
+pub mod number;
+pub mod output;
+pub mod patch;
+pub mod progress;
+pub mod range;
+pub mod rng;
+pub mod search;
+pub mod template;
+pub mod value;
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+pub use dump::format_hexdump;
+pub use number::parse_byte_quantity;
+
+/// Size of the buffer used by streaming operations.
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Reads up to `size` bytes starting at `offset`. Returns fewer bytes when
+/// the input ends first, and none when `offset` is past the end.
+pub fn read_range<R: Read + Seek>(input: &mut R, offset: u64, size: u64) -> io::Result<Vec<u8>> {
+    input.seek(SeekFrom::Start(offset))?;
+    let mut buffer = Vec::new();
+    input.take(size).read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Overwrites the bytes at `offset` with `bytes`, leaving everything else in
+/// place. Writing past the end extends the output.
+pub fn write_at<W: Write + Seek>(output: &mut W, offset: u64, bytes: &[u8]) -> io::Result<()> {
+    output.seek(SeekFrom::Start(offset))?;
+    output.write_all(bytes)
+}
+
+/// Length of the file, or 0 if it does not exist yet.
+pub fn file_len_or_zero(file_path: &Path) -> io::Result<u64> {
+    match fs::metadata(file_path) {
+        Ok(meta) => Ok(meta.len()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
+/// Reads up to `len` bytes at `offset` without opening the file for writing.
+/// A missing file reads as empty.
+pub fn read_existing(file_path: &Path, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    match File::open(file_path) {
+        Ok(mut file) => read_range(&mut file, offset, len as u64),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,155 @@
+use crate::CHUNK_SIZE;
+use std::io::{self, Read};
+
+/// A file-type signature: `bytes` found at `offset` from the start of the
+/// structure.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Magic {
+    pub name: &'static str,
+    pub offset: u64,
+    pub bytes: &'static [u8],
+}
+
+/// Known signatures. Add new formats here; order only matters between
+/// entries of the same length, where the earlier one is preferred.
+pub const MAGICS: &[Magic] = &[
+    Magic { name: "ELF executable", offset: 0, bytes: b"\x7fELF" },
+    Magic { name: "PE/DOS executable (MZ)", offset: 0, bytes: b"MZ" },
+    Magic { name: "PNG image", offset: 0, bytes: b"\x89PNG\r\n\x1a\n" },
+    Magic { name: "JPEG image", offset: 0, bytes: b"\xff\xd8\xff" },
+    Magic { name: "GIF image", offset: 0, bytes: b"GIF87a" },
+    Magic { name: "GIF image", offset: 0, bytes: b"GIF89a" },
+    Magic { name: "ZIP archive", offset: 0, bytes: b"PK\x03\x04" },
+    Magic { name: "gzip compressed data", offset: 0, bytes: b"\x1f\x8b\x08" },
+    Magic { name: "bzip2 compressed data", offset: 0, bytes: b"BZh" },
+    Magic { name: "xz compressed data", offset: 0, bytes: b"\xfd7zXZ\x00" },
+    Magic { name: "7-zip archive", offset: 0, bytes: b"7z\xbc\xaf\x27\x1c" },
+    Magic { name: "POSIX tar archive", offset: 257, bytes: b"ustar" },
+    Magic { name: "PDF document", offset: 0, bytes: b"%PDF-" },
+    Magic { name: "SQLite 3 database", offset: 0, bytes: b"SQLite format 3\x00" },
+    Magic { name: "Mach-O binary (32-bit)", offset: 0, bytes: b"\xfe\xed\xfa\xce" },
+    Magic { name: "Mach-O binary (64-bit)", offset: 0, bytes: b"\xfe\xed\xfa\xcf" },
+    Magic { name: "Mach-O binary (32-bit, little-endian)", offset: 0, bytes: b"\xce\xfa\xed\xfe" },
+    Magic { name: "Mach-O binary (64-bit, little-endian)", offset: 0, bytes: b"\xcf\xfa\xed\xfe" },
+    Magic { name: "Mach-O universal binary or Java class", offset: 0, bytes: b"\xca\xfe\xba\xbe" },
+    Magic { name: "Flattened device tree (DTB)", offset: 0, bytes: b"\xd0\x0d\xfe\xed" },
+    Magic { name: "squashfs filesystem (little-endian)", offset: 0, bytes: b"hsqs" },
+    Magic { name: "squashfs filesystem (big-endian)", offset: 0, bytes: b"sqsh" },
+    Magic { name: "U-Boot legacy image", offset: 0, bytes: b"\x27\x05\x19\x56" },
+    Magic { name: "UBI image", offset: 0, bytes: b"UBI#" },
+    Magic { name: "cpio archive (newc)", offset: 0, bytes: b"070701" },
+];
+
+/// Bytes of file header needed to test every entry of `MAGICS`.
+pub fn header_len() -> usize {
+    MAGICS
+        .iter()
+        .map(|m| m.offset as usize + m.bytes.len())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Returns every signature that matches `header`, most specific (longest)
+/// first.
+pub fn identify(header: &[u8]) -> Vec<&'static Magic> {
+    let mut matches: Vec<&Magic> = MAGICS
+        .iter()
+        .filter(|m| {
+            let start = m.offset as usize;
+            header.get(start..start + m.bytes.len()) == Some(m.bytes)
+        })
+        .collect();
+    matches.sort_by_key(|m| std::cmp::Reverse(m.bytes.len()));
+    matches
+}
+
+/// Signatures shorter than this are skipped when scanning a whole file,
+/// since they match random data far too often.
+pub const MIN_SCAN_LEN: usize = 3;
+
+/// Scans a stream for embedded signatures at any position and returns
+/// `(structure offset, magic)` pairs in file order.
+pub fn scan(input: &mut dyn Read) -> io::Result<Vec<(u64, &'static Magic)>> {
+    let candidates: Vec<&Magic> = MAGICS.iter().filter(|m| m.bytes.len() >= MIN_SCAN_LEN).collect();
+    let longest = candidates.iter().map(|m| m.bytes.len()).max().unwrap_or(1);
+
+    let mut found = Vec::new();
+    let mut window: Vec<u8> = Vec::with_capacity(CHUNK_SIZE + longest);
+    let mut window_start = 0u64;
+    // Positions before this were already tested against every candidate
+    let mut next = 0u64;
+    let mut chunk = vec![0; CHUNK_SIZE];
+    loop {
+        let n = input.read(&mut chunk)?;
+        let at_end = n == 0;
+        window.extend_from_slice(&chunk[..n]);
+
+        let mut i = (next - window_start) as usize;
+        // Hold back positions whose longest candidate could still extend
+        // into the next chunk, unless the input has ended
+        let limit = if at_end { window.len() } else { window.len().saturating_sub(longest - 1) };
+        while i < limit {
+            let pos = window_start + i as u64;
+            for magic in &candidates {
+                if window[i..].starts_with(magic.bytes) && pos >= magic.offset {
+                    found.push((pos - magic.offset, *magic));
+                }
+            }
+            i += 1;
+        }
+        next = window_start + i as u64;
+        if at_end {
+            found.sort_by_key(|(offset, _)| *offset);
+            return Ok(found);
+        }
+
+        let drop = i.min(window.len());
+        window.drain(..drop);
+        window_start += drop as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(header: &[u8]) -> Vec<&'static str> {
+        identify(header).iter().map(|m| m.name).collect()
+    }
+
+    #[test]
+    fn identifies_common_headers() {
+        let cases: &[(&[u8], &[&str])] = &[
+            (b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR", &["PNG image"]),
+            (b"\x7fELF\x02\x01\x01\x00", &["ELF executable"]),
+            (b"PK\x03\x04\x14\x00\x00\x00", &["ZIP archive"]),
+            (b"\x1f\x8b\x08\x00\x00\x00\x00\x00", &["gzip compressed data"]),
+            (b"%PDF-1.7\n", &["PDF document"]),
+            (b"\x00\x01\x02\x03 not a known format", &[]),
+            // A signature cut short by the end of the header never matches
+            (b"\x89PNG\r\n", &[]),
+            (b"", &[]),
+        ];
+        for (header, expected) in cases {
+            assert_eq!(names(header), *expected, "{:02x?}", header);
+        }
+    }
+
+    #[test]
+    fn signatures_at_an_offset() {
+        let mut header = vec![0; header_len()];
+        header[257..262].copy_from_slice(b"ustar");
+        assert_eq!(names(&header), ["POSIX tar archive"]);
+        assert_eq!(names(&header[..261]), [] as [&str; 0]);
+    }
+
+    #[test]
+    fn scan_finds_embedded_signatures_across_chunks() {
+        let mut data = vec![0; CHUNK_SIZE - 2];
+        data.extend_from_slice(b"\x1f\x8b\x08");
+        data.extend_from_slice(b"junk%PDF-");
+        let found: Vec<(u64, &str)> = scan(&mut &data[..]).unwrap().into_iter().map(|(offset, m)| (offset, m.name)).collect();
+        let gzip = (CHUNK_SIZE - 2) as u64;
+        assert_eq!(found, [(gzip, "gzip compressed data"), (gzip + 7, "PDF document")]);
+    }
+}
@@ -1,123 +1,242 @@
-use clap::Parser;
-use std::fs::{File, OpenOptions};
-use std::io::{Read, Seek, SeekFrom, Write};
+use clap::{CommandFactory, Parser, Subcommand};
+use std::ffi::OsString;
 use std::path::PathBuf;
 
-#[derive(Parser, Debug)]
-#[command(version, about = "Read and write binary files in hexadecimal", long_about = None)]
-struct Args {
-    /// Target file
-    #[arg(short, long = "file", value_name = "FILE", required = true)]
-    file: PathBuf,
-
-    /// Read mode (display hex)
-    #[arg(short, long, conflicts_with = "write")]
-    read: bool,
+mod cmd;
 
-    /// Write mode (hex string to write)
-    #[arg(short, long, value_name = "HEX")]
-    write: Option<String>,
+use cmd::Target;
 
-    /// Offset in bytes (decimal or 0x hex)
-    #[arg(short = 'o', long = "offset", value_name = "OFF", default_value = "0")]
-    offset: String,
+use cmd::cat::CatArgs;
+use cmd::copy::CopyArgs;
+use cmd::delta::{ApplypatchArgs, MkpatchArgs};
+use cmd::dump::DumpArgs;
+use cmd::extract::ExtractArgs;
+use cmd::fill::FillRandomArgs;
+use cmd::find::FindArgs;
+use cmd::identify::IdentifyArgs;
+use cmd::replace::ReplaceArgs;
+use cmd::resize::{PadArgs, TruncateArgs};
+use cmd::seal::SealArgs;
+use cmd::shell::ShellArgs;
+use cmd::split::SplitArgs;
+use cmd::transform::TransformArgs;
+use cmd::undo::UndoArgs;
+use cmd::verify::VerifyArgs;
+use cmd::write::{AppendArgs, PatchArgs, WriteArgs};
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Read and write binary files in hexadecimal",
+    long_about = None,
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    /// Number of bytes to read
-    #[arg(short, long = "size", value_name = "N")]
-    size: Option<usize>,
+    #[command(flatten)]
+    legacy: LegacyArgs,
 }
 
-fn parse_offset(offset_str: &str) -> Result<u64, std::num::ParseIntError> {
-    if let Some(stripped) = offset_str.strip_prefix("0x") {
-        u64::from_str_radix(stripped, 16)
-    } else {
-        offset_str.parse::<u64>()
-    }
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Hexdump a range, or decode it as typed values or a template
+    Dump(DumpArgs),
+    /// Write hex or base64 bytes, or a number encoded as a given type, at an offset
+    Write(WriteArgs),
+    /// Compare the bytes at an offset against a hex string
+    /// (exit 0 on match, 1 on mismatch, 2 on error)
+    Verify(VerifyArgs),
+    /// Print the offset of every occurrence of a byte sequence
+    Find(FindArgs),
+    /// Replace occurrences of one byte sequence with another
+    Replace(ReplaceArgs),
+    /// Apply every `OFFSET: HEX` line of a patch file
+    Patch(PatchArgs),
+    /// Record the differences between two files as a binary patch
+    Mkpatch(MkpatchArgs),
+    /// Turn a file into the new version described by a binary patch
+    Applypatch(ApplypatchArgs),
+    /// Write bytes at the end of a file
+    Append(AppendArgs),
+    /// Overwrite a range with seeded pseudo-random bytes
+    FillRandom(FillRandomArgs),
+    /// XOR, AND or OR a range with a cycling key, or byte-swap its words
+    Transform(TransformArgs),
+    /// Checksum a range and store the digest in the file
+    Seal(SealArgs),
+    /// Copy a byte range into another file (or elsewhere in the same file)
+    Copy(CopyArgs),
+    /// Carve a byte range out into a new file
+    Extract(ExtractArgs),
+    /// Cut a file into fixed-size chunk files, optionally with a manifest
+    Split(SplitArgs),
+    /// Concatenate files into a new one, optionally aligning each piece
+    Cat(CatArgs),
+    /// Extend a file to a target size or alignment
+    Pad(PadArgs),
+    /// Set a file's size, refusing to shrink it unless asked
+    Truncate(TruncateArgs),
+    /// Guess a file's type from its magic number
+    Identify(IdentifyArgs),
+    /// Restore bytes recorded by --journal
+    Undo(UndoArgs),
+    /// Inspect and edit a file interactively (commands read from stdin)
+    Shell(ShellArgs),
 }
 
-fn handle_write(file_path: PathBuf, offset: u64, hex_string: String) -> std::io::Result<()> {
-    let mut file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(file_path)?;
-    file.seek(SeekFrom::Start(offset))?;
-
-    match hex::decode(hex_string) {
-        Ok(bytes) => {
-            file.write_all(&bytes)?;
-            println!("Successfully written {} bytes.", bytes.len());
-            Ok(())
+impl Command {
+    /// The subcommand's name, its --file/--offset arguments if it has them,
+    /// and whether it only reads the file.
+    fn target_mut(&mut self) -> Option<(&'static str, &mut Target, bool)> {
+        match self {
+            Command::Dump(args) => Some(("dump", &mut args.target, true)),
+            Command::Write(args) => Some(("write", &mut args.target, false)),
+            Command::Verify(args) => Some(("verify", &mut args.target, true)),
+            Command::Find(args) => Some(("find", &mut args.target, true)),
+            Command::Extract(args) => Some(("extract", &mut args.target, true)),
+            Command::FillRandom(args) => Some(("fill-random", &mut args.target, false)),
+            Command::Transform(args) => Some(("transform", &mut args.target, false)),
+            _ => None,
         }
-        Err(e) => {
-            eprintln!("Error decoding hex string: {}", e);
-            Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, e))
+    }
+
+    /// Whether the user asked for JSON output, which errors then follow too.
+    fn json(&self) -> bool {
+        match self {
+            Command::Dump(args) => args.json,
+            Command::Find(args) => args.json,
+            Command::Cat(args) => args.json,
+            _ => false,
         }
     }
 }
 
-fn handle_read(file_path: PathBuf, offset: u64, size: Option<usize>) -> std::io::Result<()> {
-    let mut file = File::open(file_path)?;
-    file.seek(SeekFrom::Start(offset))?;
-
-    let mut buffer = Vec::new();
-    let bytes_read = if let Some(s) = size {
-        buffer.resize(s, 0);
-        file.read(&mut buffer)?
+/// Reports a failed command on stderr, as `{"error": ...}` in JSON mode.
+fn print_error(e: &std::io::Error, json: bool) {
+    if json {
+        eprintln!("{}", serde_json::json!({ "error": e.to_string() }));
     } else {
-        file.read_to_end(&mut buffer)?
-    };
-    buffer.truncate(bytes_read);
+        eprintln!("Error: {}", e);
+    }
+}
+
+/// The flags of the interface from before subcommands, kept hidden for one
+/// release: `-f FILE -r` runs `dump` and `-f FILE -w HEX` runs `write`.
+#[derive(clap::Args, Debug)]
+struct LegacyArgs {
+    #[arg(short, long, value_name = "FILE", hide = true)]
+    file: Option<PathBuf>,
+
+    #[arg(short, long, hide = true, conflicts_with = "write")]
+    read: bool,
+
+    #[arg(short, long, value_name = "HEX", hide = true, requires = "file")]
+    write: Option<String>,
+
+    #[arg(short = 'o', long, value_name = "OFF", hide = true, allow_hyphen_values = true)]
+    offset: Option<String>,
 
-    // Basic hexdump format
-    for (i, chunk) in buffer.chunks(16).enumerate() {
-        print!("{:08x}: ", offset as usize + i * 16);
-        for byte in chunk {
-            print!("{:02x} ", byte);
+    #[arg(short, long, value_name = "N", hide = true, conflicts_with = "write")]
+    size: Option<String>,
+}
+
+impl LegacyArgs {
+    /// Re-parses the legacy flags as the equivalent subcommand, so both
+    /// spellings go through the same validation.
+    fn into_command(self) -> Command {
+        if !self.read && (self.write.is_none() || self.file.is_none()) {
+            Cli::command()
+                .error(
+                    clap::error::ErrorKind::MissingSubcommand,
+                    "a subcommand is required, e.g. `hex_tool dump --file FILE`",
+                )
+                .exit();
         }
-        // Add padding for the last line
-        if chunk.len() < 16 {
-            for _ in 0..(16 - chunk.len()) {
-                print!("   ");
-            }
+
+        let verb = if self.read { "dump" } else { "write" };
+        eprintln!(
+            "Warning: -r/-w are deprecated and will be removed in the next release; use `hex_tool {}` instead.",
+            verb
+        );
+
+        let mut argv: Vec<OsString> = vec!["hex_tool".into(), verb.into()];
+        if let Some(file) = self.file {
+            argv.push("--file".into());
+            argv.push(file.into());
         }
-        print!("|");
-        for &byte in chunk {
-            if (32..=126).contains(&byte) {
-                print!("{}", byte as char);
-            } else {
-                print!(".");
-            }
+        if let Some(offset) = self.offset {
+            argv.push(format!("--offset={}", offset).into());
+        }
+        if let Some(size) = self.size {
+            argv.push(format!("--size={}", size).into());
         }
-        println!("|");
+        if let Some(hex_string) = self.write {
+            argv.push("--".into());
+            argv.push(hex_string.into());
+        }
+        Cli::parse_from(argv).command.expect("subcommand was just given")
     }
-
-    Ok(())
 }
 
 fn main() {
-    let args = Args::parse();
-
-    let offset = match parse_offset(&args.offset) {
-        Ok(off) => off,
-        Err(e) => {
-            eprintln!("Error: Invalid offset value: {}", e);
-            std::process::exit(1);
-        }
+    let cli = Cli::parse();
+    let mut command = match cli.command {
+        Some(command) => command,
+        None => cli.legacy.into_command(),
     };
-
-    if args.read {
-        if let Err(e) = handle_read(args.file, offset, args.size) {
-            eprintln!("Error reading file: {}", e);
-            std::process::exit(1);
+    if let Some((name, target, reads_only)) = command.target_mut() {
+        if !target.default_to_stdin(reads_only) {
+            let mut cli_command = Cli::command();
+            cli_command.build();
+            cli_command
+                .find_subcommand_mut(name)
+                .expect("target_mut names a real subcommand")
+                .error(
+                    clap::error::ErrorKind::MissingRequiredArgument,
+                    "the following required arguments were not provided:\n  --file <FILE>",
+                )
+                .exit();
         }
-    } else if let Some(hex_string) = args.write {
-        if let Err(e) = handle_write(args.file, offset, hex_string) {
-            eprintln!("Error writing to file: {}", e);
-            std::process::exit(1);
-        }
-    } else {
-        eprintln!("Error: You must specify either --read or --write mode.");
+    }
+
+    let json = command.json();
+    let result = match command {
+        Command::Dump(args) => match cmd::dump::run(args) {
+            Ok(false) => std::process::exit(1),
+            result => result.map(|_| ()),
+        },
+        Command::Write(args) => cmd::write::run(args),
+        Command::Verify(args) => match cmd::verify::run(args) {
+            Ok(true) => Ok(()),
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                print_error(&e, json);
+                std::process::exit(2);
+            }
+        },
+        Command::Find(args) => cmd::find::run(args),
+        Command::Replace(args) => cmd::replace::run(args),
+        Command::Patch(args) => cmd::write::run_patch(args),
+        Command::Mkpatch(args) => cmd::delta::run_mkpatch(args),
+        Command::Applypatch(args) => cmd::delta::run_applypatch(args),
+        Command::Append(args) => cmd::write::run_append(args),
+        Command::FillRandom(args) => cmd::fill::run(args),
+        Command::Transform(args) => cmd::transform::run(args),
+        Command::Seal(args) => cmd::seal::run(args),
+        Command::Copy(args) => cmd::copy::run(args),
+        Command::Extract(args) => cmd::extract::run(args),
+        Command::Split(args) => cmd::split::run(args),
+        Command::Cat(args) => cmd::cat::run(args),
+        Command::Pad(args) => cmd::resize::run_pad(args),
+        Command::Truncate(args) => cmd::resize::run_truncate(args),
+        Command::Identify(args) => cmd::identify::run(args),
+        Command::Undo(args) => cmd::undo::run(args),
+        Command::Shell(args) => cmd::shell::run(args),
+    };
+    if let Err(e) = result {
+        print_error(&e, json);
         std::process::exit(1);
     }
 }
@@ -0,0 +1,310 @@
+use std::fmt;
+
+/// A numeric literal that could not be parsed, with the radix its prefix
+/// implied so the message can say how the token was read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NumberError {
+    token: String,
+    radix: u32,
+    reason: String,
+}
+
+impl fmt::Display for NumberError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let radix_name = match self.radix {
+            16 => "hexadecimal",
+            8 => "octal",
+            2 => "binary",
+            _ => "decimal",
+        };
+        write!(f, "'{}' is not a valid {} number: {}", self.token, radix_name, self.reason)
+    }
+}
+
+impl std::error::Error for NumberError {}
+
+/// Parses an optionally signed integer literal: decimal, `0x` hex, `0o`
+/// octal or `0b` binary, with `_` allowed between digits (`0x10_00`).
+/// Returns whether the value was negative, and its magnitude.
+pub fn parse_signed(token: &str) -> Result<(bool, u64), NumberError> {
+    parse_literal(token).map(|(negative, value, _)| (negative, value))
+}
+
+fn parse_literal(token: &str) -> Result<(bool, u64, u32), NumberError> {
+    let (negative, unsigned) = match token.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, token.strip_prefix('+').unwrap_or(token)),
+    };
+    let lower = unsigned.to_ascii_lowercase();
+    let (radix, digits) = if let Some(d) = lower.strip_prefix("0x") {
+        (16, d)
+    } else if let Some(d) = lower.strip_prefix("0o") {
+        (8, d)
+    } else if let Some(d) = lower.strip_prefix("0b") {
+        (2, d)
+    } else {
+        (10, lower.as_str())
+    };
+
+    let error = |reason: &str| NumberError {
+        token: token.to_string(),
+        radix,
+        reason: reason.to_string(),
+    };
+    if digits.is_empty() {
+        return Err(error("no digits"));
+    }
+    if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+        return Err(error("'_' is only allowed between digits"));
+    }
+    let cleaned: String = digits.chars().filter(|&c| c != '_').collect();
+    // from_str_radix would otherwise accept a second sign after the prefix
+    if cleaned.starts_with(['+', '-']) {
+        return Err(error("misplaced sign"));
+    }
+    let value = u64::from_str_radix(&cleaned, radix).map_err(|e| error(&e.to_string()))?;
+    Ok((negative, value, radix))
+}
+
+/// Parses a non-negative byte quantity such as a size or an absolute offset.
+pub fn parse_offset(token: &str) -> Result<u64, NumberError> {
+    match parse_literal(token)? {
+        (false, value, _) | (true, value @ 0, _) => Ok(value),
+        (true, _, radix) => Err(NumberError {
+            token: token.to_string(),
+            radix,
+            reason: "negative values are not allowed here".to_string(),
+        }),
+    }
+}
+
+/// Parses a size that may carry a binary unit suffix: `K`, `M`, `G` or `T`
+/// (optionally written `KiB` and so on) multiply the number by 1024, 1024²
+/// and so on, so `4K` is 4096 and `0x10M` is 16 MiB.
+pub fn parse_byte_quantity(token: &str) -> Result<u64, NumberError> {
+    let unit_start = token
+        .strip_suffix("iB")
+        .filter(|rest| rest.ends_with(['K', 'M', 'G', 'T', 'k', 'm', 'g', 't']))
+        .unwrap_or(token);
+    let (digits, shift) = match unit_start.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&unit_start[..unit_start.len() - 1], 10),
+        Some('M') => (&unit_start[..unit_start.len() - 1], 20),
+        Some('G') => (&unit_start[..unit_start.len() - 1], 30),
+        Some('T') => (&unit_start[..unit_start.len() - 1], 40),
+        _ => (token, 0),
+    };
+
+    let value = parse_offset(digits).map_err(|e| NumberError {
+        token: token.to_string(),
+        ..e
+    })?;
+    value.checked_mul(1 << shift).ok_or_else(|| NumberError {
+        token: token.to_string(),
+        radix: parse_literal(digits).map_or(10, |(_, _, radix)| radix),
+        reason: "number too large to fit in target type".to_string(),
+    })
+}
+
+/// Where `--offset` points: from the start of the input, or a number of
+/// bytes back from its end when written with a leading `-`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Position {
+    Start(u64),
+    FromEnd(u64),
+}
+
+pub fn parse_position(token: &str) -> Result<Position, NumberError> {
+    Ok(match parse_signed(token)? {
+        (true, value) => Position::FromEnd(value),
+        (false, value) => Position::Start(value),
+    })
+}
+
+impl Position {
+    /// Converts to an absolute offset in input of `len` bytes.
+    pub fn resolve(self, len: u64) -> Result<u64, String> {
+        match self {
+            Position::Start(offset) => Ok(offset),
+            Position::FromEnd(back) => len.checked_sub(back).ok_or_else(|| {
+                format!("offset -{} is before the start of the {}-byte file", back, len)
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SplitMix64;
+
+    /// Writes `value` in a randomly chosen accepted form: any radix and
+    /// prefix case, digit case, leading zeros, `_` between digits and an
+    /// optional sign. Returns the text and whether it is negative.
+    fn random_form(rng: &mut SplitMix64, value: u64) -> (String, bool) {
+        let (prefix, digits) = match rng.next_u64() % 4 {
+            0 => ("", value.to_string()),
+            1 => (["0x", "0X"][rng.next_u64() as usize % 2], format!("{:x}", value)),
+            2 => (["0o", "0O"][rng.next_u64() as usize % 2], format!("{:o}", value)),
+            _ => (["0b", "0B"][rng.next_u64() as usize % 2], format!("{:b}", value)),
+        };
+        let digits = if rng.next_u64().is_multiple_of(2) { digits.to_uppercase() } else { digits };
+        let zeros = "0".repeat(rng.next_u64() as usize % 3);
+
+        let mut text = String::new();
+        let (negative, sign) = match rng.next_u64() % 3 {
+            0 => (false, ""),
+            1 => (false, "+"),
+            _ => (true, "-"),
+        };
+        text.push_str(sign);
+        text.push_str(prefix);
+        for (i, c) in zeros.chars().chain(digits.chars()).enumerate() {
+            if i > 0 && rng.next_u64().is_multiple_of(4) {
+                text.push('_');
+            }
+            text.push(c);
+        }
+        (text, negative)
+    }
+
+    fn random_value(rng: &mut SplitMix64) -> u64 {
+        rng.next_u64() >> (rng.next_u64() % 64)
+    }
+
+    #[test]
+    fn every_accepted_form_round_trips() {
+        let mut rng = SplitMix64::new(119);
+        for _ in 0..20_000 {
+            let value = random_value(&mut rng);
+            let (text, negative) = random_form(&mut rng, value);
+            assert_eq!(parse_signed(&text), Ok((negative, value)), "{}", text);
+            let position = if negative { Position::FromEnd(value) } else { Position::Start(value) };
+            assert_eq!(parse_position(&text), Ok(position), "{}", text);
+            if negative && value != 0 {
+                assert!(parse_offset(&text).is_err(), "{}", text);
+            } else {
+                assert_eq!(parse_offset(&text), Ok(value), "{}", text);
+            }
+        }
+    }
+
+    #[test]
+    fn offsets_as_printed_parse_back() {
+        let mut rng = SplitMix64::new(1190);
+        for _ in 0..20_000 {
+            let value = random_value(&mut rng);
+            for text in [
+                format!("{:#x}", value),
+                format!("{:#010x}", value),
+                format!("{:#o}", value),
+                format!("{:#b}", value),
+                value.to_string(),
+            ] {
+                assert_eq!(parse_offset(&text), Ok(value), "{}", text);
+            }
+        }
+    }
+
+    #[test]
+    fn misplaced_underscores_and_signs_are_rejected() {
+        let mut rng = SplitMix64::new(1191);
+        for _ in 0..5_000 {
+            let value = random_value(&mut rng);
+            let (text, _) = random_form(&mut rng, value);
+            let digits_at = text.find(|c: char| c.is_ascii_digit()).unwrap();
+            let prefixed = text[digits_at..].len() > 1
+                && matches!(text.as_bytes()[digits_at + 1], b'x' | b'X' | b'o' | b'O' | b'b' | b'B');
+            let body = if prefixed { digits_at + 2 } else { digits_at };
+            let mut bad = vec![
+                format!("{}_{}", &text[..body], &text[body..]),
+                format!("{}_", text),
+                format!("{}__{}", &text[..body + 1], &text[body + 1..]),
+                format!("+-{}", text),
+            ];
+            if prefixed {
+                bad.push(format!("{}-{}", &text[..body], &text[body..]));
+                bad.push(format!("{}+{}", &text[..body], &text[body..]));
+            }
+            for bad in bad {
+                assert!(parse_signed(&bad).is_err(), "{} was accepted", bad);
+            }
+        }
+    }
+
+    #[test]
+    fn byte_quantities_take_binary_suffixes() {
+        assert_eq!(parse_byte_quantity("0"), Ok(0));
+        assert_eq!(parse_byte_quantity("512"), Ok(512));
+        assert_eq!(parse_byte_quantity("4K"), Ok(4096));
+        assert_eq!(parse_byte_quantity("4k"), Ok(4096));
+        assert_eq!(parse_byte_quantity("4KiB"), Ok(4096));
+        assert_eq!(parse_byte_quantity("3M"), Ok(3 << 20));
+        assert_eq!(parse_byte_quantity("2GiB"), Ok(2 << 30));
+        assert_eq!(parse_byte_quantity("1T"), Ok(1 << 40));
+        assert_eq!(parse_byte_quantity("0x10M"), Ok(16 << 20));
+        assert_eq!(parse_byte_quantity("0b11K"), Ok(3 << 10));
+        assert_eq!(parse_byte_quantity("1_024K"), Ok(1 << 20));
+        assert!(parse_byte_quantity("K").is_err());
+        assert!(parse_byte_quantity("4iB").is_err());
+        assert!(parse_byte_quantity("4KB").is_err());
+        assert!(parse_byte_quantity("4 K").is_err());
+        assert!(parse_byte_quantity("-4K").is_err());
+    }
+
+    #[test]
+    fn hex_digits_are_not_mistaken_for_suffixes() {
+        // Without a 0x prefix a trailing letter is a unit; with one, hex
+        // digits stay digits and only K, M, G and T (never hex) are units
+        assert_eq!(parse_byte_quantity("0x1f"), Ok(0x1f));
+        assert_eq!(parse_byte_quantity("0xffK"), Ok(0xff << 10));
+        assert!(parse_byte_quantity("1f").is_err());
+    }
+
+    #[test]
+    fn overflow_is_an_error() {
+        assert_eq!(parse_offset("18446744073709551615"), Ok(u64::MAX));
+        assert_eq!(parse_offset("0xffff_ffff_ffff_ffff"), Ok(u64::MAX));
+        assert!(parse_offset("18446744073709551616").is_err());
+        assert!(parse_offset("0x1_0000_0000_0000_0000").is_err());
+        assert_eq!(parse_byte_quantity("16777215T"), Ok(16_777_215 << 40));
+        let err = parse_byte_quantity("16777216T").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "'16777216T' is not a valid decimal number: number too large to fit in target type"
+        );
+        assert_eq!(parse_byte_quantity("0x1000000T").unwrap_err().radix, 16);
+    }
+
+    #[test]
+    fn negative_positions_count_back_from_the_end() {
+        assert_eq!(parse_position("16"), Ok(Position::Start(16)));
+        assert_eq!(parse_position("-16"), Ok(Position::FromEnd(16)));
+        assert_eq!(parse_position("-0x10"), Ok(Position::FromEnd(16)));
+        assert_eq!(parse_position("-16").unwrap().resolve(100), Ok(84));
+        assert_eq!(parse_position("-100").unwrap().resolve(100), Ok(0));
+        assert_eq!(parse_position("200").unwrap().resolve(100), Ok(200));
+        assert_eq!(parse_position("-0").unwrap().resolve(100), Ok(100));
+        assert_eq!(
+            parse_position("-101").unwrap().resolve(100),
+            Err("offset -101 is before the start of the 100-byte file".to_string())
+        );
+    }
+
+    #[test]
+    fn errors_name_the_radix() {
+        assert_eq!(
+            parse_offset("0x1g").unwrap_err().to_string(),
+            "'0x1g' is not a valid hexadecimal number: invalid digit found in string"
+        );
+        assert_eq!(parse_offset("0o8").unwrap_err().radix, 8);
+        assert_eq!(parse_offset("0b2").unwrap_err().radix, 2);
+        assert_eq!(
+            parse_offset("0x").unwrap_err().to_string(),
+            "'0x' is not a valid hexadecimal number: no digits"
+        );
+        assert_eq!(
+            parse_offset("-5").unwrap_err().to_string(),
+            "'-5' is not a valid decimal number: negative values are not allowed here"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use crate::dump::ColorChoice;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+/// Destination for formatted output: stdout, or a file given with `--out`.
+/// Files are written to a temporary sibling and renamed into place by
+/// `finish`, so readers never observe a half-written dump; dropping an
+/// unfinished output removes the temporary file.
+pub enum Output {
+    Stdout(io::StdoutLock<'static>),
+    File {
+        writer: Option<BufWriter<File>>,
+        temp: PathBuf,
+        dest: PathBuf,
+    },
+}
+
+impl Output {
+    pub fn create(path: Option<&Path>) -> io::Result<Output> {
+        let Some(dest) = path else {
+            return Ok(Output::Stdout(io::stdout().lock()));
+        };
+
+        let temp = temp_sibling(dest)?;
+        let file = File::create(&temp)?;
+        Ok(Output::File {
+            writer: Some(BufWriter::new(file)),
+            temp,
+            dest: dest.to_path_buf(),
+        })
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            Output::Stdout(stdout) => stdout.is_terminal(),
+            Output::File { .. } => false,
+        }
+    }
+
+    /// Resolves `--color` for this destination. Files never get escape
+    /// codes, and asking for them explicitly is an error.
+    pub fn color(&self, choice: ColorChoice) -> io::Result<bool> {
+        match (self, choice) {
+            (Output::File { .. }, ColorChoice::Always) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--color always cannot be used with --out",
+            )),
+            (Output::File { .. }, _) => Ok(false),
+            (Output::Stdout(_), choice) => Ok(choice.enabled(self.is_terminal())),
+        }
+    }
+
+    /// Flushes the output and, for files, moves it into place. Returns the
+    /// final path when output went to a file.
+    pub fn finish(mut self) -> io::Result<Option<PathBuf>> {
+        match &mut self {
+            Output::Stdout(stdout) => {
+                stdout.flush()?;
+                Ok(None)
+            }
+            Output::File { writer, temp, dest } => {
+                let result = match writer.take() {
+                    Some(writer) => writer
+                        .into_inner()
+                        .map_err(|e| e.into_error())
+                        .and_then(|file| file.sync_all())
+                        .and_then(|()| fs::rename(&temp, &dest)),
+                    None => Ok(()),
+                };
+                if let Err(e) = result {
+                    let _ = fs::remove_file(&temp);
+                    return Err(e);
+                }
+                Ok(Some(dest.clone()))
+            }
+        }
+    }
+}
+
+/// A hidden temporary path in the same directory as `dest`, so it can later
+/// be renamed over `dest` atomically.
+pub fn temp_sibling(dest: &Path) -> io::Result<PathBuf> {
+    let file_name = dest.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a file path", dest.display()),
+        )
+    })?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(".tmp{}", std::process::id()));
+    Ok(dest.with_file_name(temp_name))
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stdout(stdout) => stdout.write(buf),
+            Output::File { writer, .. } => writer.as_mut().expect("output already finished").write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout(stdout) => stdout.flush(),
+            Output::File { writer, .. } => writer.as_mut().map_or(Ok(()), |w| w.flush()),
+        }
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        if let Output::File { writer: Some(_), temp, .. } = self {
+            let _ = fs::remove_file(temp);
+        }
+    }
+}
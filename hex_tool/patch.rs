@@ -0,0 +1,84 @@
+use crate::hexstr::decode_hex;
+use crate::number::parse_offset;
+
+/// One `OFFSET: HEX` line of a patch file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Patch {
+    pub line: usize,
+    pub offset: u64,
+    pub bytes: Vec<u8>,
+}
+
+impl Patch {
+    /// The offset just past the patch, which parsing checks fits in 64 bits.
+    pub fn end(&self) -> u64 {
+        self.offset + self.bytes.len() as u64
+    }
+}
+
+/// Parses every line of a patch file up front so nothing is written unless
+/// the whole file is valid. Blank lines and `#` comments are ignored.
+pub fn parse_patch_file(text: &str) -> Result<Vec<Patch>, String> {
+    let mut patches = Vec::new();
+    for (index, raw_line) in text.lines().enumerate() {
+        let line_no = index + 1;
+        let line = raw_line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (offset_str, hex_str) = line
+            .split_once(':')
+            .ok_or_else(|| format!("line {}: expected 'OFFSET: HEX', found '{}'", line_no, line))?;
+        let offset = parse_offset(offset_str.trim())
+            .map_err(|e| format!("line {}: invalid offset: {}", line_no, e))?;
+        let bytes = decode_hex(hex_str)
+            .map_err(|e| format!("line {}: invalid hex string: {}", line_no, e))?;
+        if bytes.is_empty() {
+            return Err(format!("line {}: patch contains no bytes", line_no));
+        }
+        if offset.checked_add(bytes.len() as u64).is_none() {
+            return Err(format!("line {}: patch ends past the largest 64-bit offset", line_no));
+        }
+        patches.push(Patch {
+            line: line_no,
+            offset,
+            bytes,
+        });
+    }
+    Ok(patches)
+}
+
+/// Parses an inline `OFFSET=HEX` patch. It is numbered 0; callers number
+/// their patches by position.
+pub fn parse_poke(text: &str) -> Result<Patch, String> {
+    let (offset_str, hex_str) = text
+        .split_once('=')
+        .ok_or_else(|| format!("expected OFFSET=HEX, found '{}'", text))?;
+    let offset = parse_offset(offset_str.trim()).map_err(|e| format!("invalid offset: {}", e))?;
+    let bytes = decode_hex(hex_str).map_err(|e| format!("invalid hex string: {}", e))?;
+    if bytes.is_empty() {
+        return Err("no bytes to write".to_string());
+    }
+    if offset.checked_add(bytes.len() as u64).is_none() {
+        return Err("patch ends past the largest 64-bit offset".to_string());
+    }
+    Ok(Patch { line: 0, offset, bytes })
+}
+
+/// Returns the line numbers of every pair of patches whose ranges overlap.
+pub fn find_overlaps(patches: &[Patch]) -> Vec<(usize, usize)> {
+    let mut sorted: Vec<&Patch> = patches.iter().collect();
+    sorted.sort_by_key(|p| (p.offset, p.line));
+
+    let mut overlaps = Vec::new();
+    for (i, a) in sorted.iter().enumerate() {
+        for b in &sorted[i + 1..] {
+            if b.offset >= a.end() {
+                break;
+            }
+            overlaps.push((a.line.min(b.line), a.line.max(b.line)));
+        }
+    }
+    overlaps
+}
@@ -0,0 +1,92 @@
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+/// Minimum time between two redraws of the progress bar.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Width of the bar itself, in characters.
+const BAR_WIDTH: usize = 30;
+
+/// A throttled progress bar on stderr for streaming loops over a range of
+/// known size. It stays silent when stderr is not a terminal, so it never
+/// mixes with redirected output.
+pub struct Progress {
+    total: u64,
+    enabled: bool,
+    started: Instant,
+    last_draw: Option<Instant>,
+}
+
+impl Progress {
+    pub fn new(total: u64, requested: bool) -> Progress {
+        Progress {
+            total,
+            enabled: requested && io::stderr().is_terminal(),
+            started: Instant::now(),
+            last_draw: None,
+        }
+    }
+
+    /// Records that `done` bytes have been processed so far.
+    pub fn update(&mut self, done: u64) {
+        if !self.enabled {
+            return;
+        }
+        let now = Instant::now();
+        if self.last_draw.is_some_and(|t| now - t < REDRAW_INTERVAL) && done < self.total {
+            return;
+        }
+        self.last_draw = Some(now);
+        self.draw(done, now - self.started);
+    }
+
+    /// Ends the bar's line once the loop is done.
+    pub fn finish(&mut self) {
+        if self.enabled && self.last_draw.is_some() {
+            eprintln!();
+        }
+    }
+
+    fn draw(&self, done: u64, elapsed: Duration) {
+        let fraction = if self.total == 0 {
+            1.0
+        } else {
+            done as f64 / self.total as f64
+        };
+        let filled = ((fraction * BAR_WIDTH as f64) as usize).min(BAR_WIDTH);
+        let rate = done as f64 / elapsed.as_secs_f64().max(0.001);
+        let eta = if rate > 0.0 {
+            format!("{:.0}s", (self.total.saturating_sub(done)) as f64 / rate)
+        } else {
+            "?".to_string()
+        };
+
+        let mut stderr = io::stderr().lock();
+        let _ = write!(
+            stderr,
+            "\r[{}{}] {} / {} {}/s ETA {}   ",
+            "#".repeat(filled),
+            "-".repeat(BAR_WIDTH - filled),
+            human_size(done),
+            human_size(self.total),
+            human_size(rate as u64),
+            eta
+        );
+        let _ = stderr.flush();
+    }
+}
+
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
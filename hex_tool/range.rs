@@ -0,0 +1,38 @@
+use crate::number::{parse_byte_quantity, parse_offset};
+
+/// A byte range given as `OFFSET:SIZE` on the command line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ByteRange {
+    pub offset: u64,
+    pub size: u64,
+}
+
+impl ByteRange {
+    pub fn end(&self) -> u64 {
+        self.offset.saturating_add(self.size)
+    }
+
+    pub fn overlaps(&self, other: &ByteRange) -> bool {
+        self.offset < other.end() && other.offset < self.end()
+    }
+}
+
+/// Parses `OFFSET:SIZE`, each part in decimal or 0x hex; the size may carry
+/// a unit suffix such as `4K`.
+pub fn parse_range(range_str: &str) -> Result<ByteRange, String> {
+    let (offset, size) = range_str
+        .split_once(':')
+        .ok_or_else(|| format!("expected OFFSET:SIZE, found '{}'", range_str))?;
+    let offset = parse_offset(offset).map_err(|e| format!("invalid offset: {}", e))?;
+    let size = parse_byte_quantity(size).map_err(|e| format!("invalid size: {}", e))?;
+    Ok(ByteRange { offset, size })
+}
+
+/// Sorts ranges by offset and returns the first overlapping pair, if any.
+pub fn sort_ranges(ranges: &mut [ByteRange]) -> Option<(ByteRange, ByteRange)> {
+    ranges.sort();
+    ranges
+        .windows(2)
+        .find(|pair| pair[0].overlaps(&pair[1]))
+        .map(|pair| (pair[0], pair[1]))
+}
@@ -0,0 +1,41 @@
+/// SplitMix64, a small seeded generator. Its output depends only on the
+/// seed, never on the platform or a dependency's version, so fixtures made
+/// with a given seed can be recreated byte for byte later.
+pub struct SplitMix64 {
+    state: u64,
+    /// Bytes of the last generated word not handed out yet
+    spare: [u8; 8],
+    spare_len: usize,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> SplitMix64 {
+        SplitMix64 {
+            state: seed,
+            spare: [0; 8],
+            spare_len: 0,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Fills `buffer` with the next bytes of the stream: each word is used
+    /// as 8 little-endian bytes. The stream is the same however it is split
+    /// across calls.
+    pub fn fill(&mut self, buffer: &mut [u8]) {
+        for byte in buffer {
+            if self.spare_len == 0 {
+                self.spare = self.next_u64().to_le_bytes();
+                self.spare_len = 8;
+            }
+            *byte = self.spare[8 - self.spare_len];
+            self.spare_len -= 1;
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use crate::CHUNK_SIZE;
+use std::io::{self, Read};
+
+/// Finds non-overlapping occurrences of `pattern`, scanning left to right
+/// and resuming after the end of each match, in a stream that starts at
+/// `base`. Stops after `limit` matches. Reads in chunks, keeping the last
+/// `pattern.len() - 1` bytes of each so matches across chunk boundaries are
+/// found.
+pub fn find_matches(input: &mut dyn Read, base: u64, pattern: &[u8], limit: Option<usize>) -> io::Result<Vec<u64>> {
+    let mut matches = Vec::new();
+    if pattern.is_empty() {
+        return Ok(matches);
+    }
+
+    let mut window: Vec<u8> = Vec::with_capacity(CHUNK_SIZE + pattern.len());
+    let mut window_start = base;
+    // Earliest offset a new match may start at, so matches never overlap
+    let mut next_allowed = base;
+    let mut chunk = vec![0; CHUNK_SIZE];
+    loop {
+        let n = input.read(&mut chunk)?;
+        if n == 0 {
+            return Ok(matches);
+        }
+        window.extend_from_slice(&chunk[..n]);
+
+        let mut i = (next_allowed.saturating_sub(window_start)) as usize;
+        while i + pattern.len() <= window.len() {
+            if &window[i..i + pattern.len()] == pattern {
+                matches.push(window_start + i as u64);
+                if limit.is_some_and(|l| matches.len() >= l) {
+                    return Ok(matches);
+                }
+                i += pattern.len();
+                next_allowed = window_start + i as u64;
+            } else {
+                i += 1;
+            }
+        }
+
+        let keep = (pattern.len() - 1).min(window.len());
+        let drop = window.len() - keep;
+        window.drain(..drop);
+        window_start += drop as u64;
+    }
+}
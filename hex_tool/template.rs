@@ -0,0 +1,155 @@
+use crate::value::{Decoded, Endian, ValueType};
+use serde_json::{json, Value};
+
+/// How the bytes of a template field are interpreted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldKind {
+    /// One number, or `count` consecutive numbers for an array
+    Number {
+        ty: ValueType,
+        endian: Endian,
+        count: Option<usize>,
+    },
+    /// Fixed-length string, shown up to the first NUL
+    Str(usize),
+    /// Opaque bytes, shown as hex
+    Bytes(usize),
+}
+
+/// One named field of a template, in file order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub kind: FieldKind,
+}
+
+impl Field {
+    /// Number of bytes the field occupies.
+    pub fn size(&self) -> usize {
+        match self.kind {
+            FieldKind::Number { ty, count, .. } => ty.width() * count.unwrap_or(1),
+            FieldKind::Str(n) | FieldKind::Bytes(n) => n,
+        }
+    }
+
+    /// Human-readable rendering of the field's value.
+    pub fn format(&self, bytes: &[u8]) -> String {
+        match self.kind {
+            FieldKind::Number { ty, endian, count: None } => ty.format(bytes, endian),
+            FieldKind::Number { ty, endian, count: Some(_) } => {
+                let items: Vec<String> = bytes
+                    .chunks(ty.width())
+                    .map(|b| ty.format(b, endian))
+                    .collect();
+                format!("[{}]", items.join(", "))
+            }
+            FieldKind::Str(_) => format!("{:?}", decode_str(bytes)),
+            FieldKind::Bytes(_) => hex::encode(bytes),
+        }
+    }
+
+    /// JSON rendering of the field's value.
+    pub fn to_json(&self, bytes: &[u8]) -> Value {
+        match self.kind {
+            FieldKind::Number { ty, endian, count: None } => decoded_json(ty.decode(bytes, endian)),
+            FieldKind::Number { ty, endian, count: Some(_) } => bytes
+                .chunks(ty.width())
+                .map(|b| decoded_json(ty.decode(b, endian)))
+                .collect(),
+            FieldKind::Str(_) => json!(decode_str(bytes)),
+            FieldKind::Bytes(_) => json!(hex::encode(bytes)),
+        }
+    }
+}
+
+fn decoded_json(value: Decoded) -> Value {
+    match value {
+        Decoded::Unsigned(v) => json!(v),
+        Decoded::Signed(v) => json!(v),
+        Decoded::Float(v) => json!(v),
+    }
+}
+
+fn decode_str(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+/// Parses a template made of `name: type` lines. Blank lines and `#`
+/// comments are ignored. Types are `u8`..`u64`, `i8`..`i64`, `f32`, `f64`
+/// with an optional `le`/`be` suffix (otherwise `default_endian` applies),
+/// `str[N]` and `bytes[N]`; a numeric type may take an array length, e.g.
+/// `u16le[4]`. Errors name the offending line.
+pub fn parse_template(text: &str, default_endian: Endian) -> Result<Vec<Field>, String> {
+    let mut fields = Vec::new();
+    for (index, raw_line) in text.lines().enumerate() {
+        let line_no = index + 1;
+        let line = raw_line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (name, spec) = line
+            .split_once(':')
+            .ok_or_else(|| format!("line {}: expected 'name: type', found '{}'", line_no, line))?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(format!("line {}: missing field name", line_no));
+        }
+        if fields.iter().any(|f: &Field| f.name == name) {
+            return Err(format!("line {}: duplicate field '{}'", line_no, name));
+        }
+
+        let kind = parse_kind(spec.trim(), default_endian).map_err(|e| format!("line {}: {}", line_no, e))?;
+        fields.push(Field {
+            name: name.to_string(),
+            kind,
+        });
+    }
+
+    if fields.is_empty() {
+        return Err("template defines no fields".to_string());
+    }
+    Ok(fields)
+}
+
+fn parse_kind(spec: &str, default_endian: Endian) -> Result<FieldKind, String> {
+    let (base, count) = match spec.split_once('[') {
+        Some((base, rest)) => {
+            let digits = rest
+                .strip_suffix(']')
+                .ok_or_else(|| format!("unterminated array length in '{}'", spec))?;
+            let count: usize = digits
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid array length '{}'", digits))?;
+            if count == 0 {
+                return Err(format!("array length must be positive in '{}'", spec));
+            }
+            (base.trim(), Some(count))
+        }
+        None => (spec, None),
+    };
+
+    match base {
+        "str" | "bytes" => {
+            let n = count.ok_or_else(|| format!("'{}' needs a length, e.g. {}[16]", base, base))?;
+            return Ok(if base == "str" {
+                FieldKind::Str(n)
+            } else {
+                FieldKind::Bytes(n)
+            });
+        }
+        _ => {}
+    }
+
+    let (name, endian) = if let Some(name) = base.strip_suffix("le") {
+        (name, Endian::Le)
+    } else if let Some(name) = base.strip_suffix("be") {
+        (name, Endian::Be)
+    } else {
+        (base, default_endian)
+    };
+    let ty = ValueType::from_name(name).ok_or_else(|| format!("unknown type '{}'", base))?;
+    Ok(FieldKind::Number { ty, endian, count })
+}
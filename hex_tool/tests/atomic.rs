@@ -0,0 +1,43 @@
+mod common;
+
+use common::{random_bytes, Scratch};
+use std::fs;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn killing_an_atomic_write_leaves_the_target_intact() {
+    let scratch = Scratch::new("atomic-kill");
+    let original = random_bytes(150, 64 * 1024 * 1024);
+    let path = scratch.file("data.bin", &original);
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_hex_tool"))
+        .args(["transform", "-f", "data.bin", "--xor", "5a", "--atomic"])
+        .current_dir(&scratch.dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let temp = scratch.path(&format!(".data.bin.tmp{}", child.id()));
+
+    // Kill it (SIGKILL, so nothing gets to clean up) once the copy it
+    // writes to exists, partway through the transform
+    while !temp.exists() {
+        assert!(
+            child.try_wait().unwrap().is_none(),
+            "finished before it could be killed"
+        );
+        thread::sleep(Duration::from_millis(1));
+    }
+    thread::sleep(Duration::from_millis(50));
+    child.kill().unwrap();
+    let status = child.wait().unwrap();
+    assert!(!status.success());
+
+    assert!(fs::read(&path).unwrap() == original);
+    // The copy is left behind, as --atomic's help says
+    assert!(temp.exists());
+    assert_eq!(scratch.entries().len(), 2);
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::{random_bytes, run_in, stderr, stdout, Scratch};
+
+fn differing_copies(scratch: &Scratch) {
+    let original = random_bytes(13, 100_000);
+    let mut changed = original.clone();
+    changed[5] ^= 0xff;
+    changed[90_000] ^= 0xff;
+    scratch.file("a.bin", &original);
+    scratch.file("b.bin", &changed);
+}
+
+#[test]
+fn open_ended_compare_is_capped_at_max_bytes() {
+    let scratch = Scratch::new("compare-cap");
+    differing_copies(&scratch);
+
+    let args = ["dump", "-f", "a.bin", "--compare", "b.bin", "--json"];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "{\"differing\":1,\"offset\":0,\"ranges\":[{\"offset\":5,\"size\":1}],\"size\":65536}\n"
+    );
+    assert!(stderr(&output).contains("comparison truncated to 65536 bytes"));
+}
+
+#[test]
+fn compare_with_all_or_size_is_not_capped() {
+    let scratch = Scratch::new("compare-all");
+    differing_copies(&scratch);
+
+    let args = [
+        "dump",
+        "-f",
+        "a.bin",
+        "--compare",
+        "b.bin",
+        "--json",
+        "--all",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("\"differing\":2"));
+    assert!(stdout(&output).contains("\"size\":100000"));
+    assert_eq!(stderr(&output), "");
+
+    let args = [
+        "dump",
+        "-f",
+        "a.bin",
+        "--compare",
+        "b.bin",
+        "--json",
+        "-s",
+        "95000",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(stdout(&output).contains("\"differing\":2"));
+    assert_eq!(stderr(&output), "");
+}
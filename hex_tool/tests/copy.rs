@@ -0,0 +1,146 @@
+mod common;
+
+use common::{random_bytes, run_in, stderr, stdout, Scratch};
+use std::fs;
+
+#[test]
+fn destination_past_the_largest_offset_is_rejected() {
+    let scratch = Scratch::new("copy-overflow");
+    scratch.file("src.bin", b"abcdef");
+
+    let args = [
+        "copy",
+        "-f",
+        "src.bin",
+        "-s",
+        "4",
+        "--to",
+        "dest.bin",
+        "--to-offset",
+        "0xfffffffffffffffe",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("past the largest 64-bit offset"));
+    assert!(!scratch.path("dest.bin").exists());
+}
+
+#[test]
+fn overlapping_ranges_in_the_same_file_need_allow_overlap() {
+    let scratch = Scratch::new("copy-overlap");
+    let path = scratch.file("data.bin", b"0123456789");
+
+    let args = [
+        "copy",
+        "-f",
+        "data.bin",
+        "-s",
+        "6",
+        "--to",
+        "data.bin",
+        "--to-offset",
+        "2",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("--allow-overlap"));
+    assert_eq!(fs::read(&path).unwrap(), b"0123456789");
+
+    let allowed = [&args[..], &["--allow-overlap"]].concat();
+    let output = run_in(&scratch.dir, &allowed, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fs::read(&path).unwrap(), b"0101234589");
+}
+
+/// Moving a range towards the end of its own file copies the last chunk
+/// first, so every source byte is read before it is overwritten.
+#[test]
+fn overlapping_move_spanning_chunks_keeps_the_source() {
+    let scratch = Scratch::new("copy-backward");
+    let original = random_bytes(3, 300_000);
+    let path = scratch.file("data.bin", &original);
+
+    let args = [
+        "copy",
+        "-f",
+        "data.bin",
+        "-o",
+        "1000",
+        "-s",
+        "200000",
+        "--to",
+        "data.bin",
+        "--to-offset",
+        "1500",
+        "--allow-overlap",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let mut expected = original.clone();
+    expected[1500..201_500].copy_from_slice(&original[1000..201_000]);
+    assert_eq!(fs::read(&path).unwrap(), expected);
+}
+
+#[test]
+fn copy_goes_through_the_usual_write_options() {
+    let scratch = Scratch::new("copy-write-options");
+    let src = random_bytes(148, 5000);
+    let dest = random_bytes(149, 3000);
+    scratch.file("src.bin", &src);
+    let dest_path = scratch.file("dest.bin", &dest);
+
+    let args = [
+        "copy",
+        "-f",
+        "src.bin",
+        "-o",
+        "100",
+        "-s",
+        "4000",
+        "--to",
+        "dest.bin",
+        "--to-offset",
+        "1000",
+        "--backup",
+        "--journal",
+        "dest.journal",
+        "--atomic",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let mut expected = dest[..1000].to_vec();
+    expected.extend_from_slice(&src[100..4100]);
+    assert_eq!(fs::read(&dest_path).unwrap(), expected);
+    assert_eq!(fs::read(scratch.path("dest.bin.bak")).unwrap(), dest);
+
+    let args = ["undo", "-f", "dest.bin", "--journal", "dest.journal"];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fs::read(&dest_path).unwrap(), dest);
+}
+
+#[test]
+fn block_count_past_the_end_copies_what_there_is() {
+    let scratch = Scratch::new("copy-count-past-end");
+    let src = random_bytes(144, 3000);
+    scratch.file("src.bin", &src);
+
+    let args = [
+        "copy", "-f", "src.bin", "--bs", "1K", "--skip", "1", "--count", "5", "--to", "dest.bin",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fs::read(scratch.path("dest.bin")).unwrap(), &src[1024..]);
+    assert!(stdout(&output).contains("Copied 1976 bytes from src.bin to dest.bin"));
+    assert!(stderr(&output).contains("copied 1976 of 5120 requested bytes"));
+
+    // Starting past the end copies nothing
+    let args = [
+        "copy", "-f", "src.bin", "--bs", "1K", "--skip", "3", "--count", "1", "--to", "none.bin",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("Copied 0 bytes"));
+    assert!(stderr(&output).contains("copied 0 of 1024 requested bytes"));
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use common::{run_in, stderr, stdout, Scratch};
+use std::fs::{self, File};
+use std::time::{Duration, SystemTime};
+
+const CONTENTS: &[u8] = b"hello world, hello!";
+
+/// Runs `args` with --dry-run (and --backup and --journal, which a dry run
+/// must not act on either) against a file whose mtime is set well in the
+/// past, then checks the file and its directory are exactly as they were.
+fn assert_untouched(name: &str, args: &[&str]) {
+    let scratch = Scratch::new(name);
+    let path = scratch.file("data.bin", CONTENTS);
+    scratch.file("edit.patch", b"0x1: 4142\n");
+    let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(mtime)
+        .unwrap();
+    let before = scratch.entries();
+
+    let extra = ["--dry-run", "--backup", "--journal", "data.journal"];
+    let output = run_in(&scratch.dir, &[args, &extra].concat(), b"");
+    assert!(output.status.success(), "{:?}: {}", args, stderr(&output));
+    assert!(
+        stdout(&output).contains("Dry run: would"),
+        "{:?}: {}",
+        args,
+        stdout(&output)
+    );
+
+    assert_eq!(fs::read(&path).unwrap(), CONTENTS, "{:?}", args);
+    assert_eq!(
+        fs::metadata(&path).unwrap().modified().unwrap(),
+        mtime,
+        "{:?}",
+        args
+    );
+    assert_eq!(scratch.entries(), before, "{:?}", args);
+}
+
+#[test]
+fn write_dry_run_leaves_the_file_alone() {
+    assert_untouched("dry-write", &["write", "-f", "data.bin", "-o", "2", "4142"]);
+    assert_untouched(
+        "dry-write-grow",
+        &["write", "-f", "data.bin", "-o", "30", "00"],
+    );
+    assert_untouched(
+        "dry-poke",
+        &[
+            "write", "-f", "data.bin", "--poke", "1=00", "--poke", "4=ff",
+        ],
+    );
+    assert_untouched("dry-patch", &["patch", "-f", "data.bin", "edit.patch"]);
+}
+
+#[test]
+fn transform_dry_run_leaves_the_file_alone() {
+    assert_untouched("dry-xor", &["transform", "-f", "data.bin", "--xor", "01"]);
+    assert_untouched(
+        "dry-swap",
+        &["transform", "-f", "data.bin", "-s", "8", "--swap", "4"],
+    );
+    assert_untouched(
+        "dry-output",
+        &[
+            "transform",
+            "-f",
+            "data.bin",
+            "--xor",
+            "01",
+            "--output",
+            "copy.bin",
+        ],
+    );
+}
+
+#[test]
+fn other_edits_dry_run_leave_the_file_alone() {
+    assert_untouched(
+        "dry-fill",
+        &["fill-random", "-f", "data.bin", "-s", "4", "--seed", "1"],
+    );
+    assert_untouched(
+        "dry-copy",
+        &["copy", "-f", "edit.patch", "-s", "4", "--to", "data.bin"],
+    );
+    assert_untouched("dry-replace", &["replace", "-f", "data.bin", "6c6c=4c4c"]);
+    assert_untouched(
+        "dry-seal",
+        &[
+            "seal",
+            "-f",
+            "data.bin",
+            "--range",
+            "0:8",
+            "--algo",
+            "crc32",
+            "--write-to",
+            "8",
+        ],
+    );
+}
@@ -0,0 +1,203 @@
+mod common;
+
+use common::{random_bytes, run_in, stderr, stdout, Scratch};
+
+/// The dump of `data.bin` in `scratch` with `args` after the file.
+fn dumped(scratch: &Scratch, args: &[&str]) -> String {
+    let mut all = vec!["dump", "-f", "data.bin"];
+    all.extend(args);
+    let output = run_in(&scratch.dir, &all, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    stdout(&output)
+}
+
+#[test]
+fn raw_output_honours_an_explicit_max_bytes() {
+    let scratch = Scratch::new("dump-raw-cap");
+    let data = random_bytes(124, 100_000);
+    scratch.file("data.bin", &data);
+
+    let args = [
+        "dump",
+        "-f",
+        "data.bin",
+        "--format",
+        "raw",
+        "--max-bytes",
+        "32",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(output.stdout, &data[..32]);
+    assert!(stderr(&output).contains("output truncated to 32 bytes"));
+
+    // Exactly the cap is not a truncation
+    let args = [
+        "dump",
+        "-f",
+        "data.bin",
+        "-o",
+        "99968",
+        "--format",
+        "raw",
+        "--max-bytes",
+        "32",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert_eq!(output.stdout, &data[99_968..]);
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn raw_output_without_max_bytes_is_not_capped() {
+    let scratch = Scratch::new("dump-raw-all");
+    let data = random_bytes(125, 300_000);
+    scratch.file("data.bin", &data);
+
+    let output = run_in(
+        &scratch.dir,
+        &["dump", "-f", "data.bin", "--format", "raw"],
+        b"",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(output.stdout == data);
+    assert_eq!(stderr(&output), "");
+
+    // Stdin is copied through the same way, kept within a pipe's buffer
+    // since run_in writes all of it before reading any output
+    let data = &data[..20_000];
+    let output = run_in(
+        &scratch.dir,
+        &["dump", "-f", "-", "--format", "raw", "-o", "10"],
+        data,
+    );
+    assert!(output.stdout == data[10..]);
+}
+
+#[test]
+fn open_ended_reads_of_character_devices_are_refused() {
+    let scratch = Scratch::new("dump-char-device");
+    for format in ["hex", "raw"] {
+        let args = ["dump", "-f", "/dev/zero", "--format", format];
+        let output = run_in(&scratch.dir, &args, b"");
+        assert_eq!(output.status.code(), Some(1), "{}", format);
+        assert!(output.stdout.is_empty());
+        assert!(stderr(&output)
+            .contains("/dev/zero is a character device and may never end; give --size"));
+    }
+
+    let args = ["dump", "-f", "/dev/zero", "--format", "raw", "-s", "100"];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(output.stdout, [0; 100]);
+}
+
+#[test]
+fn each_radix_renders_a_golden_line() {
+    let scratch = Scratch::new("dump-radix");
+    scratch.file("data.bin", b"Hello, world!\x00\x01\x02ABCD");
+
+    let cases = [
+        (
+            "hex",
+            "00000000: 48 65 6c 6c 6f 2c 20 77 6f 72 6c 64 21 00 01 02 |Hello, world!...|\n\
+             00000010: 41 42 43 44                                     |ABCD|\n",
+        ),
+        (
+            "oct",
+            "00000000: 110 145 154 154 157 054 040 167 157 162 154 144 041 000 001 002 |Hello, world!...|\n\
+             00000010: 101 102 103 104                                                 |ABCD|\n",
+        ),
+        (
+            "dec",
+            "00000000: 072 101 108 108 111 044 032 119 111 114 108 100 033 000 001 002 |Hello, world!...|\n\
+             00000010: 065 066 067 068                                                 |ABCD|\n",
+        ),
+        (
+            "bin",
+            "00000000: 01001000 01100101 01101100 01101100 01101111 00101100 00100000 01110111 \
+             01101111 01110010 01101100 01100100 00100001 00000000 00000001 00000010 |Hello, world!...|\n\
+             00000010: 01000001 01000010 01000011 01000100 \
+             {pad}|ABCD|\n",
+        ),
+    ];
+    for (radix, expected) in cases {
+        let expected = expected.replace("{pad}", &" ".repeat(12 * 9));
+        assert_eq!(dumped(&scratch, &["--radix", radix]), expected, "{}", radix);
+    }
+}
+
+#[test]
+fn repeated_lines_squeeze_to_a_star() {
+    let scratch = Scratch::new("dump-squeeze");
+    let line = |byte: u8| {
+        format!(
+            "{} |{}|\n",
+            vec![format!("{:02x}", byte); 16].join(" "),
+            '.'.to_string().repeat(16)
+        )
+    };
+    let zeros = line(0);
+
+    // A run ended by a partial line prints that line at its own offset
+    let mut data = vec![0xff; 16];
+    data.extend([0; 64]);
+    data.extend(b"tail!");
+    scratch.file("data.bin", &data);
+    let expected = format!(
+        "00000000: {}00000010: {}*\n00000050: 74 61 69 6c 21{}|tail!|\n",
+        line(0xff),
+        zeros,
+        " ".repeat(11 * 3 + 1)
+    );
+    assert_eq!(dumped(&scratch, &[]), expected);
+
+    // A run to the end of the file is followed by the end offset
+    scratch.file("data.bin", &data[..80]);
+    let expected = format!("00000000: {}00000010: {}*\n00000050\n", line(0xff), zeros);
+    assert_eq!(dumped(&scratch, &[]), expected);
+
+    // A partial line is never squeezed into the run before it
+    scratch.file("data.bin", &[0; 83]);
+    let expected = format!(
+        "00000000: {zeros}*\n00000050: 00 00 00{}|...|\n",
+        " ".repeat(13 * 3 + 1)
+    );
+    assert_eq!(dumped(&scratch, &[]), expected);
+
+    let unsqueezed = dumped(&scratch, &["--no-squeeze"]);
+    assert_eq!(unsqueezed.lines().count(), 6);
+    assert!(!unsqueezed.contains('*'));
+}
+
+#[test]
+fn highlights_wrap_across_lines() {
+    let scratch = Scratch::new("dump-highlight");
+    scratch.file("data.bin", b"Hello, world!\x00\x01\x02ABCD");
+
+    // Without color the run is bracketed, closing at the end of one line
+    // and opening again at the start of the next
+    let args = ["--highlight", "21000102 4142", "--highlight", "6c"];
+    assert_eq!(
+        dumped(&scratch, &args),
+        "00000000: 48 65[6c 6c]6f 2c 20 77 6f 72[6c]64[21 00 01 02]|Hello, world!...|\n\
+         00000010:[41 42]43 44                                     |ABCD|\n"
+    );
+
+    // With color both columns are painted on each side of the wrap
+    let args = ["--highlight", "0241", "--color", "always"];
+    assert_eq!(
+        dumped(&scratch, &args),
+        "00000000: 48 65 6c 6c 6f 2c 20 77 6f 72 6c 64 21 00 01 \x1b[1;31m02\x1b[0m \
+         |Hello, world!..\x1b[1;31m.\x1b[0m|\n\
+         00000010: \x1b[1;31m41\x1b[0m 42 43 44                                     \
+         |\x1b[1;31mA\x1b[0mBCD|\n"
+    );
+
+    // A highlighted line is shown even where it would be squeezed
+    scratch.file("data.bin", &[0; 48]);
+    let args = ["--highlight", "0000", "--color", "never"];
+    let dump = dumped(&scratch, &args);
+    assert_eq!(dump.lines().count(), 3);
+    assert!(!dump.contains('*'));
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::{run_in, stderr, stdout, Scratch};
+use std::fs;
+
+#[test]
+fn find_writes_its_results_to_out() {
+    let scratch = Scratch::new("find-out");
+    scratch.file("data.bin", b"hello world, hello!");
+
+    let args = ["find", "-f", "data.bin", "6c6c", "--out", "found.txt"];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "Output saved to: found.txt\n");
+    assert_eq!(
+        fs::read_to_string(scratch.path("found.txt")).unwrap(),
+        "0x00000002\n0x0000000f\n2 matches found.\n"
+    );
+
+    let args = [
+        "find",
+        "-f",
+        "data.bin",
+        "6c6c",
+        "--json",
+        "--out",
+        "found.json",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        fs::read_to_string(scratch.path("found.json")).unwrap(),
+        "[2,15]\n"
+    );
+
+    let args = [
+        "find",
+        "-f",
+        "data.bin",
+        "6f21",
+        "-C",
+        "2",
+        "--out",
+        "context.txt",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let context = fs::read_to_string(scratch.path("context.txt")).unwrap();
+    assert!(context.starts_with("Match at 0x11:\n"), "{}", context);
+    assert!(!context.contains('\x1b'));
+}
+
+#[test]
+fn find_refuses_color_always_for_out() {
+    let scratch = Scratch::new("find-out-color");
+    scratch.file("data.bin", b"abcabc");
+
+    let args = [
+        "find", "-f", "data.bin", "62", "-C", "1", "--color", "always", "--out", "x.txt",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("--color always cannot be used with --out"));
+    assert!(!scratch.path("x.txt").exists());
+    assert_eq!(scratch.entries(), ["data.bin"]);
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use common::{assert_snapshot, run_in, stderr, stdout, Scratch};
+
+fn files() -> Scratch {
+    let scratch = Scratch::new("json");
+    scratch.file("a.bin", b"hello world, hello!");
+    scratch.file("b.bin", b"hello World, hellO?");
+    scratch
+}
+
+/// Runs a command that should succeed and checks its stdout against a
+/// snapshot.
+fn assert_json(name: &str, args: &[&str]) {
+    let scratch = files();
+    let output = run_in(&scratch.dir, args, b"");
+    assert!(output.status.success(), "{:?}: {}", args, stderr(&output));
+    assert_snapshot(name, &stdout(&output));
+}
+
+/// Runs a command that should fail and checks its stderr against a
+/// snapshot.
+fn assert_json_error(name: &str, args: &[&str]) {
+    let scratch = files();
+    scratch.file("taken.bin", b"");
+    let output = run_in(&scratch.dir, args, b"");
+    assert_eq!(output.status.code(), Some(1), "{:?}", args);
+    assert_eq!(stdout(&output), "", "{:?}", args);
+    assert_snapshot(name, &stderr(&output));
+}
+
+#[test]
+fn dump_json() {
+    assert_json("dump.json", &["dump", "-f", "a.bin", "--json"]);
+    assert_json(
+        "dump_relative.json",
+        &[
+            "dump",
+            "-f",
+            "a.bin",
+            "-o",
+            "4",
+            "-s",
+            "6",
+            "--relative",
+            "--json",
+        ],
+    );
+    assert_json(
+        "dump_ranges.json",
+        &[
+            "dump", "-f", "a.bin", "--range", "0:5", "--range", "6:5", "--json",
+        ],
+    );
+    assert_json(
+        "dump_past_end.json",
+        &["dump", "-f", "a.bin", "-o", "40", "--json"],
+    );
+    assert_json(
+        "dump_histogram.json",
+        &["dump", "-f", "a.bin", "--histogram", "--json"],
+    );
+}
+
+#[test]
+fn find_json() {
+    assert_json("find.json", &["find", "-f", "a.bin", "6c6c", "--json"]);
+    assert_json("find_none.json", &["find", "-f", "a.bin", "ffff", "--json"]);
+}
+
+#[test]
+fn compare_json() {
+    assert_json(
+        "compare.json",
+        &["dump", "-f", "a.bin", "--compare", "b.bin", "--json"],
+    );
+    assert_json(
+        "compare_self.json",
+        &["dump", "-f", "a.bin", "--compare-self", "0:13:5", "--json"],
+    );
+}
+
+#[test]
+fn cat_json() {
+    assert_json(
+        "cat.json",
+        &[
+            "cat", "--inputs", "a.bin", "b.bin", "--output", "c.bin", "--align", "8", "--json",
+        ],
+    );
+}
+
+#[test]
+fn errors_are_json_on_stderr() {
+    assert_json_error(
+        "error_missing_file.json",
+        &["dump", "-f", "missing.bin", "--json"],
+    );
+    assert_json_error(
+        "error_bad_pattern.json",
+        &["find", "-f", "a.bin", "zz", "--json"],
+    );
+    assert_json_error(
+        "error_output_exists.json",
+        &[
+            "cat",
+            "--inputs",
+            "a.bin",
+            "--output",
+            "taken.bin",
+            "--json",
+        ],
+    );
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use common::{run_in, stderr, stdout, Scratch};
+use std::fs;
+
+#[test]
+fn scripted_session_writes_undoes_and_reports() {
+    let scratch = Scratch::new("shell-session");
+    let path = scratch.file("data.bin", b"hello world, hello!");
+
+    let script = "\
+len
+f 6c6c
+d 0 4
+as 0 u16
+as 0 u16 be
+w 0 4a
+y
+w 0x13 2121
+y
+len
+undo
+len
+w 6 57
+n
+d
+";
+    let output = run_in(
+        &scratch.dir,
+        &["shell", "-f", "data.bin"],
+        script.as_bytes(),
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "\
+19 bytes
+0x00000002
+0x0000000f
+2 matches found.
+00000000: 68 65 6c 6c                                     |hell|
+00000000: u16  25960 (0x6568)
+00000000: u16  26725 (0x6865)
+Old bytes:
+00000000: 68                                              |h|
+New bytes:
+00000000: 4a                                              |J|
+Write 1 bytes at 0x0? [y/N] Successfully written 1 bytes.
+Old bytes:
+New bytes:
+00000013: 21 21                                           |!!|
+Write 2 bytes at 0x13? [y/N] Successfully written 2 bytes.
+21 bytes
+Restored 0 bytes at 0x13.
+19 bytes
+Old bytes:
+00000006: 77                                              |w|
+New bytes:
+00000006: 57                                              |W|
+Write 1 bytes at 0x6? [y/N] Not written.
+00000004: 6f 20 77 6f 72 6c 64 2c 20 68 65 6c 6c 6f 21    |o world, hello!|
+"
+    );
+    assert_eq!(stderr(&output), "");
+    assert_eq!(fs::read(&path).unwrap(), b"Jello world, hello!");
+}
+
+#[test]
+fn errors_go_to_stderr_and_the_shell_carries_on() {
+    let scratch = Scratch::new("shell-errors");
+    let path = scratch.file("data.bin", b"abcd");
+
+    let script = "undo\nbogus\nw 0 zz\nas 2 u32\nd 0 2\nquit\nw 0 00\ny\n";
+    let output = run_in(
+        &scratch.dir,
+        &["shell", "-f", "data.bin"],
+        script.as_bytes(),
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "00000000: 61 62                                           |ab|\n"
+    );
+    assert_eq!(
+        stderr(&output),
+        "\
+Error: nothing to undo
+Error: unknown command or wrong arguments: 'bogus' (try 'help')
+Error: invalid hex string: invalid hex character 'z' at position 0 (after removing separators)
+Error: u32 at offset 0x2 needs 4 bytes, only 2 available
+"
+    );
+    // Nothing after quit is run
+    assert_eq!(fs::read(&path).unwrap(), b"abcd");
+}
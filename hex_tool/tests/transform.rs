@@ -0,0 +1,158 @@
+mod common;
+
+use common::{random_bytes, run_in, stderr, stdout, Scratch};
+use std::fs;
+
+/// Transforms are applied 64 KiB at a time; a 3-byte key does not divide
+/// that, so each chunk after the first starts part way through the key.
+#[test]
+fn xor_key_cycles_across_chunk_boundaries() {
+    let scratch = Scratch::new("transform-xor");
+    let original = random_bytes(7, 200_000);
+    let path = scratch.file("data.bin", &original);
+
+    let output = run_in(
+        &scratch.dir,
+        &["transform", "-f", "data.bin", "--xor", "a1b2c3"],
+        b"",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let key = [0xa1, 0xb2, 0xc3];
+    let expected: Vec<u8> = original
+        .iter()
+        .enumerate()
+        .map(|(i, byte)| byte ^ key[i % key.len()])
+        .collect();
+    assert_eq!(fs::read(&path).unwrap(), expected);
+}
+
+#[test]
+fn xor_key_cycles_from_the_start_of_an_offset_range() {
+    let scratch = Scratch::new("transform-offset");
+    let original = random_bytes(11, 140_000);
+    let path = scratch.file("data.bin", &original);
+
+    let args = [
+        "transform",
+        "-f",
+        "data.bin",
+        "-o",
+        "0x1001",
+        "-s",
+        "131075",
+        "--xor",
+        "0102030405",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let mut expected = original.clone();
+    for (i, byte) in expected[0x1001..0x1001 + 131_075].iter_mut().enumerate() {
+        *byte ^= [1, 2, 3, 4, 5][i % 5];
+    }
+    assert_eq!(fs::read(&path).unwrap(), expected);
+}
+
+#[test]
+fn output_is_not_replaced_without_force() {
+    let scratch = Scratch::new("transform-output");
+    let input = scratch.file("data.bin", b"\x00\x01\x02\x03");
+    let existing = scratch.file("out.bin", b"keep me");
+
+    let args = [
+        "transform",
+        "-f",
+        "data.bin",
+        "--xor",
+        "ff",
+        "--output",
+        "out.bin",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("already exists (use --force to overwrite)"));
+    assert_eq!(fs::read(&existing).unwrap(), b"keep me");
+
+    let forced = [&args[..], &["--force"]].concat();
+    let output = run_in(&scratch.dir, &forced, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fs::read(&existing).unwrap(), b"\xff\xfe\xfd\xfc");
+    assert_eq!(fs::read(&input).unwrap(), b"\x00\x01\x02\x03");
+}
+
+#[test]
+fn output_cannot_be_the_input() {
+    let scratch = Scratch::new("transform-same");
+    let input = scratch.file("data.bin", b"\x00\x01\x02\x03");
+
+    let args = [
+        "transform",
+        "-f",
+        "data.bin",
+        "--xor",
+        "ff",
+        "--output",
+        "./data.bin",
+        "--force",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(!output.status.success());
+    assert_eq!(fs::read(&input).unwrap(), b"\x00\x01\x02\x03");
+}
+
+#[test]
+fn swap_reverses_each_word() {
+    let scratch = Scratch::new("transform-swap");
+    let original: Vec<u8> = (0..16).collect();
+    let cases = [
+        (
+            "2",
+            "00000000: 01 00 03 02 05 04 07 06 09 08 0b 0a 0d 0c 0f 0e |................|\n",
+        ),
+        (
+            "4",
+            "00000000: 03 02 01 00 07 06 05 04 0b 0a 09 08 0f 0e 0d 0c |................|\n",
+        ),
+        (
+            "8",
+            "00000000: 07 06 05 04 03 02 01 00 0f 0e 0d 0c 0b 0a 09 08 |................|\n",
+        ),
+    ];
+    for (width, expected) in cases {
+        scratch.file("data.bin", &original);
+        let output = run_in(
+            &scratch.dir,
+            &["transform", "-f", "data.bin", "--swap", width],
+            b"",
+        );
+        assert!(output.status.success(), "{}", stderr(&output));
+        let dump = run_in(&scratch.dir, &["dump", "-f", "data.bin"], b"");
+        assert_eq!(stdout(&dump), expected, "--swap {}", width);
+    }
+
+    // Words are whole across the chunks a large range is applied in
+    let original = random_bytes(126, 200_003);
+    let path = scratch.file("data.bin", &original);
+    let args = ["transform", "-f", "data.bin", "-o", "3", "--swap", "8"];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let mut expected = original.clone();
+    expected[3..].chunks_mut(8).for_each(<[u8]>::reverse);
+    assert!(fs::read(&path).unwrap() == expected);
+}
+
+#[test]
+fn swap_rejects_a_range_of_partial_words() {
+    let scratch = Scratch::new("transform-swap-partial");
+    let original: Vec<u8> = (0..16).collect();
+    let path = scratch.file("data.bin", &original);
+
+    let args = ["transform", "-f", "data.bin", "-o", "2", "--swap", "4"];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stderr(&output).contains("14 bytes at offset 0x2 is not a whole number of 4-byte words")
+    );
+    assert_eq!(fs::read(&path).unwrap(), original);
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use common::{random_bytes, run_in, stderr, stdout, Scratch};
+use std::fs;
+
+#[test]
+fn undo_restores_a_change_spanning_several_journal_lines() {
+    let scratch = Scratch::new("undo-restore");
+    let original = random_bytes(5, 200_000);
+    let path = scratch.file("data.bin", &original);
+
+    let args = [
+        "fill-random",
+        "-f",
+        "data.bin",
+        "-o",
+        "100",
+        "-s",
+        "150000",
+        "--seed",
+        "1",
+        "--journal",
+        "data.journal",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_ne!(fs::read(&path).unwrap(), original);
+
+    let journal = fs::read_to_string(scratch.path("data.journal")).unwrap();
+    assert_eq!(
+        journal.lines().filter(|l| l.starts_with("range ")).count(),
+        3
+    );
+
+    let args = ["undo", "-f", "data.bin", "--journal", "data.journal"];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Restored 150000 bytes in 1 ranges of data.bin (200000 bytes).\n"
+    );
+    assert_eq!(fs::read(&path).unwrap(), original);
+}
+
+#[test]
+fn undo_with_a_corrupt_journal_changes_nothing() {
+    let scratch = Scratch::new("undo-corrupt");
+    let path = scratch.file("data.bin", &random_bytes(9, 100_000));
+
+    let args = [
+        "fill-random",
+        "-f",
+        "data.bin",
+        "-s",
+        "100000",
+        "--seed",
+        "2",
+        "--journal",
+        "data.journal",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let filled = fs::read(&path).unwrap();
+
+    // Damage the last range line, after the first has already been read
+    let journal = fs::read_to_string(scratch.path("data.journal")).unwrap();
+    let mut lines: Vec<String> = journal.lines().map(str::to_string).collect();
+    let last = lines.iter().rposition(|l| l.starts_with("range ")).unwrap();
+    let digit = if lines[last].ends_with('0') { "1" } else { "0" };
+    lines[last].pop();
+    lines[last].push_str(digit);
+    fs::write(scratch.path("data.journal"), lines.join("\n") + "\n").unwrap();
+
+    let args = ["undo", "-f", "data.bin", "--journal", "data.journal"];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("is corrupt"),
+        "{}",
+        stderr(&output)
+    );
+    assert_eq!(fs::read(&path).unwrap(), filled);
+}
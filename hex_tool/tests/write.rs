@@ -0,0 +1,41 @@
+mod common;
+
+use common::{run_in, stderr, Scratch};
+use std::fs;
+
+#[test]
+fn write_past_the_largest_offset_is_rejected() {
+    let scratch = Scratch::new("write-overflow");
+    let path = scratch.file("data.bin", b"x");
+
+    let args = [
+        "write",
+        "-f",
+        "data.bin",
+        "-o",
+        "0xffffffffffffffff",
+        "0a0b",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("past the largest 64-bit offset"));
+    assert_eq!(fs::read(&path).unwrap(), b"x");
+}
+
+#[test]
+fn poke_past_the_largest_offset_is_rejected() {
+    let scratch = Scratch::new("write-poke-overflow");
+    let path = scratch.file("data.bin", b"x");
+
+    let args = [
+        "write",
+        "-f",
+        "data.bin",
+        "--poke",
+        "0xffffffffffffffff=0a0b",
+    ];
+    let output = run_in(&scratch.dir, &args, b"");
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("past the largest 64-bit offset"));
+    assert_eq!(fs::read(&path).unwrap(), b"x");
+}
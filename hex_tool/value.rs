@@ -0,0 +1,149 @@
+use crate::number::parse_signed;
+use clap::ValueEnum;
+
+/// Byte order used when interpreting multi-byte values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Endian {
+    /// Little-endian (least significant byte first)
+    Le,
+    /// Big-endian (most significant byte first)
+    Be,
+}
+
+/// A decoded primitive value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Decoded {
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+}
+
+/// A primitive type that a run of bytes can be decoded as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ValueType {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+impl ValueType {
+    /// Number of bytes the type occupies.
+    pub fn width(self) -> usize {
+        match self {
+            ValueType::U8 | ValueType::I8 => 1,
+            ValueType::U16 | ValueType::I16 => 2,
+            ValueType::U32 | ValueType::I32 | ValueType::F32 => 4,
+            ValueType::U64 | ValueType::I64 | ValueType::F64 => 8,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ValueType::U8 => "u8",
+            ValueType::U16 => "u16",
+            ValueType::U32 => "u32",
+            ValueType::U64 => "u64",
+            ValueType::I8 => "i8",
+            ValueType::I16 => "i16",
+            ValueType::I32 => "i32",
+            ValueType::I64 => "i64",
+            ValueType::F32 => "f32",
+            ValueType::F64 => "f64",
+        }
+    }
+
+    /// Decodes `bytes`, which must be exactly `width()` long.
+    pub fn decode(self, bytes: &[u8], endian: Endian) -> Decoded {
+        let raw = read_raw(bytes, endian);
+        let bits = 8 * self.width() as u32;
+        match self {
+            ValueType::U8 | ValueType::U16 | ValueType::U32 | ValueType::U64 => Decoded::Unsigned(raw),
+            ValueType::I8 | ValueType::I16 | ValueType::I32 | ValueType::I64 => {
+                // Sign-extend from the type's width to 64 bits
+                Decoded::Signed(((raw << (64 - bits)) as i64) >> (64 - bits))
+            }
+            ValueType::F32 => Decoded::Float(f32::from_bits(raw as u32) as f64),
+            ValueType::F64 => Decoded::Float(f64::from_bits(raw)),
+        }
+    }
+
+    /// Formats `bytes` as a decimal value, followed by the raw hex for
+    /// integer types.
+    pub fn format(self, bytes: &[u8], endian: Endian) -> String {
+        let raw = read_raw(bytes, endian);
+        let hex_width = 2 * self.width();
+        match self.decode(bytes, endian) {
+            Decoded::Unsigned(v) => format!("{} (0x{:0w$x})", v, raw, w = hex_width),
+            Decoded::Signed(v) => format!("{} (0x{:0w$x})", v, raw, w = hex_width),
+            Decoded::Float(v) if self == ValueType::F32 => format!("{}", v as f32),
+            Decoded::Float(v) => format!("{}", v),
+        }
+    }
+
+    /// Encodes a number typed by the user: an integer literal (decimal,
+    /// `0x`, `0o` or `0b`, negative only for signed types) checked against
+    /// the type's range, or a decimal float for `f32`/`f64`.
+    pub fn encode(self, text: &str, endian: Endian) -> Result<Vec<u8>, String> {
+        let bits = 8 * self.width() as u32;
+        let raw = match self {
+            ValueType::F32 | ValueType::F64 => {
+                let value: f64 = text.trim().parse().map_err(|_| format!("'{}' is not a valid number", text))?;
+                if self == ValueType::F64 {
+                    value.to_bits()
+                } else if value.is_finite() && (value as f32).is_infinite() {
+                    return Err(format!("{} is out of range for f32", text));
+                } else {
+                    (value as f32).to_bits() as u64
+                }
+            }
+            _ => {
+                let (negative, magnitude) = parse_signed(text.trim()).map_err(|e| e.to_string())?;
+                let signed = matches!(self, ValueType::I8 | ValueType::I16 | ValueType::I32 | ValueType::I64);
+                // Largest magnitude allowed for each sign
+                let (max_positive, max_negative) = if signed {
+                    (u64::MAX >> (65 - bits), 1u64 << (bits - 1))
+                } else {
+                    (u64::MAX >> (64 - bits), 0)
+                };
+                let out_of_range = if negative {
+                    magnitude > max_negative
+                } else {
+                    magnitude > max_positive
+                };
+                if out_of_range {
+                    return Err(format!("{} is out of range for {}", text, self.name()));
+                }
+                let value = if negative { magnitude.wrapping_neg() } else { magnitude };
+                // Keep only the type's width of the two's complement pattern
+                value & (u64::MAX >> (64 - bits))
+            }
+        };
+
+        let bytes = raw.to_le_bytes()[..self.width()].to_vec();
+        Ok(match endian {
+            Endian::Le => bytes,
+            Endian::Be => bytes.into_iter().rev().collect(),
+        })
+    }
+
+    /// Looks a type up by its lowercase name (`u8`, `i32`, `f64`, ...).
+    pub fn from_name(name: &str) -> Option<ValueType> {
+        <ValueType as ValueEnum>::from_str(name, false).ok()
+    }
+}
+
+/// Assembles up to eight bytes into an unsigned integer.
+fn read_raw(bytes: &[u8], endian: Endian) -> u64 {
+    let fold = |acc: u64, &b: &u8| (acc << 8) | b as u64;
+    match endian {
+        Endian::Be => bytes.iter().fold(0, fold),
+        Endian::Le => bytes.iter().rev().fold(0, fold),
+    }
+}
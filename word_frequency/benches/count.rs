@@ -0,0 +1,80 @@
+//! Times counting with and without case folding over corpora that need
+//! different amounts of lowercasing. Each case is run several times and
+//! the fastest run is reported, in nanoseconds per word.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use word_frequency::counter::WordCounter;
+
+const WORDS: usize = 400_000;
+const RUNS: usize = 7;
+
+/// `WORDS` words drawn in turn from `vocabulary`, a line of ten at a time.
+fn corpus(vocabulary: &[&str]) -> String {
+    let mut text = String::new();
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    for i in 0..WORDS {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        text.push_str(vocabulary[(state % vocabulary.len() as u64) as usize]);
+        text.push(if i % 10 == 9 { '\n' } else { ' ' });
+    }
+    text
+}
+
+fn time(text: &str, ignore_case: bool) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let mut counter = WordCounter::builder().case_insensitive(ignore_case).build();
+            let start = Instant::now();
+            counter.feed(black_box(text));
+            counter.end_input();
+            black_box(counter.unique_words());
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let corpora = [
+        (
+            "ascii lowercase",
+            corpus(&[
+                "the", "quick", "brown", "fox", "jumps", "over", "lazy", "dog",
+            ]),
+        ),
+        (
+            "ascii capitalized",
+            corpus(&[
+                "The", "Quick", "brown", "Fox", "jumps", "OVER", "lazy", "Dog",
+            ]),
+        ),
+        (
+            "non-ascii lowercase",
+            corpus(&[
+                "café", "straße", "naïve", "élan", "über", "cœur", "façade", "año",
+            ]),
+        ),
+        (
+            "non-ascii capitalized",
+            corpus(&[
+                "Café", "Straße", "NAÏVE", "Élan", "Über", "cœur", "Façade", "AÑO",
+            ]),
+        ),
+    ];
+    println!(
+        "{:<24} {:>12} {:>14}",
+        "corpus", "as written", "ignoring case"
+    );
+    for (name, text) in &corpora {
+        let per_word = |d: Duration| d.as_nanos() as f64 / WORDS as f64;
+        println!(
+            "{:<24} {:>9.1} ns {:>11.1} ns",
+            name,
+            per_word(time(text, false)),
+            per_word(time(text, true))
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+
+/// How the vocabulary of one input differs from another's.
+#[derive(Debug, Default)]
+pub struct Comparison {
+    /// Words only found in the first input, most frequent first
+    pub only_a: Vec<(String, u64)>,
+    /// Words only found in the second input, most frequent first
+    pub only_b: Vec<(String, u64)>,
+    /// Words in both whose counts differ by more than the threshold, by
+    /// the size of the difference
+    pub changed: Vec<Change>,
+}
+
+/// A word found in both inputs.
+#[derive(Debug)]
+pub struct Change {
+    pub word: String,
+    pub count_a: u64,
+    pub count_b: u64,
+}
+
+impl Change {
+    /// How many more times the word is found in the second input.
+    pub fn difference(&self) -> i64 {
+        self.count_b as i64 - self.count_a as i64
+    }
+}
+
+/// Compares the counts of two inputs. Words in both are reported when
+/// their counts differ by more than `delta`.
+pub fn compare(a: HashMap<String, u64>, mut b: HashMap<String, u64>, delta: u64) -> Comparison {
+    let mut comparison = Comparison::default();
+    for (word, count_a) in a {
+        match b.remove(&word) {
+            None => comparison.only_a.push((word, count_a)),
+            Some(count_b) if count_a.abs_diff(count_b) > delta => comparison.changed.push(Change {
+                word,
+                count_a,
+                count_b,
+            }),
+            Some(_) => {}
+        }
+    }
+    comparison.only_b.extend(b);
+
+    let by_count = |x: &(String, u64), y: &(String, u64)| y.1.cmp(&x.1).then_with(|| x.0.cmp(&y.0));
+    comparison.only_a.sort_by(by_count);
+    comparison.only_b.sort_by(by_count);
+    comparison.changed.sort_by(|x, y| {
+        y.difference()
+            .abs()
+            .cmp(&x.difference().abs())
+            .then_with(|| x.word.cmp(&y.word))
+    });
+    comparison
+}
@@ -0,0 +1,1586 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::io::{self, BufRead};
+
+use crate::kwic::{Concordance, Occurrence};
+use crate::markup::MarkupStripper;
+use crate::normalize::Normalization;
+use crate::pattern::Pattern;
+use crate::phrases::PhraseMatcher;
+use crate::progress;
+use crate::readability::Readability;
+use crate::stats::{histogram_median, Stats};
+use crate::stem::StemLang;
+use crate::tokenize::{Delimiters, Token, Tokenizer};
+
+/// What counts as a word and which words are counted.
+#[derive(Clone, Copy, Debug)]
+pub struct CountOptions {
+    pub tokenizer: Tokenizer,
+    /// The Unicode form words are put in, before case processing
+    pub normalization: Normalization,
+    /// Remove combining marks, such as accents, along with normalizing
+    pub strip_accents: bool,
+    pub ignore_case: bool,
+    /// Count words regardless of case, as `ignore_case` does, but list each
+    /// in the casing it was most often written in
+    pub smart_case: bool,
+    /// Words shorter than this many characters are not counted
+    pub min_length: usize,
+    /// Words longer than this many characters are not counted
+    pub max_length: Option<usize>,
+    /// Count sequences of this many consecutive words instead of single words
+    pub ngrams: usize,
+    /// Count sequences of this many consecutive characters within each
+    /// word instead of the words
+    pub char_ngrams: Option<usize>,
+    /// With `char_ngrams`, also count sequences that have characters other
+    /// than letters
+    pub include_symbols: bool,
+    /// Keep n-grams from spanning line breaks
+    pub per_line: bool,
+    /// Keep n-grams from spanning sentence-ending punctuation
+    pub sentence_bounded: bool,
+    /// Leave out tokens that are entirely whitespace (for --chars)
+    pub skip_whitespace: bool,
+    /// Which numeric tokens to leave out
+    pub numbers: NumberFilter,
+    /// Count words under their stems in this language
+    pub stem: Option<StemLang>,
+    /// Keep track of the words counted under each stem
+    pub show_variants: bool,
+    /// Also count pairs of adjacent words (single words only)
+    pub collocations: bool,
+}
+
+impl Default for CountOptions {
+    /// Single words as the simple tokenizer splits them, all counted as
+    /// written.
+    fn default() -> CountOptions {
+        CountOptions {
+            tokenizer: Tokenizer::default(),
+            normalization: Normalization::default(),
+            strip_accents: false,
+            ignore_case: false,
+            smart_case: false,
+            min_length: 1,
+            max_length: None,
+            ngrams: 1,
+            char_ngrams: None,
+            include_symbols: false,
+            per_line: false,
+            sentence_bounded: false,
+            skip_whitespace: false,
+            numbers: NumberFilter::default(),
+            stem: None,
+            show_variants: false,
+            collocations: false,
+        }
+    }
+}
+
+/// Which tokens are left out as numbers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NumberFilter {
+    #[default]
+    Keep,
+    /// Tokens made only of digits (--no-numbers)
+    Digits,
+    /// Also digits mixed with number punctuation, such as 1,000, 3.14 or
+    /// 12:30 (--no-numerics)
+    Numerics,
+}
+
+impl NumberFilter {
+    fn excludes(self, word: &str) -> bool {
+        let has_digit = || word.chars().any(char::is_numeric);
+        match self {
+            NumberFilter::Keep => false,
+            NumberFilter::Digits => has_digit() && word.chars().all(char::is_numeric),
+            NumberFilter::Numerics => {
+                has_digit()
+                    && word
+                        .chars()
+                        .all(|c| c.is_numeric() || matches!(c, '.' | ',' | '\'' | ':' | '/' | '-'))
+            }
+        }
+    }
+}
+
+/// Accumulates word counts from any number of pieces of text, so memory
+/// grows with the vocabulary rather than with the input.
+#[derive(Clone, Debug)]
+pub struct WordCounter {
+    options: CountOptions,
+    /// Counts the matches of this instead of using the tokenizer
+    pattern: Option<Pattern>,
+    /// Splits words at these characters, in place of the simple tokenizer
+    delimiters: Option<Delimiters>,
+    stopwords: HashSet<String>,
+    stopwords_dropped: u64,
+    numbers_dropped: u64,
+    characters: u64,
+    /// Replacement characters read, each where the input had bytes that
+    /// could not be decoded
+    decoding_errors: u64,
+    /// How many counted tokens had each length, in characters
+    lengths: Vec<u64>,
+    /// How many distinct words had each length
+    distinct_lengths: Vec<u64>,
+    /// The last words seen, from which the next n-gram is formed
+    window: VecDeque<String>,
+    /// The words of `window` as written, with `smart_case`
+    cased_window: VecDeque<String>,
+    counts: HashMap<String, u64>,
+    /// The words counted under each stem, with `show_variants`
+    variants: HashMap<String, HashMap<String, u64>>,
+    /// Each form a word was written in, with how often, in the order first
+    /// seen, with `smart_case`
+    casings: HashMap<String, Vec<(String, u64)>>,
+    /// Adjacent pairs of counted words, with `collocations`
+    pairs: HashMap<(String, String), u64>,
+    /// Counts the phrases of `--phrases` as the words go by
+    phrases: Option<PhraseMatcher>,
+    /// The only words counted, with `--only`, by their position in the list
+    only: Option<HashMap<String, usize>>,
+    /// Removes markup from the text before it is tokenized
+    markup: Option<MarkupStripper>,
+    /// Collects the occurrences of `--kwic`
+    concordance: Option<Concordance>,
+    /// Tallies sentences and syllables for the readability statistics
+    readability: Option<Readability>,
+    /// The line of the current input being read, from 1
+    line: usize,
+}
+
+/// A pair of adjacent words and how much more often they appear together
+/// than chance would have it.
+#[derive(Clone, Debug)]
+pub struct Collocation {
+    pub pair: String,
+    pub count: u64,
+    /// Pointwise mutual information, in bits
+    pub pmi: f64,
+}
+
+/// Words spelled with the same letters, as counted, and how often they
+/// were seen together.
+#[derive(Clone, Debug)]
+pub struct AnagramGroup {
+    /// Most common first, then alphabetically
+    pub words: Vec<(String, u64)>,
+    pub count: u64,
+}
+
+/// The tokens and distinct words of one length.
+#[derive(Clone, Copy, Debug)]
+pub struct LengthCount {
+    pub length: usize,
+    pub tokens: u64,
+    pub words: u64,
+}
+
+/// The tokens and distinct words starting with one letter, and the most
+/// common of them.
+#[derive(Clone, Debug)]
+pub struct InitialCount {
+    /// `None` for the words that start with anything but a letter
+    pub initial: Option<char>,
+    pub tokens: u64,
+    pub words: usize,
+    /// The most common word, the first alphabetically of those tied
+    pub top: (String, u64),
+}
+
+/// Adds one to the entry for `length` in a histogram by length.
+fn tally(histogram: &mut Vec<u64>, length: usize) {
+    if histogram.len() <= length {
+        histogram.resize(length + 1, 0);
+    }
+    histogram[length] += 1;
+}
+
+/// Whether lowercasing changes `c`. This is wider than `char::is_uppercase`,
+/// which leaves out titlecase letters such as 'ǅ'.
+fn changes_case(c: char) -> bool {
+    let mut lower = c.to_lowercase();
+    lower.next() != Some(c) || lower.next().is_some()
+}
+
+/// Adds `count` to `form` among the casings of a word.
+fn add_casing(forms: &mut Vec<(String, u64)>, form: &str, count: u64) {
+    match forms.iter_mut().find(|(seen, _)| seen == form) {
+        Some((_, seen_count)) => *seen_count += count,
+        None => forms.push((form.to_string(), count)),
+    }
+}
+
+/// The most frequent of `forms`, the first seen among equals.
+fn dominant_casing(forms: &[(String, u64)]) -> &str {
+    let mut best: Option<&(String, u64)> = None;
+    for form in forms {
+        if best.is_none_or(|best| form.1 > best.1) {
+            best = Some(form);
+        }
+    }
+    best.map_or("", |(form, _)| form)
+}
+
+/// `key` in the casing of `cased`, a form it was counted from: `cased`
+/// itself if that only differs in case, and otherwise, as for a stem,
+/// `key` in capitals, capitalized or as it is, after how `cased` starts.
+/// The words of an n-gram are recased one by one.
+fn recase(key: &str, cased: &str) -> String {
+    if cased.to_lowercase() == key {
+        return cased.to_string();
+    }
+    let keys = Vec::from_iter(key.split(' '));
+    let forms = Vec::from_iter(cased.split(' '));
+    if keys.len() > 1 && keys.len() == forms.len() {
+        let words = keys.iter().zip(&forms).map(|(key, form)| recase(key, form));
+        return Vec::from_iter(words).join(" ");
+    }
+    let upper = cased.chars().any(char::is_uppercase);
+    let lower = cased.chars().any(char::is_lowercase);
+    if upper && !lower {
+        key.to_uppercase()
+    } else if cased.starts_with(char::is_uppercase) {
+        let mut chars = key.chars();
+        chars.next().map_or(String::new(), |first| {
+            first.to_uppercase().chain(chars).collect()
+        })
+    } else {
+        key.to_string()
+    }
+}
+
+/// The words that were counted under each stem, most frequent first.
+pub type Variants = HashMap<String, Vec<(String, u64)>>;
+
+/// Sets up a [`WordCounter`], starting from the default options.
+#[derive(Clone, Debug, Default)]
+pub struct WordCounterBuilder {
+    options: CountOptions,
+    stopwords: Vec<String>,
+}
+
+impl WordCounterBuilder {
+    /// Replaces every option at once, for those without a method here.
+    pub fn options(mut self, options: CountOptions) -> WordCounterBuilder {
+        self.options = options;
+        self
+    }
+
+    /// Counts words regardless of case, under their lowercase form.
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> WordCounterBuilder {
+        self.options.ignore_case = case_insensitive;
+        self
+    }
+
+    /// Leaves out words shorter than `min_length` characters.
+    pub fn min_length(mut self, min_length: usize) -> WordCounterBuilder {
+        self.options.min_length = min_length;
+        self
+    }
+
+    /// Adds words that are dropped instead of counted. They are matched
+    /// after case processing, so they can be given in any case once the
+    /// counter is case insensitive.
+    pub fn stopwords<S: AsRef<str>>(
+        mut self,
+        words: impl IntoIterator<Item = S>,
+    ) -> WordCounterBuilder {
+        self.stopwords
+            .extend(words.into_iter().map(|word| word.as_ref().to_string()));
+        self
+    }
+
+    /// Splits text into words with `tokenizer`.
+    pub fn tokenizer(mut self, tokenizer: Tokenizer) -> WordCounterBuilder {
+        self.options.tokenizer = tokenizer;
+        self
+    }
+
+    pub fn build(self) -> WordCounter {
+        // Stopwords wait until here to go through the final case options
+        let mut counter = WordCounter::new(self.options);
+        counter.add_stopwords(self.stopwords);
+        counter
+    }
+}
+
+impl WordCounter {
+    /// A builder for a counter with the default options.
+    pub fn builder() -> WordCounterBuilder {
+        WordCounterBuilder::default()
+    }
+
+    pub fn new(options: CountOptions) -> WordCounter {
+        WordCounter {
+            options,
+            pattern: None,
+            delimiters: None,
+            stopwords: HashSet::new(),
+            stopwords_dropped: 0,
+            numbers_dropped: 0,
+            characters: 0,
+            decoding_errors: 0,
+            lengths: Vec::new(),
+            distinct_lengths: Vec::new(),
+            window: VecDeque::with_capacity(options.ngrams),
+            cased_window: VecDeque::new(),
+            counts: HashMap::new(),
+            casings: HashMap::new(),
+            variants: HashMap::new(),
+            pairs: HashMap::new(),
+            phrases: None,
+            only: None,
+            markup: None,
+            concordance: None,
+            readability: None,
+            line: 1,
+        }
+    }
+
+    /// Adds words that are dropped instead of counted. They go through the
+    /// same case processing as the text, so they match exactly the words
+    /// they would otherwise have been counted as.
+    pub fn add_stopwords<S: AsRef<str>>(&mut self, words: impl IntoIterator<Item = S>) {
+        for word in words {
+            let word = self.normalize(word.as_ref());
+            self.stopwords.insert(word);
+        }
+    }
+
+    /// Removes markup from the text with `stripper` before it is tokenized.
+    pub fn set_markup(&mut self, stripper: MarkupStripper) {
+        self.markup = Some(stripper);
+    }
+
+    /// Treats every match of `pattern` as a word, in place of the tokenizer.
+    pub fn set_pattern(&mut self, pattern: Pattern) {
+        self.pattern = Some(pattern);
+    }
+
+    /// Collects every occurrence of `keyword`, which is matched as it would
+    /// be counted, with `window` words of context on each side.
+    pub fn set_kwic(&mut self, keyword: &str, window: usize) {
+        let keyword = self.counted_as(keyword);
+        self.concordance = Some(Concordance::new(keyword, window));
+    }
+
+    /// Tallies the sentences, words and syllables of the text for the
+    /// readability statistics, which sentences running across lines keep
+    /// from being counted a line at a time.
+    pub fn set_readability(&mut self) {
+        self.readability = Some(Readability::default());
+    }
+
+    /// Splits words at `delimiters`, in place of the simple tokenizer.
+    pub fn set_delimiters(&mut self, delimiters: Delimiters) {
+        self.delimiters = Some(delimiters);
+    }
+
+    /// Counts how often each of `phrases` is found among the counted words.
+    /// A phrase is split into words and processed just as the text is, so
+    /// it matches what would be counted as those words, but a phrase with a
+    /// word that would be left out can never be found.
+    pub fn set_phrases(&mut self, phrases: Vec<String>) {
+        let phrases = phrases
+            .into_iter()
+            .map(|phrase| {
+                let words = self.words_of(&phrase);
+                (phrase, words)
+            })
+            .collect();
+        self.phrases = Some(PhraseMatcher::new(phrases));
+    }
+
+    /// Counts only `words`, which go through the same case processing and
+    /// stemming as the text, and lists them all, in order, even if they
+    /// are never seen.
+    pub fn set_only<S: AsRef<str>>(&mut self, words: impl IntoIterator<Item = S>) {
+        let mut only = HashMap::new();
+        for word in words {
+            let word = self.counted_as(word.as_ref());
+            let position = only.len();
+            only.entry(word).or_insert(position);
+        }
+        self.only = Some(only);
+    }
+
+    /// What `word` is counted as, after case processing and stemming.
+    fn counted_as(&self, word: &str) -> String {
+        let word = self.normalize(word);
+        match self.options.stem {
+            Some(lang) => lang.stem(&word).into_owned(),
+            None => word,
+        }
+    }
+
+    /// The words of `text` as they would be counted, if none is left out.
+    fn words_of(&self, text: &str) -> Vec<String> {
+        self.tokens(self.pattern.as_ref(), self.delimiters.as_ref(), text)
+            .filter_map(|token| match token {
+                Token::Word(word) | Token::Emoji(word) => Some(word),
+                Token::SentenceEnd => None,
+            })
+            .map(|word| self.counted_as(word))
+            .collect()
+    }
+
+    /// The tokens of `text`, from the pattern or the delimiters if given.
+    /// They are passed in so that they can be moved out of `self` while it
+    /// is used mutably.
+    fn tokens<'a>(
+        &self,
+        pattern: Option<&'a Pattern>,
+        delimiters: Option<&'a Delimiters>,
+        text: &'a str,
+    ) -> Box<dyn Iterator<Item = Token<'a>> + 'a> {
+        match (pattern, delimiters) {
+            (Some(pattern), _) => Box::new(pattern.find_iter(text).map(Token::Word)),
+            (None, Some(delimiters)) => delimiters.tokens(text),
+            (None, None) => self.options.tokenizer.tokens(text),
+        }
+    }
+
+    /// An empty counter with the same options, pattern, delimiters,
+    /// stopwords, phrases, allowed words and markup.
+    pub fn fresh(&self) -> WordCounter {
+        WordCounter {
+            pattern: self.pattern.clone(),
+            delimiters: self.delimiters.clone(),
+            stopwords: self.stopwords.clone(),
+            phrases: self.phrases.as_ref().map(PhraseMatcher::fresh),
+            only: self.only.clone(),
+            markup: self.markup.as_ref().map(MarkupStripper::fresh),
+            concordance: self.concordance.as_ref().map(Concordance::fresh),
+            readability: self.readability.as_ref().map(Readability::fresh),
+            ..WordCounter::new(self.options)
+        }
+    }
+
+    /// Adds everything `other` has counted to this counter.
+    pub fn merge(&mut self, other: &WordCounter) {
+        for (word, count) in &other.counts {
+            match self.counts.entry(word.clone()) {
+                // Words both have seen are only distinct once
+                Entry::Occupied(mut entry) => *entry.get_mut() += count,
+                Entry::Vacant(entry) => {
+                    tally(&mut self.distinct_lengths, word.chars().count());
+                    entry.insert(*count);
+                }
+            }
+        }
+        if self.lengths.len() < other.lengths.len() {
+            self.lengths.resize(other.lengths.len(), 0);
+        }
+        for (tokens, other_tokens) in self.lengths.iter_mut().zip(&other.lengths) {
+            *tokens += other_tokens;
+        }
+        for (stem, words) in &other.variants {
+            let merged = self.variants.entry(stem.clone()).or_default();
+            for (word, count) in words {
+                *merged.entry(word.clone()).or_insert(0) += count;
+            }
+        }
+        for (key, forms) in &other.casings {
+            let merged = self.casings.entry(key.clone()).or_default();
+            for (form, count) in forms {
+                add_casing(merged, form, *count);
+            }
+        }
+        for (pair, count) in &other.pairs {
+            *self.pairs.entry(pair.clone()).or_insert(0) += count;
+        }
+        if let (Some(phrases), Some(other_phrases)) = (&mut self.phrases, &other.phrases) {
+            phrases.merge(other_phrases);
+        }
+        if let (Some(readability), Some(other_readability)) =
+            (&mut self.readability, &other.readability)
+        {
+            readability.merge(other_readability);
+        }
+        self.characters += other.characters;
+        self.decoding_errors += other.decoding_errors;
+        self.stopwords_dropped += other.stopwords_dropped;
+        self.numbers_dropped += other.numbers_dropped;
+    }
+
+    /// How many tokens have been dropped as stopwords so far.
+    pub fn stopwords_dropped(&self) -> u64 {
+        self.stopwords_dropped
+    }
+
+    /// How many tokens have been dropped as numbers so far.
+    pub fn numbers_dropped(&self) -> u64 {
+        self.numbers_dropped
+    }
+
+    /// Whether n-grams, pairs, phrases, markup, a concordance or sentences
+    /// may join words from different lines, so that lines cannot be counted
+    /// apart from each other.
+    pub fn spans_lines(&self) -> bool {
+        let joins_words =
+            self.options.ngrams > 1 || self.options.collocations || self.phrases.is_some();
+        (joins_words && !self.options.per_line)
+            || self.markup.is_some()
+            || self.concordance.is_some()
+            || self.readability.is_some()
+    }
+
+    fn normalize(&self, word: &str) -> String {
+        self.normalized(word).into_owned()
+    }
+
+    /// `word` after normalization and case processing, borrowed when those
+    /// leave it as it is, as they do for most words. Only a word with a
+    /// character that lowercasing changes is copied.
+    fn normalized<'a>(&self, word: &'a str) -> Cow<'a, str> {
+        let word = self
+            .options
+            .normalization
+            .apply(word, self.options.strip_accents);
+        if !(self.options.ignore_case || self.options.smart_case) {
+            return word;
+        }
+        if word.is_ascii() {
+            if word.bytes().any(|b| b.is_ascii_uppercase()) {
+                Cow::Owned(word.to_ascii_lowercase())
+            } else {
+                word
+            }
+        } else if word.chars().any(changes_case) {
+            Cow::Owned(word.to_lowercase())
+        } else {
+            word
+        }
+    }
+
+    /// Counts the words of `text`, which must not be split in the middle of
+    /// a word between calls. N-grams and markup continue from the previous
+    /// call.
+    pub fn feed(&mut self, text: &str) {
+        self.characters += text.chars().count() as u64;
+        self.decoding_errors += text.matches(char::REPLACEMENT_CHARACTER).count() as u64;
+        if let Some(progress) = progress::active() {
+            progress.words(self.counts.len());
+        }
+        match &mut self.markup {
+            Some(stripper) => {
+                let text = stripper.strip(text);
+                self.feed_text(&text);
+            }
+            None => self.feed_text(text),
+        }
+    }
+
+    fn feed_text(&mut self, text: &str) {
+        if self.options.per_line || self.concordance.is_some() {
+            // A line at a time, so that occurrences know their line
+            for line in text.split_inclusive('\n') {
+                self.feed_tokens(line);
+                if self.options.per_line {
+                    self.break_run();
+                }
+                if line.ends_with('\n') {
+                    self.line += 1;
+                }
+            }
+        } else {
+            self.feed_tokens(text);
+        }
+    }
+
+    /// Starts afresh for the next input, so n-grams and markup never span
+    /// two files. Text held back by the markup stripper is counted first.
+    pub fn end_input(&mut self) {
+        if let Some(stripper) = &mut self.markup {
+            let held = stripper.finish();
+            self.feed_text(&held);
+        }
+        self.break_run();
+        if let Some(concordance) = &mut self.concordance {
+            concordance.end_input();
+        }
+        if let Some(readability) = &mut self.readability {
+            readability.end_input();
+        }
+        self.line = 1;
+    }
+
+    /// Ends the run of adjacent words that n-grams, pairs and phrases are
+    /// made of.
+    fn break_run(&mut self) {
+        self.window.clear();
+        self.cased_window.clear();
+        if let Some(phrases) = &mut self.phrases {
+            phrases.reset();
+        }
+    }
+
+    fn feed_tokens(&mut self, text: &str) {
+        // Moved out for the loop, which needs `self` mutably
+        let pattern = self.pattern.take();
+        let delimiters = self.delimiters.take();
+        let tokens = self.tokens(pattern.as_ref(), delimiters.as_ref(), text);
+        for token in tokens {
+            let (word, emoji) = match token {
+                Token::Word(word) => (word, false),
+                Token::Emoji(word) => (word, true),
+                Token::SentenceEnd => {
+                    if self.options.sentence_bounded {
+                        self.break_run();
+                    }
+                    if let Some(readability) = &mut self.readability {
+                        readability.sentence_end();
+                    }
+                    continue;
+                }
+            };
+            if self.options.skip_whitespace && word.chars().all(char::is_whitespace) {
+                continue;
+            }
+            if let (Some(readability), false) = (&mut self.readability, emoji) {
+                readability.word(word);
+            }
+            let processed_word = self.normalized(word);
+            if self.concordance.is_some() {
+                let key = match self.options.stem {
+                    Some(lang) => lang.stem(&processed_word).into_owned(),
+                    None => processed_word.to_string(),
+                };
+                let line = self.line;
+                if let Some(concordance) = &mut self.concordance {
+                    concordance.step(word, &key, line);
+                }
+            }
+
+            // Filters apply to each word; a word that is left out also breaks
+            // the run, so n-grams only ever join words adjacent in the text
+            if self.options.numbers.excludes(&processed_word) {
+                self.numbers_dropped += 1;
+                self.break_run();
+            } else if self.stopwords.contains(processed_word.as_ref()) {
+                self.stopwords_dropped += 1;
+                self.break_run();
+            } else if emoji || self.fits_length(&processed_word) {
+                let cased = self.options.smart_case.then(|| {
+                    self.options
+                        .normalization
+                        .apply(word, self.options.strip_accents)
+                        .into_owned()
+                });
+                match self.options.char_ngrams {
+                    Some(n) => self.count_char_ngrams(&processed_word, n),
+                    None => self.record(processed_word, cased),
+                }
+            } else {
+                self.break_run();
+            }
+        }
+        self.pattern = pattern;
+        self.delimiters = delimiters;
+    }
+
+    fn fits_length(&self, word: &str) -> bool {
+        let length = word.chars().count();
+        length >= self.options.min_length && self.options.max_length.is_none_or(|max| length <= max)
+    }
+
+    /// Counts each run of `n` characters in `word`, leaving out those with
+    /// anything but letters unless `include_symbols`.
+    fn count_char_ngrams(&mut self, word: &str, n: usize) {
+        let bounds = Vec::from_iter(word.char_indices().map(|(i, _)| i).chain([word.len()]));
+        for window in bounds.windows(n + 1) {
+            let gram = &word[window[0]..window[n]];
+            if self.options.include_symbols || gram.chars().all(char::is_alphabetic) {
+                self.count(gram, None);
+            }
+        }
+    }
+
+    /// Counts `word`, or the n-gram it ends. `cased` is the word as written,
+    /// with `smart_case`.
+    fn record(&mut self, word: Cow<str>, cased: Option<String>) {
+        let n = self.options.ngrams;
+        if n == 1 {
+            self.count(&word, cased);
+            return;
+        }
+        if self.window.len() == n {
+            self.window.pop_front();
+            self.cased_window.pop_front();
+        }
+        self.window.push_back(word.into_owned());
+        self.cased_window.extend(cased);
+        if self.window.len() == n {
+            let ngram = Vec::from_iter(self.window.iter().map(String::as_str)).join(" ");
+            let cased = self
+                .options
+                .smart_case
+                .then(|| Vec::from_iter(self.cased_window.iter().map(String::as_str)).join(" "));
+            self.count(&ngram, cased);
+        }
+    }
+
+    /// Counts `key`, allocating it only the first time it is seen.
+    fn count(&mut self, key: &str, cased: Option<String>) {
+        let key = match self.options.stem {
+            None => Cow::Borrowed(key),
+            Some(lang) => {
+                // The words of an n-gram are stemmed one by one
+                let stem = if key.contains(' ') {
+                    Cow::Owned(Vec::from_iter(key.split(' ').map(|word| lang.stem(word))).join(" "))
+                } else {
+                    lang.stem(key)
+                };
+                if self.options.show_variants {
+                    let words = self.variants.entry(stem.to_string()).or_default();
+                    *words.entry(key.to_string()).or_insert(0) += 1;
+                }
+                stem
+            }
+        };
+        let key = key.as_ref();
+        if self
+            .only
+            .as_ref()
+            .is_some_and(|only| !only.contains_key(key))
+        {
+            return;
+        }
+        if self.options.collocations {
+            // The window holds the word before, as it was counted
+            if let Some(previous) = self.window.pop_back() {
+                *self.pairs.entry((previous, key.to_string())).or_insert(0) += 1;
+            }
+            self.window.push_back(key.to_string());
+        }
+        if let Some(phrases) = &mut self.phrases {
+            phrases.step(key);
+        }
+        if let Some(cased) = cased {
+            match self.casings.get_mut(key) {
+                Some(forms) => add_casing(forms, &cased, 1),
+                None => {
+                    self.casings.insert(key.to_string(), vec![(cased, 1)]);
+                }
+            }
+        }
+        let length = key.chars().count();
+        tally(&mut self.lengths, length);
+        match self.counts.get_mut(key) {
+            Some(count) => *count += 1,
+            None => {
+                tally(&mut self.distinct_lengths, length);
+                self.counts.insert(key.to_string(), 1);
+            }
+        }
+    }
+
+    /// Feeds the input one line at a time, reusing a single line buffer.
+    pub fn feed_reader(&mut self, mut reader: impl BufRead) -> io::Result<()> {
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 0 {
+            self.feed(&line);
+            line.clear();
+        }
+        Ok(())
+    }
+
+    /// How many words (or n-grams) have been counted, repeats included.
+    pub fn total_tokens(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    /// How many distinct words have been counted.
+    pub fn unique_words(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Figures about everything counted so far. `rare_suppressed` and
+    /// `below_min_percent` are left for the caller, since they depend on
+    /// what is dropped afterwards.
+    pub fn stats(&self) -> Stats {
+        let total_tokens = self.total_tokens();
+        let total_length: u64 = self
+            .lengths
+            .iter()
+            .enumerate()
+            .map(|(length, tokens)| length as u64 * tokens)
+            .sum();
+        Stats {
+            total_tokens,
+            unique_words: self.unique_words(),
+            hapax_legomena: self.counts.values().filter(|&&count| count == 1).count(),
+            mean_length: total_length as f64 / total_tokens.max(1) as f64,
+            median_length: histogram_median(&self.lengths),
+            characters: self.characters,
+            decoding_errors: self.decoding_errors,
+            readability: self.readability.clone(),
+            stopwords_dropped: self.stopwords_dropped,
+            numbers_dropped: self.numbers_dropped,
+            rare_suppressed: 0,
+            below_min_percent: None,
+            zipf_exponent: None,
+        }
+    }
+
+    /// How many tokens and distinct words of each length, in characters,
+    /// have been counted, shortest first. Lengths nothing had are left out.
+    pub fn length_distribution(&self) -> Vec<LengthCount> {
+        self.lengths
+            .iter()
+            .enumerate()
+            .filter(|&(_, &tokens)| tokens > 0)
+            .map(|(length, &tokens)| LengthCount {
+                length,
+                tokens,
+                words: self.distinct_lengths[length],
+            })
+            .collect()
+    }
+
+    /// How many tokens and distinct words start with each letter, as
+    /// counted, in the order of the letters' code points, with the words
+    /// that start with anything else last under an initial of `None`.
+    pub fn initial_distribution(&self) -> Vec<InitialCount> {
+        let mut initials: HashMap<Option<char>, InitialCount> = HashMap::new();
+        for (word, &count) in &self.counts {
+            let initial = word.chars().next().filter(|c| c.is_alphabetic());
+            let entry = initials.entry(initial).or_insert_with(|| InitialCount {
+                initial,
+                tokens: 0,
+                words: 0,
+                top: (word.clone(), count),
+            });
+            entry.tokens += count;
+            entry.words += 1;
+            let (top, top_count) = &entry.top;
+            if count > *top_count || (count == *top_count && word < top) {
+                entry.top = (word.clone(), count);
+            }
+        }
+        let mut initials = Vec::from_iter(initials.into_values());
+        initials.sort_by_key(|entry| (entry.initial.is_none(), entry.initial));
+        initials
+    }
+
+    /// The pairs seen at least `min_count` times, by PMI, highest first
+    /// (then by count and alphabetically). Must be called before any words
+    /// are dropped, since the score of a pair depends on the counts of its
+    /// words.
+    pub fn collocations(&self, min_count: u64) -> Vec<Collocation> {
+        let total = self.total_tokens() as f64;
+        let mut collocations: Vec<Collocation> = self
+            .pairs
+            .iter()
+            .filter(|&(_, &count)| count >= min_count)
+            .map(|((first, second), &count)| {
+                let expected = self.counts[first] as f64 * self.counts[second] as f64 / total;
+                Collocation {
+                    pair: format!("{} {}", first, second),
+                    count,
+                    pmi: (count as f64 / expected).log2(),
+                }
+            })
+            .collect();
+        collocations.sort_by(|a, b| {
+            b.pmi
+                .partial_cmp(&a.pmi)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| b.count.cmp(&a.count))
+                .then_with(|| a.pair.cmp(&b.pair))
+        });
+        collocations
+    }
+
+    /// The counted words gathered into groups of anagrams: words made of the
+    /// same characters, each as often, in any order. The largest groups
+    /// come first, then those seen most, then alphabetically. A word that
+    /// is an anagram of no other is left out unless `singles`.
+    pub fn anagrams(&self, singles: bool) -> Vec<AnagramGroup> {
+        let mut groups: HashMap<String, Vec<(String, u64)>> = HashMap::new();
+        for (word, &count) in &self.counts {
+            let mut letters = Vec::from_iter(word.chars());
+            letters.sort_unstable();
+            groups
+                .entry(String::from_iter(letters))
+                .or_default()
+                .push((word.clone(), count));
+        }
+        let mut groups = Vec::from_iter(
+            groups
+                .into_values()
+                .filter(|words| singles || words.len() > 1)
+                .map(|mut words| {
+                    words.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                    AnagramGroup {
+                        count: words.iter().map(|(_, count)| count).sum(),
+                        words,
+                    }
+                }),
+        );
+        groups.sort_by(|a, b| {
+            b.words
+                .len()
+                .cmp(&a.words.len())
+                .then_with(|| b.count.cmp(&a.count))
+                .then_with(|| a.words[0].0.cmp(&b.words[0].0))
+        });
+        groups
+    }
+
+    /// Forgets the words seen fewer than `min_count` times, returning how
+    /// many distinct words that was.
+    pub fn drop_rare(&mut self, min_count: u64) -> usize {
+        let before = self.counts.len();
+        self.counts.retain(|_, count| *count >= min_count);
+        self.variants
+            .retain(|stem, _| self.counts.contains_key(stem));
+        before - self.counts.len()
+    }
+
+    /// Forgets the words that make up less than `percent` of `total`
+    /// tokens, returning how many distinct words that was.
+    pub fn drop_below_percent(&mut self, percent: Percent, total: u64) -> usize {
+        let before = self.counts.len();
+        self.counts
+            .retain(|_, count| !percent.exceeds(*count, total));
+        self.variants
+            .retain(|stem, _| self.counts.contains_key(stem));
+        before - self.counts.len()
+    }
+
+    /// Takes the words counted under each stem, if `show_variants` is set.
+    pub fn take_variants(&mut self) -> Variants {
+        self.variants
+            .drain()
+            .map(|(stem, words)| {
+                let mut words: Vec<_> = words.into_iter().collect();
+                words.sort_by(|a, b| SortKey::Count.compare(a, b));
+                (stem, words)
+            })
+            .collect()
+    }
+
+    /// Every phrase given to `set_phrases` with how many times it was
+    /// found, in the order listed, or `None` without phrases.
+    pub fn phrase_counts(&self) -> Option<Vec<(String, u64)>> {
+        self.phrases.as_ref().map(PhraseMatcher::counts)
+    }
+
+    /// Every occurrence found for `set_kwic`, in the order of the text, or
+    /// `None` without a keyword.
+    pub fn occurrences(&self) -> Option<&[Occurrence]> {
+        self.concordance.as_ref().map(Concordance::occurrences)
+    }
+
+    /// Whether only the words given to `set_only` are counted.
+    pub fn has_only(&self) -> bool {
+        self.only.is_some()
+    }
+
+    /// Every word given to `set_only` with its count, in the order listed,
+    /// including those never seen.
+    pub fn into_listed(mut self) -> Vec<(String, u64)> {
+        let mut listed: Vec<(usize, String)> = self
+            .only
+            .take()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(word, position)| (position, word))
+            .collect();
+        listed.sort();
+        listed
+            .into_iter()
+            .map(|(_, word)| {
+                let count = self.counts.get(&word).copied().unwrap_or(0);
+                (word, count)
+            })
+            .collect()
+    }
+
+    /// Every word counted so far with its count, in no particular order.
+    pub fn counts(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.counts
+            .iter()
+            .map(|(word, &count)| (word.as_str(), count))
+    }
+
+    /// The counts, in no particular order.
+    pub fn into_counts(self) -> HashMap<String, u64> {
+        self.counts
+    }
+
+    /// The counts, in `order`. With `smart_case` the words are sorted in
+    /// lowercase and then listed in their most common casing.
+    pub fn into_sorted_vec(mut self, order: SortOrder) -> Vec<(String, u64)> {
+        let mut sorted_counts: Vec<_> = std::mem::take(&mut self.counts).into_iter().collect();
+        order.key.sort(&mut sorted_counts, order.reverse);
+        if self.options.smart_case {
+            for (word, _) in &mut sorted_counts {
+                if let Some(forms) = self.casings.get(word.as_str()) {
+                    *word = recase(word, dominant_casing(forms));
+                }
+            }
+        }
+        sorted_counts
+    }
+}
+
+/// A percentage of at most 100, kept exactly as written in decimal so that
+/// a count right at the threshold is never lost to rounding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Percent {
+    /// In billionths of a percent
+    billionths: u64,
+}
+
+/// The decimal places a [`Percent`] can have.
+const PERCENT_PLACES: usize = 9;
+
+impl Percent {
+    /// Parses a percentage such as "5", "12.5" or "0.05".
+    pub fn parse(s: &str) -> Result<Percent, String> {
+        let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+        let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if (whole.is_empty() && fraction.is_empty()) || !digits(whole) || !digits(fraction) {
+            return Err(format!("{} is not a decimal number", s));
+        }
+        if fraction.len() > PERCENT_PLACES {
+            return Err(format!("at most {} decimal places", PERCENT_PLACES));
+        }
+        let whole: u64 = if whole.is_empty() {
+            0
+        } else {
+            whole
+                .parse()
+                .map_err(|_| "must be at most 100".to_string())?
+        };
+        let fraction: u64 = format!("{:0<1$}", fraction, PERCENT_PLACES)
+            .parse()
+            .unwrap();
+        let billionths = whole
+            .checked_mul(10u64.pow(PERCENT_PLACES as u32))
+            .and_then(|whole| whole.checked_add(fraction))
+            .filter(|&billionths| billionths <= 100 * 10u64.pow(PERCENT_PLACES as u32));
+        match billionths {
+            Some(billionths) => Ok(Percent { billionths }),
+            None => Err("must be at most 100".to_string()),
+        }
+    }
+
+    /// Whether this share of `total` is more than `count`.
+    fn exceeds(self, count: u64, total: u64) -> bool {
+        let scale = 100 * 10u128.pow(PERCENT_PLACES as u32);
+        (count as u128) * scale < self.billionths as u128 * total as u128
+    }
+}
+
+impl fmt::Display for Percent {
+    /// As it was written, less any trailing zeros of the fraction.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unit = 10u64.pow(PERCENT_PLACES as u32);
+        let fraction = format!("{:01$}", self.billionths % unit, PERCENT_PLACES);
+        let fraction = fraction.trim_end_matches('0');
+        write!(f, "{}", self.billionths / unit)?;
+        if !fraction.is_empty() {
+            write!(f, ".{}", fraction)?;
+        }
+        Ok(())
+    }
+}
+
+/// What a cut to the first N counts does with the counts tied with the
+/// last one kept.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Ties {
+    /// Keep exactly N, cutting between tied counts where it falls
+    #[default]
+    Truncate,
+    /// Keep every count equal to the last one kept, so a tie is never split
+    Include,
+}
+
+impl Ties {
+    /// Cuts `counts`, in their order, to the first `n`, returning how many
+    /// more were kept for ties.
+    pub fn truncate(self, counts: &mut Vec<(String, u64)>, n: usize) -> usize {
+        let keep = match (self, n.checked_sub(1).and_then(|last| counts.get(last))) {
+            (Ties::Include, Some(&(_, boundary))) => {
+                n + counts[n..]
+                    .iter()
+                    .take_while(|&&(_, count)| count == boundary)
+                    .count()
+            }
+            _ => n,
+        };
+        counts.truncate(keep);
+        counts.len().saturating_sub(n)
+    }
+}
+
+/// The order of [`WordCounter::into_sorted_vec`]: by `key`, reversed
+/// entirely with `reverse`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SortOrder {
+    pub key: SortKey,
+    pub reverse: bool,
+}
+
+/// The order words are listed in. Every key falls back to the others, so
+/// the order is always the same for the same counts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortKey {
+    /// Most frequent first; equal counts alphabetically
+    #[default]
+    Count,
+    /// Alphabetically, by Unicode code point
+    Alpha,
+    /// Longest first, in characters; equal lengths by count, then
+    /// alphabetically
+    Length,
+    /// Least frequent first; equal counts alphabetically (for --bottom)
+    #[value(skip)]
+    Rarest,
+}
+
+impl SortKey {
+    /// Orders `counts` by this key, reversed entirely with `reverse`.
+    pub fn sort(self, counts: &mut [(String, u64)], reverse: bool) {
+        counts.sort_by(|a, b| self.compare(a, b));
+        if reverse {
+            counts.reverse();
+        }
+    }
+
+    fn compare(self, a: &(String, u64), b: &(String, u64)) -> Ordering {
+        let by_count = || b.1.cmp(&a.1);
+        let by_word = || a.0.cmp(&b.0);
+        match self {
+            SortKey::Count => by_count().then_with(by_word),
+            SortKey::Alpha => by_word(),
+            SortKey::Rarest => a.1.cmp(&b.1).then_with(by_word),
+            SortKey::Length => {
+                b.0.chars()
+                    .count()
+                    .cmp(&a.0.chars().count())
+                    .then_with(by_count)
+                    .then_with(by_word)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small xorshift generator, so corpora are the same on every run.
+    struct Seeded(u64);
+
+    impl Seeded {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    fn counts_of(counter: &WordCounter) -> HashMap<String, u64> {
+        counter
+            .counts()
+            .map(|(word, count)| (word.to_string(), count))
+            .collect()
+    }
+
+    const CASED_WORDS: [&str; 20] = [
+        "word",
+        "Word",
+        "WORD",
+        "café",
+        "Café",
+        "ÉCOLE",
+        "straße",
+        "STRASSE",
+        "ǅemal",
+        "ǆemal",
+        "ΟΔΟΣ",
+        "ὈΔΥΣΣΕΎΣ",
+        "İstanbul",
+        "ıspanak",
+        "日本語",
+        "naïve",
+        "NAÏVE",
+        "Ωmega",
+        "ﬁne",
+        "Ǉ",
+    ];
+
+    #[test]
+    fn normalized_words_match_full_lowercasing() {
+        let counter = WordCounter::builder().case_insensitive(true).build();
+        for word in CASED_WORDS {
+            assert_eq!(counter.normalized(word), word.to_lowercase(), "{}", word);
+        }
+    }
+
+    #[test]
+    fn only_words_that_change_are_copied() {
+        let counter = WordCounter::builder().case_insensitive(true).build();
+        for word in [
+            "word",
+            "café",
+            "straße",
+            "ǆemal",
+            "日本語",
+            "ıspanak",
+            "ﬁne",
+            "οδος",
+        ] {
+            assert!(
+                matches!(counter.normalized(word), Cow::Borrowed(_)),
+                "{}",
+                word
+            );
+        }
+        for word in ["Word", "Café", "ǅemal", "ΟΔΟΣ", "İstanbul", "Ǉ"] {
+            assert!(
+                matches!(counter.normalized(word), Cow::Owned(_)),
+                "{}",
+                word
+            );
+        }
+        let cased = WordCounter::builder().build();
+        assert!(matches!(cased.normalized("Café"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn case_insensitive_counts_match_lowercasing_every_word() {
+        let mut rng = Seeded(194);
+        let mut text = String::new();
+        let mut expected: HashMap<String, u64> = HashMap::new();
+        for i in 0..20_000 {
+            let word = CASED_WORDS[rng.below(CASED_WORDS.len())];
+            let word = match rng.below(3) {
+                0 => word.to_string(),
+                1 => word.to_uppercase(),
+                _ => word.to_lowercase(),
+            };
+            *expected.entry(word.to_lowercase()).or_insert(0) += 1;
+            text.push_str(&word);
+            text.push(if i % 10 == 9 { '\n' } else { ' ' });
+        }
+
+        let mut counter = WordCounter::builder().case_insensitive(true).build();
+        counter.feed(&text);
+        counter.end_input();
+        assert_eq!(counts_of(&counter), expected);
+        assert_eq!(counter.total_tokens(), 20_000);
+    }
+
+    /// Hands out at most `step` bytes per read, so that a small buffer
+    /// around it splits words, and characters, between reads.
+    struct Trickle<'a> {
+        bytes: &'a [u8],
+        step: usize,
+    }
+
+    impl io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.step.min(buf.len()).min(self.bytes.len());
+            buf[..n].copy_from_slice(&self.bytes[..n]);
+            self.bytes = &self.bytes[n..];
+            Ok(n)
+        }
+    }
+
+    fn fed_through(text: &str, capacity: usize, step: usize) -> WordCounter {
+        fed_through_with(WordCounter::builder(), text, capacity, step)
+    }
+
+    fn fed_through_with(
+        builder: WordCounterBuilder,
+        text: &str,
+        capacity: usize,
+        step: usize,
+    ) -> WordCounter {
+        let reader = io::BufReader::with_capacity(
+            capacity,
+            Trickle {
+                bytes: text.as_bytes(),
+                step,
+            },
+        );
+        let mut counter = builder.build();
+        counter.feed_reader(reader).unwrap();
+        counter.end_input();
+        counter
+    }
+
+    #[test]
+    fn words_split_between_reads_are_counted_whole() {
+        let text = "extraordinarily long words straddle every read\nnaïve café über";
+        let counter = fed_through(text, 4, 3);
+        let expected: HashMap<String, u64> = text
+            .split_whitespace()
+            .map(|word| (word.to_string(), 1))
+            .collect();
+        assert_eq!(counts_of(&counter), expected);
+    }
+
+    #[test]
+    fn multibyte_words_split_between_reads_are_measured_whole() {
+        // Five characters in 15 bytes are right at the limit, while the
+        // eight bytes of "naïvety" are seven characters, two too many
+        let text = "東京都庁舎 naïve naïvety 北京 ça\n東京都庁舎";
+        let options = CountOptions {
+            min_length: 2,
+            max_length: Some(5),
+            ..CountOptions::default()
+        };
+        for (capacity, step) in [(1, 1), (2, 1), (4, 3), (5, 2)] {
+            let builder = WordCounter::builder().options(options);
+            let counter = fed_through_with(builder, text, capacity, step);
+            assert_eq!(
+                counts_of(&counter),
+                expected(&[("東京都庁舎", 2), ("naïve", 1), ("北京", 1), ("ça", 1)]),
+                "{}-byte buffer",
+                capacity
+            );
+        }
+    }
+
+    #[test]
+    fn a_repeated_name_outranks_chance_pairs() {
+        // Common words in random order, so "of the" is frequent but only
+        // by chance, with "New York" dropped in now and then
+        let common = ["of", "the", "a", "city", "in", "and", "to", "is"];
+        let mut rng = Seeded(174);
+        let mut text = String::new();
+        for i in 0..2_000 {
+            text.push_str(common[rng.below(common.len())]);
+            text.push(' ');
+            if i % 100 == 0 {
+                text.push_str("New York ");
+            }
+        }
+        let options = CountOptions {
+            collocations: true,
+            ..CountOptions::default()
+        };
+        let mut counter = WordCounter::new(options);
+        counter.feed(&text);
+        counter.end_input();
+
+        let collocations = counter.collocations(3);
+        assert_eq!(collocations[0].pair, "New York");
+        assert_eq!(collocations[0].count, 20);
+        // 20 pairs among 2,040 tokens, against the 20 × 20 / 2,040 that
+        // two words seen 20 times each would make by chance
+        let expected = (20.0 * 2_040.0 / (20.0 * 20.0f64)).log2();
+        assert!((collocations[0].pmi - expected).abs() < 1e-9);
+        let of_the = collocations.iter().find(|c| c.pair == "of the").unwrap();
+        assert!(of_the.count > 20);
+        assert!(of_the.pmi.abs() < 1.0, "{}", of_the.pmi);
+    }
+
+    #[test]
+    fn large_input_through_a_small_buffer_counts_as_fed_whole() {
+        let mut rng = Seeded(152);
+        let mut text = String::new();
+        let mut expected: HashMap<String, u64> = HashMap::new();
+        for i in 0..100_000 {
+            let word = CASED_WORDS[rng.below(CASED_WORDS.len())];
+            *expected.entry(word.to_string()).or_insert(0) += 1;
+            text.push_str(word);
+            // Some lines run far past the buffer, and some are blank
+            text.push_str(match rng.below(200) {
+                0 => "\n\n",
+                1..=20 => "\n",
+                _ if i % 2 == 0 => " ",
+                _ => "\t",
+            });
+        }
+
+        let mut whole = WordCounter::builder().build();
+        whole.feed(&text);
+        whole.end_input();
+        assert_eq!(counts_of(&whole), expected);
+        for (capacity, step) in [(8, 8), (16, 5), (64, 61), (8192, 8192)] {
+            let counter = fed_through(&text, capacity, step);
+            assert_eq!(counts_of(&counter), expected, "{}-byte buffer", capacity);
+            assert_eq!(counter.stats().characters, whole.stats().characters);
+        }
+    }
+
+    fn counted(builder: WordCounterBuilder, text: &str) -> HashMap<String, u64> {
+        let mut counter = builder.build();
+        counter.feed(text);
+        counter.end_input();
+        counts_of(&counter)
+    }
+
+    fn expected(counts: &[(&str, u64)]) -> HashMap<String, u64> {
+        counts
+            .iter()
+            .map(|&(word, count)| (word.to_string(), count))
+            .collect()
+    }
+
+    #[test]
+    fn builder_case_insensitive() {
+        let text = "The the THE Été été";
+        assert_eq!(
+            counted(WordCounter::builder(), text),
+            expected(&[("The", 1), ("the", 1), ("THE", 1), ("Été", 1), ("été", 1)])
+        );
+        let builder = WordCounter::builder().case_insensitive(true);
+        assert_eq!(counted(builder, text), expected(&[("the", 3), ("été", 2)]));
+    }
+
+    #[test]
+    fn builder_min_length_counts_characters() {
+        let builder = WordCounter::builder().min_length(3);
+        assert_eq!(
+            counted(builder, "a an ant été ça"),
+            expected(&[("ant", 1), ("été", 1)])
+        );
+    }
+
+    #[test]
+    fn builder_stopwords_follow_the_case_options() {
+        let text = "The cat and THE dog";
+        let builder = WordCounter::builder().stopwords(["the", "and"]);
+        assert_eq!(
+            counted(builder, text),
+            expected(&[("The", 1), ("cat", 1), ("THE", 1), ("dog", 1)])
+        );
+        // Given before the counter is made case insensitive, and in any case
+        let builder = WordCounter::builder()
+            .stopwords(vec!["THE".to_string()])
+            .stopwords(["And"])
+            .case_insensitive(true);
+        assert_eq!(counted(builder, text), expected(&[("cat", 1), ("dog", 1)]));
+        let mut counter = WordCounter::builder().stopwords(["the"]).build();
+        counter.feed("the the end");
+        assert_eq!(counter.stopwords_dropped(), 2);
+    }
+
+    #[test]
+    fn builder_tokenizer() {
+        let text = "l'été, c'est-à-dire";
+        assert_eq!(
+            counted(WordCounter::builder(), text),
+            expected(&[("l'été", 1), ("c'est-à-dire", 1)])
+        );
+        let builder = WordCounter::builder().tokenizer(Tokenizer::Unicode);
+        assert_eq!(
+            counted(builder, text),
+            expected(&[("l'été", 1), ("c'est", 1), ("à", 1), ("dire", 1)])
+        );
+        let builder = WordCounter::builder().tokenizer(Tokenizer::Chars);
+        assert_eq!(counted(builder, "aba"), expected(&[("a", 2), ("b", 1)]));
+    }
+
+    #[test]
+    fn char_ngrams_count_every_run_within_words() {
+        let char_ngrams = |n, include_symbols| {
+            WordCounter::builder().options(CountOptions {
+                char_ngrams: Some(n),
+                include_symbols,
+                ..CountOptions::default()
+            })
+        };
+        assert_eq!(
+            counted(char_ngrams(2, false), "aaa banana"),
+            expected(&[("aa", 2), ("ba", 1), ("an", 2), ("na", 2)])
+        );
+        assert_eq!(
+            counted(char_ngrams(3, false), "aaaa ab a"),
+            expected(&[("aaa", 2)])
+        );
+        // Characters, not bytes, and never across a space
+        assert_eq!(
+            counted(char_ngrams(2, false), "Été ab"),
+            expected(&[("Ét", 1), ("té", 1), ("ab", 1)])
+        );
+        assert_eq!(
+            counted(char_ngrams(2, false), "don't"),
+            expected(&[("do", 1), ("on", 1)])
+        );
+        assert_eq!(
+            counted(char_ngrams(2, true), "don't"),
+            expected(&[("do", 1), ("on", 1), ("n'", 1), ("'t", 1)])
+        );
+    }
+
+    fn anagram_groups(text: &str) -> Vec<(Vec<(String, u64)>, u64)> {
+        let mut counter = WordCounter::builder().case_insensitive(true).build();
+        counter.feed(text);
+        counter.end_input();
+        counter
+            .anagrams(false)
+            .into_iter()
+            .map(|group| (group.words, group.count))
+            .collect()
+    }
+
+    #[test]
+    fn unicode_anagrams_group_by_character() {
+        let groups = anagram_groups("кот ТОК ток noël lëon Léon léon");
+        let group = |words: &[(&str, u64)], count| {
+            let words = words.iter().map(|&(w, c)| (w.to_string(), c)).collect();
+            (words, count)
+        };
+        assert_eq!(
+            groups,
+            [
+                group(&[("ток", 2), ("кот", 1)], 3),
+                group(&[("lëon", 1), ("noël", 1)], 2),
+            ]
+        );
+        // The same bytes in a different order, but not the same characters
+        let bytes = |word: &str| {
+            let mut bytes = word.as_bytes().to_vec();
+            bytes.sort_unstable();
+            bytes
+        };
+        assert_eq!(bytes("éĀ"), bytes("Àĩ"));
+        assert!(anagram_groups("éĀ Àĩ").is_empty());
+    }
+
+    #[test]
+    fn builder_options_replace_every_option() {
+        let options = CountOptions {
+            ignore_case: true,
+            ngrams: 2,
+            ..CountOptions::default()
+        };
+        let builder = WordCounter::builder().options(options);
+        assert_eq!(
+            counted(builder, "A b a B"),
+            expected(&[("a b", 2), ("b a", 1)])
+        );
+        // Options set before are replaced; stopwords are kept
+        let builder = WordCounter::builder()
+            .min_length(5)
+            .stopwords(["b"])
+            .options(CountOptions {
+                ignore_case: true,
+                ..CountOptions::default()
+            });
+        assert_eq!(counted(builder, "A b a B"), expected(&[("a", 2)]));
+    }
+}
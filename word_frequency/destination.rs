@@ -0,0 +1,126 @@
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Mutex;
+
+/// Temporary files not yet moved into place. [`exit`] removes them, since
+/// `process::exit` runs no destructors.
+static PENDING: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Where results are written: stdout, or a file given with `--out`. A file
+/// is written to a temporary sibling and renamed into place by `finish`,
+/// so it is never seen half-written; an unfinished file is removed.
+pub enum Destination {
+    Stdout(io::StdoutLock<'static>),
+    File {
+        writer: Option<BufWriter<File>>,
+        temp: PathBuf,
+        dest: PathBuf,
+    },
+}
+
+impl Destination {
+    /// Opens `path`, or stdout for `-`. The temporary file is created at
+    /// once, so a path that cannot be written fails before any work is
+    /// done.
+    pub fn create(path: &Path) -> io::Result<Destination> {
+        if path == Path::new("-") {
+            return Ok(Destination::Stdout(io::stdout().lock()));
+        }
+        let temp = temp_sibling(path)?;
+        let file = File::create(&temp)?;
+        PENDING.lock().unwrap().push(temp.clone());
+        Ok(Destination::File {
+            writer: Some(BufWriter::new(file)),
+            temp,
+            dest: path.to_path_buf(),
+        })
+    }
+
+    /// The file written to, or `None` for stdout.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Destination::Stdout(_) => None,
+            Destination::File { dest, .. } => Some(dest),
+        }
+    }
+
+    /// Flushes the output and, for a file, moves it into place.
+    pub fn finish(&mut self) -> io::Result<()> {
+        match self {
+            Destination::Stdout(stdout) => stdout.flush(),
+            Destination::File { writer, temp, dest } => {
+                let result = match writer.take() {
+                    Some(writer) => writer
+                        .into_inner()
+                        .map_err(|e| e.into_error())
+                        .and_then(|file| file.sync_all())
+                        .and_then(|()| fs::rename(&temp, &dest)),
+                    None => return Ok(()),
+                };
+                if result.is_err() {
+                    let _ = fs::remove_file(&temp);
+                }
+                PENDING.lock().unwrap().retain(|pending| pending != temp);
+                result
+            }
+        }
+    }
+}
+
+impl Write for Destination {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Destination::Stdout(stdout) => stdout.write(buf),
+            Destination::File { writer, .. } => {
+                writer.as_mut().expect("output already finished").write(buf)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Destination::Stdout(stdout) => stdout.flush(),
+            Destination::File { writer, .. } => writer.as_mut().map_or(Ok(()), |w| w.flush()),
+        }
+    }
+}
+
+impl Drop for Destination {
+    fn drop(&mut self) {
+        if let Destination::File {
+            writer: Some(_),
+            temp,
+            ..
+        } = self
+        {
+            let _ = fs::remove_file(&temp);
+            PENDING.lock().unwrap().retain(|pending| pending != temp);
+        }
+    }
+}
+
+/// A hidden temporary path in the same directory as `dest`, so that it can
+/// be renamed over `dest` atomically.
+fn temp_sibling(dest: &Path) -> io::Result<PathBuf> {
+    let file_name = dest.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a file path", dest.display()),
+        )
+    })?;
+    let mut temp_name = OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(".tmp{}", process::id()));
+    Ok(dest.with_file_name(temp_name))
+}
+
+/// Exits with `code` after removing any unfinished output files.
+pub fn exit(code: i32) -> ! {
+    for temp in PENDING.lock().unwrap().drain(..) {
+        let _ = fs::remove_file(temp);
+    }
+    process::exit(code);
+}
@@ -0,0 +1,354 @@
+use std::io::{self, BufRead, Read};
+
+/// The character encoding input is read in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// ISO-8859-1, where every byte is the character of the same number
+    Latin1,
+    /// Latin-1 with printable characters, such as curly quotes and the
+    /// euro sign, in place of the control codes 0x80 to 0x9F
+    #[value(name = "windows-1252")]
+    Windows1252,
+    Utf16le,
+    Utf16be,
+    /// UTF-16 if the input starts with a UTF-16 byte order mark, otherwise
+    /// UTF-8
+    Auto,
+}
+
+/// The characters of Windows-1252 from 0x80 to 0x9F. The five bytes it
+/// leaves undefined are read as the control codes Latin-1 has there, as
+/// web browsers do.
+const WINDOWS_1252: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16BE_BOM: &[u8] = b"\xFE\xFF";
+
+/// Reads `inner` as text in an encoding, giving it out as UTF-8. A byte
+/// order mark at the start is dropped, and bytes that are not valid in the
+/// encoding are replaced by U+FFFD, one for each maximal invalid sequence,
+/// rather than failing the read.
+pub struct Decoder<R> {
+    inner: R,
+    /// `None` until the start of the input has been read, which `Auto`
+    /// needs to see
+    encoding: Option<Encoding>,
+    given: Encoding,
+    /// Text decoded and not yet read, from `position`
+    decoded: String,
+    position: usize,
+    /// The start of a character split by the end of what `inner` gave
+    pending: Vec<u8>,
+}
+
+impl<R: BufRead> Decoder<R> {
+    pub fn new(inner: R, encoding: Encoding) -> Decoder<R> {
+        Decoder {
+            inner,
+            encoding: None,
+            given: encoding,
+            decoded: String::new(),
+            position: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Reads the first few bytes into `pending`, enough to find a byte
+    /// order mark, and drops the mark if it belongs to the encoding.
+    fn start(&mut self) -> io::Result<Encoding> {
+        while self.pending.len() < UTF8_BOM.len() {
+            let input = self.inner.fill_buf()?;
+            if input.is_empty() {
+                break;
+            }
+            let taken = input.len().min(UTF8_BOM.len() - self.pending.len());
+            self.pending.extend_from_slice(&input[..taken]);
+            self.inner.consume(taken);
+        }
+        let encoding = match self.given {
+            Encoding::Auto if self.pending.starts_with(UTF16LE_BOM) => Encoding::Utf16le,
+            Encoding::Auto if self.pending.starts_with(UTF16BE_BOM) => Encoding::Utf16be,
+            Encoding::Auto => Encoding::Utf8,
+            given => given,
+        };
+        let bom = match encoding {
+            Encoding::Utf8 => UTF8_BOM,
+            Encoding::Utf16le => UTF16LE_BOM,
+            Encoding::Utf16be => UTF16BE_BOM,
+            _ => b"",
+        };
+        if self.pending.starts_with(bom) {
+            self.pending.drain(..bom.len());
+        }
+        self.encoding = Some(encoding);
+        Ok(encoding)
+    }
+
+    /// Decodes the next bytes `inner` has into `decoded`. Returns false
+    /// once the input has ended and everything has been decoded.
+    fn decode_more(&mut self) -> io::Result<bool> {
+        let encoding = match self.encoding {
+            Some(encoding) => encoding,
+            None => self.start()?,
+        };
+        let input = self.inner.fill_buf()?;
+        let end = input.is_empty();
+        if end && self.pending.is_empty() {
+            return Ok(false);
+        }
+        let length = input.len();
+        if self.pending.is_empty() {
+            let used = decode(encoding, input, end, &mut self.decoded);
+            self.pending.extend_from_slice(&input[used..]);
+        } else {
+            self.pending.extend_from_slice(input);
+            let used = decode(encoding, &self.pending, end, &mut self.decoded);
+            self.pending.drain(..used);
+        }
+        self.inner.consume(length);
+        Ok(true)
+    }
+}
+
+impl<R: BufRead> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.consume(count);
+        Ok(count)
+    }
+}
+
+impl<R: BufRead> BufRead for Decoder<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.position == self.decoded.len() {
+            self.decoded.clear();
+            self.position = 0;
+            // A read can end in the middle of a character and decode to
+            // nothing
+            while self.decoded.is_empty() && self.decode_more()? {}
+        }
+        Ok(&self.decoded.as_bytes()[self.position..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position += amount;
+    }
+}
+
+/// Decodes `bytes` onto the end of `out`, returning how many were used.
+/// What is left is the start of a character that continues in the bytes
+/// that follow, unless the input has `ended`, when it is all used.
+fn decode(encoding: Encoding, bytes: &[u8], ended: bool, out: &mut String) -> usize {
+    match encoding {
+        Encoding::Latin1 => {
+            out.extend(bytes.iter().map(|&byte| byte as char));
+            bytes.len()
+        }
+        Encoding::Windows1252 => {
+            out.extend(bytes.iter().map(|&byte| match byte {
+                0x80..=0x9F => WINDOWS_1252[byte as usize - 0x80],
+                _ => byte as char,
+            }));
+            bytes.len()
+        }
+        Encoding::Utf16le => decode_utf16(bytes, ended, out, u16::from_le_bytes),
+        Encoding::Utf16be => decode_utf16(bytes, ended, out, u16::from_be_bytes),
+        Encoding::Utf8 | Encoding::Auto => decode_utf8(bytes, ended, out),
+    }
+}
+
+fn decode_utf8(mut bytes: &[u8], ended: bool, out: &mut String) -> usize {
+    let length = bytes.len();
+    loop {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => {
+                out.push_str(text);
+                return length;
+            }
+            Err(e) => {
+                let (valid, rest) = bytes.split_at(e.valid_up_to());
+                // SAFETY: from_utf8 found these bytes valid
+                out.push_str(unsafe { std::str::from_utf8_unchecked(valid) });
+                match e.error_len() {
+                    Some(invalid) => {
+                        out.push(char::REPLACEMENT_CHARACTER);
+                        bytes = &rest[invalid..];
+                    }
+                    // The last character is unfinished
+                    None if ended => {
+                        out.push(char::REPLACEMENT_CHARACTER);
+                        return length;
+                    }
+                    None => return length - rest.len(),
+                }
+            }
+        }
+    }
+}
+
+fn decode_utf16(bytes: &[u8], ended: bool, out: &mut String, unit: fn([u8; 2]) -> u16) -> usize {
+    let pairs = bytes.chunks_exact(2);
+    let odd = pairs.remainder().len();
+    let units: Vec<u16> = pairs.map(|pair| unit([pair[0], pair[1]])).collect();
+    // A high surrogate at the end may be paired with the next unit
+    let split = !ended
+        && units
+            .last()
+            .is_some_and(|&last| (0xD800..0xDC00).contains(&last));
+    let complete = if split {
+        &units[..units.len() - 1]
+    } else {
+        &units[..]
+    };
+    out.extend(
+        char::decode_utf16(complete.iter().copied())
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)),
+    );
+    if ended {
+        if odd > 0 {
+            out.push(char::REPLACEMENT_CHARACTER);
+        }
+        bytes.len()
+    } else {
+        complete.len() * 2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes `bytes` through a reader that hands them out `step` at a
+    /// time, so characters are split between reads.
+    fn decode_in_steps(bytes: &[u8], encoding: Encoding, step: usize) -> String {
+        struct Trickle<'a> {
+            bytes: &'a [u8],
+            step: usize,
+        }
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let n = self.step.min(buf.len()).min(self.bytes.len());
+                buf[..n].copy_from_slice(&self.bytes[..n]);
+                self.bytes = &self.bytes[n..];
+                Ok(n)
+            }
+        }
+        let inner = io::BufReader::with_capacity(step, Trickle { bytes, step });
+        let mut text = String::new();
+        Decoder::new(inner, encoding)
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    }
+
+    /// Decodes `bytes` whole and with every small read size, checking they
+    /// all agree.
+    fn decoded(bytes: &[u8], encoding: Encoding) -> String {
+        let whole = decode_in_steps(bytes, encoding, bytes.len().max(1));
+        for step in 1..=5 {
+            assert_eq!(
+                decode_in_steps(bytes, encoding, step),
+                whole,
+                "{} bytes at a time",
+                step
+            );
+        }
+        whole
+    }
+
+    #[test]
+    fn windows_1252_maps_the_c1_range() {
+        let bytes: Vec<u8> = (0x80..=0x9F).collect();
+        let text = decoded(&bytes, Encoding::Windows1252);
+        assert_eq!(text, String::from_iter(WINDOWS_1252));
+        assert_eq!(
+            decoded(b"\x80 \x93quoted\x94 \x85", Encoding::Windows1252),
+            "€ \u{201C}quoted\u{201D} …"
+        );
+        // The five undefined bytes stay the control codes Latin-1 has
+        assert_eq!(
+            decoded(b"\x81\x8D\x8F\x90\x9D", Encoding::Windows1252),
+            "\u{81}\u{8D}\u{8F}\u{90}\u{9D}"
+        );
+        // Latin-1 keeps all of them as control codes
+        assert_eq!(decoded(b"\x80\x93", Encoding::Latin1), "\u{80}\u{93}");
+        assert_eq!(decoded(b"caf\xE9", Encoding::Windows1252), "café");
+    }
+
+    #[test]
+    fn utf16_in_both_byte_orders() {
+        assert_eq!(decoded(b"h\0i\0 \0=\x26", Encoding::Utf16le), "hi ☽");
+        assert_eq!(decoded(b"\0h\0i\0 \x26=", Encoding::Utf16be), "hi ☽");
+        // A surrogate pair, which reads of one byte split four ways
+        assert_eq!(decoded(b"\x3D\xD8\x00\xDE", Encoding::Utf16le), "😀");
+        // An unpaired surrogate is one replacement
+        assert_eq!(decoded(b"\x3D\xD8a\0", Encoding::Utf16le), "\u{FFFD}a");
+        assert_eq!(decoded(b"a\0\x00\xDE", Encoding::Utf16le), "a\u{FFFD}");
+    }
+
+    #[test]
+    fn odd_length_utf16_ends_in_a_replacement() {
+        assert_eq!(decoded(b"h\0i\0!", Encoding::Utf16le), "hi\u{FFFD}");
+        assert_eq!(decoded(b"\0h\0", Encoding::Utf16be), "h\u{FFFD}");
+        assert_eq!(decoded(b"x", Encoding::Utf16le), "\u{FFFD}");
+    }
+
+    #[test]
+    fn byte_order_marks_are_stripped() {
+        assert_eq!(decoded(b"\xEF\xBB\xBFword", Encoding::Utf8), "word");
+        assert_eq!(decoded(b"\xFF\xFEw\0", Encoding::Utf16le), "w");
+        assert_eq!(decoded(b"\xFE\xFF\0w", Encoding::Utf16be), "w");
+        assert_eq!(decoded(b"\xFF\xFEw\0", Encoding::Auto), "w");
+        assert_eq!(decoded(b"\xFE\xFF\0w", Encoding::Auto), "w");
+        assert_eq!(decoded(b"\xEF\xBB\xBFword", Encoding::Auto), "word");
+        assert_eq!(decoded(b"word", Encoding::Auto), "word");
+        // Only at the start, and only the encoding's own mark
+        assert_eq!(decoded(b"a\xEF\xBB\xBF", Encoding::Utf8), "a\u{FEFF}");
+        assert_eq!(decoded(b"\xEF\xBB\xBF", Encoding::Latin1), "ï»¿");
+        assert_eq!(decoded(b"\xEF\xBB", Encoding::Utf8), "\u{FFFD}");
+        assert_eq!(decoded(b"", Encoding::Auto), "");
+    }
+
+    #[test]
+    fn one_replacement_per_maximal_invalid_sequence() {
+        // A lone continuation byte, and a stray lead byte, are one each
+        assert_eq!(decoded(b"a\x80b", Encoding::Utf8), "a\u{FFFD}b");
+        assert_eq!(decoded(b"a\x80\x80b", Encoding::Utf8), "a\u{FFFD}\u{FFFD}b");
+        // The start of a three-byte character cut short is one
+        assert_eq!(decoded(b"a\xE2\x82b", Encoding::Utf8), "a\u{FFFD}b");
+        // Bytes never valid in UTF-8 are one each
+        assert_eq!(decoded(b"\xC0\xAF", Encoding::Utf8), "\u{FFFD}\u{FFFD}");
+        assert_eq!(
+            decoded(b"\xFF\xFE\xFD", Encoding::Utf8),
+            "\u{FFFD}\u{FFFD}\u{FFFD}"
+        );
+        // An encoded surrogate is three
+        assert_eq!(
+            decoded(b"\xED\xA0\x80", Encoding::Utf8),
+            "\u{FFFD}\u{FFFD}\u{FFFD}"
+        );
+        // An unfinished character at the very end is one
+        assert_eq!(decoded(b"ab\xF0\x9F\x98", Encoding::Utf8), "ab\u{FFFD}");
+        // Valid text either side is kept whole, however the reads fall
+        assert_eq!(decoded("€\u{80}😀".as_bytes(), Encoding::Utf8), "€\u{80}😀");
+    }
+
+    #[test]
+    fn replacements_are_counted_as_decoding_errors() {
+        let mut counter = crate::counter::WordCounter::builder().build();
+        let reader = Decoder::new(&b"caf\xE9 ol\xE2\x82 \xC0\xAF ok"[..], Encoding::Utf8);
+        counter.feed_reader(io::BufReader::new(reader)).unwrap();
+        counter.end_input();
+        assert_eq!(counter.stats().decoding_errors, 4);
+    }
+}
@@ -0,0 +1,150 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Seek};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use word_frequency::WordCounter;
+
+/// How often a followed file is checked for new text once it has all been
+/// read.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Set by the Ctrl-C handler.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn on_interrupt(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// What the reading thread passes on.
+enum Event {
+    /// A complete line, or the unfinished end of stdin once it closes
+    Line(String),
+    /// The file was truncated or replaced and is being read from the start
+    Reopened,
+    Failed(io::Error),
+}
+
+/// Keeps counting `path`, or stdin if `None`, as text is added to it, and
+/// calls `report` with the counts so far every `interval` and once more at
+/// the end. A followed file is read until Ctrl-C; stdin until it closes.
+pub fn follow(
+    counter: &mut WordCounter,
+    path: Option<&Path>,
+    interval: Duration,
+    mut report: impl FnMut(&WordCounter) -> io::Result<()>,
+) -> io::Result<()> {
+    #[cfg(unix)]
+    // SAFETY: the handler only stores to an atomic, which is signal-safe
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            on_interrupt as *const () as libc::sighandler_t,
+        );
+    }
+    let (sender, receiver) = mpsc::channel();
+    // Reads on a thread of its own, since a read from stdin blocks until
+    // there is more text and cannot be interrupted by Ctrl-C
+    let source = path.map(Path::to_path_buf);
+    thread::spawn(move || match source {
+        Some(path) => follow_file(&path, &sender),
+        None => send_lines(io::stdin().lock(), &sender),
+    });
+
+    let mut next_report = Instant::now() + interval;
+    while !INTERRUPTED.load(Ordering::SeqCst) {
+        let wait = next_report
+            .saturating_duration_since(Instant::now())
+            .min(POLL_INTERVAL);
+        match receiver.recv_timeout(wait) {
+            Ok(Event::Line(line)) => counter.feed(&line),
+            Ok(Event::Reopened) => counter.end_input(),
+            Ok(Event::Failed(e)) => return Err(e),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        if Instant::now() >= next_report {
+            report(counter)?;
+            next_report = Instant::now() + interval;
+        }
+    }
+    counter.end_input();
+    report(counter)
+}
+
+/// Sends the lines of `reader` until it ends, including an unfinished last
+/// line.
+fn send_lines(mut reader: impl BufRead, sender: &Sender<Event>) {
+    let mut line = String::new();
+    loop {
+        match reader.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {
+                if sender.send(Event::Line(std::mem::take(&mut line))).is_err() {
+                    return;
+                }
+            }
+            Err(e) => {
+                let _ = sender.send(Event::Failed(e));
+                return;
+            }
+        }
+    }
+    if !line.is_empty() {
+        let _ = sender.send(Event::Line(line));
+    }
+}
+
+/// Sends the lines of the file at `path` as they are written, reopening it
+/// from the start when it is truncated or replaced by another file (as when
+/// a log is rotated). A line is only sent once its line break is written,
+/// so a word is never split by the writer being caught mid-line.
+fn follow_file(path: &Path, sender: &Sender<Event>) {
+    let result = (|| -> io::Result<()> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut line = String::new();
+        loop {
+            if reader.read_line(&mut line)? > 0 {
+                if line.ends_with('\n')
+                    && sender.send(Event::Line(std::mem::take(&mut line))).is_err()
+                {
+                    return Ok(());
+                }
+                continue;
+            }
+            thread::sleep(POLL_INTERVAL);
+            // While a rotated file is being replaced the path may be missing
+            let Ok(current) = fs::metadata(path) else {
+                continue;
+            };
+            let file = reader.get_mut();
+            let position = file.stream_position()?;
+            if current.len() < position || !same_file(&file.metadata()?, &current) {
+                reader = BufReader::new(File::open(path)?);
+                line.clear();
+                if sender.send(Event::Reopened).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    })();
+    if let Err(e) = result {
+        let _ = sender.send(Event::Failed(e));
+    }
+}
+
+#[cfg(unix)]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+/// Without inode numbers only truncation can be told apart.
+#[cfg(not(unix))]
+fn same_file(_: &fs::Metadata, _: &fs::Metadata) -> bool {
+    true
+}
@@ -0,0 +1,525 @@
+use std::io::{self, BufRead, Read};
+
+/// Back-references reach at most this far into the output.
+const WINDOW_SIZE: usize = 32 * 1024;
+
+/// Order in which the code length code lengths of a dynamic block are sent.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Base lengths and extra bits of length symbols 257 to 285.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base distances and extra bits of distance symbols 0 to 29.
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+fn corrupt(msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid gzip data: {}", msg),
+    )
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "gzip stream ends early")
+}
+
+/// Decompresses a gzip stream (RFC 1952) as it is read, checking the CRC
+/// and length of every member. Concatenated members are read one after
+/// another, as `gzip -d` does.
+pub struct GzDecoder<R> {
+    input: BitReader<R>,
+    state: State,
+    /// Output of the current member, of which at least the last
+    /// `WINDOW_SIZE` bytes are kept for back-references
+    output: Vec<u8>,
+    /// How much of `output` has been returned by `read`
+    returned: usize,
+    crc: crc32fast::Hasher,
+    /// Bytes of output in the current member, modulo 2^32
+    size: u32,
+}
+
+enum State {
+    Header,
+    BlockStart,
+    Stored { remaining: usize, last: bool },
+    Compressed { codes: Box<BlockCodes>, last: bool },
+    Trailer,
+    Done,
+}
+
+struct BlockCodes {
+    literals: Huffman,
+    distances: Huffman,
+}
+
+impl<R: BufRead> GzDecoder<R> {
+    pub fn new(input: R) -> GzDecoder<R> {
+        GzDecoder {
+            input: BitReader::new(input),
+            state: State::Header,
+            output: Vec::new(),
+            returned: 0,
+            crc: crc32fast::Hasher::new(),
+            size: 0,
+        }
+    }
+
+    /// Decodes until there is output to return or the stream has ended.
+    fn decode(&mut self) -> io::Result<()> {
+        // Forget what has been returned, except the window
+        if self.returned > 2 * WINDOW_SIZE {
+            let drop = self.returned - WINDOW_SIZE;
+            self.output.drain(..drop);
+            self.returned -= drop;
+        }
+        while self.output.len() - self.returned < WINDOW_SIZE {
+            match &mut self.state {
+                State::Header => {
+                    self.read_header()?;
+                    self.state = State::BlockStart;
+                }
+                State::BlockStart => self.state = self.read_block_header()?,
+                State::Stored { remaining, last } => {
+                    let (remaining, last) = (*remaining, *last);
+                    let copied = self.input.copy_bytes(remaining, &mut self.output)?;
+                    self.state = if copied < remaining {
+                        State::Stored {
+                            remaining: remaining - copied,
+                            last,
+                        }
+                    } else {
+                        end_of_block(last)
+                    };
+                }
+                State::Compressed { codes, last } => {
+                    let last = *last;
+                    if !inflate_symbol(&mut self.input, codes, &mut self.output)? {
+                        self.state = end_of_block(last);
+                    }
+                }
+                // The CRC covers what `read` has returned, so the member's
+                // output has to be returned before the trailer is checked
+                State::Trailer if self.returned < self.output.len() => break,
+                State::Trailer => {
+                    self.check_trailer()?;
+                    self.state = if self.input.at_end()? {
+                        State::Done
+                    } else {
+                        State::Header
+                    };
+                }
+                State::Done => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn read_header(&mut self) -> io::Result<()> {
+        let mut fixed = [0; 10];
+        self.input.read_exact(&mut fixed)?;
+        if fixed[0..2] != [0x1f, 0x8b] {
+            return Err(corrupt("not a gzip stream"));
+        }
+        if fixed[2] != 8 {
+            return Err(corrupt("unknown compression method"));
+        }
+        let flags = fixed[3];
+        if flags & 0xe0 != 0 {
+            return Err(corrupt("reserved header flags set"));
+        }
+        if flags & 0x04 != 0 {
+            let mut length = [0; 2];
+            self.input.read_exact(&mut length)?;
+            for _ in 0..u16::from_le_bytes(length) {
+                self.input.next_byte()?;
+            }
+        }
+        // The original file name, then a comment, each ending in a zero byte
+        for flag in [0x08, 0x10] {
+            if flags & flag != 0 {
+                while self.input.next_byte()? != 0 {}
+            }
+        }
+        if flags & 0x02 != 0 {
+            self.input.read_exact(&mut [0; 2])?;
+        }
+        // Back-references never reach into an earlier member
+        self.output.drain(..self.returned);
+        self.returned = 0;
+        self.crc = crc32fast::Hasher::new();
+        self.size = 0;
+        Ok(())
+    }
+
+    fn read_block_header(&mut self) -> io::Result<State> {
+        let last = self.input.bits(1)? == 1;
+        match self.input.bits(2)? {
+            0 => {
+                self.input.align();
+                let mut lengths = [0; 4];
+                self.input.read_exact(&mut lengths)?;
+                let length = u16::from_le_bytes([lengths[0], lengths[1]]);
+                let complement = u16::from_le_bytes([lengths[2], lengths[3]]);
+                if length != !complement {
+                    return Err(corrupt("stored block length does not match its complement"));
+                }
+                Ok(State::Stored {
+                    remaining: length as usize,
+                    last,
+                })
+            }
+            1 => Ok(State::Compressed {
+                codes: Box::new(fixed_codes()),
+                last,
+            }),
+            2 => Ok(State::Compressed {
+                codes: Box::new(read_dynamic_codes(&mut self.input)?),
+                last,
+            }),
+            _ => Err(corrupt("invalid block type")),
+        }
+    }
+
+    fn check_trailer(&mut self) -> io::Result<()> {
+        self.input.align();
+        let mut trailer = [0; 8];
+        self.input.read_exact(&mut trailer)?;
+        // Every byte of the member has been returned by now
+        let crc = std::mem::take(&mut self.crc).finalize();
+        if u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]) != crc {
+            return Err(corrupt("CRC mismatch"));
+        }
+        if u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]) != self.size {
+            return Err(corrupt("length mismatch"));
+        }
+        Ok(())
+    }
+}
+
+fn end_of_block(last: bool) -> State {
+    if last {
+        State::Trailer
+    } else {
+        State::BlockStart
+    }
+}
+
+impl<R: BufRead> Read for GzDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.returned == self.output.len() {
+            self.decode()?;
+        }
+        let available = &self.output[self.returned..];
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.crc.update(&available[..n]);
+        self.size = self.size.wrapping_add(n as u32);
+        self.returned += n;
+        Ok(n)
+    }
+}
+
+/// Decodes one symbol of a compressed block into `output`. Returns false at
+/// the end of the block.
+fn inflate_symbol<R: BufRead>(
+    input: &mut BitReader<R>,
+    codes: &BlockCodes,
+    output: &mut Vec<u8>,
+) -> io::Result<bool> {
+    let symbol = codes.literals.decode(input)?;
+    match symbol {
+        0..=255 => output.push(symbol as u8),
+        256 => return Ok(false),
+        257..=285 => {
+            let i = (symbol - 257) as usize;
+            let length = LENGTH_BASE[i] as usize + input.bits(LENGTH_EXTRA[i])? as usize;
+            let d = codes.distances.decode(input)? as usize;
+            if d >= DISTANCE_BASE.len() {
+                return Err(corrupt("invalid distance symbol"));
+            }
+            let distance = DISTANCE_BASE[d] as usize + input.bits(DISTANCE_EXTRA[d])? as usize;
+            if distance > output.len() {
+                return Err(corrupt("distance too far back"));
+            }
+            // Copied a byte at a time, since the copy may overlap itself
+            let start = output.len() - distance;
+            for i in 0..length {
+                output.push(output[start + i]);
+            }
+        }
+        _ => return Err(corrupt("invalid literal/length symbol")),
+    }
+    Ok(true)
+}
+
+fn fixed_codes() -> BlockCodes {
+    let mut lengths = [0; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    BlockCodes {
+        literals: Huffman::new(&lengths).expect("the fixed codes are valid"),
+        distances: Huffman::new(&[5; 30]).expect("the fixed codes are valid"),
+    }
+}
+
+fn read_dynamic_codes<R: BufRead>(input: &mut BitReader<R>) -> io::Result<BlockCodes> {
+    let literal_count = input.bits(5)? as usize + 257;
+    let distance_count = input.bits(5)? as usize + 1;
+    let code_length_count = input.bits(4)? as usize + 4;
+    if literal_count > 286 || distance_count > 30 {
+        return Err(corrupt("too many codes"));
+    }
+    let mut code_length_lengths = [0; 19];
+    for &i in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_length_lengths[i] = input.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_length_lengths)?;
+
+    let mut lengths = vec![0; literal_count + distance_count];
+    let mut i = 0;
+    while i < lengths.len() {
+        let symbol = code_lengths.decode(input)?;
+        let (length, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *i
+                    .checked_sub(1)
+                    .map(|p| &lengths[p])
+                    .ok_or_else(|| corrupt("repeated code length with none before it"))?;
+                (previous, 3 + input.bits(2)? as usize)
+            }
+            17 => (0, 3 + input.bits(3)? as usize),
+            _ => (0, 11 + input.bits(7)? as usize),
+        };
+        if i + repeat > lengths.len() {
+            return Err(corrupt("code lengths overrun"));
+        }
+        lengths[i..i + repeat].fill(length);
+        i += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err(corrupt("no end-of-block code"));
+    }
+    Ok(BlockCodes {
+        literals: Huffman::new(&lengths[..literal_count])?,
+        distances: Huffman::new(&lengths[literal_count..])?,
+    })
+}
+
+/// A canonical Huffman code, decoded a bit at a time.
+struct Huffman {
+    /// How many codes there are of each length
+    counts: [u16; 16],
+    /// The symbols, ordered by code
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> io::Result<Huffman> {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+        // Each length doubles the codes available; more than that is invalid
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = left * 2 - count as i32;
+            if left < 0 {
+                return Err(corrupt("over-subscribed code"));
+            }
+        }
+        let mut offsets = [0u16; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode<R: BufRead>(&self, input: &mut BitReader<R>) -> io::Result<u16> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for &count in &self.counts[1..] {
+            code |= input.bits(1)? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(corrupt("invalid code"))
+    }
+}
+
+/// Reads the bits of a deflate stream, least significant first, loading a
+/// byte only when one is needed so that nothing is read past the stream.
+struct BitReader<R> {
+    inner: R,
+    bits: u32,
+    count: u8,
+}
+
+impl<R: BufRead> BitReader<R> {
+    fn new(inner: R) -> BitReader<R> {
+        BitReader {
+            inner,
+            bits: 0,
+            count: 0,
+        }
+    }
+
+    fn bits(&mut self, n: u8) -> io::Result<u32> {
+        while self.count < n {
+            self.bits |= (self.next_byte()? as u32) << self.count;
+            self.count += 8;
+        }
+        let value = self.bits & ((1 << n) - 1);
+        self.bits >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    /// Skips to the next byte boundary.
+    fn align(&mut self) {
+        self.bits = 0;
+        self.count = 0;
+    }
+
+    /// The next whole byte, which is only in step with the bits once
+    /// aligned.
+    fn next_byte(&mut self) -> io::Result<u8> {
+        let byte = *self.inner.fill_buf()?.first().ok_or_else(truncated)?;
+        self.inner.consume(1);
+        Ok(byte)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        for b in buf {
+            *b = self.next_byte()?;
+        }
+        Ok(())
+    }
+
+    /// Copies up to `n` bytes, once aligned, returning how many there were.
+    fn copy_bytes(&mut self, n: usize, output: &mut Vec<u8>) -> io::Result<usize> {
+        if n == 0 {
+            return Ok(0);
+        }
+        let available = self.inner.fill_buf()?;
+        if available.is_empty() {
+            return Err(truncated());
+        }
+        let taken = available.len().min(n);
+        output.extend_from_slice(&available[..taken]);
+        self.inner.consume(taken);
+        Ok(taken)
+    }
+
+    /// Whether the input has run out, once aligned.
+    fn at_end(&mut self) -> io::Result<bool> {
+        Ok(self.inner.fill_buf()?.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A file in tests/fixtures/gzip, where make.sh compresses text.txt
+    /// and noise.bin with the system gzip.
+    fn fixture(name: &str) -> Vec<u8> {
+        let path = format!(
+            "{}/tests/fixtures/gzip/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        );
+        std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {}", path, e))
+    }
+
+    /// Decompresses `compressed` through a buffer of `capacity` bytes.
+    fn gunzip(compressed: &[u8], capacity: usize) -> io::Result<Vec<u8>> {
+        let mut output = Vec::new();
+        GzDecoder::new(io::BufReader::with_capacity(capacity, compressed))
+            .read_to_end(&mut output)?;
+        Ok(output)
+    }
+
+    fn assert_decompresses_to(name: &str, expected: &[u8]) {
+        let compressed = fixture(name);
+        for capacity in [1, 7, 8192] {
+            let output = gunzip(&compressed, capacity).unwrap();
+            assert!(output == expected, "{} through {} bytes", name, capacity);
+        }
+    }
+
+    #[test]
+    fn decompresses_what_gzip_compressed_at_any_level() {
+        let text = fixture("text.txt");
+        assert_decompresses_to("level1.gz", &text);
+        assert_decompresses_to("level9.gz", &text);
+        assert_decompresses_to("noise.gz", &fixture("noise.bin"));
+    }
+
+    #[test]
+    fn reads_every_member_in_turn() {
+        assert_decompresses_to("multi.gz", &fixture("text.txt"));
+    }
+
+    #[test]
+    fn an_empty_file_compressed_decompresses_to_nothing() {
+        assert_decompresses_to("empty.gz", b"");
+    }
+
+    #[test]
+    fn a_truncated_stream_is_an_error() {
+        let error = gunzip(&fixture("truncated.gz"), 8192).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(error.to_string(), "gzip stream ends early");
+
+        let error = gunzip(b"", 8192).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn a_bad_crc_is_an_error() {
+        let error = gunzip(&fixture("bad_crc.gz"), 8192).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "invalid gzip data: CRC mismatch");
+    }
+
+    #[test]
+    fn trailing_garbage_is_an_error() {
+        let mut compressed = fixture("empty.gz");
+        compressed.extend_from_slice(b"plainly not gzip data");
+        let error = gunzip(&compressed, 8192).unwrap_err();
+        assert_eq!(error.to_string(), "invalid gzip data: not a gzip stream");
+    }
+}
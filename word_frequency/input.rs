@@ -0,0 +1,40 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use crate::encoding::{Decoder, Encoding};
+use crate::gzip::GzDecoder;
+use crate::progress::{self, ProgressReader};
+
+/// How inputs are read.
+#[derive(Clone, Copy, Debug)]
+pub struct ReadOptions {
+    /// Threads to count on
+    pub threads: usize,
+    /// Decompress every input, whatever its name
+    pub gzip: bool,
+    /// What the text is encoded in, once decompressed
+    pub encoding: Encoding,
+}
+
+impl ReadOptions {
+    /// Opens a file, or stdin for `-`, decompressing it if its name ends in
+    /// `.gz` or `gzip` is set, and decodes it to UTF-8. The bytes read
+    /// count towards the progress, if it is being reported, before they are
+    /// decompressed.
+    pub fn open(&self, path: &Path) -> io::Result<Box<dyn BufRead>> {
+        let gzip = self.gzip || path.extension().is_some_and(|extension| extension == "gz");
+        let mut reader: Box<dyn BufRead> = if path == Path::new("-") {
+            Box::new(io::stdin().lock())
+        } else {
+            Box::new(BufReader::new(File::open(path)?))
+        };
+        if let Some(progress) = progress::active() {
+            reader = Box::new(ProgressReader::new(reader, progress));
+        }
+        if gzip {
+            reader = Box::new(BufReader::new(GzDecoder::new(reader)));
+        }
+        Ok(Box::new(Decoder::new(reader, self.encoding)))
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::VecDeque;
+
+/// Finds every occurrence of one word with the words around it, for a
+/// keyword-in-context concordance. Only the last few words and the
+/// occurrences still waiting for their right context are held while the
+/// text streams past.
+#[derive(Clone, Debug)]
+pub struct Concordance {
+    /// The word looked for, as it is counted
+    keyword: String,
+    /// Words of context on each side
+    window: usize,
+    /// The last `window` words, as written
+    before: VecDeque<String>,
+    /// Occurrences whose right context is not yet complete, oldest first
+    open: Vec<Occurrence>,
+    found: Vec<Occurrence>,
+}
+
+/// One occurrence of the keyword, with its context as written.
+#[derive(Clone, Debug)]
+pub struct Occurrence {
+    /// The line of its input it is on, from 1
+    pub line: usize,
+    pub before: Vec<String>,
+    pub word: String,
+    pub after: Vec<String>,
+}
+
+impl Concordance {
+    /// Looks for `keyword`, already processed as counted words are, with
+    /// `window` words of context on each side.
+    pub fn new(keyword: String, window: usize) -> Concordance {
+        Concordance {
+            keyword,
+            window,
+            before: VecDeque::with_capacity(window + 1),
+            open: Vec::new(),
+            found: Vec::new(),
+        }
+    }
+
+    /// The same search, with nothing found yet.
+    pub fn fresh(&self) -> Concordance {
+        Concordance::new(self.keyword.clone(), self.window)
+    }
+
+    /// Moves on by one word, given as written and as it is counted.
+    pub fn step(&mut self, word: &str, key: &str, line: usize) {
+        for occurrence in &mut self.open {
+            occurrence.after.push(word.to_string());
+        }
+        // Every open occurrence gets the same words, so those complete are
+        // the oldest
+        let complete = self
+            .open
+            .iter()
+            .take_while(|occurrence| occurrence.after.len() >= self.window)
+            .count();
+        self.found.extend(self.open.drain(..complete));
+
+        if key == self.keyword {
+            let occurrence = Occurrence {
+                line,
+                before: self.before.iter().cloned().collect(),
+                word: word.to_string(),
+                after: Vec::new(),
+            };
+            if self.window == 0 {
+                self.found.push(occurrence);
+            } else {
+                self.open.push(occurrence);
+            }
+        }
+        self.before.push_back(word.to_string());
+        if self.before.len() > self.window {
+            self.before.pop_front();
+        }
+    }
+
+    /// Ends the input, so that context never runs from one input into the
+    /// next. Occurrences near the end keep what right context they have.
+    pub fn end_input(&mut self) {
+        self.found.append(&mut self.open);
+        self.before.clear();
+    }
+
+    /// Every occurrence found, in the order of the text.
+    pub fn occurrences(&self) -> &[Occurrence] {
+        &self.found
+    }
+}
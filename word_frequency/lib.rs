@@ -0,0 +1,42 @@
+//! Word counting as used by the `word_frequency` command, for use from
+//! other programs.
+//!
+//! ```
+//! use word_frequency::{SortKey, SortOrder, Tokenizer, WordCounter};
+//!
+//! let mut counter = WordCounter::builder()
+//!     .case_insensitive(true)
+//!     .min_length(2)
+//!     .stopwords(["the"])
+//!     .tokenizer(Tokenizer::Simple)
+//!     .build();
+//! counter.feed("The cat saw the other cat.");
+//! let counts = counter.into_sorted_vec(SortOrder { key: SortKey::Count, reverse: false });
+//! assert_eq!(counts[0], ("cat".to_string(), 2));
+//! ```
+
+pub mod compare;
+pub mod counter;
+pub mod encoding;
+mod gzip;
+pub mod input;
+pub mod kwic;
+pub mod markup;
+pub mod normalize;
+pub mod parallel;
+pub mod pattern;
+pub mod phrases;
+pub mod progress;
+pub mod readability;
+pub mod stats;
+pub mod stem;
+pub mod stopwords;
+pub mod tfidf;
+pub mod tokenize;
+mod unicode_tables;
+pub mod zipf;
+
+pub use counter::{
+    CountOptions, Percent, SortKey, SortOrder, Ties, WordCounter, WordCounterBuilder,
+};
+pub use tokenize::Tokenizer;
@@ -1,66 +1,1270 @@
-use clap::Parser;
-use std::collections::HashMap;
-use std::io::{self, Read};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+mod destination;
+mod follow;
+mod output;
+
+use destination::Destination;
+use output::{
+    write_anagrams, write_collocations, write_comparison, write_concordance, write_counts,
+    write_initials, write_length_distribution, write_tfidf, Format, GroupBy, OutputOptions,
+    PerFileWriter, Summary, Table,
+};
+use word_frequency::counter::NumberFilter;
+use word_frequency::encoding::Encoding;
+use word_frequency::input::ReadOptions;
+use word_frequency::markup::{Markup, MarkupStripper};
+use word_frequency::normalize::Normalization;
+use word_frequency::pattern::Pattern;
+use word_frequency::phrases::load_phrases;
+use word_frequency::progress;
+use word_frequency::stem::StemLang;
+use word_frequency::stopwords::{load_word_list, StopwordLang};
+use word_frequency::tokenize::Delimiters;
+use word_frequency::zipf::ZipfFit;
+use word_frequency::{compare, parallel, tfidf};
+use word_frequency::{CountOptions, Percent, SortKey, SortOrder, Ties, Tokenizer, WordCounter};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
     /// The text to process. If not provided, reads from stdin.
+    #[arg(conflicts_with = "files")]
     text: Option<String>,
 
+    /// Read the text from a file instead; repeat to combine the counts of
+    /// several files. Use - for stdin.
+    #[arg(short, long = "file", value_name = "PATH")]
+    files: Vec<PathBuf>,
+
+    /// Fail instead of waiting for text to be typed when there is no input
+    /// but a terminal. Without this a hint is printed and the text is read
+    /// from the terminal, up to Ctrl-D.
+    #[arg(long)]
+    no_tty_wait: bool,
+
+    /// Stop at the first file that cannot be read instead of skipping it
+    #[arg(long, requires = "files")]
+    fail_fast: bool,
+
+    /// Decompress the input as gzip even if its name doesn't end in .gz
+    /// (files ending in .gz always are)
+    #[arg(long)]
+    gzip: bool,
+
+    /// The encoding of the input. auto reads UTF-16 if the input starts
+    /// with a UTF-16 byte order mark, and UTF-8 otherwise. Bytes that are
+    /// not valid in the encoding are read as U+FFFD and counted as decoding
+    /// errors in --stats. This includes the default, utf8: invalid UTF-8 is
+    /// replaced rather than stopping the run with an error, as it once did
+    #[arg(long, value_enum, default_value_t = Encoding::Utf8)]
+    encoding: Encoding,
+
+    /// Keep reading the file (or stdin) as it grows, printing the table
+    /// every --interval seconds, until Ctrl-C or the end of stdin. A file
+    /// that is truncated or replaced, as when a log is rotated, is read
+    /// again from the start.
+    #[arg(
+        long,
+        conflicts_with_all = ["text", "out", "gzip", "encoding", "per_file", "tfidf", "compare", "collocations"]
+    )]
+    follow: bool,
+
+    /// With --follow, seconds between tables [default: 2]
+    #[arg(long, value_name = "SECONDS", value_parser = parse_positive_u64)]
+    interval: Option<u64>,
+
+    /// Count on N threads: several files are shared out whole, while a
+    /// single input is split into chunks of lines (unless n-grams may span
+    /// lines, without --per-line). The counts are the same as with one
+    /// thread.
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = parse_positive)]
+    threads: usize,
+
+    /// Print a table for each file, under its name, before the combined
+    /// table. CSV and TSV output gains a leading file column instead, left
+    /// empty for the combined rows.
+    #[arg(long, requires = "files")]
+    per_file: bool,
+
+    /// Print a table for each line, or each paragraph of lines between
+    /// blank lines, of a single input, headed by its number from 1. Groups
+    /// without a counted word are left out. CSV and TSV output gains a
+    /// leading group column of the numbers instead.
+    #[arg(
+        long,
+        value_name = "GROUPS",
+        value_enum,
+        default_value_t = GroupBy::None,
+        conflicts_with_all = ["per_file", "tfidf", "compare", "collocations", "follow", "length_distribution", "only", "only_words"]
+    )]
+    group_by: GroupBy,
+
+    /// Treat each file as a document and list its words by TF-IDF, highest
+    /// first, instead of by count. With N documents, df of which contain
+    /// the word: tf = count / words in the document, idf = ln((1 + N) /
+    /// (1 + df)) + 1, and the score is tf * idf. --top and --min-count
+    /// apply to each document.
+    #[arg(
+        long,
+        requires = "files",
+        conflicts_with_all = ["per_file", "bottom", "sort", "reverse", "percent", "stats", "stats_only", "show_variants"]
+    )]
+    tfidf: bool,
+
+    /// List pairs of adjacent words by pointwise mutual information, the
+    /// log2 of how much more often they appear together than their counts
+    /// would predict: PMI = log2(pair count * words / (first word's count *
+    /// second word's count)). Pairs are broken up by the same things that
+    /// break up n-grams.
+    #[arg(
+        long,
+        conflicts_with_all = ["ngrams", "chars", "per_file", "tfidf", "compare", "bottom", "sort", "reverse", "min_count", "percent", "stats", "stats_only", "show_variants"]
+    )]
+    collocations: bool,
+
+    /// List groups of words that are anagrams of each other, spelled with
+    /// the same characters in another order, as counted (so --ignore-case
+    /// puts "Listen" with "silent"). Each group is given with the count of
+    /// each word and their total, the largest groups first, then the most
+    /// seen. --min-count leaves out rare words before they are grouped.
+    #[arg(
+        long,
+        conflicts_with_all = ["ngrams", "chars", "collocations", "phrases", "only", "only_words", "length_distribution", "kwic", "per_file", "tfidf", "compare", "follow", "group_by", "bottom", "sort", "reverse", "ties", "min_percent", "percent", "zipf", "stats", "stats_only", "show_variants", "smart_case"]
+    )]
+    anagrams: bool,
+
+    /// With --anagrams, also list words that are anagrams of no other
+    #[arg(long, requires = "anagrams")]
+    all_groups: bool,
+
+    /// With --collocations, leave out pairs seen fewer than N times, whose
+    /// PMI is mostly noise
+    #[arg(long, value_name = "N", default_value_t = 3, requires = "collocations")]
+    min_pair_count: u64,
+
+    /// Number the words by rank and add the count predicted by a Zipf
+    /// distribution, count = C / rank^s, fitted by least squares on the
+    /// log of both. The exponent s is added to the statistics, which this
+    /// turns on.
+    #[arg(
+        long,
+        conflicts_with_all = ["bottom", "sort", "reverse", "tfidf", "compare", "collocations"]
+    )]
+    zipf: bool,
+
+    /// With --zipf, fit only the words seen at least N times, so that the
+    /// long tail of rare words does not skew the fit. Every word still gets
+    /// a prediction.
+    #[arg(long, value_name = "N", default_value_t = 2, requires = "zipf")]
+    zipf_min_count: u64,
+
+    /// Count two inputs with the same settings and list the words found
+    /// only in the first, only in the second, and in both with different
+    /// counts, largest difference first. --top limits each list.
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["FILE_A", "FILE_B"],
+        conflicts_with_all = ["text", "files", "per_file", "tfidf", "bottom", "sort", "reverse", "min_count", "percent", "stats", "stats_only", "show_variants"]
+    )]
+    compare: Vec<PathBuf>,
+
+    /// With --compare, list words in both inputs only if their counts
+    /// differ by more than N [default: 0]
+    #[arg(long, value_name = "N")]
+    delta: Option<u64>,
+
+    /// Remove HTML or Markdown markup before splitting the text into words,
+    /// so that tag names, URLs and code are not counted. Each file is a
+    /// document of its own, and is read in turn even with --threads.
+    #[arg(long, value_name = "MARKUP", value_enum)]
+    strip_markup: Option<Markup>,
+
+    /// How to split the text into words
+    #[arg(long, value_enum, default_value_t = Tokenizer::Simple)]
+    tokenizer: Tokenizer,
+
+    /// Count every non-overlapping match of REGEX instead of tokenizing
+    /// (e.g. "[A-Za-z']+", or "\d{4}" for years). Matches never span lines.
+    #[arg(long, value_name = "REGEX", value_parser = Pattern::parse, conflicts_with = "tokenizer")]
+    pattern: Option<Pattern>,
+
+    /// Trim only trailing punctuation, as before leading punctuation was
+    /// trimmed too, so that "(hello" and "hello" count separately again
+    #[arg(long, conflicts_with_all = ["tokenizer", "pattern", "chars"])]
+    legacy_trim: bool,
+
+    /// With --tokenizer unicode, also count emoji, each whole with its
+    /// skin-tone modifier or the emoji it is joined to (as in 👩‍💻) and
+    /// flags as pairs. Emoji are counted whatever --min-length and
+    /// --max-length say.
+    #[arg(long, conflicts_with_all = ["pattern", "chars", "legacy_trim"])]
+    keep_emoji: bool,
+
+    /// Also split words at each of the characters in CHARS, before
+    /// punctuation is trimmed, so that "foo.bar(baz)" with ".()" counts
+    /// foo, bar and baz. Nothing is counted between adjacent delimiters.
+    #[arg(
+        long,
+        value_name = "CHARS",
+        conflicts_with_all = ["tokenizer", "pattern", "legacy_trim", "keep_emoji", "chars"]
+    )]
+    delimiters: Option<String>,
+
+    /// With --delimiters, split words only at those characters and line
+    /// breaks, and not at spaces or tabs
+    #[arg(long, requires = "delimiters")]
+    delimiters_only: bool,
+
+    /// Count characters instead of words
+    #[arg(long, conflicts_with_all = ["tokenizer", "pattern"])]
+    chars: bool,
+
+    /// With --chars, count grapheme clusters (what a reader sees as one
+    /// character, such as e plus a combining accent) instead
+    #[arg(long, requires = "chars")]
+    graphemes: bool,
+
+    /// With --chars, leave out spaces, tabs and line breaks
+    #[arg(long, requires = "chars")]
+    no_whitespace: bool,
+
+    /// Put words in a Unicode normalization form before counting, so that
+    /// the same text typed or encoded differently counts as one word. This
+    /// comes before --ignore-case, and --min-length and --max-length
+    /// measure the normalized word.
+    #[arg(long, value_name = "FORM", value_enum, default_value_t = Normalization::None)]
+    normalize: Normalization,
+
+    /// Remove combining marks, such as accents, from the decomposed words,
+    /// so that "café" and "cafe" are counted as one word. Letters that are
+    /// not an accented form of another stay apart: Turkish dotless "ı" is
+    /// not "i", while dotted capital "İ" becomes "I". Without this,
+    /// --ignore-case lowercases "İ" to "i" followed by a combining dot.
+    #[arg(long)]
+    strip_accents: bool,
+
     /// Ignore case when counting words
     #[arg(long)]
     ignore_case: bool,
 
-    /// Minimum length of words to count
+    /// Count words regardless of case, as --ignore-case does, but list
+    /// each in the casing it was most often written in ("London", "NASA"),
+    /// the first seen among equally common ones. Stems are capitalized or
+    /// put in capitals to match.
+    #[arg(
+        long,
+        conflicts_with_all = ["ignore_case", "show_variants", "collocations", "phrases", "only", "only_words", "tfidf", "compare"]
+    )]
+    smart_case: bool,
+
+    /// Minimum length of words to count, in characters
     #[arg(long, default_value_t = 1)]
     min_length: usize,
 
+    /// Leave out tokens made only of digits
+    #[arg(long)]
+    no_numbers: bool,
+
+    /// Also leave out numbers written with punctuation, such as 1,000, 3.14
+    /// or 2024-01-31
+    #[arg(long)]
+    no_numerics: bool,
+
+    /// Maximum length of words to count, in characters
+    #[arg(long, value_name = "N")]
+    max_length: Option<usize>,
+
+    /// Count words under their stems, so that "runs" and "running" are
+    /// counted as "run". Stemming comes after --ignore-case, which it needs
+    /// to stem capitalized words, and after the length limits, which apply
+    /// to the words as written.
+    #[arg(long, conflicts_with = "chars")]
+    stem: bool,
+
+    /// Language of the stemmer
+    #[arg(long, value_name = "LANG", value_enum, default_value_t = StemLang::En, requires = "stem")]
+    stem_lang: StemLang,
+
+    /// With --stem, list the words counted under each stem with their counts
+    #[arg(long, requires = "stem")]
+    show_variants: bool,
+
     /// Show only the top N words
     #[arg(short, long)]
     top: Option<usize>,
+
+    /// Show only the N least frequent words, rarest first. Words with equal
+    /// counts are listed alphabetically, so among words seen once this is
+    /// the alphabetically first N.
+    #[arg(long, value_name = "N", conflicts_with_all = ["top", "sort", "reverse"])]
+    bottom: Option<usize>,
+
+    /// Whether --top or --bottom keeps exactly N words or also every word
+    /// whose count equals the last one's. How many more words were kept is
+    /// noted on stderr, and JSON output gains the last count as
+    /// metadata.boundary_count.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Ties::Truncate,
+        conflicts_with_all = ["sort", "only", "only_words", "tfidf", "compare", "collocations", "length_distribution"]
+    )]
+    ties: Ties,
+
+    /// Count the phrases listed in FILE, one per line, instead of single
+    /// words, listing every phrase even if it is never found. A phrase is
+    /// matched against the words as they are counted (after --ignore-case
+    /// and --stem), across line breaks like n-grams. Every occurrence of
+    /// every phrase counts, however they overlap: "machine learning model"
+    /// counts once for both "machine learning" and "machine learning
+    /// model", and "ha ha ha" twice for "ha ha".
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["ngrams", "chars", "collocations", "tfidf", "compare", "zipf", "bottom", "min_count", "show_variants", "stats_only"]
+    )]
+    phrases: Option<PathBuf>,
+
+    /// Count only the words listed in FILE, one per line, and list them all
+    /// in that order, including those never seen. The words are matched as
+    /// they are counted, after --ignore-case and --stem. CSV and TSV output
+    /// of --per-file becomes a matrix of counts, with a row for each word
+    /// and a column for each file, then the total.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["ngrams", "collocations", "phrases", "bottom", "sort", "reverse", "min_count", "zipf", "show_variants"]
+    )]
+    only: Option<PathBuf>,
+
+    /// Count only the word W, as with --only; repeat for more words, which
+    /// follow those of --only
+    #[arg(
+        long = "only-word",
+        value_name = "W",
+        conflicts_with_all = ["ngrams", "collocations", "phrases", "bottom", "sort", "reverse", "min_count", "zipf", "show_variants"]
+    )]
+    only_words: Vec<String>,
+
+    /// Instead of the words, list how many tokens and how many distinct
+    /// words of each length, in characters, were counted. --format chart
+    /// draws a bar for the tokens of each length.
+    #[arg(
+        long,
+        conflicts_with_all = ["tfidf", "compare", "collocations", "phrases", "only", "only_words", "per_file", "follow", "zipf", "show_variants", "top", "bottom", "sort", "reverse", "min_count", "percent", "stats_only"]
+    )]
+    length_distribution: bool,
+
+    /// Instead of the words, list for each letter how many tokens and how
+    /// many distinct words start with it, as counted (so --ignore-case
+    /// puts "A" with "a"), and the most common of them. Words starting
+    /// with anything but a letter are listed together under "other", last.
+    /// --format chart draws a bar for the tokens of each letter.
+    #[arg(
+        long,
+        conflicts_with_all = ["ngrams", "chars", "tfidf", "compare", "collocations", "anagrams", "phrases", "only", "only_words", "length_distribution", "kwic", "per_file", "follow", "group_by", "zipf", "show_variants", "top", "bottom", "sort", "reverse", "ties", "min_count", "min_percent", "percent", "stats_only"]
+    )]
+    by_initial: bool,
+
+    /// Instead of counting, list every occurrence of WORD with the words
+    /// around it, numbered, the keywords lined up in a column. WORD is
+    /// matched as it would be counted, so --ignore-case and --stem apply.
+    /// Context runs across lines but not from one input into the next.
+    #[arg(
+        long,
+        value_name = "WORD",
+        conflicts_with_all = ["ngrams", "chars", "collocations", "phrases", "only", "only_words", "length_distribution", "per_file", "tfidf", "compare", "follow", "group_by", "threads", "top", "bottom", "sort", "reverse", "min_count", "min_percent", "percent", "zipf", "stats", "stats_only", "show_variants", "smart_case"]
+    )]
+    kwic: Option<String>,
+
+    /// With --kwic, the words of context on each side [default: 5]
+    #[arg(long, value_name = "N")]
+    window: Option<usize>,
+
+    /// With --kwic, give the line each occurrence is on, counted from the
+    /// start of its input
+    #[arg(long, requires = "kwic")]
+    line_numbers: bool,
+
+    /// Count sequences of N consecutive words instead of single words
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = parse_positive)]
+    ngrams: usize,
+
+    /// Count sequences of N consecutive characters within each word
+    /// instead of the words, such as letter pairs with 2. They are taken
+    /// from the words as counted, after --ignore-case and --normalize, and
+    /// never run from one word into the next. Words shorter than N give
+    /// none.
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = parse_positive,
+        conflicts_with_all = ["ngrams", "chars", "stem", "collocations", "phrases", "only", "only_words", "anagrams", "kwic", "smart_case", "show_variants"]
+    )]
+    char_ngrams: Option<usize>,
+
+    /// With --char-ngrams, also count sequences with characters other than
+    /// letters, such as digits, apostrophes and hyphens
+    #[arg(long, requires = "char_ngrams")]
+    include_symbols: bool,
+
+    /// Don't let n-grams span line breaks
+    #[arg(long)]
+    per_line: bool,
+
+    /// Don't let n-grams span sentence-ending punctuation (. ! ?)
+    #[arg(long)]
+    sentence_bounded: bool,
+
+    /// Leave out words seen fewer than N times
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    min_count: u64,
+
+    /// Leave out words making up less than F percent of all counted
+    /// tokens, such as 0.05. The total is taken after stopwords, numbers
+    /// and lengths are filtered but before --min-count, as with --percent,
+    /// and the words are left out before --top is applied.
+    #[arg(
+        long,
+        value_name = "F",
+        value_parser = Percent::parse,
+        conflicts_with_all = ["only", "only_words", "phrases", "collocations", "tfidf", "compare", "length_distribution"]
+    )]
+    min_percent: Option<Percent>,
+
+    /// Order to list the words in (before --top is applied)
+    #[arg(long, value_enum, default_value_t = SortKey::Count)]
+    sort: SortKey,
+
+    /// Reverse the whole order, tie-breaks included
+    #[arg(long)]
+    reverse: bool,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Show each word's share of all counted words (after filtering)
+    #[arg(long)]
+    percent: bool,
+
+    /// With --percent, also show the running total of the shares
+    #[arg(long, requires = "percent")]
+    cumulative: bool,
+
+    /// Columns for the longest bar with --format chart
+    #[arg(long, value_name = "COLUMNS", default_value_t = 40, value_parser = parse_positive)]
+    width: usize,
+
+    /// Draw --format chart bars with Unicode block characters
+    #[arg(long)]
+    unicode_bars: bool,
+
+    /// Also print corpus statistics: totals, type/token ratio, word
+    /// lengths, hapax legomena and characters read, and how readable the
+    /// text is: its sentences (ended by . ! or ? before whitespace, though
+    /// not after an abbreviation such as "Dr."), their average length in
+    /// words, the syllables per word (estimated for English) and the
+    /// Flesch reading ease and Flesch-Kincaid grade level. These count
+    /// every word of the text, before any are filtered out
+    #[arg(long)]
+    stats: bool,
+
+    /// Print only the statistics, without the counts
+    #[arg(long)]
+    stats_only: bool,
+
+    /// Write the results to FILE instead of stdout, or to stdout for -. The
+    /// file is written under a temporary name and renamed into place once
+    /// complete, and CSV and TSV statistics go to stdout instead of stderr.
+    /// With --per-file, a {file} in FILE gives each input a file of its
+    /// own, named for the input without its extension (stdin for -), and
+    /// the combined table one named "combined".
+    #[arg(short, long, value_name = "FILE")]
+    out: Option<PathBuf>,
+
+    /// Skip the words listed in FILE, one per line; lines starting with #
+    /// are comments
+    #[arg(long, value_name = "FILE")]
+    stopwords: Option<PathBuf>,
+
+    /// Skip the most common words of a language
+    #[arg(long, value_name = "LANG", value_enum)]
+    stopwords_lang: Option<StopwordLang>,
+
+    /// While reading, show on stderr how much of the input has been read,
+    /// out of the size of the files (a spinner for stdin), how fast, and
+    /// how many distinct words have been seen. Only shown when stderr is a
+    /// terminal, and cleared before the results are printed.
+    #[arg(long, conflicts_with_all = ["text", "per_file", "follow", "group_by"])]
+    progress: bool,
+
+    /// Report on stderr how many words were skipped as stopwords, as
+    /// numbers or by --min-count
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+impl Args {
+    fn reading(&self) -> ReadOptions {
+        ReadOptions {
+            threads: self.threads,
+            gzip: self.gzip,
+            encoding: self.encoding,
+        }
+    }
+}
+
+/// Parses a count that must be at least 1.
+fn parse_positive(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(0) => Err("must be at least 1".to_string()),
+        Ok(n) => Ok(n),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Parses a number of seconds that must be at least 1.
+fn parse_positive_u64(s: &str) -> Result<u64, String> {
+    parse_positive(s).map(|n| n as u64)
 }
 
 fn main() {
     let args = Args::parse();
+    if let Some(max) = args.max_length.filter(|&max| max < args.min_length) {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                format!(
+                    "--max-length {} is shorter than --min-length {}",
+                    max, args.min_length
+                ),
+            )
+            .exit();
+    }
+    // Checked here since clap lets an argument that conflicts with the one
+    // required, such as the text for --file, stand in for it
+    let has_files = !args.files.is_empty();
+    let requirements = [
+        ("--fail-fast", args.fail_fast, "--file", has_files),
+        ("--per-file", args.per_file, "--file", has_files),
+        ("--tfidf", args.tfidf, "--file", has_files),
+        (
+            "--delta",
+            args.delta.is_some(),
+            "--compare",
+            !args.compare.is_empty(),
+        ),
+        (
+            "--interval",
+            args.interval.is_some(),
+            "--follow",
+            args.follow,
+        ),
+        (
+            "--keep-emoji",
+            args.keep_emoji,
+            "--tokenizer unicode",
+            args.tokenizer == Tokenizer::Unicode,
+        ),
+        (
+            "--window",
+            args.window.is_some(),
+            "--kwic",
+            args.kwic.is_some(),
+        ),
+        (
+            "--ties",
+            args.ties != Ties::Truncate,
+            "--top or --bottom",
+            args.top.is_some() || args.bottom.is_some(),
+        ),
+    ];
+    let missing = requirements
+        .iter()
+        .find(|&&(_, given, _, present)| given && !present);
+    if let Some((name, _, required, _)) = missing {
+        Args::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                format!("{} can only be used with {}", name, required),
+            )
+            .exit();
+    }
+    let templated = args
+        .out
+        .as_ref()
+        .is_some_and(|out| out.to_string_lossy().contains(FILE_PLACEHOLDER));
+    if templated && !args.per_file {
+        Args::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                format!(
+                    "{} in --out can only be used with --per-file",
+                    FILE_PLACEHOLDER
+                ),
+            )
+            .exit();
+    }
+    if args.group_by != GroupBy::None && args.files.len() > 1 {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--group-by reads a single file, stdin or the text",
+            )
+            .exit();
+    }
+    if args.follow && args.files.len() > 1 {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--follow reads a single file or stdin",
+            )
+            .exit();
+    }
 
-    let mut input = String::new();
-    if let Some(text) = args.text {
-        input = text;
-    } else if let Err(e) = io::stdin().read_to_string(&mut input) {
-        eprintln!("Failed to read from stdin: {}", e);
-        return;
+    let reads_terminal = args.text.is_none()
+        && args.files.is_empty()
+        && args.compare.is_empty()
+        && io::stdin().is_terminal();
+    if reads_terminal {
+        let hint = "reading from terminal; pipe text in, pass it as an argument, or use --file";
+        if args.no_tty_wait {
+            Args::command()
+                .error(ErrorKind::MissingRequiredArgument, hint)
+                .exit();
+        }
+        eprintln!("{} (end with Ctrl-D)", hint);
     }
 
-    let mut word_counts = HashMap::new();
+    // Opened before anything is read, so that an unwritable --out fails at once
+    let outputs = open_outputs(&args);
+    if args.progress && io::stderr().is_terminal() {
+        progress::start(input_size(&args));
+    }
 
-    for word in input.split_whitespace() {
-        // Remove punctuation from the end of the word
-        let trimmed_word = word.trim_end_matches(|c: char| !c.is_alphanumeric());
+    let tokenizer = match (args.chars, args.graphemes) {
+        (true, true) => Tokenizer::Graphemes,
+        (true, false) => Tokenizer::Chars,
+        (false, _) if args.legacy_trim => Tokenizer::LegacySimple,
+        (false, _) if args.keep_emoji => Tokenizer::UnicodeEmoji,
+        (false, _) => args.tokenizer,
+    };
+    let mut builder = WordCounter::builder()
+        .options(CountOptions {
+            normalization: args.normalize,
+            strip_accents: args.strip_accents,
+            max_length: args.max_length,
+            smart_case: args.smart_case,
+            ngrams: args.ngrams,
+            char_ngrams: args.char_ngrams,
+            include_symbols: args.include_symbols,
+            per_line: args.per_line,
+            sentence_bounded: args.sentence_bounded,
+            skip_whitespace: args.no_whitespace,
+            numbers: if args.no_numerics {
+                NumberFilter::Numerics
+            } else if args.no_numbers {
+                NumberFilter::Digits
+            } else {
+                NumberFilter::Keep
+            },
+            stem: args.stem.then_some(args.stem_lang),
+            show_variants: args.show_variants,
+            collocations: args.collocations,
+            ..CountOptions::default()
+        })
+        .tokenizer(tokenizer)
+        .case_insensitive(args.ignore_case)
+        .min_length(args.min_length);
+    if let Some(lang) = args.stopwords_lang {
+        builder = builder.stopwords(lang.words());
+    }
+    if let Some(path) = &args.stopwords {
+        match load_word_list(path) {
+            Ok(words) => builder = builder.stopwords(words),
+            Err(e) => {
+                eprintln!("Failed to read stopwords from {}: {}", path.display(), e);
+                destination::exit(1);
+            }
+        }
+    }
+    let mut counter = builder.build();
+    if let Some(markup) = args.strip_markup {
+        counter.set_markup(MarkupStripper::new(markup));
+    }
+    if let Some(keyword) = &args.kwic {
+        counter.set_kwic(keyword, args.window.unwrap_or(5));
+    }
+    if (args.stats || args.stats_only) && !args.chars {
+        counter.set_readability();
+    }
+    if let Some(chars) = &args.delimiters {
+        counter.set_delimiters(Delimiters::new(chars, !args.delimiters_only));
+    }
+    if let Some(pattern) = &args.pattern {
+        counter.set_pattern(
+            pattern
+                .clone()
+                .ignore_case(args.ignore_case || args.smart_case),
+        );
+    }
+    if args.only.is_some() || !args.only_words.is_empty() {
+        let mut words = match &args.only {
+            Some(path) => match load_word_list(path) {
+                Ok(words) => words,
+                Err(e) => {
+                    eprintln!("Failed to read words from {}: {}", path.display(), e);
+                    destination::exit(1);
+                }
+            },
+            None => Vec::new(),
+        };
+        words.extend(args.only_words.iter().cloned());
+        counter.set_only(words);
+    }
+    if let Some(path) = &args.phrases {
+        match load_phrases(path) {
+            Ok(phrases) => counter.set_phrases(phrases),
+            Err(e) => {
+                eprintln!("Failed to read phrases from {}: {}", path.display(), e);
+                destination::exit(1);
+            }
+        }
+    }
+
+    let output = OutputOptions {
+        format: args.format,
+        percent: args.percent,
+        cumulative: args.cumulative,
+        width: args.width,
+        unicode_bars: args.unicode_bars,
+        stats_only: args.stats_only,
+        show_variants: args.show_variants,
+        zipf: args.zipf,
+        stats_to_stdout: args.out.as_ref().is_some_and(|out| out != Path::new("-")),
+        matrix: args.only.is_some() || !args.only_words.is_empty(),
+    };
+    if args.per_file {
+        count_per_file(&args, counter, output, outputs);
+        return;
+    }
+    let Outputs::Single(mut out) = outputs else {
+        unreachable!("--out templates are only allowed with --per-file");
+    };
+    if args.tfidf {
+        count_tfidf(&args, counter, output, out);
+        return;
+    }
+    if let [a, b] = args.compare.as_slice() {
+        compare_inputs(&args, [a, b], counter, output, out);
+        return;
+    }
+    if args.follow {
+        follow_input(&args, counter, output);
+        return;
+    }
+    if args.group_by != GroupBy::None {
+        count_groups(&args, counter, output, out);
+        return;
+    }
 
-        let processed_word = if args.ignore_case {
-            trimmed_word.to_lowercase()
+    let mut failed = false;
+    if let Some(text) = &args.text {
+        counter.feed(text);
+        counter.end_input();
+    } else if args.files.is_empty() {
+        if let Err(e) = parallel::feed_path(&mut counter, Path::new("-"), args.reading()) {
+            eprintln!("Failed to read from stdin: {}", e);
+            destination::exit(1);
+        }
+    } else {
+        let results = if args.threads > 1 && args.files.len() > 1 {
+            parallel::feed_files(&mut counter, &args.files, args.reading())
         } else {
-            trimmed_word.to_string()
+            let mut results = Vec::new();
+            for path in &args.files {
+                results.push(parallel::feed_path(&mut counter, path, args.reading()));
+            }
+            results
         };
+        for (path, result) in args.files.iter().zip(results) {
+            if let Err(e) = result {
+                eprintln!("Failed to read {}: {}", path.display(), e);
+                if args.fail_fast {
+                    destination::exit(1);
+                }
+                failed = true;
+            }
+        }
+    }
 
-        if processed_word.len() >= args.min_length {
-            *word_counts.entry(processed_word).or_insert(0) += 1;
+    progress::finish();
+    let written = if let Some(occurrences) = counter.occurrences() {
+        write_concordance(&mut out, &output, occurrences, args.line_numbers)
+    } else if args.collocations {
+        let mut collocations = counter.collocations(args.min_pair_count);
+        if let Some(n) = args.top {
+            collocations.truncate(n);
         }
+        write_collocations(&mut out, &output, &collocations)
+    } else if args.anagrams {
+        counter.drop_rare(args.min_count);
+        let mut groups = counter.anagrams(args.all_groups);
+        if let Some(n) = args.top {
+            groups.truncate(n);
+        }
+        write_anagrams(&mut out, &output, &groups)
+    } else if args.by_initial {
+        let stats = args.stats.then(|| counter.stats());
+        let initials = counter.initial_distribution();
+        write_initials(&mut out, &output, &initials, stats.as_ref())
+    } else if args.length_distribution {
+        let stats = args.stats.then(|| counter.stats());
+        let lengths = counter.length_distribution();
+        write_length_distribution(&mut out, &output, &lengths, stats.as_ref())
+    } else {
+        let table = tabulate(counter, &args, args.verbose);
+        write_counts(&mut out, &output, &table)
+    };
+    if let Err(e) = written {
+        write_failed(&out, e);
     }
+    finish_output(out);
 
-    let mut sorted_counts: Vec<_> = word_counts.into_iter().collect();
-    sorted_counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    // Counts from the readable files are still printed, but the run failed
+    if failed {
+        destination::exit(1);
+    }
+}
+
+/// Counts each file with a counter of its own, printing its table before
+/// the next file is read, and then the combined table. An unreadable file
+/// gets no table and adds nothing to the combined counts. With an `--out`
+/// template each table is a file of its own, written as if its input had
+/// been counted alone.
+fn count_per_file(args: &Args, mut combined: WordCounter, output: OutputOptions, outputs: Outputs) {
+    let (mut out, file_outputs) = match outputs {
+        Outputs::Single(out) => (out, Vec::new()),
+        Outputs::PerFile { files, combined } => (combined, files),
+    };
+    let mut file_outputs = file_outputs.into_iter();
+    let mut writer = PerFileWriter::new(output);
+    let mut failed = false;
+    for path in &args.files {
+        // An unreadable file's output is dropped, which removes it
+        let file_out = file_outputs.next();
+        let mut counter = combined.fresh();
+        if let Err(e) = parallel::feed_path(&mut counter, path, args.reading()) {
+            eprintln!("Failed to read {}: {}", path.display(), e);
+            if args.fail_fast {
+                destination::exit(1);
+            }
+            failed = true;
+            continue;
+        }
+        combined.merge(&counter);
+        let table = tabulate(counter, args, false);
+        match file_out {
+            Some(mut file_out) => {
+                if let Err(e) = write_counts(&mut file_out, &output, &table) {
+                    write_failed(&file_out, e);
+                }
+                finish_output(file_out);
+            }
+            None => {
+                if let Err(e) = writer.write_file(&mut out, &input_name(path), &table) {
+                    write_failed(&out, e);
+                }
+            }
+        }
+    }
+
+    let table = tabulate(combined, args, args.verbose);
+    let written = if matches!(args.out.as_deref(), Some(out) if is_template(out)) {
+        write_counts(&mut out, &output, &table)
+    } else {
+        writer.finish(&mut out, &table)
+    };
+    if let Err(e) = written {
+        write_failed(&out, e);
+    }
+    finish_output(out);
+    if failed {
+        destination::exit(1);
+    }
+}
 
-    let results = sorted_counts.into_iter();
+/// Counts each file as a document of its own and prints the words of each
+/// by TF-IDF. All the documents' counts are held until the last file is
+/// read, since a word's score depends on how many documents contain it.
+fn count_tfidf(args: &Args, counter: WordCounter, output: OutputOptions, mut out: Destination) {
+    let mut names = Vec::new();
+    let mut documents = Vec::new();
+    let mut failed = false;
+    for path in &args.files {
+        let mut document = counter.fresh();
+        if let Err(e) = parallel::feed_path(&mut document, path, args.reading()) {
+            eprintln!("Failed to read {}: {}", path.display(), e);
+            if args.fail_fast {
+                destination::exit(1);
+            }
+            failed = true;
+            continue;
+        }
+        names.push(input_name(path));
+        documents.push(document.into_counts());
+    }
+
+    // Rare words still count towards the document frequencies
+    let scored = names.into_iter().zip(tfidf::score(documents));
+    let scored = Vec::from_iter(scored.map(|(name, mut scores)| {
+        scores.retain(|scored| scored.count >= args.min_count);
+        if let Some(n) = args.top {
+            scores.truncate(n);
+        }
+        (name, scores)
+    }));
+    progress::finish();
+    if let Err(e) = write_tfidf(&mut out, &output, &scored) {
+        write_failed(&out, e);
+    }
+    finish_output(out);
+    if failed {
+        destination::exit(1);
+    }
+}
+
+/// Counts two inputs with the same settings and prints how their
+/// vocabularies differ.
+fn compare_inputs(
+    args: &Args,
+    paths: [&Path; 2],
+    counter: WordCounter,
+    output: OutputOptions,
+    mut out: Destination,
+) {
+    let [a, b] = paths.map(|path| {
+        let mut side = counter.fresh();
+        if let Err(e) = parallel::feed_path(&mut side, path, args.reading()) {
+            eprintln!("Failed to read {}: {}", path.display(), e);
+            destination::exit(1);
+        }
+        side.into_counts()
+    });
+    progress::finish();
+    let mut comparison = compare::compare(a, b, args.delta.unwrap_or(0));
+    if let Some(n) = args.top {
+        comparison.only_a.truncate(n);
+        comparison.only_b.truncate(n);
+        comparison.changed.truncate(n);
+    }
+    let names = paths.map(input_name);
+    let names = [names[0].as_str(), names[1].as_str()];
+    if let Err(e) = write_comparison(&mut out, &output, names, &comparison) {
+        write_failed(&out, e);
+    }
+    finish_output(out);
+}
 
-    if let Some(top_n) = args.top {
-        for (word, count) in results.take(top_n) {
-            println!("{}: {}", word, count);
+/// Counts each line or paragraph of the input with a counter of its own,
+/// printing its table as soon as the group ends. Lines are read one at a
+/// time, so a group is tokenized only once and never held in memory
+/// whole.
+fn count_groups(args: &Args, counter: WordCounter, output: OutputOptions, mut out: Destination) {
+    let path = args.files.first().map_or(Path::new("-"), PathBuf::as_path);
+    let mut reader: Box<dyn BufRead> = match &args.text {
+        Some(text) => Box::new(io::Cursor::new(text.as_bytes())),
+        None => match args.reading().open(path) {
+            Ok(reader) => reader,
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", input_name(path), e);
+                destination::exit(1);
+            }
+        },
+    };
+    let unit = match args.group_by {
+        GroupBy::Line => "line",
+        _ => "paragraph",
+    };
+    let mut writer = PerFileWriter::grouped(output, unit);
+    let mut group = counter.fresh();
+    // The number of the group being read, and whether it has any lines
+    let mut index = 1;
+    let mut open = false;
+    let mut line = String::new();
+    let mut write_group = |group: &mut WordCounter, index: usize, out: &mut Destination| {
+        group.end_input();
+        let table = tabulate(std::mem::replace(group, counter.fresh()), args, false);
+        if table.summary.total_tokens == 0 {
+            return;
         }
+        if let Err(e) = writer.write_group(out, index, &table) {
+            write_failed(out, e);
+        }
+    };
+    loop {
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", input_name(path), e);
+                destination::exit(1);
+            }
+        }
+        if args.group_by == GroupBy::Line {
+            group.feed(&line);
+            write_group(&mut group, index, &mut out);
+            index += 1;
+        } else if line.trim().is_empty() {
+            // A run of blank lines ends the paragraph once
+            if open {
+                write_group(&mut group, index, &mut out);
+                index += 1;
+                open = false;
+            }
+        } else {
+            group.feed(&line);
+            open = true;
+        }
+    }
+    if open {
+        write_group(&mut group, index, &mut out);
+    }
+    if let Err(e) = writer.finish_groups(&mut out) {
+        write_failed(&out, e);
+    }
+    finish_output(out);
+}
+
+/// Counts a file or stdin as it grows, printing the table every
+/// `--interval` seconds and once more at the end.
+fn follow_input(args: &Args, mut counter: WordCounter, output: OutputOptions) {
+    let path = args.files.first().map(PathBuf::as_path);
+    let interval = Duration::from_secs(args.interval.unwrap_or(2));
+    let terminal = io::stdout().is_terminal();
+    let mut reports = 0;
+    let followed = follow::follow(&mut counter, path, interval, |counter| {
+        let table = tabulate(counter.clone(), args, false);
+        let mut out = io::stdout().lock();
+        if terminal {
+            // Clear the screen and start again at the top
+            write!(out, "\x1b[2J\x1b[H")?;
+        } else if reports > 0 {
+            writeln!(out, "---")?;
+        }
+        reports += 1;
+        write_counts(&mut out, &output, &table)
+    });
+    if let Err(e) = followed {
+        match path {
+            Some(path) => eprintln!("Failed to follow {}: {}", path.display(), e),
+            None => eprintln!("Failed to follow stdin: {}", e),
+        }
+        destination::exit(1);
+    }
+}
+
+/// The bytes of all the input files together, or `None` if stdin is read
+/// or a file's size cannot be found.
+fn input_size(args: &Args) -> Option<u64> {
+    let paths = args.files.iter().chain(&args.compare);
+    let mut total = 0;
+    let mut any = false;
+    for path in paths {
+        if path == Path::new("-") {
+            return None;
+        }
+        total += std::fs::metadata(path).ok()?.len();
+        any = true;
+    }
+    any.then_some(total)
+}
+
+/// How an input is named in output: its path, or "standard input" for `-`.
+fn input_name(path: &Path) -> String {
+    if path == Path::new("-") {
+        "standard input".to_string()
     } else {
-        for (word, count) in results {
-            println!("{}: {}", word, count);
+        path.display().to_string()
+    }
+}
+
+/// Applies `--min-count`, the sort order and `--top` to everything
+/// `counter` has counted, reporting what was left out if `verbose`.
+fn tabulate(mut counter: WordCounter, args: &Args, verbose: bool) -> Table {
+    // The totals cover every counted word, including those --min-count hides
+    let summary = Summary {
+        total_tokens: counter.total_tokens(),
+        unique_words: counter.unique_words(),
+    };
+    let mut stats = (args.stats || args.stats_only || args.zipf).then(|| counter.stats());
+    let rare = counter.drop_rare(args.min_count);
+    let below = args
+        .min_percent
+        .map(|percent| counter.drop_below_percent(percent, summary.total_tokens));
+    if let Some(stats) = &mut stats {
+        stats.rare_suppressed = rare;
+        stats.below_min_percent = below;
+    }
+    if verbose {
+        eprintln!("Dropped {} stopwords.", counter.stopwords_dropped());
+        if args.no_numbers || args.no_numerics {
+            eprintln!("Dropped {} numbers.", counter.numbers_dropped());
+        }
+        if args.min_count > 1 {
+            eprintln!(
+                "Suppressed {} distinct words seen fewer than {} times.",
+                rare, args.min_count
+            );
+        }
+        if let (Some(below), Some(percent)) = (below, args.min_percent) {
+            eprintln!(
+                "Suppressed {} distinct words below {}% of the tokens.",
+                below, percent
+            );
+        }
+    }
+
+    let variants = counter.take_variants();
+    let mut counts = match (counter.phrase_counts(), args.bottom) {
+        (Some(mut phrases), _) => {
+            args.sort.sort(&mut phrases, args.reverse);
+            phrases
+        }
+        (None, _) if counter.has_only() => counter.into_listed(),
+        (None, Some(_)) => counter.into_sorted_vec(SortOrder {
+            key: SortKey::Rarest,
+            reverse: false,
+        }),
+        (None, None) => counter.into_sorted_vec(SortOrder {
+            key: args.sort,
+            reverse: args.reverse,
+        }),
+    };
+    // Fitted to every word that is left, before --top cuts the list short
+    let zipf = args
+        .zipf
+        .then(|| ZipfFit::fit(&counts, args.zipf_min_count))
+        .flatten();
+    if let Some(stats) = &mut stats {
+        stats.zipf_exponent = zipf.map(|fit| fit.exponent);
+    }
+    let mut boundary_count = None;
+    if let Some(n) = args.top.or(args.bottom) {
+        if args.ties == Ties::Include && counts.len() > n && n > 0 {
+            boundary_count = Some(counts[n - 1].1);
         }
+        let tied = args.ties.truncate(&mut counts, n);
+        if tied > 0 {
+            eprintln!(
+                "Included {} more words tied at count {}.",
+                tied,
+                counts[n - 1].1
+            );
+        }
+    }
+    Table {
+        counts,
+        summary,
+        stats,
+        variants,
+        zipf,
+        boundary_count,
+    }
+}
+
+/// Stands for each input's name in an `--out` path with `--per-file`.
+const FILE_PLACEHOLDER: &str = "{file}";
+
+fn is_template(out: &Path) -> bool {
+    out.to_string_lossy().contains(FILE_PLACEHOLDER)
+}
+
+/// Where the results go.
+enum Outputs {
+    Single(Destination),
+    /// From an `--out` template: one for each input, then the combined
+    /// table's
+    PerFile {
+        files: Vec<Destination>,
+        combined: Destination,
+    },
+}
+
+/// Opens everything `--out` names, or stdout. A path that cannot be
+/// created ends the run, as do two inputs that a template would give the
+/// same file.
+fn open_outputs(args: &Args) -> Outputs {
+    let Some(out) = args.out.as_deref().filter(|out| is_template(out)) else {
+        let path = args.out.as_deref().unwrap_or(Path::new("-"));
+        return Outputs::Single(open_output(path));
+    };
+    let template = out.to_string_lossy();
+    let fill = |name: &str| PathBuf::from(template.replace(FILE_PLACEHOLDER, name));
+    let mut paths: Vec<(String, PathBuf)> = args
+        .files
+        .iter()
+        .map(|path| {
+            let name = match path.file_stem() {
+                _ if path == Path::new("-") => "stdin".into(),
+                Some(stem) => stem.to_string_lossy(),
+                None => path.to_string_lossy(),
+            };
+            (input_name(path), fill(&name))
+        })
+        .collect();
+    paths.push(("the combined table".to_string(), fill("combined")));
+    for (i, (input, path)) in paths.iter().enumerate() {
+        if let Some((other, _)) = paths[..i].iter().find(|(_, other)| other == path) {
+            Args::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    format!(
+                        "--out {} names {} for both {} and {}",
+                        template,
+                        path.display(),
+                        other,
+                        input
+                    ),
+                )
+                .exit();
+        }
+    }
+    let mut files: Vec<Destination> = paths.iter().map(|(_, path)| open_output(path)).collect();
+    let combined = files.pop().expect("the combined table has a path");
+    Outputs::PerFile { files, combined }
+}
+
+/// Opens `path` for writing, or stdout for `-`, or ends the run.
+fn open_output(path: &Path) -> Destination {
+    match Destination::create(path) {
+        Ok(out) => out,
+        Err(e) => {
+            eprintln!("Failed to create {}: {}", path.display(), e);
+            destination::exit(1);
+        }
+    }
+}
+
+/// Moves a complete output into place, or ends the run.
+fn finish_output(mut out: Destination) {
+    if let Err(e) = out.finish() {
+        write_failed(&out, e);
+    }
+}
+
+fn write_failed(out: &Destination, e: io::Error) -> ! {
+    match out.path() {
+        Some(path) => eprintln!("Failed to write {}: {}", path.display(), e),
+        None => eprintln!("Failed to write output: {}", e),
     }
+    destination::exit(1);
 }
@@ -0,0 +1,610 @@
+/// Markup to remove before the text is split into words.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Markup {
+    /// Tags, comments and the contents of <script> and <style> are dropped
+    /// and entities such as &amp; decoded
+    Html,
+    /// Code blocks and code spans, link and image URLs, inline HTML and
+    /// emphasis markers are dropped
+    Markdown,
+}
+
+/// An entity longer than this is taken for text.
+const MAX_ENTITY: usize = 32;
+
+/// An inline tag or autolink longer than this is taken for text.
+const MAX_ANGLE: usize = 1024;
+
+/// Removes markup from text given a piece at a time. A tag, entity or code
+/// block may be split between pieces anywhere, since where each piece left
+/// off is carried over to the next.
+#[derive(Clone, Debug)]
+pub struct MarkupStripper {
+    markup: Markup,
+    html: Html,
+    markdown: Markdown,
+}
+
+impl MarkupStripper {
+    pub fn new(markup: Markup) -> MarkupStripper {
+        MarkupStripper {
+            markup,
+            html: Html::default(),
+            markdown: Markdown::default(),
+        }
+    }
+
+    /// The same kind of stripper, at the start of a document.
+    pub fn fresh(&self) -> MarkupStripper {
+        MarkupStripper::new(self.markup)
+    }
+
+    /// The text of `piece`, as far as it can be told from markup yet.
+    /// Markup is replaced by a space, so that the words either side of a
+    /// tag are not joined.
+    pub fn strip(&mut self, piece: &str) -> String {
+        let mut out = String::with_capacity(piece.len());
+        for c in piece.chars() {
+            match self.markup {
+                Markup::Html => self.html.push(c, &mut out),
+                Markup::Markdown => self.markdown.push(c, &mut out),
+            }
+        }
+        out
+    }
+
+    /// Ends the document, returning whatever text was held back in case it
+    /// began some markup (such as a lone `&`). Markup left open, such as an
+    /// unterminated tag, is dropped.
+    pub fn finish(&mut self) -> String {
+        let held = match self.markup {
+            Markup::Html => std::mem::take(&mut self.html).held(),
+            Markup::Markdown => std::mem::take(&mut self.markdown).held(),
+        };
+        held.unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+enum HtmlState {
+    #[default]
+    Text,
+    /// After `&`, with what has followed held back
+    Entity,
+    /// Just after `<`
+    Open,
+    /// In the name of a tag
+    TagName,
+    /// In the attributes of a tag, maybe inside a quoted value
+    Tag { quote: Option<char> },
+    /// After `<!`, with how many dashes have followed (two start a comment)
+    Bang(usize),
+    /// In a comment, with how many dashes were just seen
+    Comment(usize),
+    /// In a doctype, CDATA section or processing instruction
+    Declaration,
+    /// In the contents of <script> or <style>, with how much of the
+    /// closing tag has been seen
+    Raw { closing: String, matched: usize },
+}
+
+#[derive(Clone, Debug, Default)]
+struct Html {
+    state: HtmlState,
+    /// Text held back until it is known whether it is markup
+    held: String,
+    /// The name of the tag being read, in lowercase
+    tag: String,
+    closing: bool,
+    self_closing: bool,
+}
+
+impl Html {
+    fn push(&mut self, c: char, out: &mut String) {
+        match &mut self.state {
+            HtmlState::Text => match c {
+                '<' => self.state = HtmlState::Open,
+                '&' => {
+                    self.held.push('&');
+                    self.state = HtmlState::Entity;
+                }
+                c => out.push(c),
+            },
+            HtmlState::Entity => {
+                if c == ';' {
+                    match decode_entity(&self.held[1..]) {
+                        Some(decoded) => out.push(decoded),
+                        None => {
+                            out.push_str(&self.held);
+                            out.push(';');
+                        }
+                    }
+                    self.held.clear();
+                    self.state = HtmlState::Text;
+                } else if (c.is_ascii_alphanumeric() || c == '#') && self.held.len() < MAX_ENTITY {
+                    self.held.push(c);
+                } else {
+                    // Not an entity after all
+                    out.push_str(&self.held);
+                    self.held.clear();
+                    self.state = HtmlState::Text;
+                    self.push(c, out);
+                }
+            }
+            HtmlState::Open => match c {
+                c if c.is_ascii_alphabetic() || c == '/' => {
+                    self.tag.clear();
+                    self.closing = c == '/';
+                    self.self_closing = false;
+                    if !self.closing {
+                        self.tag.push(c.to_ascii_lowercase());
+                    }
+                    self.state = HtmlState::TagName;
+                }
+                '!' => self.state = HtmlState::Bang(0),
+                '?' => self.state = HtmlState::Declaration,
+                c => {
+                    // A "<" on its own, as in "a < b"
+                    out.push('<');
+                    self.state = HtmlState::Text;
+                    self.push(c, out);
+                }
+            },
+            HtmlState::TagName => match c {
+                '>' => self.end_tag(out),
+                c if c.is_ascii_alphanumeric() || c == '-' || c == ':' => {
+                    self.tag.push(c.to_ascii_lowercase());
+                }
+                c => {
+                    self.self_closing = c == '/';
+                    self.state = HtmlState::Tag { quote: None };
+                }
+            },
+            HtmlState::Tag { quote } => match (*quote, c) {
+                (Some(open), c) if c == open => *quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => *quote = Some(c),
+                (None, '>') => self.end_tag(out),
+                (None, c) => {
+                    if !c.is_whitespace() {
+                        self.self_closing = c == '/';
+                    }
+                }
+            },
+            HtmlState::Bang(dashes) => match c {
+                '-' if *dashes == 1 => self.state = HtmlState::Comment(0),
+                '-' => *dashes += 1,
+                '>' => self.end_markup(out),
+                _ => self.state = HtmlState::Declaration,
+            },
+            HtmlState::Comment(dashes) => match c {
+                '-' => *dashes += 1,
+                '>' if *dashes >= 2 => self.end_markup(out),
+                _ => *dashes = 0,
+            },
+            HtmlState::Declaration => {
+                if c == '>' {
+                    self.end_markup(out);
+                }
+            }
+            HtmlState::Raw { closing, matched } => {
+                let expected = closing[*matched..].chars().next();
+                if expected == Some(c.to_ascii_lowercase()) {
+                    *matched += c.len_utf8();
+                    if *matched == closing.len() {
+                        // The rest of the closing tag, up to its ">"
+                        self.closing = true;
+                        self.tag.clear();
+                        self.state = HtmlState::Tag { quote: None };
+                    }
+                } else {
+                    *matched = if c == '<' { 1 } else { 0 };
+                }
+            }
+        }
+    }
+
+    fn end_tag(&mut self, out: &mut String) {
+        self.end_markup(out);
+        let raw = matches!(self.tag.as_str(), "script" | "style");
+        if raw && !self.closing && !self.self_closing {
+            self.state = HtmlState::Raw {
+                closing: format!("</{}", self.tag),
+                matched: 0,
+            };
+        }
+    }
+
+    fn end_markup(&mut self, out: &mut String) {
+        out.push(' ');
+        self.state = HtmlState::Text;
+    }
+
+    fn held(self) -> Option<String> {
+        match self.state {
+            HtmlState::Entity => Some(self.held),
+            HtmlState::Open => Some("<".to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// The character an entity such as `amp`, `#38` or `#x26` stands for.
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    let c = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{A0}',
+        "shy" => '\u{AD}',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "deg" => '°',
+        "middot" => '·',
+        "bull" => '•',
+        "hellip" => '…',
+        "ndash" => '–',
+        "mdash" => '—',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "sbquo" => '‚',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "bdquo" => '„',
+        "laquo" => '«',
+        "raquo" => '»',
+        "euro" => '€',
+        "pound" => '£',
+        "yen" => '¥',
+        "cent" => '¢',
+        "sect" => '§',
+        "para" => '¶',
+        "times" => '×',
+        "divide" => '÷',
+        "szlig" => 'ß',
+        "agrave" => 'à',
+        "aacute" => 'á',
+        "acirc" => 'â',
+        "auml" => 'ä',
+        "aring" => 'å',
+        "aelig" => 'æ',
+        "ccedil" => 'ç',
+        "egrave" => 'è',
+        "eacute" => 'é',
+        "ecirc" => 'ê',
+        "euml" => 'ë',
+        "igrave" => 'ì',
+        "iacute" => 'í',
+        "icirc" => 'î',
+        "iuml" => 'ï',
+        "ntilde" => 'ñ',
+        "ograve" => 'ò',
+        "oacute" => 'ó',
+        "ocirc" => 'ô',
+        "ouml" => 'ö',
+        "oslash" => 'ø',
+        "ugrave" => 'ù',
+        "uacute" => 'ú',
+        "ucirc" => 'û',
+        "uuml" => 'ü',
+        "Agrave" => 'À',
+        "Aacute" => 'Á',
+        "Auml" => 'Ä',
+        "Aring" => 'Å',
+        "Ccedil" => 'Ç',
+        "Eacute" => 'É',
+        "Ntilde" => 'Ñ',
+        "Ouml" => 'Ö',
+        "Uuml" => 'Ü',
+        _ => return None,
+    };
+    Some(c)
+}
+
+#[derive(Clone, Debug)]
+enum MarkdownState {
+    /// At the start of a line, with the run of backticks or tildes so far
+    LineStart {
+        fence: char,
+        run: usize,
+    },
+    Text,
+    /// After a backslash, which makes the next character plain text
+    Escape,
+    /// After `_`, which is kept only between two letters or digits
+    Underscore,
+    /// In a run of backticks opening a code span
+    Ticks(usize),
+    /// In a code span opened by this many backticks, with the run of
+    /// backticks just seen
+    Code {
+        ticks: usize,
+        run: usize,
+    },
+    /// After `]`, which a URL may follow
+    Bracket,
+    /// In the URL of a link or image, with how many parentheses are open
+    Url(usize),
+    /// After `<`, with what has followed held back
+    Angle,
+    /// The rest of the line is dropped: a code block line, a fence's info
+    /// string or a link reference definition
+    SkipLine,
+}
+
+impl Default for MarkdownState {
+    fn default() -> MarkdownState {
+        MarkdownState::LineStart { fence: '`', run: 0 }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Markdown {
+    state: MarkdownState,
+    /// The fence character and length of the code block we are in
+    fence: Option<(char, usize)>,
+    /// The last character of text, for telling intraword underscores apart
+    previous: char,
+    /// Text held back until it is known whether it is markup
+    held: String,
+}
+
+impl Markdown {
+    fn push(&mut self, c: char, out: &mut String) {
+        if c == '\n' {
+            self.end_line(out);
+            return;
+        }
+        match &mut self.state {
+            MarkdownState::LineStart { fence, run } => match c {
+                ' ' | '\t' if *run == 0 => {
+                    if self.fence.is_none() {
+                        out.push(c);
+                    }
+                }
+                '`' | '~' if *run == 0 || c == *fence => {
+                    *fence = c;
+                    *run += 1;
+                }
+                c => {
+                    let (fence, run) = (*fence, *run);
+                    self.start_line(fence, run);
+                    self.push(c, out);
+                }
+            },
+            MarkdownState::Text => match c {
+                '\\' => self.state = MarkdownState::Escape,
+                '_' => self.state = MarkdownState::Underscore,
+                '`' => self.state = MarkdownState::Ticks(1),
+                ']' => {
+                    out.push(' ');
+                    self.state = MarkdownState::Bracket;
+                }
+                '<' => {
+                    self.held.push('<');
+                    self.state = MarkdownState::Angle;
+                }
+                '*' | '~' => {}
+                c => self.text(c, out),
+            },
+            MarkdownState::Escape => {
+                self.state = MarkdownState::Text;
+                self.text(c, out);
+            }
+            MarkdownState::Underscore => {
+                self.state = MarkdownState::Text;
+                if self.previous.is_alphanumeric() && c.is_alphanumeric() {
+                    out.push('_');
+                }
+                self.push(c, out);
+            }
+            MarkdownState::Ticks(ticks) => {
+                if c == '`' {
+                    *ticks += 1;
+                } else {
+                    let ticks = *ticks;
+                    self.state = MarkdownState::Code { ticks, run: 0 };
+                    self.push(c, out);
+                }
+            }
+            MarkdownState::Code { ticks, run } => {
+                if c == '`' {
+                    *run += 1;
+                } else if *run == *ticks {
+                    out.push(' ');
+                    self.state = MarkdownState::Text;
+                    self.push(c, out);
+                } else {
+                    *run = 0;
+                }
+            }
+            MarkdownState::Bracket => match c {
+                '(' => self.state = MarkdownState::Url(1),
+                ':' => self.state = MarkdownState::SkipLine,
+                c => {
+                    self.state = MarkdownState::Text;
+                    self.push(c, out);
+                }
+            },
+            MarkdownState::Url(open) => match c {
+                '(' => *open += 1,
+                ')' if *open == 1 => self.state = MarkdownState::Text,
+                ')' => *open -= 1,
+                _ => {}
+            },
+            MarkdownState::Angle => {
+                let starts_tag =
+                    self.held.len() > 1 || c.is_ascii_alphabetic() || matches!(c, '/' | '!');
+                if c == '>' && self.held.len() > 1 {
+                    // An inline tag or an autolink
+                    out.push(' ');
+                    self.held.clear();
+                    self.state = MarkdownState::Text;
+                } else if starts_tag && self.held.len() < MAX_ANGLE {
+                    self.held.push(c);
+                } else {
+                    out.push_str(&self.held);
+                    self.held.clear();
+                    self.state = MarkdownState::Text;
+                    self.push(c, out);
+                }
+            }
+            MarkdownState::SkipLine => {}
+        }
+    }
+
+    /// Decides what a line is once its first other character is seen, with
+    /// the run of backticks or tildes before it. A shorter run of tildes is
+    /// a strikethrough marker, and dropped.
+    fn start_line(&mut self, fence: char, run: usize) {
+        self.state = match self.fence {
+            Some((open, length)) if fence == open && run >= length => {
+                self.fence = None;
+                MarkdownState::SkipLine
+            }
+            Some(_) => MarkdownState::SkipLine,
+            None if run >= 3 => {
+                self.fence = Some((fence, run));
+                MarkdownState::SkipLine
+            }
+            None if fence == '`' && run > 0 => MarkdownState::Code { ticks: run, run: 0 },
+            None => MarkdownState::Text,
+        };
+    }
+
+    /// Ends the line: code spans, URLs and inline tags do not run on to the
+    /// next.
+    fn end_line(&mut self, out: &mut String) {
+        if let MarkdownState::LineStart { fence, run } = self.state {
+            self.start_line(fence, run);
+        }
+        match self.state {
+            MarkdownState::Angle => out.push_str(&self.held),
+            MarkdownState::Escape => self.text('\\', out),
+            _ => {}
+        }
+        self.held.clear();
+        out.push('\n');
+        self.previous = '\n';
+        self.state = MarkdownState::default();
+    }
+
+    fn text(&mut self, c: char, out: &mut String) {
+        out.push(c);
+        self.previous = c;
+    }
+
+    fn held(self) -> Option<String> {
+        match self.state {
+            MarkdownState::Angle => Some(self.held),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A file in tests/fixtures/markup.
+    fn fixture(name: &str) -> String {
+        let path = format!(
+            "{}/tests/fixtures/markup/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        );
+        std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e))
+    }
+
+    /// The words left of `text` by a stripper given it `size` characters
+    /// at a time.
+    fn stripped_words(markup: Markup, text: &str, size: usize) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut stripper = MarkupStripper::new(markup);
+        let mut out = String::new();
+        for piece in chars.chunks(size) {
+            out.push_str(&stripper.strip(&piece.iter().collect::<String>()));
+        }
+        out.push_str(&stripper.finish());
+        out.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn fixtures_strip_the_same_however_they_are_split() {
+        for (markup, name) in [(Markup::Html, "page.html"), (Markup::Markdown, "README.md")] {
+            let text = fixture(name);
+            let whole = stripped_words(markup, &text, text.len());
+            for size in [1, 2, 3, 7, 64] {
+                assert_eq!(
+                    stripped_words(markup, &text, size),
+                    whole,
+                    "{} by {}",
+                    name,
+                    size
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn html_leaves_no_tags_scripts_or_attributes() {
+        let words = stripped_words(Markup::Html, &fixture("page.html"), 5).join(" ");
+        for markup in ["<", "div", "href", "https", "color", "track", "nav", "amp"] {
+            assert!(!words.contains(markup), "{} in {}", markup, words);
+        }
+        assert!(words.contains("Widgets & Gadgets"));
+        assert!(words.contains("Café owners love widgets & gadgets."));
+    }
+
+    #[test]
+    fn markdown_leaves_no_code_or_urls() {
+        let words = stripped_words(Markup::Markdown, &fixture("README.md"), 5).join(" ");
+        for markup in [
+            "cargo install",
+            "--help",
+            "fn",
+            "https",
+            "png",
+            "**",
+            "`",
+            "(",
+            "_options",
+        ] {
+            assert!(!words.contains(markup), "{} in {}", markup, words);
+        }
+        // Link text is kept, brackets and all, for the tokenizer to trim
+        assert!(words.contains("the widget tool with [cargo :"));
+        assert!(words.contains("See the ![logo and for more."));
+        assert!(words.contains("A widget_name is kept whole."));
+    }
+
+    #[test]
+    fn the_end_of_a_document_drops_open_markup_but_keeps_text() {
+        assert_eq!(
+            stripped_words(Markup::Html, "fish &chips", 1),
+            ["fish", "&chips"]
+        );
+        assert_eq!(
+            stripped_words(Markup::Html, "fish & chips &", 1),
+            ["fish", "&", "chips", "&"]
+        );
+        assert_eq!(
+            stripped_words(Markup::Html, "before <a href=\"x", 1),
+            ["before"]
+        );
+        assert_eq!(
+            stripped_words(Markup::Markdown, "see <not closed", 1),
+            ["see", "<not", "closed"]
+        );
+    }
+}
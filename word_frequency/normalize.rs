@@ -0,0 +1,249 @@
+use std::borrow::Cow;
+
+use crate::unicode_tables::{CANONICAL, COMBINING_CLASSES, COMPATIBILITY, COMPOSITIONS};
+
+/// Which Unicode normalization form words are put in before counting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Normalization {
+    /// Count words as written, so that a precomposed "é" and an "e" with a
+    /// combining accent are different words
+    #[default]
+    None,
+    /// Canonical composition: the two spellings of "é" become one
+    Nfc,
+    /// Compatibility composition: also folds ligatures, full-width forms,
+    /// superscripts and the like into their plain letters ("ﬁ" to "fi")
+    Nfkc,
+}
+
+// The Hangul syllables are composed and decomposed arithmetically
+const S_BASE: u32 = 0xAC00;
+const L_BASE: u32 = 0x1100;
+const V_BASE: u32 = 0x1161;
+const T_BASE: u32 = 0x11A7;
+const L_COUNT: u32 = 19;
+const V_COUNT: u32 = 21;
+const T_COUNT: u32 = 28;
+const N_COUNT: u32 = V_COUNT * T_COUNT;
+const S_COUNT: u32 = L_COUNT * N_COUNT;
+
+impl Normalization {
+    /// `word` in this form, with combining marks removed if `strip_accents`
+    /// (so that "café" becomes "cafe"). Marks are stripped from the
+    /// decomposed form, and what is left composed again.
+    pub fn apply(self, word: &str, strip_accents: bool) -> Cow<'_, str> {
+        // ASCII is in every form already and has no marks
+        if word.is_ascii() || (self == Normalization::None && !strip_accents) {
+            return Cow::Borrowed(word);
+        }
+        let mut chars = decompose(word, self == Normalization::Nfkc);
+        if strip_accents {
+            chars.retain(|&c| combining_class(c) == 0);
+        }
+        // Composed even for `None`, so that stripping marks changes nothing
+        // else
+        let normalized = String::from_iter(compose(chars));
+        if normalized == word {
+            Cow::Borrowed(word)
+        } else {
+            Cow::Owned(normalized)
+        }
+    }
+}
+
+/// The full decomposition of `word` (NFD, or NFKD if `compatibility`), its
+/// combining marks in canonical order.
+fn decompose(word: &str, compatibility: bool) -> Vec<char> {
+    let mut chars = Vec::with_capacity(word.len());
+    for c in word.chars() {
+        let code = c as u32;
+        if (S_BASE..S_BASE + S_COUNT).contains(&code) {
+            let index = code - S_BASE;
+            chars.push(char::from_u32(L_BASE + index / N_COUNT).unwrap());
+            chars.push(char::from_u32(V_BASE + index % N_COUNT / T_COUNT).unwrap());
+            if !index.is_multiple_of(T_COUNT) {
+                chars.push(char::from_u32(T_BASE + index % T_COUNT).unwrap());
+            }
+            continue;
+        }
+        let decomposed = compatibility
+            .then(|| lookup(COMPATIBILITY, c))
+            .flatten()
+            .or_else(|| lookup(CANONICAL, c));
+        match decomposed {
+            Some(decomposed) => chars.extend(decomposed.chars()),
+            None => chars.push(c),
+        }
+    }
+
+    // Sort each run of marks by combining class, keeping the order of
+    // marks of the same class
+    let mut start = 0;
+    while start < chars.len() {
+        if combining_class(chars[start]) == 0 {
+            start += 1;
+            continue;
+        }
+        let end = chars[start..]
+            .iter()
+            .position(|&c| combining_class(c) == 0)
+            .map_or(chars.len(), |n| start + n);
+        chars[start..end].sort_by_key(|&c| combining_class(c));
+        start = end;
+    }
+    chars
+}
+
+/// Composes decomposed `chars` as NFC does: each mark (or Hangul jamo)
+/// joins the last starter before it, unless a mark of the same or a higher
+/// class comes between them.
+fn compose(chars: Vec<char>) -> Vec<char> {
+    let mut composed: Vec<char> = Vec::with_capacity(chars.len());
+    // The position of the last starter, and the class of the last
+    // character after it that did not join it
+    let mut starter: Option<usize> = None;
+    let mut last_class = 0;
+    for c in chars {
+        let class = combining_class(c);
+        if let Some(position) = starter {
+            let adjacent = composed.len() == position + 1;
+            let blocked = !adjacent && (last_class == 0 || last_class >= class);
+            if !blocked {
+                if let Some(joined) = compose_pair(composed[position], c) {
+                    composed[position] = joined;
+                    continue;
+                }
+            }
+        }
+        if class == 0 {
+            starter = Some(composed.len());
+            last_class = 0;
+        } else {
+            last_class = class;
+        }
+        composed.push(c);
+    }
+    composed
+}
+
+fn compose_pair(first: char, second: char) -> Option<char> {
+    let (a, b) = (first as u32, second as u32);
+    if (L_BASE..L_BASE + L_COUNT).contains(&a) && (V_BASE..V_BASE + V_COUNT).contains(&b) {
+        let index = (a - L_BASE) * N_COUNT + (b - V_BASE) * T_COUNT;
+        return char::from_u32(S_BASE + index);
+    }
+    if (S_BASE..S_BASE + S_COUNT).contains(&a)
+        && (a - S_BASE).is_multiple_of(T_COUNT)
+        && (T_BASE + 1..T_BASE + T_COUNT).contains(&b)
+    {
+        return char::from_u32(a + b - T_BASE);
+    }
+    COMPOSITIONS
+        .binary_search_by(|&(x, y, _)| (x, y).cmp(&(first, second)))
+        .ok()
+        .map(|i| COMPOSITIONS[i].2)
+}
+
+fn lookup(table: &'static [(char, &'static str)], c: char) -> Option<&'static str> {
+    table
+        .binary_search_by_key(&c, |&(key, _)| key)
+        .ok()
+        .map(|i| table[i].1)
+}
+
+/// The canonical combining class of `c`: 0 for starters, and for marks the
+/// order in which they are sorted.
+fn combining_class(c: char) -> u8 {
+    if c.is_ascii() {
+        return 0;
+    }
+    match COMBINING_CLASSES.binary_search_by(|&(first, last, _)| {
+        if last < c {
+            std::cmp::Ordering::Less
+        } else if first > c {
+            std::cmp::Ordering::Greater
+        } else {
+            std::cmp::Ordering::Equal
+        }
+    }) {
+        Ok(i) => COMBINING_CLASSES[i].2,
+        Err(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRECOMPOSED_E: &str = "caf\u{e9}";
+    const DECOMPOSED_E: &str = "cafe\u{301}";
+
+    fn nfc(word: &str) -> Cow<'_, str> {
+        Normalization::Nfc.apply(word, false)
+    }
+
+    fn nfkc(word: &str) -> Cow<'_, str> {
+        Normalization::Nfkc.apply(word, false)
+    }
+
+    #[test]
+    fn both_spellings_of_e_acute_compose_to_one() {
+        assert_eq!(nfc(DECOMPOSED_E), PRECOMPOSED_E);
+        assert_eq!(nfkc(DECOMPOSED_E), PRECOMPOSED_E);
+        assert!(matches!(nfc(PRECOMPOSED_E), Cow::Borrowed(_)));
+        // Without a form, words are counted as written
+        assert_eq!(Normalization::None.apply(DECOMPOSED_E, false), DECOMPOSED_E);
+        // Marks are put in canonical order before composing
+        assert_eq!(nfc("a\u{323}\u{302}"), "\u{1ead}");
+        assert_eq!(nfc("a\u{302}\u{323}"), "\u{1ead}");
+    }
+
+    #[test]
+    fn compatibility_forms_fold_only_with_nfkc() {
+        assert_eq!(nfc("\u{fb01}ne"), "\u{fb01}ne");
+        assert_eq!(nfkc("\u{fb01}ne"), "fine");
+        // Full-width letters and digits
+        assert_eq!(
+            nfc("\u{ff37}\u{ff4f}\u{ff52}\u{ff44}"),
+            "\u{ff37}\u{ff4f}\u{ff52}\u{ff44}"
+        );
+        assert_eq!(nfkc("\u{ff37}\u{ff4f}\u{ff52}\u{ff44}\u{ff11}"), "Word1");
+        assert_eq!(nfkc("x\u{b2}"), "x2");
+        // A compatibility decomposition that recomposes canonically
+        assert_eq!(nfkc("\u{1e9b}\u{323}"), "\u{1e69}");
+    }
+
+    #[test]
+    fn hangul_syllables_and_jamo_compose() {
+        assert_eq!(nfc("\u{1112}\u{1161}\u{11ab}"), "\u{d55c}");
+        assert!(matches!(nfc("\u{d55c}\u{ae00}"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn accents_are_stripped_from_the_decomposed_word() {
+        for form in [Normalization::None, Normalization::Nfc, Normalization::Nfkc] {
+            assert_eq!(form.apply(PRECOMPOSED_E, true), "cafe");
+            assert_eq!(form.apply(DECOMPOSED_E, true), "cafe");
+            assert_eq!(form.apply("\u{c5}ngstr\u{f6}m", true), "Angstrom");
+        }
+        assert_eq!(
+            Normalization::Nfkc.apply("\u{fb01}anc\u{e9}", true),
+            "fiance"
+        );
+    }
+
+    #[test]
+    fn turkish_dotted_and_dotless_i() {
+        // Dotless ı has no decomposition, so stays apart from i
+        assert_eq!(nfkc("\u{131}s\u{131}k"), "\u{131}s\u{131}k");
+        assert_eq!(
+            Normalization::Nfc.apply("\u{131}s\u{131}k", true),
+            "\u{131}s\u{131}k"
+        );
+        // Dotted İ is I with a combining dot above, which is stripped
+        assert_eq!(nfc("\u{130}stanbul"), "\u{130}stanbul");
+        assert_eq!(Normalization::Nfc.apply("\u{130}stanbul", true), "Istanbul");
+        // Lowercased, as --ignore-case does, it keeps the dot as a mark
+        assert_eq!(nfc("\u{130}stanbul").to_lowercase(), "i\u{307}stanbul");
+    }
+}
@@ -0,0 +1,1424 @@
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use word_frequency::compare::Comparison;
+use word_frequency::counter::{AnagramGroup, Collocation, InitialCount, LengthCount, Variants};
+use word_frequency::kwic::Occurrence;
+use word_frequency::stats::Stats;
+use word_frequency::tfidf::Scored;
+use word_frequency::zipf::ZipfFit;
+
+/// How the counts are printed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// One `word: count` line per word
+    #[default]
+    Text,
+    /// A JSON object with the totals and an array of words and counts
+    Json,
+    /// Comma-separated `word,count` rows after a header, quoted as in RFC 4180
+    Csv,
+    /// Tab-separated `word\tcount` rows after a header, with tabs, newlines
+    /// and backslashes in words escaped as `\t`, `\n`, `\r` and `\\`
+    Tsv,
+    /// Aligned words and counts with a bar scaled to the largest count
+    Chart,
+    /// A GitHub-flavored Markdown table, padded to line up in the raw text,
+    /// with pipes and backticks in words escaped
+    Markdown,
+}
+
+/// Words longer than this are cut short in a chart to keep its columns
+/// aligned.
+const CHART_LABEL_WIDTH: usize = 24;
+
+/// Partial blocks for `--unicode-bars`, by eighths.
+const EIGHTHS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+/// Totals over everything that was counted, not just the words shown.
+#[derive(Clone, Copy, Debug)]
+pub struct Summary {
+    pub total_tokens: u64,
+    pub unique_words: usize,
+}
+
+/// How to lay out the counts.
+#[derive(Clone, Copy, Debug)]
+pub struct OutputOptions {
+    pub format: Format,
+    /// Add each word's share of all counted tokens
+    pub percent: bool,
+    /// Add the running total of those shares, down from the first word
+    pub cumulative: bool,
+    /// Columns for the longest bar of a chart
+    pub width: usize,
+    /// Draw chart bars with block characters, to an eighth of a column
+    pub unicode_bars: bool,
+    /// Print only the statistics, not the counts
+    pub stats_only: bool,
+    /// List the words counted under each stem
+    pub show_variants: bool,
+    /// Add each word's rank and the count a fitted Zipf distribution
+    /// predicts for it
+    pub zipf: bool,
+    /// Print the statistics of CSV and TSV tables on stdout rather than
+    /// stderr, as the tables are going to a file
+    pub stats_to_stdout: bool,
+    /// Lay out the CSV and TSV tables of `--per-file` as a matrix, with a
+    /// row for each word and a column for each file
+    pub matrix: bool,
+}
+
+/// The counts to print, already sorted and cut to `--top`, with their
+/// totals.
+#[derive(Debug)]
+pub struct Table {
+    pub counts: Vec<(String, u64)>,
+    pub summary: Summary,
+    pub stats: Option<Stats>,
+    /// Empty unless `--show-variants` is given
+    pub variants: Variants,
+    /// The distribution fitted with `--zipf`, if there were enough words
+    pub zipf: Option<ZipfFit>,
+    /// With `--ties include`, the count of the last word within `--top`,
+    /// when the list was long enough to be cut
+    pub boundary_count: Option<u64>,
+}
+
+/// One word's line of output.
+struct Row<'a> {
+    /// Position in the full list, counting from 1
+    rank: usize,
+    word: &'a str,
+    count: u64,
+    percent: f64,
+    cumulative: f64,
+    /// The words counted under this stem
+    variants: &'a [(String, u64)],
+    /// The count the Zipf fit predicts at this rank
+    predicted: Option<f64>,
+}
+
+fn rows<'a>(options: &OutputOptions, table: &'a Table) -> Vec<Row<'a>> {
+    if options.stats_only {
+        return Vec::new();
+    }
+    // Shares are kept exact here and only rounded when printed
+    let total = table.summary.total_tokens.max(1) as f64;
+    let mut running = 0;
+    table
+        .counts
+        .iter()
+        .enumerate()
+        .map(|(i, (word, count))| {
+            running += count;
+            Row {
+                rank: i + 1,
+                word,
+                count: *count,
+                percent: *count as f64 * 100.0 / total,
+                cumulative: running as f64 * 100.0 / total,
+                variants: table.variants.get(word).map_or(&[], Vec::as_slice),
+                predicted: table.zipf.map(|fit| fit.predict(i + 1)),
+            }
+        })
+        .collect()
+}
+
+/// Writes the counts of `table` followed by its statistics, if any. CSV and
+/// TSV keep a single table, so there the statistics go to stderr unless
+/// they are all that is printed.
+pub fn write_counts(
+    out: &mut impl Write,
+    options: &OutputOptions,
+    table: &Table,
+) -> io::Result<()> {
+    let rows = rows(options, table);
+    let stats = table.stats.as_ref();
+    match options.format {
+        Format::Text | Format::Chart => {
+            if options.format == Format::Chart {
+                write_chart(out, options, &rows)?;
+            } else {
+                write_text(out, options, &rows)?;
+            }
+            if let Some(stats) = stats {
+                if !options.stats_only {
+                    writeln!(out)?;
+                }
+                write_stats(out, stats)?;
+            }
+        }
+        Format::Json => {
+            let document = json_document(options, &rows, table);
+            writeln!(out, "{}", serde_json::to_string_pretty(&document)?)?;
+        }
+        Format::Markdown => {
+            if !options.stats_only {
+                let cells = Vec::from_iter(rows.iter().map(|row| row_cells(options, row)));
+                write_markdown(out, &table_header(options, None), &cells)?;
+            }
+            if let Some(stats) = stats {
+                if !options.stats_only {
+                    writeln!(out)?;
+                }
+                write_markdown_stats(out, stats)?;
+            }
+        }
+        Format::Csv | Format::Tsv => {
+            let (separator, escape) = delimiters(options.format);
+            if options.stats_only {
+                writeln!(out, "stat{}value", separator)?;
+                for (key, _, value) in stats.map(Stats::entries).unwrap_or_default() {
+                    writeln!(out, "{}{}{}", key, separator, value)?;
+                }
+            } else {
+                writeln!(out, "{}", table_header(options, None).join(separator))?;
+                write_rows(out, options, &rows, separator, escape, None)?;
+                if let Some(stats) = stats {
+                    write_stats(&mut stats_stream(options), stats)?;
+                }
+            }
+        }
+    }
+    out.flush()
+}
+
+/// How the input is split into tables by `--group-by`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum GroupBy {
+    /// One table for the whole input
+    #[default]
+    None,
+    /// A table for each line
+    Line,
+    /// A table for each paragraph, separated by blank lines
+    Paragraph,
+}
+
+/// The tables of `--per-file`: one for each file under its name, then the
+/// combined table. Each table is written as soon as its file is counted,
+/// except in JSON, which is a single document, and in a matrix, whose
+/// rows take in every file; those are held until [`PerFileWriter::finish`].
+/// The tables of `--group-by` are written the same way, labeled by the
+/// position of their line or paragraph, with no combined table.
+pub struct PerFileWriter {
+    options: OutputOptions,
+    /// "line" or "paragraph" with `--group-by`
+    unit: Option<&'static str>,
+    /// The JSON documents of the files so far
+    files: Vec<Value>,
+    /// The counts of the files so far, for a matrix
+    columns: Vec<(String, HashMap<String, u64>)>,
+    started: bool,
+}
+
+/// Which table is being written.
+#[derive(Clone, Copy)]
+enum Section<'a> {
+    File(&'a str),
+    /// A line or paragraph, by its position from 1
+    Group(usize),
+    Combined,
+}
+
+impl PerFileWriter {
+    pub fn new(options: OutputOptions) -> PerFileWriter {
+        PerFileWriter {
+            options,
+            unit: None,
+            files: Vec::new(),
+            columns: Vec::new(),
+            started: false,
+        }
+    }
+
+    /// A writer for the groups of `--group-by`, each a `unit`.
+    pub fn grouped(options: OutputOptions, unit: &'static str) -> PerFileWriter {
+        PerFileWriter {
+            unit: Some(unit),
+            ..PerFileWriter::new(options)
+        }
+    }
+
+    /// Writes the table of one file, named `file`.
+    pub fn write_file(
+        &mut self,
+        out: &mut impl Write,
+        file: &str,
+        table: &Table,
+    ) -> io::Result<()> {
+        self.write_section(out, Section::File(file), table)
+    }
+
+    /// Writes the table of the line or paragraph at `index`, from 1.
+    pub fn write_group(
+        &mut self,
+        out: &mut impl Write,
+        index: usize,
+        table: &Table,
+    ) -> io::Result<()> {
+        self.write_section(out, Section::Group(index), table)
+    }
+
+    /// Ends the output after the last group.
+    pub fn finish_groups(self, out: &mut impl Write) -> io::Result<()> {
+        if self.options.format == Format::Json {
+            let document = json!({ "groups": self.files });
+            writeln!(out, "{}", serde_json::to_string_pretty(&document)?)?;
+        }
+        out.flush()
+    }
+
+    /// Writes the combined table after the last file.
+    pub fn finish(mut self, out: &mut impl Write, table: &Table) -> io::Result<()> {
+        self.write_section(out, Section::Combined, table)?;
+        if self.options.format == Format::Json {
+            let combined = json_document(&self.options, &rows(&self.options, table), table);
+            let document = json!({ "files": self.files, "combined": combined });
+            writeln!(out, "{}", serde_json::to_string_pretty(&document)?)?;
+        }
+        if let (Format::Csv | Format::Tsv, true, false) = (
+            self.options.format,
+            self.options.matrix,
+            self.options.stats_only,
+        ) {
+            let (separator, escape) = delimiters(self.options.format);
+            write!(out, "word")?;
+            // The file names are escaped already
+            for (file, _) in &self.columns {
+                write!(out, "{}{}", separator, file)?;
+            }
+            writeln!(out, "{}total", separator)?;
+            // The combined table has every word, in the order listed
+            for (word, total) in &table.counts {
+                write!(out, "{}", escape(word))?;
+                for (_, counts) in &self.columns {
+                    write!(out, "{}{}", separator, counts.get(word).unwrap_or(&0))?;
+                }
+                writeln!(out, "{}{}", separator, total)?;
+            }
+        }
+        out.flush()
+    }
+
+    fn write_section(
+        &mut self,
+        out: &mut impl Write,
+        section: Section,
+        table: &Table,
+    ) -> io::Result<()> {
+        let options = self.options;
+        let rows = rows(&options, table);
+        let stats = table.stats.as_ref();
+        let first = !self.started;
+        self.started = true;
+        let column = if self.unit.is_some() { "group" } else { "file" };
+        let header = match section {
+            Section::File(file) => format!("==> {} <==", file),
+            Section::Group(index) => format!("==> {} {} <==", self.unit.unwrap_or(""), index),
+            Section::Combined => "==> combined <==".to_string(),
+        };
+        match options.format {
+            Format::Text | Format::Chart => {
+                if !first {
+                    writeln!(out)?;
+                }
+                writeln!(out, "{}", header)?;
+                write_counts(out, &options, table)?;
+            }
+            Format::Markdown => {
+                if !first {
+                    writeln!(out)?;
+                }
+                let heading = match section {
+                    Section::File(file) => markdown_cell(file).into_owned(),
+                    Section::Group(index) => format!("{} {}", self.unit.unwrap_or(""), index),
+                    Section::Combined => "combined".to_string(),
+                };
+                writeln!(out, "### {}\n", heading)?;
+                write_counts(out, &options, table)?;
+            }
+            Format::Json => {
+                // The combined document is built by `finish`
+                let label = match section {
+                    Section::File(file) => json!(file),
+                    Section::Group(index) => json!(index),
+                    Section::Combined => return Ok(()),
+                };
+                let mut document = json_document(&options, &rows, table);
+                document[column] = label;
+                self.files.push(document);
+            }
+            Format::Csv | Format::Tsv => {
+                let combined = matches!(section, Section::Combined);
+                // Combined rows have an empty file column
+                let file = match section {
+                    Section::File(file) => escape_for(options.format)(file),
+                    Section::Group(index) => Cow::Owned(index.to_string()),
+                    Section::Combined => Cow::Borrowed(""),
+                };
+                let (separator, escape) = delimiters(options.format);
+                if options.stats_only {
+                    if first {
+                        writeln!(out, "{1}{0}stat{0}value", separator, column)?;
+                    }
+                    for (key, _, value) in stats.map(Stats::entries).unwrap_or_default() {
+                        writeln!(out, "{}{}{}{}{}", file, separator, key, separator, value)?;
+                    }
+                } else {
+                    if options.matrix {
+                        // Written by `finish`, from the combined table
+                        if !combined {
+                            let counts = HashMap::from_iter(table.counts.iter().cloned());
+                            self.columns.push((file.into_owned(), counts));
+                        }
+                    } else {
+                        if first {
+                            writeln!(
+                                out,
+                                "{}",
+                                table_header(&options, Some(column)).join(separator)
+                            )?;
+                        }
+                        write_rows(out, &options, &rows, separator, escape, Some(&file))?;
+                    }
+                    if let Some(stats) = stats {
+                        let mut stream = stats_stream(&options);
+                        writeln!(stream, "{}", header)?;
+                        write_stats(&mut stream, stats)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the `--tfidf` scores of each document, already cut to `--top`,
+/// under its name or, in CSV and TSV, with a leading file column.
+pub fn write_tfidf(
+    out: &mut impl Write,
+    options: &OutputOptions,
+    documents: &[(String, Vec<Scored>)],
+) -> io::Result<()> {
+    match options.format {
+        Format::Text | Format::Chart => {
+            for (i, (file, scores)) in documents.iter().enumerate() {
+                if i > 0 {
+                    writeln!(out)?;
+                }
+                writeln!(out, "==> {} <==", file)?;
+                for scored in scores {
+                    writeln!(
+                        out,
+                        "{}: {:.6} ({})",
+                        display_word(&scored.word),
+                        scored.score,
+                        scored.count
+                    )?;
+                }
+            }
+        }
+        Format::Json => {
+            let files = Vec::from_iter(documents.iter().map(|(file, scores)| {
+                let words = Vec::from_iter(scores.iter().map(|scored| {
+                    json!({ "word": scored.word, "count": scored.count, "tf_idf": scored.score })
+                }));
+                json!({ "file": file, "words": words })
+            }));
+            let document = json!({ "files": files });
+            writeln!(out, "{}", serde_json::to_string_pretty(&document)?)?;
+        }
+        Format::Markdown => {
+            let cells = Vec::from_iter(documents.iter().flat_map(|(file, scores)| {
+                scores.iter().map(move |scored| {
+                    vec![
+                        file.clone(),
+                        scored.word.clone(),
+                        scored.count.to_string(),
+                        format!("{:.6}", scored.score),
+                    ]
+                })
+            }));
+            write_markdown(out, &["file", "word", "count", "tf_idf"], &cells)?;
+        }
+        Format::Csv | Format::Tsv => {
+            let (separator, escape) = delimiters(options.format);
+            writeln!(out, "file{0}word{0}count{0}tf_idf", separator)?;
+            for (file, scores) in documents {
+                for scored in scores {
+                    writeln!(
+                        out,
+                        "{}{}{}{}{}{}{:.6}",
+                        escape(file),
+                        separator,
+                        escape(&scored.word),
+                        separator,
+                        scored.count,
+                        separator,
+                        scored.score
+                    )?;
+                }
+            }
+        }
+    }
+    out.flush()
+}
+
+/// Writes the occurrences of `--kwic`, numbered from 1 and each with its
+/// line if `line_numbers`. In text the keywords form a column, with the
+/// left context aligned against them.
+pub fn write_concordance(
+    out: &mut impl Write,
+    options: &OutputOptions,
+    occurrences: &[Occurrence],
+    line_numbers: bool,
+) -> io::Result<()> {
+    let contexts = Vec::from_iter(
+        occurrences
+            .iter()
+            .map(|occurrence| (occurrence.before.join(" "), occurrence.after.join(" "))),
+    );
+    match options.format {
+        Format::Text | Format::Chart => {
+            let number_width = occurrences.len().to_string().len();
+            let line_width = occurrences
+                .iter()
+                .map(|occurrence| occurrence.line.to_string().len())
+                .max()
+                .unwrap_or(0);
+            let left_width = contexts
+                .iter()
+                .map(|(left, _)| left.chars().count())
+                .max()
+                .unwrap_or(0);
+            for (i, (occurrence, (left, right))) in occurrences.iter().zip(&contexts).enumerate() {
+                write!(out, "{:>1$}  ", i + 1, number_width)?;
+                if line_numbers {
+                    write!(out, "{:>1$}:  ", occurrence.line, line_width)?;
+                }
+                if left_width > 0 {
+                    write!(out, "{:>1$}  ", left, left_width)?;
+                }
+                let line = format!("{}  {}", occurrence.word, right);
+                writeln!(out, "{}", line.trim_end())?;
+            }
+        }
+        Format::Json => {
+            let entries = Vec::from_iter(occurrences.iter().zip(&contexts).enumerate().map(
+                |(i, (occurrence, (left, right)))| {
+                    let mut entry = json!({
+                        "occurrence": i + 1,
+                        "left": left,
+                        "keyword": occurrence.word,
+                        "right": right,
+                    });
+                    if line_numbers {
+                        entry["line"] = json!(occurrence.line);
+                    }
+                    entry
+                },
+            ));
+            let document = json!({ "occurrences": entries });
+            writeln!(out, "{}", serde_json::to_string_pretty(&document)?)?;
+        }
+        Format::Csv | Format::Tsv | Format::Markdown => {
+            let mut header = vec!["occurrence"];
+            if line_numbers {
+                header.push("line");
+            }
+            header.extend(["left", "keyword", "right"]);
+            if options.format == Format::Markdown {
+                let cells = Vec::from_iter(occurrences.iter().zip(&contexts).enumerate().map(
+                    |(i, (occurrence, (left, right)))| {
+                        let mut row = vec![(i + 1).to_string()];
+                        if line_numbers {
+                            row.push(occurrence.line.to_string());
+                        }
+                        row.extend([left.clone(), occurrence.word.clone(), right.clone()]);
+                        row
+                    },
+                ));
+                write_markdown(out, &header, &cells)?;
+                return out.flush();
+            }
+            let (separator, escape) = delimiters(options.format);
+            writeln!(out, "{}", header.join(separator))?;
+            for (i, (occurrence, (left, right))) in occurrences.iter().zip(&contexts).enumerate() {
+                write!(out, "{}{}", i + 1, separator)?;
+                if line_numbers {
+                    write!(out, "{}{}", occurrence.line, separator)?;
+                }
+                writeln!(
+                    out,
+                    "{1}{0}{2}{0}{3}",
+                    separator,
+                    escape(left),
+                    escape(&occurrence.word),
+                    escape(right)
+                )?;
+            }
+        }
+    }
+    out.flush()
+}
+
+/// Writes the pairs of `--collocations`, already cut to `--top`.
+pub fn write_collocations(
+    out: &mut impl Write,
+    options: &OutputOptions,
+    collocations: &[Collocation],
+) -> io::Result<()> {
+    match options.format {
+        Format::Text | Format::Chart => {
+            for collocation in collocations {
+                writeln!(
+                    out,
+                    "{}: {} (PMI {:.4})",
+                    display_word(&collocation.pair),
+                    collocation.count,
+                    collocation.pmi
+                )?;
+            }
+        }
+        Format::Json => {
+            let pairs = Vec::from_iter(collocations.iter().map(|collocation| {
+                json!({
+                    "pair": collocation.pair,
+                    "count": collocation.count,
+                    "pmi": collocation.pmi,
+                })
+            }));
+            let document = json!({ "collocations": pairs });
+            writeln!(out, "{}", serde_json::to_string_pretty(&document)?)?;
+        }
+        Format::Markdown => {
+            let cells = Vec::from_iter(collocations.iter().map(|collocation| {
+                vec![
+                    collocation.pair.clone(),
+                    collocation.count.to_string(),
+                    format!("{:.4}", collocation.pmi),
+                ]
+            }));
+            write_markdown(out, &["pair", "count", "pmi"], &cells)?;
+        }
+        Format::Csv | Format::Tsv => {
+            let (separator, escape) = delimiters(options.format);
+            writeln!(out, "pair{0}count{0}pmi", separator)?;
+            for collocation in collocations {
+                writeln!(
+                    out,
+                    "{1}{0}{2}{0}{3:.4}",
+                    separator,
+                    escape(&collocation.pair),
+                    collocation.count,
+                    collocation.pmi
+                )?;
+            }
+        }
+    }
+    out.flush()
+}
+
+/// Writes the groups of `--anagrams`, already cut to `--top`. Outside JSON
+/// each group is one line or row, its words as `word (count)` joined by
+/// commas in text and by semicolons in a table.
+pub fn write_anagrams(
+    out: &mut impl Write,
+    options: &OutputOptions,
+    groups: &[AnagramGroup],
+) -> io::Result<()> {
+    let members = |group: &AnagramGroup, separator: &str| {
+        Vec::from_iter(
+            group
+                .words
+                .iter()
+                .map(|(word, count)| format!("{} ({})", word, count)),
+        )
+        .join(separator)
+    };
+    match options.format {
+        Format::Text | Format::Chart => {
+            for group in groups {
+                writeln!(
+                    out,
+                    "{}: {}",
+                    display_word(&members(group, ", ")),
+                    group.count
+                )?;
+            }
+        }
+        Format::Json => {
+            let groups = Vec::from_iter(groups.iter().map(|group| {
+                let words = Vec::from_iter(
+                    group
+                        .words
+                        .iter()
+                        .map(|(word, count)| json!({ "word": word, "count": count })),
+                );
+                json!({ "words": words, "count": group.count })
+            }));
+            let document = json!({ "anagrams": groups });
+            writeln!(out, "{}", serde_json::to_string_pretty(&document)?)?;
+        }
+        Format::Markdown => {
+            let cells = Vec::from_iter(groups.iter().map(|group| {
+                vec![
+                    members(group, "; "),
+                    group.words.len().to_string(),
+                    group.count.to_string(),
+                ]
+            }));
+            write_markdown(out, &["words", "size", "count"], &cells)?;
+        }
+        Format::Csv | Format::Tsv => {
+            let (separator, escape) = delimiters(options.format);
+            writeln!(out, "words{0}size{0}count", separator)?;
+            for group in groups {
+                writeln!(
+                    out,
+                    "{1}{0}{2}{0}{3}",
+                    separator,
+                    escape(&members(group, "; ")),
+                    group.words.len(),
+                    group.count
+                )?;
+            }
+        }
+    }
+    out.flush()
+}
+
+/// Writes the `--length-distribution`, followed by the statistics, if any,
+/// as `write_counts` places them. A chart draws a bar for the tokens of
+/// each length.
+pub fn write_length_distribution(
+    out: &mut impl Write,
+    options: &OutputOptions,
+    lengths: &[LengthCount],
+    stats: Option<&Stats>,
+) -> io::Result<()> {
+    match options.format {
+        Format::Text | Format::Chart => {
+            let length_width = lengths
+                .last()
+                .map_or(0, |last| last.length.to_string().len());
+            let tokens_width = lengths
+                .iter()
+                .map(|bucket| bucket.tokens.to_string().len())
+                .max()
+                .unwrap_or(0);
+            let max_tokens = lengths
+                .iter()
+                .map(|bucket| bucket.tokens)
+                .max()
+                .unwrap_or(0);
+            for bucket in lengths {
+                if options.format == Format::Chart {
+                    writeln!(
+                        out,
+                        "{:>length_width$}  {:>tokens_width$}  {} ({} distinct)",
+                        bucket.length,
+                        bucket.tokens,
+                        bar(bucket.tokens, max_tokens.max(1), options),
+                        bucket.words
+                    )?;
+                } else {
+                    writeln!(
+                        out,
+                        "{}: {} tokens, {} distinct",
+                        bucket.length, bucket.tokens, bucket.words
+                    )?;
+                }
+            }
+            if let Some(stats) = stats {
+                writeln!(out)?;
+                write_stats(out, stats)?;
+            }
+        }
+        Format::Json => {
+            let lengths = Vec::from_iter(lengths.iter().map(|bucket| {
+                json!({ "length": bucket.length, "tokens": bucket.tokens, "words": bucket.words })
+            }));
+            let mut document = json!({ "lengths": lengths });
+            if let Some(stats) = stats {
+                document["stats"] = stats.to_json();
+            }
+            writeln!(out, "{}", serde_json::to_string_pretty(&document)?)?;
+        }
+        Format::Markdown => {
+            let cells = Vec::from_iter(lengths.iter().map(|bucket| {
+                vec![
+                    bucket.length.to_string(),
+                    bucket.tokens.to_string(),
+                    bucket.words.to_string(),
+                ]
+            }));
+            write_markdown(out, &["length", "tokens", "words"], &cells)?;
+            if let Some(stats) = stats {
+                writeln!(out)?;
+                write_markdown_stats(out, stats)?;
+            }
+        }
+        Format::Csv | Format::Tsv => {
+            let (separator, _) = delimiters(options.format);
+            writeln!(out, "length{0}tokens{0}words", separator)?;
+            for bucket in lengths {
+                writeln!(
+                    out,
+                    "{1}{0}{2}{0}{3}",
+                    separator, bucket.length, bucket.tokens, bucket.words
+                )?;
+            }
+            if let Some(stats) = stats {
+                write_stats(&mut stats_stream(options), stats)?;
+            }
+        }
+    }
+    out.flush()
+}
+
+/// Writes the `--by-initial` report, followed by the statistics, if any,
+/// as `write_counts` places them. Words that start with anything but a
+/// letter are listed under "other". A chart draws a bar for the tokens of
+/// each initial.
+pub fn write_initials(
+    out: &mut impl Write,
+    options: &OutputOptions,
+    initials: &[InitialCount],
+    stats: Option<&Stats>,
+) -> io::Result<()> {
+    let names = Vec::from_iter(
+        initials
+            .iter()
+            .map(|entry| entry.initial.map_or("other".to_string(), String::from)),
+    );
+    match options.format {
+        Format::Text | Format::Chart => {
+            let name_width = names
+                .iter()
+                .map(|name| name.chars().count())
+                .max()
+                .unwrap_or(0);
+            let tokens_width = initials
+                .iter()
+                .map(|entry| entry.tokens.to_string().len())
+                .max()
+                .unwrap_or(0);
+            let max_tokens = initials.iter().map(|entry| entry.tokens).max().unwrap_or(0);
+            for (entry, name) in initials.iter().zip(&names) {
+                let (top, top_count) = &entry.top;
+                if options.format == Format::Chart {
+                    writeln!(
+                        out,
+                        "{:<name_width$}  {:>tokens_width$}  {} ({} distinct)",
+                        name,
+                        entry.tokens,
+                        bar(entry.tokens, max_tokens.max(1), options),
+                        entry.words
+                    )?;
+                } else {
+                    writeln!(
+                        out,
+                        "{}: {} tokens, {} distinct, top {} ({})",
+                        name,
+                        entry.tokens,
+                        entry.words,
+                        display_word(top),
+                        top_count
+                    )?;
+                }
+            }
+            if let Some(stats) = stats {
+                writeln!(out)?;
+                write_stats(out, stats)?;
+            }
+        }
+        Format::Json => {
+            let initials = Vec::from_iter(initials.iter().zip(&names).map(|(entry, name)| {
+                json!({
+                    "initial": name,
+                    "tokens": entry.tokens,
+                    "words": entry.words,
+                    "top_word": entry.top.0,
+                    "top_count": entry.top.1,
+                })
+            }));
+            let mut document = json!({ "initials": initials });
+            if let Some(stats) = stats {
+                document["stats"] = stats.to_json();
+            }
+            writeln!(out, "{}", serde_json::to_string_pretty(&document)?)?;
+        }
+        Format::Markdown => {
+            let cells = Vec::from_iter(initials.iter().zip(&names).map(|(entry, name)| {
+                vec![
+                    name.clone(),
+                    entry.tokens.to_string(),
+                    entry.words.to_string(),
+                    entry.top.0.clone(),
+                    entry.top.1.to_string(),
+                ]
+            }));
+            let header = ["initial", "tokens", "words", "top_word", "top_count"];
+            write_markdown(out, &header, &cells)?;
+            if let Some(stats) = stats {
+                writeln!(out)?;
+                write_markdown_stats(out, stats)?;
+            }
+        }
+        Format::Csv | Format::Tsv => {
+            let (separator, escape) = delimiters(options.format);
+            writeln!(
+                out,
+                "initial{0}tokens{0}words{0}top_word{0}top_count",
+                separator
+            )?;
+            for (entry, name) in initials.iter().zip(&names) {
+                writeln!(
+                    out,
+                    "{1}{0}{2}{0}{3}{0}{4}{0}{5}",
+                    separator,
+                    escape(name),
+                    entry.tokens,
+                    entry.words,
+                    escape(&entry.top.0),
+                    entry.top.1
+                )?;
+            }
+            if let Some(stats) = stats {
+                write_stats(&mut stats_stream(options), stats)?;
+            }
+        }
+    }
+    out.flush()
+}
+
+/// Writes the result of `--compare`, naming the inputs `names`. Each list
+/// is already cut to `--top`.
+pub fn write_comparison(
+    out: &mut impl Write,
+    options: &OutputOptions,
+    names: [&str; 2],
+    comparison: &Comparison,
+) -> io::Result<()> {
+    let [name_a, name_b] = names;
+    match options.format {
+        Format::Text | Format::Chart => {
+            writeln!(out, "Only in {}:", name_a)?;
+            for (word, count) in &comparison.only_a {
+                writeln!(out, "  {}: {}", display_word(word), count)?;
+            }
+            writeln!(out, "\nOnly in {}:", name_b)?;
+            for (word, count) in &comparison.only_b {
+                writeln!(out, "  {}: {}", display_word(word), count)?;
+            }
+            writeln!(out, "\nChanged:")?;
+            for change in &comparison.changed {
+                writeln!(
+                    out,
+                    "  {}: {} -> {} ({:+})",
+                    display_word(&change.word),
+                    change.count_a,
+                    change.count_b,
+                    change.difference()
+                )?;
+            }
+        }
+        Format::Json => {
+            let words = |counts: &[(String, u64)]| {
+                Vec::from_iter(
+                    counts
+                        .iter()
+                        .map(|(word, count)| json!({ "word": word, "count": count })),
+                )
+            };
+            let changed = Vec::from_iter(comparison.changed.iter().map(|change| {
+                json!({
+                    "word": change.word,
+                    "count_a": change.count_a,
+                    "count_b": change.count_b,
+                    "difference": change.difference(),
+                })
+            }));
+            let document = json!({
+                "file_a": name_a,
+                "file_b": name_b,
+                "only_a": words(&comparison.only_a),
+                "only_b": words(&comparison.only_b),
+                "changed": changed,
+            });
+            writeln!(out, "{}", serde_json::to_string_pretty(&document)?)?;
+        }
+        Format::Csv | Format::Tsv | Format::Markdown => {
+            // Words missing from one side have a count of 0 there
+            let rows = comparison
+                .only_a
+                .iter()
+                .map(|(word, count)| ("only_a", word, *count, 0))
+                .chain(
+                    comparison
+                        .only_b
+                        .iter()
+                        .map(|(word, count)| ("only_b", word, 0, *count)),
+                )
+                .chain(
+                    comparison
+                        .changed
+                        .iter()
+                        .map(|change| ("changed", &change.word, change.count_a, change.count_b)),
+                );
+            let header = ["status", "word", "count_a", "count_b", "difference"];
+            if options.format == Format::Markdown {
+                let cells = Vec::from_iter(rows.map(|(status, word, count_a, count_b)| {
+                    vec![
+                        status.to_string(),
+                        word.clone(),
+                        count_a.to_string(),
+                        count_b.to_string(),
+                        (count_b as i64 - count_a as i64).to_string(),
+                    ]
+                }));
+                write_markdown(out, &header, &cells)?;
+                return out.flush();
+            }
+            let (separator, escape) = delimiters(options.format);
+            writeln!(out, "{}", header.join(separator))?;
+            for (status, word, count_a, count_b) in rows {
+                writeln!(
+                    out,
+                    "{1}{0}{2}{0}{3}{0}{4}{0}{5}",
+                    separator,
+                    status,
+                    escape(word),
+                    count_a,
+                    count_b,
+                    count_b as i64 - count_a as i64
+                )?;
+            }
+        }
+    }
+    out.flush()
+}
+
+fn json_document(options: &OutputOptions, rows: &[Row], table: &Table) -> Value {
+    let words: Vec<_> = rows
+        .iter()
+        .map(|row| {
+            let mut entry = json!({ "word": row.word, "count": row.count });
+            if options.percent {
+                entry["percent"] = json!(row.percent);
+            }
+            if options.cumulative {
+                entry["cumulative_percent"] = json!(row.cumulative);
+            }
+            if options.zipf {
+                entry["rank"] = json!(row.rank);
+                entry["predicted"] = json!(row.predicted);
+            }
+            if options.show_variants {
+                entry["variants"] = Value::from_iter(
+                    row.variants
+                        .iter()
+                        .map(|(word, count)| json!({ "word": word, "count": count })),
+                );
+            }
+            entry
+        })
+        .collect();
+    let mut document = json!({
+        "metadata": {
+            "total_tokens": table.summary.total_tokens,
+            "unique_words": table.summary.unique_words,
+        },
+    });
+    if let Some(count) = table.boundary_count {
+        document["metadata"]["boundary_count"] = json!(count);
+    }
+    if !options.stats_only {
+        document["words"] = json!(words);
+    }
+    if let Some(stats) = &table.stats {
+        document["stats"] = stats.to_json();
+    }
+    document
+}
+
+/// Where the statistics of a CSV or TSV table are printed.
+fn stats_stream(options: &OutputOptions) -> Box<dyn Write> {
+    if options.stats_to_stdout {
+        Box::new(io::stdout().lock())
+    } else {
+        Box::new(io::stderr().lock())
+    }
+}
+
+/// The field separator and escaping of a CSV or TSV table.
+fn delimiters(format: Format) -> (&'static str, fn(&str) -> Cow<'_, str>) {
+    (
+        if format == Format::Csv { "," } else { "\t" },
+        escape_for(format),
+    )
+}
+
+fn escape_for(format: Format) -> fn(&str) -> Cow<'_, str> {
+    if format == Format::Csv {
+        csv_field
+    } else {
+        tsv_field
+    }
+}
+
+fn write_stats(out: &mut impl Write, stats: &Stats) -> io::Result<()> {
+    for (_, label, value) in stats.entries() {
+        writeln!(out, "{}: {}", label, value)?;
+    }
+    Ok(())
+}
+
+fn write_text(out: &mut impl Write, options: &OutputOptions, rows: &[Row]) -> io::Result<()> {
+    for row in rows {
+        if options.zipf {
+            write!(out, "{}. ", row.rank)?;
+        }
+        write!(out, "{}: {}", display_word(row.word), row.count)?;
+        match (options.percent, options.cumulative) {
+            (true, true) => write!(
+                out,
+                " ({:.2}%, {:.2}% cumulative)",
+                row.percent, row.cumulative
+            )?,
+            (true, false) => write!(out, " ({:.2}%)", row.percent)?,
+            _ => {}
+        }
+        if let Some(predicted) = row.predicted {
+            write!(out, " (Zipf {:.2})", predicted)?;
+        }
+        writeln!(out)?;
+        write_variants(out, row)?;
+    }
+    Ok(())
+}
+
+/// Lists the words counted under a stem, indented below it.
+fn write_variants(out: &mut impl Write, row: &Row) -> io::Result<()> {
+    for (word, count) in row.variants {
+        writeln!(out, "  {}: {}", display_word(word), count)?;
+    }
+    Ok(())
+}
+
+/// The column names of a CSV, TSV or Markdown table, after `leading` if
+/// given.
+fn table_header(options: &OutputOptions, leading: Option<&'static str>) -> Vec<&'static str> {
+    let mut header = Vec::from_iter(leading);
+    if options.zipf {
+        header.push("rank");
+    }
+    header.extend(["word", "count"]);
+    if options.percent {
+        header.push("percent");
+    }
+    if options.cumulative {
+        header.push("cumulative_percent");
+    }
+    if options.zipf {
+        header.push("predicted");
+    }
+    if options.show_variants {
+        header.push("variants");
+    }
+    header
+}
+
+/// Writes the rows of a CSV or TSV table, each starting with `file` if
+/// given, which must already be escaped.
+fn write_rows(
+    out: &mut impl Write,
+    options: &OutputOptions,
+    rows: &[Row],
+    separator: &str,
+    escape: fn(&str) -> Cow<'_, str>,
+    file: Option<&str>,
+) -> io::Result<()> {
+    for row in rows {
+        if let Some(file) = file {
+            write!(out, "{}{}", file, separator)?;
+        }
+        let cells = row_cells(options, row);
+        let fields = Vec::from_iter(cells.iter().map(|cell| escape(cell)));
+        writeln!(out, "{}", fields.join(separator))?;
+    }
+    Ok(())
+}
+
+/// The fields of a row of a CSV, TSV or Markdown table, unescaped, in the
+/// order of [`table_header`].
+fn row_cells(options: &OutputOptions, row: &Row) -> Vec<String> {
+    let mut cells = Vec::new();
+    if options.zipf {
+        cells.push(row.rank.to_string());
+    }
+    cells.extend([row.word.to_string(), row.count.to_string()]);
+    if options.percent {
+        cells.push(format!("{:.2}", row.percent));
+    }
+    if options.cumulative {
+        cells.push(format!("{:.2}", row.cumulative));
+    }
+    if options.zipf {
+        // Left empty if there were too few words to fit
+        cells.push(
+            row.predicted
+                .map_or(String::new(), |predicted| format!("{:.2}", predicted)),
+        );
+    }
+    if options.show_variants {
+        // One field such as "running (2); runs (1)"
+        let variants = Vec::from_iter(
+            row.variants
+                .iter()
+                .map(|(word, count)| format!("{} ({})", word, count)),
+        );
+        cells.push(variants.join("; "));
+    }
+    cells
+}
+
+/// Writes a Markdown table with a column for each of `header`, each padded
+/// to its widest cell so that the raw text lines up. Columns of numbers
+/// are aligned right, others left.
+fn write_markdown(out: &mut impl Write, header: &[&str], rows: &[Vec<String>]) -> io::Result<()> {
+    let cells = Vec::from_iter(
+        rows.iter()
+            .map(|row| Vec::from_iter(row.iter().map(|cell| markdown_cell(cell)))),
+    );
+    let numeric = Vec::from_iter((0..header.len()).map(|column| {
+        rows.iter().any(|row| !row[column].is_empty())
+            && rows
+                .iter()
+                .all(|row| row[column].is_empty() || is_number(&row[column]))
+    }));
+    // The alignment row needs at least three dashes
+    let widths = Vec::from_iter(header.iter().enumerate().map(|(column, name)| {
+        cells
+            .iter()
+            .map(|row| row[column].chars().count())
+            .chain([name.chars().count(), 3])
+            .max()
+            .unwrap_or(3)
+    }));
+    let write_row = |out: &mut dyn Write, row: &[&str]| -> io::Result<()> {
+        write!(out, "|")?;
+        for ((cell, &width), &right) in row.iter().zip(&widths).zip(&numeric) {
+            if right {
+                write!(out, " {:>width$} |", cell)?;
+            } else {
+                write!(out, " {:<width$} |", cell)?;
+            }
+        }
+        writeln!(out)
+    };
+    write_row(out, header)?;
+    let rules = Vec::from_iter(widths.iter().zip(&numeric).map(|(&width, &right)| {
+        if right {
+            format!("{}:", "-".repeat(width - 1))
+        } else {
+            format!(":{}", "-".repeat(width - 1))
+        }
+    }));
+    write_row(out, &Vec::from_iter(rules.iter().map(String::as_str)))?;
+    for row in &cells {
+        write_row(out, &Vec::from_iter(row.iter().map(|cell| cell.as_ref())))?;
+    }
+    Ok(())
+}
+
+/// The statistics as a two-column Markdown table.
+fn write_markdown_stats(out: &mut impl Write, stats: &Stats) -> io::Result<()> {
+    let cells = Vec::from_iter(
+        stats
+            .entries()
+            .into_iter()
+            .map(|(_, label, value)| vec![label.to_string(), value]),
+    );
+    write_markdown(out, &["stat", "value"], &cells)
+}
+
+/// Whether a table cell is a number, for its column to be aligned right.
+fn is_number(cell: &str) -> bool {
+    cell.bytes().any(|byte| byte.is_ascii_digit())
+        && cell
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || matches!(byte, b'.' | b'-' | b'+'))
+}
+
+/// Escapes what would end a Markdown table cell early or start code: pipes,
+/// backticks and the backslashes that escape them, with control characters
+/// shown as by [`display_word`] so the row stays on one line.
+fn markdown_cell(cell: &str) -> Cow<'_, str> {
+    if !cell.contains(['|', '`', '\\']) {
+        return display_word(cell);
+    }
+    let mut escaped = String::with_capacity(cell.len() + 2);
+    for c in cell.chars() {
+        if matches!(c, '|' | '`' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    Cow::Owned(display_word(&escaped).into_owned())
+}
+
+fn write_chart(out: &mut impl Write, options: &OutputOptions, rows: &[Row]) -> io::Result<()> {
+    let labels: Vec<String> = rows
+        .iter()
+        .map(|row| chart_label(&display_word(row.word)))
+        .collect();
+    let label_width = labels
+        .iter()
+        .map(|label| label.chars().count())
+        .max()
+        .unwrap_or(0);
+    let count_width = rows
+        .iter()
+        .map(|row| row.count.to_string().len())
+        .max()
+        .unwrap_or(0);
+    let max_count = rows.iter().map(|row| row.count).max().unwrap_or(0).max(1);
+    for (row, label) in rows.iter().zip(&labels) {
+        write!(
+            out,
+            "{:<label_width$}  {:>count_width$}  {}",
+            label,
+            row.count,
+            bar(row.count, max_count, options),
+        )?;
+        if options.percent {
+            write!(out, " {:.2}%", row.percent)?;
+        }
+        if options.cumulative {
+            write!(out, " ({:.2}% cumulative)", row.cumulative)?;
+        }
+        if let Some(predicted) = row.predicted {
+            write!(out, " (Zipf {:.2})", predicted)?;
+        }
+        writeln!(out)?;
+        write_variants(out, row)?;
+    }
+    Ok(())
+}
+
+/// Escapes control characters (`\n`, `\t`, `U+0007`) so that every word
+/// prints on one line.
+fn display_word(word: &str) -> Cow<'_, str> {
+    if !word.chars().any(char::is_control) {
+        return Cow::Borrowed(word);
+    }
+    let mut shown = String::new();
+    for c in word.chars() {
+        match c {
+            '\n' => shown.push_str("\\n"),
+            '\t' => shown.push_str("\\t"),
+            '\r' => shown.push_str("\\r"),
+            c if c.is_control() => shown.push_str(&format!("U+{:04X}", c as u32)),
+            c => shown.push(c),
+        }
+    }
+    Cow::Owned(shown)
+}
+
+/// The word, shortened with an ellipsis if it is too long for a chart.
+fn chart_label(word: &str) -> String {
+    if word.chars().count() <= CHART_LABEL_WIDTH {
+        word.to_string()
+    } else {
+        let mut label: String = word.chars().take(CHART_LABEL_WIDTH - 1).collect();
+        label.push('…');
+        label
+    }
+}
+
+/// A bar for `count` in which `max_count` fills `options.width` columns.
+/// Any nonzero count gets at least the smallest visible bar.
+fn bar(count: u64, max_count: u64, options: &OutputOptions) -> String {
+    let units = if options.unicode_bars { 8 } else { 1 };
+    // Wide enough that no count can overflow
+    let (count, max_count) = (count as u128, max_count as u128);
+    let scaled = ((count * (options.width * units) as u128 + max_count / 2) / max_count) as usize;
+    let scaled = if count > 0 { scaled.max(1) } else { 0 };
+    if !options.unicode_bars {
+        return "#".repeat(scaled);
+    }
+    let mut bar = "█".repeat(scaled / 8);
+    if scaled % 8 > 0 {
+        bar.push(EIGHTHS[scaled % 8]);
+    }
+    bar
+}
+
+/// Quotes a CSV field if it contains a delimiter, quote or line break,
+/// doubling any quotes inside it.
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// Escapes the characters that would break a TSV row.
+fn tsv_field(field: &str) -> Cow<'_, str> {
+    if !field.contains(['\t', '\n', '\r', '\\']) {
+        return Cow::Borrowed(field);
+    }
+    let mut escaped = String::with_capacity(field.len() + 2);
+    for c in field.chars() {
+        match c {
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\\' => escaped.push_str("\\\\"),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields_are_quoted_only_when_needed() {
+        let cases = [
+            ("plain", "plain"),
+            ("tab\there", "tab\there"),
+            ("a,b", "\"a,b\""),
+            ("say \"hi\"", "\"say \"\"hi\"\"\""),
+            ("two\nlines", "\"two\nlines\""),
+            ("crlf\r\n", "\"crlf\r\n\""),
+            ("\"", "\"\"\"\""),
+        ];
+        for (field, expected) in cases {
+            assert_eq!(csv_field(field), expected, "{:?}", field);
+        }
+    }
+
+    #[test]
+    fn tsv_fields_escape_tabs_breaks_and_backslashes() {
+        let cases = [
+            ("plain, \"quoted\"", "plain, \"quoted\""),
+            ("tab\there", "tab\\there"),
+            ("two\nlines", "two\\nlines"),
+            ("crlf\r\n", "crlf\\r\\n"),
+            ("C:\\dir\\n", "C:\\\\dir\\\\n"),
+        ];
+        for (field, expected) in cases {
+            assert_eq!(tsv_field(field), expected, "{:?}", field);
+        }
+    }
+}
@@ -0,0 +1,297 @@
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::counter::WordCounter;
+use crate::input::ReadOptions;
+
+/// Roughly how much text each worker is handed at a time, in bytes. Chunks
+/// always end at a line break, so they run a line longer than this.
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// Streams a file, or stdin for `-`, into the counter. With more than one
+/// thread the input is split into chunks of whole lines that are counted
+/// concurrently, unless n-grams may span lines, in which case it is read
+/// in turn as with one thread. The input is ended afterwards, as with
+/// [`WordCounter::end_input`].
+pub fn feed_path(counter: &mut WordCounter, path: &Path, options: ReadOptions) -> io::Result<()> {
+    let reader = options.open(path)?;
+    let result = if options.threads == 1 || counter.spans_lines() {
+        counter.feed_reader(reader)
+    } else {
+        feed_chunked(counter, reader, options.threads)
+    };
+    counter.end_input();
+    result
+}
+
+/// Counts each of `files` wholly on one of the worker threads, so
+/// n-grams come out as they do when the files are counted in turn. Returns
+/// how reading each file went, in the order of `files`.
+pub fn feed_files(
+    counter: &mut WordCounter,
+    files: &[PathBuf],
+    options: ReadOptions,
+) -> Vec<io::Result<()>> {
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<io::Result<()>>> = files.iter().map(|_| None).collect();
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..options.threads.min(files.len()))
+            .map(|_| {
+                let mut local = counter.fresh();
+                let next = &next;
+                scope.spawn(move || {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = files.get(index) else {
+                            break;
+                        };
+                        let result = options
+                            .open(path)
+                            .and_then(|reader| local.feed_reader(reader));
+                        local.end_input();
+                        done.push((index, result));
+                    }
+                    (local, done)
+                })
+            })
+            .collect();
+        for worker in workers {
+            let (local, done) = worker.join().expect("counting thread panicked");
+            counter.merge(&local);
+            for (index, result) in done {
+                results[index] = Some(result);
+            }
+        }
+    });
+    results
+        .into_iter()
+        .map(|result| result.expect("every file is counted"))
+        .collect()
+}
+
+/// Hands chunks of whole lines to `threads` workers, each counting into a
+/// counter of its own that is merged into `counter` at the end. Every line
+/// is fed on its own, as `WordCounter::feed_reader` does, so as long as
+/// n-grams stop at line breaks the counts are the same as in one thread.
+fn feed_chunked(
+    counter: &mut WordCounter,
+    mut reader: impl BufRead,
+    threads: usize,
+) -> io::Result<()> {
+    let (sender, receiver) = mpsc::sync_channel::<String>(threads * 2);
+    let receiver = Mutex::new(receiver);
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                let mut local = counter.fresh();
+                let receiver = &receiver;
+                scope.spawn(move || {
+                    loop {
+                        // The lock is only held while waiting for the next chunk
+                        let chunk = receiver.lock().expect("no thread panics holding it").recv();
+                        let Ok(chunk) = chunk else {
+                            break;
+                        };
+                        for line in chunk.split_inclusive('\n') {
+                            local.feed(line);
+                        }
+                    }
+                    local
+                })
+            })
+            .collect();
+        let result = send_chunks(&mut reader, &sender);
+        // Lets the workers finish once the chunks sent so far are counted
+        drop(sender);
+        for worker in workers {
+            counter.merge(&worker.join().expect("counting thread panicked"));
+        }
+        result
+    })
+}
+
+/// Reads `reader` to the end in chunks, keeping what was read before an
+/// error so that it is still counted.
+fn send_chunks(reader: &mut impl BufRead, sender: &SyncSender<String>) -> io::Result<()> {
+    loop {
+        let mut chunk = String::with_capacity(CHUNK_SIZE);
+        let result = fill_chunk(reader, &mut chunk);
+        // Sending only fails if every worker is gone, which the join reports
+        if !chunk.is_empty() && sender.send(chunk).is_err() {
+            return Ok(());
+        }
+        match result {
+            Ok(true) => {}
+            Ok(false) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Reads whole lines into `chunk` until it holds at least `CHUNK_SIZE`
+/// bytes. Returns false once the input has ended.
+fn fill_chunk(reader: &mut impl BufRead, chunk: &mut String) -> io::Result<bool> {
+    while chunk.len() < CHUNK_SIZE {
+        if reader.read_line(chunk)? == 0 {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::{CountOptions, SortKey, SortOrder};
+    use crate::encoding::Encoding;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::io::Cursor;
+
+    const VOCABULARY: [&str; 16] = [
+        "the", "The", "river", "runs", "RUNS", "under", "café", "Café", "bridge", "of", "stone",
+        "and", "light", "naïve", "42", "x",
+    ];
+
+    /// About `bytes` of text, from a seeded xorshift generator, in lines of
+    /// one to twenty words with some punctuation and blank lines.
+    fn corpus(seed: u64, bytes: usize) -> String {
+        let mut state = seed;
+        let mut next = move |n: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % n as u64) as usize
+        };
+        let mut text = String::new();
+        while text.len() < bytes {
+            for i in 0..next(20) + 1 {
+                if i > 0 {
+                    text.push(' ');
+                }
+                text.push_str(VOCABULARY[next(VOCABULARY.len())]);
+                if next(8) == 0 {
+                    text.push_str([",", ".", "!", ";"][next(4)]);
+                }
+            }
+            text.push('\n');
+            if next(10) == 0 {
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    fn counts_of(counter: &WordCounter) -> HashMap<String, u64> {
+        counter
+            .counts()
+            .map(|(word, count)| (word.to_string(), count))
+            .collect()
+    }
+
+    fn reading(threads: usize) -> ReadOptions {
+        ReadOptions {
+            threads,
+            gzip: false,
+            encoding: Encoding::Utf8,
+        }
+    }
+
+    /// Counts `text` on one thread and in chunks on four, checking the two
+    /// agree and returning the counts.
+    fn assert_same_counts(text: &str, options: CountOptions) -> HashMap<String, u64> {
+        let mut single = WordCounter::new(options);
+        single.feed_reader(Cursor::new(text)).unwrap();
+        single.end_input();
+
+        let mut chunked = WordCounter::new(options);
+        assert!(!chunked.spans_lines());
+        feed_chunked(&mut chunked, Cursor::new(text), 4).unwrap();
+        chunked.end_input();
+
+        let counts = counts_of(&single);
+        assert_eq!(counts_of(&chunked), counts);
+        let (one, four) = (single.stats(), chunked.stats());
+        assert_eq!(four.total_tokens, one.total_tokens);
+        assert_eq!(four.characters, one.characters);
+        assert_eq!(four.median_length, one.median_length);
+        counts
+    }
+
+    #[test]
+    fn chunked_words_match_one_thread() {
+        // Several chunks' worth, so they are shared among the workers
+        let text = corpus(170, 3 * CHUNK_SIZE + 777);
+        let counts = assert_same_counts(&text, CountOptions::default());
+        assert_eq!(counts.len(), VOCABULARY.len());
+        let ignoring_case = CountOptions {
+            ignore_case: true,
+            ..CountOptions::default()
+        };
+        assert_same_counts(&text, ignoring_case);
+    }
+
+    #[test]
+    fn chunked_ngrams_within_lines_match_one_thread() {
+        let text = corpus(1700, 2 * CHUNK_SIZE + 777);
+        let options = CountOptions {
+            ngrams: 2,
+            per_line: true,
+            ..CountOptions::default()
+        };
+        assert_same_counts(&text, options);
+    }
+
+    #[test]
+    fn four_threads_count_a_file_as_one_does() {
+        let dir =
+            std::env::temp_dir().join(format!("word_frequency-parallel-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("corpus.txt");
+        fs::write(&path, corpus(17, 2 * CHUNK_SIZE + 12_345)).unwrap();
+
+        let sorted = |threads| {
+            let mut counter = WordCounter::builder().build();
+            feed_path(&mut counter, &path, reading(threads)).unwrap();
+            let order = SortOrder {
+                key: SortKey::Count,
+                reverse: false,
+            };
+            counter.into_sorted_vec(order)
+        };
+        assert_eq!(sorted(4), sorted(1));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn files_shared_out_count_as_in_turn() {
+        let dir = std::env::temp_dir().join(format!("word_frequency-files-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let files: Vec<PathBuf> = (0..7)
+            .map(|i| {
+                let path = dir.join(format!("{}.txt", i));
+                fs::write(&path, corpus(i + 1, 50_000 + i as usize * 10_000)).unwrap();
+                path
+            })
+            .collect();
+        let options = CountOptions {
+            ngrams: 3,
+            ..CountOptions::default()
+        };
+
+        let mut in_turn = WordCounter::new(options);
+        for path in &files {
+            feed_path(&mut in_turn, path, reading(1)).unwrap();
+        }
+        let mut shared = WordCounter::new(options);
+        let results = feed_files(&mut shared, &files, reading(4));
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_eq!(counts_of(&shared), counts_of(&in_turn));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
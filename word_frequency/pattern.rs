@@ -0,0 +1,706 @@
+//! A small regular expression engine for `--pattern`: Perl-style syntax,
+//! leftmost-first matching, and a Pike VM that runs in time linear in the
+//! input whatever the pattern, so no pattern can make matching blow up.
+
+/// Patterns compiling to more instructions than this are rejected, which
+/// bounds memory and per-character work (`x{1000}{1000}` is refused).
+const SIZE_LIMIT: usize = 100_000;
+
+/// A compiled pattern.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    program: Vec<Inst>,
+    ignore_case: bool,
+}
+
+#[derive(Clone, Debug)]
+enum Inst {
+    Char(char),
+    /// Any character except a line break
+    Any,
+    Class(Box<Class>),
+    Assert(Assertion),
+    /// Try both targets, the first with higher priority
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Assertion {
+    LineStart,
+    LineEnd,
+    WordBoundary,
+    NotWordBoundary,
+}
+
+impl Assertion {
+    fn holds(self, prev: Option<char>, next: Option<char>) -> bool {
+        let at_boundary = || prev.is_some_and(is_word_char) != next.is_some_and(is_word_char);
+        match self {
+            Assertion::LineStart => prev.is_none() || prev == Some('\n'),
+            Assertion::LineEnd => next.is_none() || next == Some('\n'),
+            Assertion::WordBoundary => at_boundary(),
+            Assertion::NotWordBoundary => !at_boundary(),
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// A bracketed class or a `\d`-style shorthand.
+#[derive(Clone, Debug)]
+struct Class {
+    negated: bool,
+    items: Vec<ClassItem>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum ClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+impl ClassItem {
+    fn contains(self, c: char) -> bool {
+        match self {
+            ClassItem::Range(lo, hi) => (lo..=hi).contains(&c),
+            ClassItem::Digit(negated) => c.is_numeric() != negated,
+            ClassItem::Word(negated) => is_word_char(c) != negated,
+            ClassItem::Space(negated) => c.is_whitespace() != negated,
+        }
+    }
+}
+
+impl Class {
+    fn matches(&self, c: char, ignore_case: bool) -> bool {
+        let contains = |c: char| self.items.iter().any(|item| item.contains(c));
+        let found = contains(c)
+            || (ignore_case && (c.to_lowercase().any(contains) || c.to_uppercase().any(contains)));
+        found != self.negated
+    }
+}
+
+fn chars_equal(a: char, b: char, ignore_case: bool) -> bool {
+    a == b || (ignore_case && a.to_lowercase().eq(b.to_lowercase()))
+}
+
+#[derive(Debug)]
+enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class(Class),
+    Assert(Assertion),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn error(&self, msg: &str) -> String {
+        format!("{} at position {}", msg, self.pos)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_next(&mut self, what: &str) -> Result<char, String> {
+        let c = self.peek().ok_or_else(|| self.error(what))?;
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn parse_alternation(&mut self) -> Result<Node, String> {
+        let mut branches = vec![self.parse_concat()?];
+        while self.eat('|') {
+            branches.push(self.parse_concat()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().expect("one branch")
+        } else {
+            Node::Alternate(branches)
+        })
+    }
+
+    fn parse_concat(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_repetition(atom)?);
+        }
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.pop().expect("one node"),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn parse_repetition(&mut self, mut node: Node) -> Result<Node, String> {
+        loop {
+            let (min, max) = if self.eat('*') {
+                (0, None)
+            } else if self.eat('+') {
+                (1, None)
+            } else if self.eat('?') {
+                (0, Some(1))
+            } else if self.eat('{') {
+                self.parse_counts()?
+            } else {
+                return Ok(node);
+            };
+            let greedy = !self.eat('?');
+            node = Node::Repeat {
+                node: Box::new(node),
+                min,
+                max,
+                greedy,
+            };
+        }
+    }
+
+    /// Parses the inside of `{n}`, `{n,}` or `{n,m}` and the closing brace.
+    fn parse_counts(&mut self) -> Result<(u32, Option<u32>), String> {
+        let min = self.parse_number()?;
+        let max = if self.eat(',') {
+            if self.peek() == Some('}') {
+                None
+            } else {
+                Some(self.parse_number()?)
+            }
+        } else {
+            Some(min)
+        };
+        if !self.eat('}') {
+            return Err(self.error("unclosed counted repetition"));
+        }
+        if max.is_some_and(|max| max < min) {
+            return Err(self.error("invalid repetition range (the maximum is below the minimum)"));
+        }
+        Ok((min, max))
+    }
+
+    fn parse_number(&mut self) -> Result<u32, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        digits
+            .parse()
+            .map_err(|_| self.error("expected a repetition count"))
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, String> {
+        let c = self.expect_next("unexpected end of pattern")?;
+        Ok(match c {
+            '(' => {
+                if self.eat('?') && !self.eat(':') {
+                    return Err(self.error("unsupported group flag (only (?:...) is supported)"));
+                }
+                let inner = self.parse_alternation()?;
+                if !self.eat(')') {
+                    return Err(self.error("unclosed group"));
+                }
+                inner
+            }
+            ')' => return Err(self.error("unopened group")),
+            '*' | '+' | '?' | '{' => {
+                self.pos -= 1;
+                return Err(self.error("repetition operator missing expression"));
+            }
+            '[' => Node::Class(self.parse_class()?),
+            '.' => Node::Any,
+            '^' => Node::Assert(Assertion::LineStart),
+            '$' => Node::Assert(Assertion::LineEnd),
+            '\\' => match self.parse_escape()? {
+                Escape::Char(c) => Node::Char(c),
+                Escape::Item(item) => Node::Class(Class {
+                    negated: false,
+                    items: vec![item],
+                }),
+                Escape::Assert(assertion) => Node::Assert(assertion),
+            },
+            c => Node::Char(c),
+        })
+    }
+
+    fn parse_escape(&mut self) -> Result<Escape, String> {
+        let c = self.expect_next("incomplete escape sequence")?;
+        Ok(match c {
+            'd' => Escape::Item(ClassItem::Digit(false)),
+            'D' => Escape::Item(ClassItem::Digit(true)),
+            'w' => Escape::Item(ClassItem::Word(false)),
+            'W' => Escape::Item(ClassItem::Word(true)),
+            's' => Escape::Item(ClassItem::Space(false)),
+            'S' => Escape::Item(ClassItem::Space(true)),
+            'b' => Escape::Assert(Assertion::WordBoundary),
+            'B' => Escape::Assert(Assertion::NotWordBoundary),
+            'n' => Escape::Char('\n'),
+            't' => Escape::Char('\t'),
+            'r' => Escape::Char('\r'),
+            c if !c.is_alphanumeric() => Escape::Char(c),
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("unrecognized escape sequence"));
+            }
+        })
+    }
+
+    fn parse_class(&mut self) -> Result<Class, String> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.expect_next("unclosed character class")?;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+            let lo = if c == '\\' {
+                match self.parse_escape()? {
+                    Escape::Char(c) => c,
+                    Escape::Item(item) => {
+                        items.push(item);
+                        continue;
+                    }
+                    Escape::Assert(_) => {
+                        return Err(self.error("assertions are not allowed in a character class"))
+                    }
+                }
+            } else {
+                c
+            };
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|&c| c != ']') {
+                self.pos += 1;
+                let mut hi = self.expect_next("unclosed character class")?;
+                if hi == '\\' {
+                    match self.parse_escape()? {
+                        Escape::Char(c) => hi = c,
+                        _ => return Err(self.error("invalid character class range")),
+                    }
+                }
+                if hi < lo {
+                    return Err(self.error("invalid character class range"));
+                }
+                items.push(ClassItem::Range(lo, hi));
+            } else {
+                items.push(ClassItem::Range(lo, lo));
+            }
+        }
+        Ok(Class { negated, items })
+    }
+}
+
+enum Escape {
+    Char(char),
+    Item(ClassItem),
+    Assert(Assertion),
+}
+
+struct Compiler {
+    program: Vec<Inst>,
+}
+
+impl Compiler {
+    fn emit(&mut self, inst: Inst) -> Result<usize, String> {
+        if self.program.len() >= SIZE_LIMIT {
+            return Err(format!(
+                "pattern is too large: it compiles to more than {} instructions",
+                SIZE_LIMIT
+            ));
+        }
+        self.program.push(inst);
+        Ok(self.program.len() - 1)
+    }
+
+    fn compile(&mut self, node: &Node) -> Result<(), String> {
+        match node {
+            Node::Empty => {}
+            Node::Char(c) => {
+                self.emit(Inst::Char(*c))?;
+            }
+            Node::Any => {
+                self.emit(Inst::Any)?;
+            }
+            Node::Class(class) => {
+                self.emit(Inst::Class(Box::new(class.clone())))?;
+            }
+            Node::Assert(assertion) => {
+                self.emit(Inst::Assert(*assertion))?;
+            }
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    self.compile(node)?;
+                }
+            }
+            Node::Alternate(branches) => {
+                let mut jumps = Vec::new();
+                for (i, branch) in branches.iter().enumerate() {
+                    if i + 1 == branches.len() {
+                        self.compile(branch)?;
+                    } else {
+                        let split = self.emit(Inst::Split(0, 0))?;
+                        self.compile(branch)?;
+                        jumps.push(self.emit(Inst::Jump(0))?);
+                        self.program[split] = Inst::Split(split + 1, self.program.len());
+                    }
+                }
+                let end = self.program.len();
+                for jump in jumps {
+                    self.program[jump] = Inst::Jump(end);
+                }
+            }
+            Node::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+            } => {
+                for _ in 0..*min {
+                    self.compile(node)?;
+                }
+                let split_to = |split: usize, out: usize| {
+                    if *greedy {
+                        Inst::Split(split + 1, out)
+                    } else {
+                        Inst::Split(out, split + 1)
+                    }
+                };
+                match max {
+                    None => {
+                        let split = self.emit(Inst::Split(0, 0))?;
+                        self.compile(node)?;
+                        self.emit(Inst::Jump(split))?;
+                        self.program[split] = split_to(split, self.program.len());
+                    }
+                    Some(max) => {
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            splits.push(self.emit(Inst::Split(0, 0))?);
+                            self.compile(node)?;
+                        }
+                        let out = self.program.len();
+                        for split in splits {
+                            self.program[split] = split_to(split, out);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A thread of the VM: where it is in the program and where its match began.
+#[derive(Clone, Copy)]
+struct Thread {
+    pc: usize,
+    start: usize,
+}
+
+impl Pattern {
+    /// Compiles `pattern`, or describes what is wrong with it and where.
+    pub fn parse(pattern: &str) -> Result<Pattern, String> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+        };
+        let node = parser.parse_alternation()?;
+        if parser.pos < parser.chars.len() {
+            // parse_alternation only stops early at a ')' with no '('
+            return Err(parser.error("unopened group"));
+        }
+        let mut compiler = Compiler {
+            program: Vec::new(),
+        };
+        compiler.compile(&node)?;
+        compiler.emit(Inst::Match)?;
+        Ok(Pattern {
+            program: compiler.program,
+            ignore_case: false,
+        })
+    }
+
+    /// Makes letters match regardless of case, including non-ASCII ones.
+    pub fn ignore_case(mut self, ignore_case: bool) -> Pattern {
+        self.ignore_case = ignore_case;
+        self
+    }
+
+    /// Every non-overlapping, non-empty match in `text`, leftmost first.
+    pub fn find_iter<'p, 't>(&'p self, text: &'t str) -> impl Iterator<Item = &'t str> + 'p
+    where
+        't: 'p,
+    {
+        let mut pos = 0;
+        std::iter::from_fn(move || {
+            while pos <= text.len() {
+                let (start, end) = self.find_at(text, pos)?;
+                if end > start {
+                    pos = end;
+                    return Some(&text[start..end]);
+                }
+                // Skip empty matches, which are never useful as words
+                pos = start + text[start..].chars().next().map_or(1, char::len_utf8);
+            }
+            None
+        })
+    }
+
+    /// The leftmost-first match starting at or after `from`.
+    fn find_at(&self, text: &str, from: usize) -> Option<(usize, usize)> {
+        let mut current = Vec::new();
+        let mut next = Vec::new();
+        let mut seen = vec![usize::MAX; self.program.len()];
+        let mut found = None;
+        let mut pos = from;
+        let mut prev = text[..from].chars().next_back();
+        let mut step = 0;
+        loop {
+            let c = text[pos..].chars().next();
+            if found.is_none() {
+                self.add_thread(&mut current, &mut seen, step, 0, pos, prev, c);
+            }
+            if current.is_empty() && found.is_some() {
+                break;
+            }
+            let after = c.map(|c| pos + c.len_utf8());
+            let following = after.and_then(|after| text[after..].chars().next());
+            for thread in current.drain(..) {
+                let advances = match (&self.program[thread.pc], c) {
+                    (Inst::Match, _) => {
+                        found = Some((thread.start, pos));
+                        // Lower-priority threads can no longer win
+                        break;
+                    }
+                    (Inst::Char(expected), Some(c)) => chars_equal(*expected, c, self.ignore_case),
+                    (Inst::Any, Some(c)) => c != '\n',
+                    (Inst::Class(class), Some(c)) => class.matches(c, self.ignore_case),
+                    _ => false,
+                };
+                if advances {
+                    let start = thread.start;
+                    self.add_thread(
+                        &mut next,
+                        &mut seen,
+                        step + 1,
+                        thread.pc + 1,
+                        start,
+                        c,
+                        following,
+                    );
+                }
+            }
+            std::mem::swap(&mut current, &mut next);
+            match after {
+                Some(after) => pos = after,
+                None => break,
+            }
+            prev = c;
+            step += 1;
+        }
+        found
+    }
+
+    /// Adds the thread at `pc` to `list`, following jumps and assertions so
+    /// that only threads waiting on a character (or matching) are queued.
+    #[allow(clippy::too_many_arguments)]
+    fn add_thread(
+        &self,
+        list: &mut Vec<Thread>,
+        seen: &mut [usize],
+        step: usize,
+        pc: usize,
+        start: usize,
+        prev: Option<char>,
+        next: Option<char>,
+    ) {
+        let mut stack = vec![pc];
+        while let Some(pc) = stack.pop() {
+            if seen[pc] == step {
+                continue;
+            }
+            seen[pc] = step;
+            match &self.program[pc] {
+                Inst::Jump(target) => stack.push(*target),
+                Inst::Split(first, second) => {
+                    stack.push(*second);
+                    stack.push(*first);
+                }
+                Inst::Assert(assertion) => {
+                    if assertion.holds(prev, next) {
+                        stack.push(pc + 1);
+                    }
+                }
+                _ => list.push(Thread { pc, start }),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches<'t>(pattern: &str, text: &'t str) -> Vec<&'t str> {
+        Pattern::parse(pattern).unwrap().find_iter(text).collect()
+    }
+
+    fn matches_ignoring_case<'t>(pattern: &str, text: &'t str) -> Vec<&'t str> {
+        let pattern = Pattern::parse(pattern).unwrap().ignore_case(true);
+        pattern.find_iter(text).collect()
+    }
+
+    fn error(pattern: &str) -> String {
+        Pattern::parse(pattern).unwrap_err()
+    }
+
+    #[test]
+    fn classes_match_ranges_lists_and_negations() {
+        assert_eq!(matches("[a-c]+", "abcdcba"), ["abc", "cba"]);
+        assert_eq!(matches("[^a-c ]+", "abd efa"), ["d", "ef"]);
+        assert_eq!(matches("[-x]+", "a-x-b"), ["-x-"]);
+        assert_eq!(matches("[x-]+", "a-x-b"), ["-x-"]);
+        assert_eq!(matches("[]a]+", "b]a]b"), ["]a]"]);
+        assert_eq!(matches(r"[\]\\]+", r"a]\b"), [r"]\"]);
+        assert_eq!(matches(r"[\d.]+", "pi is 3.14!"), ["3.14"]);
+        assert_eq!(matches("[à-ÿ]+", "déjà vu"), ["é", "à"]);
+        assert_eq!(matches(".+", "one\ntwo"), ["one", "two"]);
+    }
+
+    #[test]
+    fn shorthands_and_their_negations() {
+        assert_eq!(matches(r"\d+", "a1b22 ٣٤"), ["1", "22", "٣٤"]);
+        assert_eq!(matches(r"\D+", "a1b22"), ["a", "b"]);
+        assert_eq!(
+            matches(r"\w+", "snake_case, naïve!"),
+            ["snake_case", "naïve"]
+        );
+        assert_eq!(matches(r"\W+", "a, b!"), [", ", "!"]);
+        assert_eq!(matches(r"\s+", "a \t\nb\u{3000}c"), [" \t\n", "\u{3000}"]);
+        assert_eq!(matches(r"\S+", " ab  c "), ["ab", "c"]);
+        assert_eq!(matches(r"[^\W\d]+", "abc123def"), ["abc", "def"]);
+        assert_eq!(matches(r"\bcat\b", "cat concat cat."), ["cat", "cat"]);
+        assert_eq!(matches(r"\Bcat", "cat concat"), ["cat"]);
+    }
+
+    #[test]
+    fn repetition_bounds() {
+        assert_eq!(matches("a{2}", "aaaaa"), ["aa", "aa"]);
+        assert_eq!(matches("a{2,}", "a aa aaaa"), ["aa", "aaaa"]);
+        assert_eq!(matches("a{1,3}", "aaaaa"), ["aaa", "aa"]);
+        assert_eq!(matches("a{0,2}b", "aaab b"), ["aab", "b"]);
+        assert_eq!(matches("a{2,3}?", "aaaaa"), ["aa", "aa"]);
+        assert_eq!(matches("a+?", "aaa"), ["a", "a", "a"]);
+        assert_eq!(matches("ab?c", "ac abc abbc"), ["ac", "abc"]);
+        assert_eq!(matches("(?:ab)+", "ababa"), ["abab"]);
+        // Empty matches are skipped rather than returned
+        assert_eq!(matches("a*", "baab"), ["aa"]);
+    }
+
+    #[test]
+    fn alternation_prefers_the_leftmost_branch() {
+        assert_eq!(matches("a|ab", "ab"), ["a"]);
+        assert_eq!(matches("ab|a", "ab"), ["ab"]);
+        assert_eq!(matches("^(?:foo|bar)$", "foo\nbaz\nbar"), ["foo", "bar"]);
+    }
+
+    #[test]
+    fn patterns_past_the_instruction_limit_are_refused() {
+        let message = "pattern is too large: it compiles to more than 100000 instructions";
+        assert_eq!(error("x{1000}{1000}"), message);
+        assert_eq!(error("x{100000}"), message);
+        // With the final Match, exactly at the limit
+        assert!(Pattern::parse("x{99999}").is_ok());
+    }
+
+    #[test]
+    fn nested_repetition_takes_linear_time() {
+        let text = "a".repeat(20_000);
+        assert_eq!(matches("(?:a*)*b", &text), Vec::<&str>::new());
+        assert_eq!(matches("(?:a|a)*", &text), [text.as_str()]);
+    }
+
+    #[test]
+    fn ignore_case_covers_unicode_letters() {
+        // Letter by letter, so ß does not match SS
+        assert_eq!(
+            matches_ignoring_case("straße", "STRASSE Straße"),
+            ["Straße"]
+        );
+        assert_eq!(matches_ignoring_case("ÉTÉ", "été Été"), ["été", "Été"]);
+        assert_eq!(matches_ignoring_case("σ+", "ΣΣσς"), ["ΣΣσ"]);
+        assert_eq!(matches_ignoring_case("[α-ω]+", "ΑΒΓ abc"), ["ΑΒΓ"]);
+        assert_eq!(matches_ignoring_case("[^a-z]+", "ABC123"), ["123"]);
+        assert_eq!(matches_ignoring_case("ǆ", "Ǆ ǅ ǆ"), ["Ǆ", "ǅ", "ǆ"]);
+        assert_eq!(matches("é", "É"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn errors_give_the_position_in_characters() {
+        assert_eq!(error("a)"), "unopened group at position 1");
+        assert_eq!(error("é)"), "unopened group at position 1");
+        assert_eq!(error("(ab"), "unclosed group at position 3");
+        assert_eq!(
+            error("(?i)x"),
+            "unsupported group flag (only (?:...) is supported) at position 2"
+        );
+        assert_eq!(
+            error("*a"),
+            "repetition operator missing expression at position 0"
+        );
+        assert_eq!(
+            error("a|+"),
+            "repetition operator missing expression at position 2"
+        );
+        assert_eq!(error("a{2"), "unclosed counted repetition at position 3");
+        assert_eq!(error("a{x}"), "expected a repetition count at position 2");
+        assert_eq!(
+            error("a{3,1}"),
+            "invalid repetition range (the maximum is below the minimum) at position 6"
+        );
+        assert_eq!(error(r"\q"), "unrecognized escape sequence at position 1");
+        assert_eq!(error("ab\\"), "incomplete escape sequence at position 3");
+        assert_eq!(error("[abc"), "unclosed character class at position 4");
+        assert_eq!(
+            error("[z-a]"),
+            "invalid character class range at position 4"
+        );
+        assert_eq!(
+            error(r"[a-\d]"),
+            "invalid character class range at position 5"
+        );
+        assert_eq!(
+            error(r"[\b]"),
+            "assertions are not allowed in a character class at position 3"
+        );
+    }
+}
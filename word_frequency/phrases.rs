@@ -0,0 +1,267 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Counts a list of phrases in a stream of words with an Aho-Corasick
+/// automaton whose symbols are whole words, so every phrase is looked for
+/// at once in a single pass, however many there are.
+///
+/// Every occurrence of every phrase is counted, however occurrences
+/// overlap: "machine learning model" counts once for both "machine
+/// learning" and "machine learning model" (and for "learning model", if
+/// listed), and "ha ha ha" counts twice for "ha ha".
+#[derive(Clone, Debug)]
+pub struct PhraseMatcher {
+    /// The phrases as listed
+    phrases: Vec<String>,
+    counts: Vec<u64>,
+    /// The trie of the phrases' words, with the root first
+    nodes: Vec<Node>,
+    /// The node for the longest run of words just seen that begins a phrase
+    state: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Node {
+    next: HashMap<String, usize>,
+    /// The node for the longest proper suffix of this one's words that is
+    /// also in the trie
+    fail: usize,
+    /// The phrases that end here, including those ending at a suffix
+    found: Vec<usize>,
+}
+
+impl PhraseMatcher {
+    /// Builds the automaton for `phrases`, each given as listed and as the
+    /// words it is matched as. A phrase without words is never found.
+    pub fn new(phrases: Vec<(String, Vec<String>)>) -> PhraseMatcher {
+        let mut nodes = vec![Node::default()];
+        let mut names = Vec::with_capacity(phrases.len());
+        for (index, (name, words)) in phrases.into_iter().enumerate() {
+            names.push(name);
+            if words.is_empty() {
+                continue;
+            }
+            let mut node = 0;
+            for word in words {
+                node = match nodes[node].next.get(&word) {
+                    Some(&next) => next,
+                    None => {
+                        nodes.push(Node::default());
+                        let next = nodes.len() - 1;
+                        nodes[node].next.insert(word, next);
+                        next
+                    }
+                };
+            }
+            nodes[node].found.push(index);
+        }
+
+        // Breadth first, so every node's suffixes are linked before it is
+        let mut queue: VecDeque<usize> = nodes[0].next.values().copied().collect();
+        while let Some(node) = queue.pop_front() {
+            let children: Vec<(String, usize)> = nodes[node]
+                .next
+                .iter()
+                .map(|(word, &child)| (word.clone(), child))
+                .collect();
+            for (word, child) in children {
+                let mut fail = nodes[node].fail;
+                let fail = loop {
+                    if let Some(&next) = nodes[fail].next.get(&word) {
+                        break next;
+                    }
+                    if fail == 0 {
+                        break 0;
+                    }
+                    fail = nodes[fail].fail;
+                };
+                nodes[child].fail = fail;
+                let inherited = nodes[fail].found.clone();
+                nodes[child].found.extend(inherited);
+                queue.push_back(child);
+            }
+        }
+
+        PhraseMatcher {
+            counts: vec![0; names.len()],
+            phrases: names,
+            nodes,
+            state: 0,
+        }
+    }
+
+    /// The same phrases, none of them found yet.
+    pub fn fresh(&self) -> PhraseMatcher {
+        PhraseMatcher {
+            counts: vec![0; self.counts.len()],
+            state: 0,
+            ..self.clone()
+        }
+    }
+
+    /// Moves on by one word, counting each phrase it ends.
+    pub fn step(&mut self, word: &str) {
+        let mut node = self.state;
+        self.state = loop {
+            if let Some(&next) = self.nodes[node].next.get(word) {
+                break next;
+            }
+            if node == 0 {
+                break 0;
+            }
+            node = self.nodes[node].fail;
+        };
+        for &phrase in &self.nodes[self.state].found {
+            self.counts[phrase] += 1;
+        }
+    }
+
+    /// Forgets the words seen so far, so no phrase spans the break.
+    pub fn reset(&mut self) {
+        self.state = 0;
+    }
+
+    /// Adds what `other`, built from the same phrases, has found.
+    pub fn merge(&mut self, other: &PhraseMatcher) {
+        for (count, other_count) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other_count;
+        }
+    }
+
+    /// Every phrase with how many times it was found, in the order listed.
+    pub fn counts(&self) -> Vec<(String, u64)> {
+        self.phrases
+            .iter()
+            .cloned()
+            .zip(self.counts.iter().copied())
+            .collect()
+    }
+}
+
+/// Reads a phrase file of one phrase per line, trimmed like a stopword
+/// file. A phrase listed twice is only counted once.
+pub fn load_phrases(path: &Path) -> io::Result<Vec<String>> {
+    let text = fs::read_to_string(path)?;
+    let mut seen = HashSet::new();
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|phrase| !phrase.is_empty() && seen.insert(*phrase))
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::{CountOptions, WordCounter};
+
+    fn matcher(phrases: &[&str]) -> PhraseMatcher {
+        PhraseMatcher::new(
+            phrases
+                .iter()
+                .map(|phrase| {
+                    let words = phrase.split(' ').map(str::to_string).collect();
+                    (phrase.to_string(), words)
+                })
+                .collect(),
+        )
+    }
+
+    fn found(matcher: &mut PhraseMatcher, text: &str) -> Vec<(String, u64)> {
+        text.split_whitespace().for_each(|word| matcher.step(word));
+        matcher.counts()
+    }
+
+    fn listed(counts: &[(&str, u64)]) -> Vec<(String, u64)> {
+        counts
+            .iter()
+            .map(|&(phrase, count)| (phrase.to_string(), count))
+            .collect()
+    }
+
+    #[test]
+    fn overlapping_occurrences_all_count() {
+        let mut phrases = matcher(&["ha ha", "learning model", "machine learning"]);
+        assert_eq!(
+            found(&mut phrases, "ha ha ha ha machine learning model"),
+            listed(&[("ha ha", 3), ("learning model", 1), ("machine learning", 1)])
+        );
+    }
+
+    #[test]
+    fn a_phrase_inside_a_longer_one_counts_as_well() {
+        let mut phrases = matcher(&["machine learning model", "machine learning", "model"]);
+        assert_eq!(
+            found(
+                &mut phrases,
+                "machine learning model and machine learning and machine model"
+            ),
+            listed(&[
+                ("machine learning model", 1),
+                ("machine learning", 2),
+                ("model", 2)
+            ])
+        );
+    }
+
+    #[test]
+    fn a_failed_match_still_finds_the_phrase_it_ends_in() {
+        // "a b a b c" only matches "a b c" after falling back from the
+        // first "a b"
+        let mut phrases = matcher(&["a b c", "b a"]);
+        assert_eq!(
+            found(&mut phrases, "a b a b c"),
+            listed(&[("a b c", 1), ("b a", 1)])
+        );
+    }
+
+    #[test]
+    fn reset_keeps_phrases_from_spanning_the_break() {
+        let mut phrases = matcher(&["machine learning"]);
+        phrases.step("machine");
+        phrases.reset();
+        phrases.step("learning");
+        assert_eq!(phrases.counts(), listed(&[("machine learning", 0)]));
+    }
+
+    #[test]
+    fn fresh_and_merge_add_up_separate_runs() {
+        let mut phrases = matcher(&["ha ha", "never"]);
+        let mut other = phrases.fresh();
+        found(&mut phrases, "ha ha");
+        found(&mut other, "ha ha ha");
+        phrases.merge(&other);
+        assert_eq!(phrases.counts(), listed(&[("ha ha", 3), ("never", 0)]));
+    }
+
+    #[test]
+    fn phrases_match_across_line_breaks_unless_counted_per_line() {
+        let text = "we use machine\nlearning model daily\nmachine learning model\n";
+        let phrases = || vec!["machine learning".to_string(), "learning model".to_string()];
+
+        let mut counter = WordCounter::builder().build();
+        counter.set_phrases(phrases());
+        counter.feed(text);
+        counter.end_input();
+        assert_eq!(
+            counter.phrase_counts(),
+            Some(listed(&[("machine learning", 2), ("learning model", 2)]))
+        );
+
+        let options = CountOptions {
+            per_line: true,
+            ..CountOptions::default()
+        };
+        let mut counter = WordCounter::builder().options(options).build();
+        counter.set_phrases(phrases());
+        counter.feed(text);
+        counter.end_input();
+        assert_eq!(
+            counter.phrase_counts(),
+            Some(listed(&[("machine learning", 1), ("learning model", 2)]))
+        );
+    }
+}
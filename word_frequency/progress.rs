@@ -0,0 +1,153 @@
+use std::io::{self, BufRead, Read, Write};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// How often the progress line is redrawn at most.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+/// The progress of `--progress`, once started. Inputs opened while it is
+/// set count their bytes towards it.
+static PROGRESS: OnceLock<Progress> = OnceLock::new();
+
+/// How much of the input has been read, drawn as a single line on stderr
+/// that is rewritten in place.
+pub struct Progress {
+    /// The bytes of every input together, if known (not for stdin)
+    total: Option<u64>,
+    bytes: AtomicU64,
+    /// The distinct words of the largest counter so far
+    unique: AtomicUsize,
+    started: Instant,
+    /// When the line was last drawn, and how many times
+    drawn: Mutex<(Option<Instant>, usize)>,
+}
+
+/// Starts reporting progress towards `total` bytes, or with a spinner if
+/// the total is unknown.
+pub fn start(total: Option<u64>) {
+    let _ = PROGRESS.set(Progress {
+        total,
+        bytes: AtomicU64::new(0),
+        unique: AtomicUsize::new(0),
+        started: Instant::now(),
+        drawn: Mutex::new((None, 0)),
+    });
+}
+
+/// The progress being reported, if any.
+pub fn active() -> Option<&'static Progress> {
+    PROGRESS.get()
+}
+
+/// Clears the progress line, so that what is printed next starts on a
+/// clean line.
+pub fn finish() {
+    if let Some(progress) = active() {
+        let drawn = progress.drawn.lock().unwrap();
+        if drawn.0.is_some() {
+            let _ = write!(io::stderr(), "\r\x1b[K");
+        }
+    }
+}
+
+impl Progress {
+    /// Notes `count` more bytes read.
+    fn read(&self, count: usize) {
+        self.bytes.fetch_add(count as u64, Ordering::Relaxed);
+        self.redraw();
+    }
+
+    /// Notes how many distinct words a counter has seen. With several
+    /// counting threads this is the largest of them.
+    pub fn words(&self, unique: usize) {
+        self.unique.fetch_max(unique, Ordering::Relaxed);
+    }
+
+    /// Draws the line if it has not been drawn too recently. A thread that
+    /// finds another drawing skips it.
+    fn redraw(&self) {
+        let Ok(mut drawn) = self.drawn.try_lock() else {
+            return;
+        };
+        let now = Instant::now();
+        if drawn
+            .0
+            .is_some_and(|last| now.duration_since(last) < REDRAW_INTERVAL)
+        {
+            return;
+        }
+        let bytes = self.bytes.load(Ordering::Relaxed);
+        let seconds = now.duration_since(self.started).as_secs_f64().max(0.001);
+        let done = match self.total {
+            Some(total) => format!(
+                "{} / {} ({:.0}%)",
+                human_bytes(bytes),
+                human_bytes(total),
+                bytes as f64 * 100.0 / total.max(1) as f64
+            ),
+            None => format!(
+                "{} {}",
+                SPINNER[drawn.1 % SPINNER.len()],
+                human_bytes(bytes)
+            ),
+        };
+        let _ = write!(
+            io::stderr(),
+            "\r\x1b[K{}, {}/s, {} unique words",
+            done,
+            human_bytes((bytes as f64 / seconds) as u64),
+            self.unique.load(Ordering::Relaxed)
+        );
+        *drawn = (Some(now), drawn.1 + 1);
+    }
+}
+
+/// `bytes` in the largest binary unit that keeps it at least 1.
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Counts the bytes read through it towards the progress.
+pub struct ProgressReader<R> {
+    inner: R,
+    progress: &'static Progress,
+}
+
+impl<R> ProgressReader<R> {
+    pub fn new(inner: R, progress: &'static Progress) -> ProgressReader<R> {
+        ProgressReader { inner, progress }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.progress.read(count);
+        Ok(count)
+    }
+}
+
+impl<R: BufRead> BufRead for ProgressReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.inner.consume(amount);
+        self.progress.read(amount);
+    }
+}
@@ -0,0 +1,234 @@
+/// Abbreviations whose full stop does not end a sentence, lowercase and
+/// without the final stop. A single letter, as in "J. Smith", is one too.
+const ABBREVIATIONS: [&str; 19] = [
+    "mr", "mrs", "ms", "dr", "prof", "sr", "jr", "st", "vs", "etc", "e.g", "i.e", "cf", "al",
+    "inc", "ltd", "approx", "fig", "dept",
+];
+
+/// Sentences, words and syllables tallied for `--stats`, from every word
+/// of the text before any are filtered out. A sentence ends at `.`, `!`
+/// or `?` (or their relatives) followed by whitespace or the end of the
+/// input, unless the stop follows an abbreviation.
+#[derive(Clone, Debug, Default)]
+pub struct Readability {
+    pub sentences: u64,
+    pub words: u64,
+    pub syllables: u64,
+    /// Whether words have been seen since the last sentence ended
+    open: bool,
+    /// Whether the last word was an abbreviation
+    abbreviation: bool,
+}
+
+impl Readability {
+    /// The same tallies, none taken yet.
+    pub fn fresh(&self) -> Readability {
+        Readability::default()
+    }
+
+    pub fn word(&mut self, word: &str) {
+        self.words += 1;
+        self.syllables += syllables(word) as u64;
+        self.open = true;
+        self.abbreviation = is_abbreviation(word);
+    }
+
+    /// Sentence-ending punctuation after the last word.
+    pub fn sentence_end(&mut self) {
+        if self.open && !self.abbreviation {
+            self.sentences += 1;
+            self.open = false;
+        }
+        self.abbreviation = false;
+    }
+
+    /// Ends the sentence the input stopped in, if it has any words.
+    pub fn end_input(&mut self) {
+        if self.open {
+            self.sentences += 1;
+            self.open = false;
+        }
+        self.abbreviation = false;
+    }
+
+    /// Adds what `other` has tallied, after the end of its input.
+    pub fn merge(&mut self, other: &Readability) {
+        self.sentences += other.sentences;
+        self.words += other.words;
+        self.syllables += other.syllables;
+    }
+
+    /// Words per sentence, or 0 if there were none.
+    pub fn mean_sentence_length(&self) -> f64 {
+        self.words as f64 / self.sentences.max(1) as f64
+    }
+
+    /// Estimated syllables per word, or 0 if there were none.
+    pub fn syllables_per_word(&self) -> f64 {
+        self.syllables as f64 / self.words.max(1) as f64
+    }
+
+    /// Flesch reading ease: 206.835 - 1.015 * words per sentence - 84.6 *
+    /// syllables per word. Higher is easier; plain English is 60 to 70.
+    pub fn flesch_reading_ease(&self) -> f64 {
+        206.835 - 1.015 * self.mean_sentence_length() - 84.6 * self.syllables_per_word()
+    }
+
+    /// Flesch-Kincaid grade level: 0.39 * words per sentence + 11.8 *
+    /// syllables per word - 15.59, roughly the US school grade the text
+    /// suits.
+    pub fn flesch_kincaid_grade(&self) -> f64 {
+        0.39 * self.mean_sentence_length() + 11.8 * self.syllables_per_word() - 15.59
+    }
+}
+
+fn is_abbreviation(word: &str) -> bool {
+    let mut chars = word.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return c.is_alphabetic();
+    }
+    let lower = word.to_lowercase();
+    ABBREVIATIONS.contains(&lower.as_str())
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(
+        c,
+        'a' | 'e'
+            | 'i'
+            | 'o'
+            | 'u'
+            | 'y'
+            | 'à'..='æ'
+            | 'è'..='ï'
+            | 'ò'..='ö'
+            | 'ø'..='ü'
+            | 'ý'
+            | 'ÿ'
+    )
+}
+
+/// An estimate of the syllables in `word`, for English: its runs of
+/// vowels, less a silent final "e" (but not in "-le" after a consonant, as
+/// in "table") and the "e" of "-ed" and "-es" where it is not sounded
+/// ("jumped", "makes", but "wanted", "boxes"). A leading "y" is a
+/// consonant. Every word has at least one.
+pub fn syllables(word: &str) -> u32 {
+    let letters = Vec::from_iter(
+        word.chars()
+            .flat_map(char::to_lowercase)
+            .filter(|c| c.is_alphabetic()),
+    );
+    let vowel = |i: usize| is_vowel(letters[i]) && !(i == 0 && letters[i] == 'y');
+    let mut count = 0;
+    for i in 0..letters.len() {
+        if vowel(i) && (i == 0 || !vowel(i - 1)) {
+            count += 1;
+        }
+    }
+
+    let n = letters.len();
+    let ends = |suffix: &str| {
+        letters
+            .iter()
+            .rev()
+            .copied()
+            .take(suffix.len())
+            .eq(suffix.chars().rev())
+    };
+    let before = |back: usize| n.checked_sub(back + 1).map(|i| letters[i]);
+    let consonant_before = |back: usize| n > back && !vowel(n - back - 1);
+    if n > 2 && count > 1 {
+        let silent = if ends("le") {
+            // "table", but "mile"
+            !consonant_before(2)
+        } else if ends("ed") {
+            !matches!(before(2), Some('t' | 'd')) && consonant_before(2)
+        } else if ends("es") {
+            let sibilant = matches!(before(2), Some('s' | 'x' | 'z' | 'c' | 'g'))
+                || ends("ches")
+                || ends("shes");
+            !sibilant && consonant_before(2)
+        } else {
+            ends("e") && consonant_before(1)
+        };
+        if silent {
+            count -= 1;
+        }
+    }
+    count.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn syllables_of_known_words() {
+        let known = [
+            ("the", 1),
+            ("cat", 1),
+            ("a", 1),
+            ("I", 1),
+            ("yes", 1),
+            ("rhythm", 1),
+            ("queue", 1),
+            ("create", 1),
+            ("happy", 2),
+            ("people", 2),
+            ("area", 2),
+            ("reading", 2),
+            ("café", 2),
+            ("syllable", 3),
+            ("beautiful", 3),
+            ("Everything", 4),
+            ("readability", 5),
+        ];
+        for (word, expected) in known {
+            assert_eq!(syllables(word), expected, "{}", word);
+        }
+    }
+
+    #[test]
+    fn silent_endings_are_not_counted() {
+        let known = [
+            // A final "e" after a consonant
+            ("make", 1),
+            ("fire", 1),
+            ("mile", 1),
+            ("ease", 1),
+            // but "-le" after a consonant is sounded
+            ("table", 2),
+            ("little", 2),
+            ("apple", 2),
+            // "-ed" only after "t" or "d"
+            ("jumped", 1),
+            ("freed", 1),
+            ("wanted", 2),
+            // "-es" after a sibilant
+            ("makes", 1),
+            ("boxes", 2),
+            ("watches", 2),
+        ];
+        for (word, expected) in known {
+            assert_eq!(syllables(word), expected, "{}", word);
+        }
+    }
+
+    #[test]
+    fn sentences_do_not_end_after_abbreviations() {
+        let mut readability = Readability::default();
+        for word in ["Dr", "Smith", "met", "J", "Jones"] {
+            readability.word(word);
+            if word == "Dr" || word == "J" || word == "Jones" {
+                readability.sentence_end();
+            }
+        }
+        readability.word("Then");
+        readability.word("left");
+        readability.end_input();
+        assert_eq!(readability.sentences, 2);
+        assert_eq!(readability.words, 7);
+        assert_eq!(readability.mean_sentence_length(), 3.5);
+    }
+}
@@ -0,0 +1,113 @@
+#!/usr/bin/env python3
+"""Writes unicode_tables.rs from the Unicode Character Database that comes
+with Python's unicodedata module.
+
+The committed tables are from UCD 14.0.0, which is what Python 3.11 ships.
+Another Python gives the tables of its own UCD version, which is recorded
+at the top of the output; pass --check to compare with the committed file
+instead of writing it.
+
+    python3 scripts/gen_unicode_tables.py [--check]
+"""
+
+import os
+import sys
+import unicodedata as u
+
+OUTPUT = os.path.join(os.path.dirname(os.path.abspath(__file__)), "..", "unicode_tables.rs")
+
+HEADER = """\
+//! Tables for Unicode normalization, generated from the Unicode Character
+//! Database %s with Python's `unicodedata` module by
+//! scripts/gen_unicode_tables.py:
+//!
+//! ```text
+//! CANONICAL:         every character whose NFD differs from it, with
+//!                    its NFD (Hangul syllables excepted)
+//! COMPATIBILITY:     every character whose NFKD differs from its NFD,
+//!                    with its NFKD
+//! COMBINING_CLASSES: ranges of characters with a nonzero canonical
+//!                    combining class
+//! COMPOSITIONS:      the pairs that NFC composes, from the two-character
+//!                    canonical decompositions of characters that are
+//!                    their own NFC
+//! ```
+//!
+//! All are sorted for binary search. Regenerate rather than edit.
+"""
+
+
+def escape(s):
+    return "".join("\\u{%X}" % ord(c) for c in s)
+
+
+def code_points():
+    for c in range(0x110000):
+        if not 0xD800 <= c <= 0xDFFF:
+            yield c
+
+
+def tables():
+    canonical, compatibility, compositions = [], [], []
+    for c in code_points():
+        # normalize.rs decomposes Hangul syllables arithmetically
+        if 0xAC00 <= c <= 0xD7A3:
+            continue
+        ch = chr(c)
+        nfd = u.normalize("NFD", ch)
+        nfkd = u.normalize("NFKD", ch)
+        if nfd != ch:
+            canonical.append((c, nfd))
+        if nfkd != nfd:
+            compatibility.append((c, nfkd))
+        decomposition = u.decomposition(ch)
+        if decomposition and not decomposition.startswith("<"):
+            parts = [int(part, 16) for part in decomposition.split()]
+            # Excludes singletons and the composition exclusions
+            if len(parts) == 2 and u.normalize("NFC", ch) == ch:
+                compositions.append((parts[0], parts[1], c))
+    compositions.sort()
+
+    classes = []
+    for c in code_points():
+        ccc = u.combining(chr(c))
+        if not ccc:
+            continue
+        if classes and classes[-1][1] == c - 1 and classes[-1][2] == ccc:
+            classes[-1][1] = c
+        else:
+            classes.append([c, c, ccc])
+    return canonical, compatibility, classes, compositions
+
+
+def render():
+    canonical, compatibility, classes, compositions = tables()
+    lines = [HEADER % u.unidata_version]
+    lines.append("pub const CANONICAL: &[(char, &str)] = &[")
+    lines += ["    ('\\u{%X}', \"%s\")," % (c, escape(d)) for c, d in canonical]
+    lines.append("];\n\npub const COMPATIBILITY: &[(char, &str)] = &[")
+    lines += ["    ('\\u{%X}', \"%s\")," % (c, escape(d)) for c, d in compatibility]
+    lines.append("];\n\npub const COMBINING_CLASSES: &[(char, char, u8)] = &[")
+    lines += ["    ('\\u{%X}', '\\u{%X}', %d)," % tuple(r) for r in classes]
+    lines.append("];\n\npub const COMPOSITIONS: &[(char, char, char)] = &[")
+    lines += ["    ('\\u{%X}', '\\u{%X}', '\\u{%X}')," % t for t in compositions]
+    lines.append("];")
+    return "\n".join(lines) + "\n"
+
+
+def main():
+    output = render()
+    if sys.argv[1:] == ["--check"]:
+        with open(OUTPUT, encoding="utf-8") as f:
+            if f.read() != output:
+                sys.exit("unicode_tables.rs differs from UCD %s" % u.unidata_version)
+        print("unicode_tables.rs matches UCD %s" % u.unidata_version)
+    elif sys.argv[1:]:
+        sys.exit(__doc__)
+    else:
+        with open(OUTPUT, "w", encoding="utf-8") as f:
+            f.write(output)
+
+
+if __name__ == "__main__":
+    main()
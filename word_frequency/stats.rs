@@ -0,0 +1,256 @@
+use serde_json::{json, Value};
+
+use crate::readability::Readability;
+
+/// Figures about the whole corpus for `--stats`, gathered while counting.
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    /// Words (or n-grams) counted, repeats included
+    pub total_tokens: u64,
+    pub unique_words: usize,
+    /// Words counted exactly once
+    pub hapax_legomena: usize,
+    /// Mean length of the counted tokens, in characters
+    pub mean_length: f64,
+    /// Median length of the counted tokens, in characters
+    pub median_length: f64,
+    /// Characters of input read, whether or not they were part of a word
+    pub characters: u64,
+    /// Invalid byte sequences in the input, each read as U+FFFD
+    pub decoding_errors: u64,
+    pub stopwords_dropped: u64,
+    pub numbers_dropped: u64,
+    /// Distinct words hidden by --min-count
+    pub rare_suppressed: usize,
+    /// Distinct words hidden by --min-percent, if given
+    pub below_min_percent: Option<usize>,
+    /// The exponent of the Zipf distribution fitted with --zipf
+    pub zipf_exponent: Option<f64>,
+    /// Sentences and syllables, if they were tallied
+    pub readability: Option<Readability>,
+}
+
+impl Stats {
+    /// Unique words per token, or 0 for empty input.
+    pub fn type_token_ratio(&self) -> f64 {
+        if self.total_tokens == 0 {
+            0.0
+        } else {
+            self.unique_words as f64 / self.total_tokens as f64
+        }
+    }
+
+    /// `(key, label, value)` for each figure, in display order.
+    pub fn entries(&self) -> Vec<(&'static str, &'static str, String)> {
+        let mut entries = vec![
+            (
+                "total_tokens",
+                "Total tokens",
+                self.total_tokens.to_string(),
+            ),
+            (
+                "unique_words",
+                "Unique words",
+                self.unique_words.to_string(),
+            ),
+            (
+                "type_token_ratio",
+                "Type/token ratio",
+                format!("{:.4}", self.type_token_ratio()),
+            ),
+            (
+                "mean_length",
+                "Average word length",
+                format!("{:.2}", self.mean_length),
+            ),
+            (
+                "median_length",
+                "Median word length",
+                self.median_length.to_string(),
+            ),
+            (
+                "hapax_legomena",
+                "Hapax legomena",
+                self.hapax_legomena.to_string(),
+            ),
+            (
+                "characters",
+                "Characters processed",
+                self.characters.to_string(),
+            ),
+            (
+                "decoding_errors",
+                "Decoding errors",
+                self.decoding_errors.to_string(),
+            ),
+            (
+                "stopwords_dropped",
+                "Stopwords dropped",
+                self.stopwords_dropped.to_string(),
+            ),
+            (
+                "numbers_dropped",
+                "Numbers dropped",
+                self.numbers_dropped.to_string(),
+            ),
+            (
+                "rare_suppressed",
+                "Words below --min-count",
+                self.rare_suppressed.to_string(),
+            ),
+        ];
+        if let Some(below) = self.below_min_percent {
+            entries.push((
+                "below_min_percent",
+                "Words below --min-percent",
+                below.to_string(),
+            ));
+        }
+        if let Some(exponent) = self.zipf_exponent {
+            entries.push(("zipf_exponent", "Zipf exponent", format!("{:.4}", exponent)));
+        }
+        if let Some(readability) = &self.readability {
+            entries.extend([
+                ("sentences", "Sentences", readability.sentences.to_string()),
+                (
+                    "mean_sentence_length",
+                    "Average sentence length",
+                    format!("{:.2}", readability.mean_sentence_length()),
+                ),
+                (
+                    "syllables_per_word",
+                    "Syllables per word",
+                    format!("{:.2}", readability.syllables_per_word()),
+                ),
+                (
+                    "flesch_reading_ease",
+                    "Flesch reading ease",
+                    format!("{:.2}", readability.flesch_reading_ease()),
+                ),
+                (
+                    "flesch_kincaid_grade",
+                    "Flesch-Kincaid grade",
+                    format!("{:.2}", readability.flesch_kincaid_grade()),
+                ),
+            ]);
+        }
+        entries
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut document = json!({
+            "total_tokens": self.total_tokens,
+            "unique_words": self.unique_words,
+            "type_token_ratio": self.type_token_ratio(),
+            "mean_length": self.mean_length,
+            "median_length": self.median_length,
+            "hapax_legomena": self.hapax_legomena,
+            "characters": self.characters,
+            "decoding_errors": self.decoding_errors,
+            "stopwords_dropped": self.stopwords_dropped,
+            "numbers_dropped": self.numbers_dropped,
+            "rare_suppressed": self.rare_suppressed,
+        });
+        if let Some(below) = self.below_min_percent {
+            document["below_min_percent"] = json!(below);
+        }
+        if let Some(exponent) = self.zipf_exponent {
+            document["zipf_exponent"] = json!(exponent);
+        }
+        if let Some(readability) = &self.readability {
+            document["sentences"] = json!(readability.sentences);
+            document["mean_sentence_length"] = json!(readability.mean_sentence_length());
+            document["syllables_per_word"] = json!(readability.syllables_per_word());
+            document["flesch_reading_ease"] = json!(readability.flesch_reading_ease());
+            document["flesch_kincaid_grade"] = json!(readability.flesch_kincaid_grade());
+        }
+        document
+    }
+}
+
+/// The median of values given as `histogram[value] = occurrences`.
+pub fn histogram_median(histogram: &[u64]) -> f64 {
+    let total: u64 = histogram.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+    // The values at the two middle positions (the same one for odd totals)
+    let nth = |n: u64| {
+        let mut seen = 0;
+        histogram
+            .iter()
+            .position(|&occurrences| {
+                seen += occurrences;
+                seen > n
+            })
+            .unwrap_or(0)
+    };
+    (nth((total - 1) / 2) + nth(total / 2)) as f64 / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::WordCounter;
+
+    fn stats_of(text: &str) -> Stats {
+        let mut counter = WordCounter::builder().case_insensitive(true).build();
+        counter.feed(text);
+        counter.end_input();
+        counter.stats()
+    }
+
+    #[test]
+    fn figures_of_a_corpus_small_enough_to_count_by_hand() {
+        // the ×3, cat, sat, on, mat, end: eight tokens of six words, five
+        // of them once; lengths 2 once and 3 seven times, 23 in all
+        let text = "The cat sat on the mat.\nThe end";
+        let stats = stats_of(text);
+        assert_eq!(stats.total_tokens, 8);
+        assert_eq!(stats.unique_words, 6);
+        assert_eq!(stats.hapax_legomena, 5);
+        assert_eq!(stats.type_token_ratio(), 0.75);
+        assert_eq!(stats.mean_length, 2.875);
+        assert_eq!(stats.median_length, 3.0);
+        assert_eq!(stats.characters, 31);
+
+        let entries: Vec<(&str, String)> = stats
+            .entries()
+            .into_iter()
+            .map(|(key, _, value)| (key, value))
+            .take(6)
+            .collect();
+        let expected = [
+            ("total_tokens", "8"),
+            ("unique_words", "6"),
+            ("type_token_ratio", "0.7500"),
+            ("mean_length", "2.88"),
+            ("median_length", "3"),
+            ("hapax_legomena", "5"),
+        ];
+        assert_eq!(
+            entries,
+            expected.map(|(key, value)| (key, value.to_string()))
+        );
+    }
+
+    #[test]
+    fn empty_input_has_zero_ratios() {
+        let stats = stats_of("");
+        assert_eq!(stats.total_tokens, 0);
+        assert_eq!(stats.type_token_ratio(), 0.0);
+        assert_eq!(stats.mean_length, 0.0);
+        assert_eq!(stats.median_length, 0.0);
+    }
+
+    #[test]
+    fn median_of_an_even_total_is_between_the_middle_two() {
+        assert_eq!(histogram_median(&[]), 0.0);
+        // 1, 1, 4
+        assert_eq!(histogram_median(&[0, 2, 0, 0, 1]), 1.0);
+        // 1, 2, 4, 4
+        assert_eq!(histogram_median(&[0, 1, 1, 0, 2]), 3.0);
+        // 2, 3
+        assert_eq!(histogram_median(&[0, 0, 1, 1]), 2.5);
+    }
+}
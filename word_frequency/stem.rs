@@ -0,0 +1,304 @@
+use std::borrow::Cow;
+
+/// Languages that words can be stemmed in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum StemLang {
+    /// English, with the Porter algorithm
+    #[default]
+    En,
+}
+
+impl StemLang {
+    /// The stem of `word`. Only words made entirely of lowercase ASCII
+    /// letters are stemmed, so without case folding capitalized words are
+    /// left as they are, as are numbers and words with apostrophes.
+    pub fn stem(self, word: &str) -> Cow<'_, str> {
+        match self {
+            StemLang::En => {
+                if word.len() <= 2 || !word.bytes().all(|b| b.is_ascii_lowercase()) {
+                    return Cow::Borrowed(word);
+                }
+                let stem = Porter::new(word).stem();
+                if stem == word {
+                    Cow::Borrowed(word)
+                } else {
+                    Cow::Owned(stem)
+                }
+            }
+        }
+    }
+}
+
+/// M. F. Porter, "An algorithm for suffix stripping" (1980), as published.
+struct Porter {
+    b: Vec<u8>,
+}
+
+/// Step 2 and 3 replacements, tried longest first.
+const STEP2: &[(&str, &str)] = &[
+    ("ational", "ate"),
+    ("iveness", "ive"),
+    ("fulness", "ful"),
+    ("ousness", "ous"),
+    ("ization", "ize"),
+    ("tional", "tion"),
+    ("biliti", "ble"),
+    ("entli", "ent"),
+    ("ousli", "ous"),
+    ("alism", "al"),
+    ("ation", "ate"),
+    ("aliti", "al"),
+    ("iviti", "ive"),
+    ("enci", "ence"),
+    ("anci", "ance"),
+    ("izer", "ize"),
+    ("abli", "able"),
+    ("alli", "al"),
+    ("ator", "ate"),
+    ("eli", "e"),
+];
+
+const STEP3: &[(&str, &str)] = &[
+    ("icate", "ic"),
+    ("ative", ""),
+    ("alize", "al"),
+    ("iciti", "ic"),
+    ("ical", "ic"),
+    ("ness", ""),
+    ("ful", ""),
+];
+
+const STEP4: &[&str] = &[
+    "ement", "ance", "ence", "able", "ible", "ment", "ant", "ent", "ion", "ism", "ate", "iti",
+    "ous", "ive", "ize", "al", "er", "ic", "ou",
+];
+
+impl Porter {
+    fn new(word: &str) -> Porter {
+        Porter {
+            b: word.as_bytes().to_vec(),
+        }
+    }
+
+    fn stem(mut self) -> String {
+        self.step1a();
+        self.step1b();
+        self.step1c();
+        self.replace_first(STEP2);
+        self.replace_first(STEP3);
+        self.step4();
+        self.step5();
+        String::from_utf8(self.b).expect("only ASCII letters are stemmed")
+    }
+
+    /// Whether the letter at `i` is a consonant: anything but a, e, i, o, u,
+    /// and y when it follows a consonant.
+    fn is_consonant(&self, i: usize) -> bool {
+        match self.b[i] {
+            b'a' | b'e' | b'i' | b'o' | b'u' => false,
+            b'y' => i == 0 || !self.is_consonant(i - 1),
+            _ => true,
+        }
+    }
+
+    /// The number of vowel-consonant sequences in the first `len` letters.
+    fn measure(&self, len: usize) -> usize {
+        let mut m = 0;
+        let mut in_vowels = false;
+        for i in 0..len {
+            if self.is_consonant(i) {
+                if in_vowels {
+                    m += 1;
+                }
+                in_vowels = false;
+            } else {
+                in_vowels = true;
+            }
+        }
+        m
+    }
+
+    fn has_vowel(&self, len: usize) -> bool {
+        (0..len).any(|i| !self.is_consonant(i))
+    }
+
+    fn ends_double_consonant(&self, len: usize) -> bool {
+        len >= 2 && self.b[len - 1] == self.b[len - 2] && self.is_consonant(len - 1)
+    }
+
+    /// Whether the first `len` letters end consonant-vowel-consonant, with
+    /// the last consonant not w, x or y ("hop", but not "snow").
+    fn ends_cvc(&self, len: usize) -> bool {
+        len >= 3
+            && self.is_consonant(len - 3)
+            && !self.is_consonant(len - 2)
+            && self.is_consonant(len - 1)
+            && !matches!(self.b[len - 1], b'w' | b'x' | b'y')
+    }
+
+    /// The length of the word without `suffix`, if it ends with it.
+    fn stem_len(&self, suffix: &str) -> Option<usize> {
+        self.b
+            .ends_with(suffix.as_bytes())
+            .then(|| self.b.len() - suffix.len())
+    }
+
+    fn set_end(&mut self, len: usize, replacement: &str) {
+        self.b.truncate(len);
+        self.b.extend_from_slice(replacement.as_bytes());
+    }
+
+    fn step1a(&mut self) {
+        if let Some(len) = self.stem_len("sses").or_else(|| self.stem_len("ies")) {
+            self.set_end(len, if self.b[len] == b's' { "ss" } else { "i" });
+        } else if self.stem_len("ss").is_none() {
+            if let Some(len) = self.stem_len("s") {
+                self.b.truncate(len);
+            }
+        }
+    }
+
+    fn step1b(&mut self) {
+        if let Some(len) = self.stem_len("eed") {
+            if self.measure(len) > 0 {
+                self.set_end(len, "ee");
+            }
+            return;
+        }
+        let Some(len) = self
+            .stem_len("ed")
+            .or_else(|| self.stem_len("ing"))
+            .filter(|&len| self.has_vowel(len))
+        else {
+            return;
+        };
+        self.b.truncate(len);
+        if self.b.ends_with(b"at") || self.b.ends_with(b"bl") || self.b.ends_with(b"iz") {
+            self.b.push(b'e');
+        } else if self.ends_double_consonant(len) && !matches!(self.b[len - 1], b'l' | b's' | b'z')
+        {
+            self.b.pop();
+        } else if self.measure(len) == 1 && self.ends_cvc(len) {
+            self.b.push(b'e');
+        }
+    }
+
+    fn step1c(&mut self) {
+        if let Some(len) = self.stem_len("y").filter(|&len| self.has_vowel(len)) {
+            self.b[len] = b'i';
+        }
+    }
+
+    /// Replaces the first of `rules` the word ends with, if what comes
+    /// before it has a measure above zero.
+    fn replace_first(&mut self, rules: &[(&str, &str)]) {
+        let found = rules
+            .iter()
+            .find_map(|&(suffix, replacement)| Some((self.stem_len(suffix)?, replacement)));
+        if let Some((len, replacement)) = found {
+            if self.measure(len) > 0 {
+                self.set_end(len, replacement);
+            }
+        }
+    }
+
+    fn step4(&mut self) {
+        let Some((suffix, len)) = STEP4
+            .iter()
+            .find_map(|&suffix| Some((suffix, self.stem_len(suffix)?)))
+        else {
+            return;
+        };
+        let allowed = suffix != "ion" || (len > 0 && matches!(self.b[len - 1], b's' | b't'));
+        if allowed && self.measure(len) > 1 {
+            self.b.truncate(len);
+        }
+    }
+
+    fn step5(&mut self) {
+        if let Some(len) = self.stem_len("e") {
+            let m = self.measure(len);
+            if m > 1 || (m == 1 && !self.ends_cvc(len)) {
+                self.b.truncate(len);
+            }
+        }
+        let len = self.b.len();
+        if self.measure(len) > 1 && self.ends_double_consonant(len) && self.b[len - 1] == b'l' {
+            self.b.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stem(word: &str) -> Cow<'_, str> {
+        StemLang::En.stem(word)
+    }
+
+    #[test]
+    fn porter_stems_of_known_words() {
+        let known = [
+            ("running", "run"),
+            ("easily", "easili"),
+            ("caresses", "caress"),
+            ("ponies", "poni"),
+            ("ties", "ti"),
+            ("cats", "cat"),
+            ("feed", "feed"),
+            ("agreed", "agre"),
+            ("plastered", "plaster"),
+            ("motoring", "motor"),
+            ("sing", "sing"),
+            ("conflated", "conflat"),
+            ("troubled", "troubl"),
+            ("sized", "size"),
+            ("hopping", "hop"),
+            ("falling", "fall"),
+            ("hissing", "hiss"),
+            ("fizzed", "fizz"),
+            ("failing", "fail"),
+            ("filing", "file"),
+            ("happy", "happi"),
+            ("sky", "sky"),
+            ("relational", "relat"),
+            ("conditional", "condit"),
+            ("rational", "ration"),
+            ("digitizer", "digit"),
+            ("operator", "oper"),
+            ("hopefulness", "hope"),
+            ("goodness", "good"),
+            ("adjustable", "adjust"),
+            ("replacement", "replac"),
+            ("adoption", "adopt"),
+            ("communism", "commun"),
+            ("effective", "effect"),
+            ("controlling", "control"),
+            ("roll", "roll"),
+            ("probate", "probat"),
+            ("rate", "rate"),
+            ("cease", "ceas"),
+            ("generalizations", "gener"),
+            ("oscillators", "oscil"),
+        ];
+        for (word, expected) in known {
+            assert_eq!(stem(word), expected, "{}", word);
+        }
+    }
+
+    #[test]
+    fn only_lowercase_ascii_words_are_stemmed() {
+        for word in [
+            "Running", "RUNNING", "don't", "naïvely", "1990s", "is", "as",
+        ] {
+            assert!(
+                matches!(stem(word), Cow::Borrowed(w) if w == word),
+                "{}",
+                word
+            );
+        }
+        assert!(matches!(stem("cat"), Cow::Borrowed("cat")));
+        assert!(matches!(stem("cats"), Cow::Owned(_)));
+    }
+}
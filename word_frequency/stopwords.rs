@@ -0,0 +1,85 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Languages with a built-in stopword list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum StopwordLang {
+    En,
+    De,
+    Fr,
+    Es,
+}
+
+impl StopwordLang {
+    /// A short list of the most common function words, in lowercase.
+    pub fn words(self) -> &'static [&'static str] {
+        match self {
+            StopwordLang::En => &[
+                "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "from", "had",
+                "has", "have", "he", "her", "his", "i", "if", "in", "is", "it", "its", "not", "of",
+                "on", "or", "she", "that", "the", "their", "they", "this", "to", "was", "we",
+                "were", "with", "you",
+            ],
+            StopwordLang::De => &[
+                "aber", "als", "am", "an", "auch", "auf", "aus", "bei", "das", "dass", "dem",
+                "den", "der", "des", "die", "ein", "eine", "einen", "er", "es", "für", "hat",
+                "ich", "im", "in", "ist", "mit", "nicht", "noch", "sich", "sie", "sind", "und",
+                "von", "war", "wie", "zu", "zum", "zur",
+            ],
+            StopwordLang::Fr => &[
+                "au", "aux", "avec", "ce", "ces", "dans", "de", "des", "du", "elle", "en", "est",
+                "et", "il", "ils", "je", "la", "le", "les", "leur", "mais", "ne", "nous", "ou",
+                "par", "pas", "pour", "qu", "que", "qui", "sa", "se", "son", "sur", "un", "une",
+                "vous",
+            ],
+            StopwordLang::Es => &[
+                "a", "al", "como", "con", "de", "del", "el", "en", "es", "esta", "este", "ha",
+                "la", "las", "le", "lo", "los", "más", "no", "o", "para", "pero", "por", "que",
+                "se", "si", "su", "sus", "también", "un", "una", "y", "ya",
+            ],
+        }
+    }
+}
+
+/// Reads a word list (such as stopwords) of one word per line. Surrounding
+/// whitespace (including the `\r` of CRLF line endings) is trimmed, and
+/// blank lines and comment lines starting with `#` are skipped.
+pub fn load_word_list(path: &Path) -> io::Result<Vec<String>> {
+    let text = fs::read_to_string(path)?;
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|word| !word.is_empty() && !word.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_lists_skip_crlf_blank_lines_and_comments() {
+        let dir =
+            std::env::temp_dir().join(format!("word_frequency-stopwords-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("stopwords.txt");
+        fs::write(
+            &path,
+            "# English extras\r\nthe\r\n\r\n  and \r\n\t\r\n  # indented comment\r\nOf\r\nlast",
+        )
+        .unwrap();
+        assert_eq!(load_word_list(&path).unwrap(), ["the", "and", "Of", "last"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_missing_word_list_is_an_error() {
+        let path = std::env::temp_dir().join("word_frequency-stopwords-missing.txt");
+        assert_eq!(
+            load_word_list(&path).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use common::{run_in, stderr, stdout, Scratch};
+use std::fs::File;
+use std::process::Stdio;
+
+#[test]
+fn unreadable_stdin_exits_non_zero() {
+    let scratch = Scratch::new("stdin-error");
+    // A directory opens as a file but fails on the first read
+    let stdin = File::open(&scratch.dir).unwrap();
+    let output = run_in(&scratch.dir, &[], Stdio::from(stdin));
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stderr(&output).starts_with("Failed to read from stdin: "),
+        "{}",
+        stderr(&output)
+    );
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn unreadable_file_exits_non_zero_after_counting_the_rest() {
+    let scratch = Scratch::new("file-error");
+    scratch.file("words.txt", b"one two two\n");
+    let output = run_in(
+        &scratch.dir,
+        &["-f", "words.txt", "-f", "missing.txt"],
+        Stdio::null(),
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stderr(&output).starts_with("Failed to read missing.txt: "),
+        "{}",
+        stderr(&output)
+    );
+    assert!(stdout(&output).contains("two"));
+}
+
+#[test]
+fn readable_stdin_exits_zero() {
+    let scratch = Scratch::new("stdin-ok");
+    let path = scratch.file("words.txt", b"one two two\n");
+    let output = run_in(&scratch.dir, &[], Stdio::from(File::open(path).unwrap()));
+    assert!(output.status.success(), "{}", stderr(&output));
+}
+
+const TERMINAL_HINT: &str = "reading from terminal";
+
+#[test]
+fn piped_stdin_is_read_without_the_terminal_hint() {
+    let scratch = Scratch::new("stdin-piped");
+    let path = scratch.file("words.txt", b"one two two\n");
+    for args in [&[][..], &["--no-tty-wait"]] {
+        let stdin = Stdio::from(File::open(&path).unwrap());
+        let output = run_in(&scratch.dir, args, stdin);
+        assert_eq!(output.status.code(), Some(0), "{:?}", args);
+        assert_eq!(stderr(&output), "");
+        assert_eq!(stdout(&output), "two: 2\none: 1\n");
+    }
+
+    // Empty piped input is no input, not a reason to wait
+    let output = run_in(&scratch.dir, &["--no-tty-wait"], Stdio::null());
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn a_missing_file_fails_without_reading_stdin() {
+    let scratch = Scratch::new("file-missing");
+    let stdin = Stdio::from(File::open(scratch.file("stdin.txt", b"from stdin\n")).unwrap());
+    let output = run_in(&scratch.dir, &["--no-tty-wait", "-f", "missing.txt"], stdin);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stderr(&output).starts_with("Failed to read missing.txt: "),
+        "{}",
+        stderr(&output)
+    );
+    assert!(!stderr(&output).contains(TERMINAL_HINT));
+    assert!(!stdout(&output).contains("stdin"));
+}
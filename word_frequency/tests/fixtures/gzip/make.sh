@@ -0,0 +1,28 @@
+#!/bin/sh
+# Rebuilds the compressed fixtures from text.txt and noise.bin with the
+# system gzip (these were made with gzip 1.12). The gzip tests in gzip.rs
+# decompress them and compare the result with the originals.
+set -e
+cd "$(dirname "$0")"
+
+gzip -1 -n -c text.txt > level1.gz
+# Keeps the file name in the header, to exercise the FNAME field
+gzip -9 -c text.txt > level9.gz
+# Incompressible, so gzip falls back to stored blocks
+gzip -6 -n -c noise.bin > noise.gz
+: | gzip -n -c > empty.gz
+
+# Two members, as `cat a.gz b.gz` gives; they decompress to text.txt
+head -c 60000 text.txt | gzip -n -c > multi.gz
+tail -c +60001 text.txt | gzip -9 -n -c >> multi.gz
+
+# The last 1000 bytes of level1.gz are missing
+head -c "$(($(wc -c < level1.gz) - 1000))" level1.gz > truncated.gz
+# The first byte of the CRC in the trailer is flipped
+size=$(wc -c < level1.gz)
+head -c "$((size - 8))" level1.gz > bad_crc.gz
+tail -c 8 level1.gz | head -c 1 | od -An -tu1 | {
+    read -r byte
+    printf "\\$(printf %o $((byte ^ 255)))"
+} >> bad_crc.gz
+tail -c 7 level1.gz >> bad_crc.gz
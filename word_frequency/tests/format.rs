@@ -0,0 +1,146 @@
+mod common;
+
+use common::{assert_snapshot, run, stderr, stdout};
+
+const TEXT: &str = "The cat saw the dog. The dog ran!\nÉté, été: the end.\n";
+
+/// Runs word_frequency on `input` with `args` and checks its output
+/// against the snapshot `name`.
+fn assert_output(name: &str, args: &[&str], input: &str) {
+    let output = run(args, input.as_bytes());
+    assert!(output.status.success(), "{:?}: {}", args, stderr(&output));
+    assert_snapshot(name, &stdout(&output));
+}
+
+#[test]
+fn json_lists_every_word_with_totals() {
+    assert_output("counts.json", &["--format", "json"], TEXT);
+}
+
+#[test]
+fn json_totals_cover_words_cut_by_top() {
+    let args = ["--format", "json", "--ignore-case", "--top", "2"];
+    assert_output("top.json", &args, TEXT);
+}
+
+#[test]
+fn json_of_no_input_has_an_empty_word_list() {
+    assert_output("empty.json", &["--format", "json"], "");
+}
+
+#[test]
+fn json_escapes_quotes_and_backslashes() {
+    let args = ["--format", "json", "--delimiters-only", "--delimiters", " "];
+    assert_output(
+        "escaped.json",
+        &args,
+        "say \"hi\" or he\"d C:\\dir tab\there\n",
+    );
+}
+
+#[test]
+fn json_of_ngrams() {
+    let args = ["--format", "json", "--ngrams", "2", "--ignore-case"];
+    assert_output("bigrams.json", &args, TEXT);
+}
+
+#[test]
+fn markdown_table_of_counts() {
+    assert_output("counts.md", &["--format", "markdown"], TEXT);
+    let args = [
+        "--format",
+        "markdown",
+        "--percent",
+        "--cumulative",
+        "--top",
+        "3",
+    ];
+    assert_output("percent.md", &args, TEXT);
+}
+
+#[test]
+fn markdown_escapes_what_would_break_a_row() {
+    let args = [
+        "--format",
+        "markdown",
+        "--delimiters-only",
+        "--delimiters",
+        " ",
+    ];
+    let input = "a|b a|b c`d back\\slash tab\there plain\n";
+    assert_output("escaped.md", &args, input);
+    let output = run(&args, input.as_bytes());
+    assert!(stdout(&output).contains("| a\\|b "));
+}
+
+#[test]
+fn markdown_groups_go_under_headings() {
+    let args = ["--format", "markdown", "--group-by", "paragraph"];
+    assert_output("groups.md", &args, "one two\n\nthree two\n");
+}
+
+#[test]
+fn markdown_stats_follow_the_counts() {
+    assert_output("stats.md", &["--format", "markdown", "--stats"], TEXT);
+}
+
+#[test]
+fn csv_and_tsv_escape_what_would_break_a_row() {
+    let args = ["--delimiters-only", "--delimiters", " ", "--format"];
+    let input = "say he\"d a,b C:\\dir tab\there a,b \"quoted\"\n";
+    let table = |format| {
+        let output = run(&[&args[..], &[format]].concat(), input.as_bytes());
+        assert!(output.status.success(), "{}", stderr(&output));
+        stdout(&output)
+    };
+    assert_eq!(
+        table("csv"),
+        "word,count\n\"a,b\",2\nC:\\dir,1\n\"he\"\"d\",1\nquoted,1\nsay,1\ntab\there,1\n"
+    );
+    assert_eq!(
+        table("tsv"),
+        "word\tcount\na,b\t2\nC:\\\\dir\t1\nhe\"d\t1\nquoted\t1\nsay\t1\ntab\\there\t1\n"
+    );
+}
+
+#[test]
+fn chart_bars_scale_to_the_widest_count() {
+    let input = format!(
+        "{}{}{}once",
+        "widest ".repeat(100),
+        "half ".repeat(50),
+        "fifth ".repeat(20)
+    );
+    let chart = |extra: &[&str]| {
+        let args = [&["--format", "chart", "--width", "10"], extra].concat();
+        let output = run(&args, input.as_bytes());
+        assert!(output.status.success(), "{}", stderr(&output));
+        stdout(&output)
+    };
+    // A count too small for a whole column still gets one
+    assert_eq!(
+        chart(&[]),
+        "widest  100  ##########\n\
+         half     50  #####\n\
+         fifth    20  ##\n\
+         once      1  #\n"
+    );
+    // In eighths of a column, where the 0.8 of "once" rounds to one
+    assert_eq!(
+        chart(&["--unicode-bars"]),
+        "widest  100  ██████████\n\
+         half     50  █████\n\
+         fifth    20  ██\n\
+         once      1  ▏\n"
+    );
+
+    // Bars of 2.5 and 1.25 columns end in a partial block
+    let output = run(
+        &["--format", "chart", "--width", "10", "--unicode-bars"],
+        b"a a a a a a a a b b b b c c d",
+    );
+    assert_eq!(
+        stdout(&output),
+        "a  8  ██████████\nb  4  █████\nc  2  ██▌\nd  1  █▎\n"
+    );
+}
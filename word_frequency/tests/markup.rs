@@ -0,0 +1,94 @@
+mod common;
+
+use common::{run, stderr, stdout};
+
+/// The `(word, count)` rows of `--format csv` for FIXTURE with `args`, in
+/// alphabetical order.
+fn counts(fixture: &str, args: &[&str]) -> Vec<(String, u64)> {
+    let path = format!(
+        "{}/tests/fixtures/markup/{}",
+        env!("CARGO_MANIFEST_DIR"),
+        fixture
+    );
+    let common = [
+        "-f",
+        &path,
+        "--ignore-case",
+        "--sort",
+        "alpha",
+        "--format",
+        "csv",
+    ];
+    let output = run(&[&common[..], args].concat(), b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    stdout(&output)
+        .lines()
+        .skip(1)
+        .map(|line| {
+            let (word, count) = line.rsplit_once(',').unwrap();
+            (word.to_string(), count.parse().unwrap())
+        })
+        .collect()
+}
+
+fn listed(counts: &[(&str, u64)]) -> Vec<(String, u64)> {
+    counts
+        .iter()
+        .map(|&(word, count)| (word.to_string(), count))
+        .collect()
+}
+
+#[test]
+fn html_pages_count_only_their_text() {
+    assert_eq!(
+        counts("page.html", &["--strip-markup", "html"]),
+        listed(&[
+            ("are", 1),
+            ("café", 1),
+            ("fast", 1),
+            ("faster", 1),
+            ("gadgets", 3),
+            ("love", 1),
+            ("our", 1),
+            ("owners", 1),
+            ("than", 1),
+            ("widgets", 5),
+        ])
+    );
+
+    // Without stripping, the markup dominates
+    let raw = counts("page.html", &[]);
+    for word in ["div", "href", "class", "script"] {
+        assert!(raw.iter().any(|(w, _)| w.contains(word)), "{}", word);
+    }
+}
+
+#[test]
+fn markdown_readmes_count_no_code_or_urls() {
+    assert_eq!(
+        counts("README.md", &["--strip-markup", "markdown"]),
+        listed(&[
+            ("a", 1),
+            ("and", 1),
+            ("cargo", 1),
+            ("for", 1),
+            ("install", 1),
+            ("is", 1),
+            ("its", 1),
+            ("kept", 1),
+            ("logo", 1),
+            ("more", 1),
+            ("options", 1),
+            ("run", 1),
+            ("see", 2),
+            ("the", 2),
+            ("then", 1),
+            ("to", 1),
+            ("tool", 2),
+            ("whole", 1),
+            ("widget", 2),
+            ("widget_name", 1),
+            ("with", 1),
+        ])
+    );
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use common::{run, stderr, stdout};
+
+fn counted(args: &[&str], text: &str) -> String {
+    let args = [&["--smart-case"], args].concat();
+    let output = run(&args, text.as_bytes());
+    assert!(output.status.success(), "{:?}: {}", args, stderr(&output));
+    stdout(&output)
+}
+
+#[test]
+fn casings_merge_under_the_most_common_one() {
+    assert_eq!(
+        counted(&[], "london London the London LONDON The"),
+        "London: 4\nthe: 2\n"
+    );
+    // Equally common casings go to the first seen
+    assert_eq!(
+        counted(&[], "Mixed tie mixed TIE Tie mixed Mixed"),
+        "Mixed: 4\ntie: 3\n"
+    );
+}
+
+#[test]
+fn acronyms_keep_their_capitals() {
+    assert_eq!(
+        counted(&[], "NASA and the ESA. NASA, nasa, NASA! esa ESA"),
+        "NASA: 4\nESA: 3\nand: 1\nthe: 1\n"
+    );
+}
+
+#[test]
+fn stems_take_the_casing_of_their_words() {
+    let text = "Running RUNNING Runs runs running RUNNING RUNNING Paris PARIS";
+    assert_eq!(counted(&["--stem"], text), "RUN: 7\nPari: 2\n");
+    // The stem is cased like the most common spelling of any of its
+    // words, or the first seen of those tied
+    assert_eq!(
+        counted(&["--stem"], "Houses HOUSE house housing"),
+        "Hous: 4\n"
+    );
+    assert_eq!(
+        counted(&["--stem"], "Houses house HOUSE housing house"),
+        "hous: 5\n"
+    );
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::{run, stderr, stdout};
+
+/// Ties on every key: two words of count 2, five of count 1, and words of
+/// three characters where "ééé" is longer in bytes than in characters.
+const TEXT: &str = "bb a ccc a bb dd été ééé ab ab ab zz";
+
+/// The words of the text output for `args`, in order.
+fn order(args: &[&str]) -> Vec<String> {
+    let output = run(args, TEXT.as_bytes());
+    assert!(output.status.success(), "{:?}: {}", args, stderr(&output));
+    stdout(&output)
+        .lines()
+        .map(|line| line.split(':').next().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn each_sort_key_breaks_ties_the_documented_way() {
+    let cases: [(&[&str], &[&str]); 6] = [
+        (
+            &["--sort", "count"],
+            &["ab", "a", "bb", "ccc", "dd", "zz", "été", "ééé"],
+        ),
+        (
+            &["--sort", "count", "--reverse"],
+            &["ééé", "été", "zz", "dd", "ccc", "bb", "a", "ab"],
+        ),
+        (
+            &["--sort", "alpha"],
+            &["a", "ab", "bb", "ccc", "dd", "zz", "été", "ééé"],
+        ),
+        (
+            &["--sort", "alpha", "--reverse"],
+            &["ééé", "été", "zz", "dd", "ccc", "bb", "ab", "a"],
+        ),
+        // Characters, not bytes: "ééé" ties with "ccc", and "ab" comes
+        // before "bb" for its higher count
+        (
+            &["--sort", "length"],
+            &["ccc", "été", "ééé", "ab", "bb", "dd", "zz", "a"],
+        ),
+        (
+            &["--sort", "length", "--reverse"],
+            &["a", "zz", "dd", "bb", "ab", "ééé", "été", "ccc"],
+        ),
+    ];
+    for (args, expected) in cases {
+        assert_eq!(order(args), expected, "{:?}", args);
+    }
+    assert_eq!(order(&[]), order(&["--sort", "count"]));
+}
+
+#[test]
+fn sorting_comes_before_top() {
+    assert_eq!(order(&["--sort", "alpha", "--top", "2"]), ["a", "ab"]);
+    assert_eq!(
+        order(&["--sort", "length", "--reverse", "--top", "3"]),
+        ["a", "zz", "dd"]
+    );
+    assert_eq!(
+        order(&["--sort", "count", "--reverse", "--top", "2"]),
+        ["ééé", "été"]
+    );
+}
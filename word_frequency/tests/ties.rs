@@ -0,0 +1,84 @@
+mod common;
+
+use common::{run, stderr, stdout};
+use serde_json::Value;
+
+/// "a" three times, then b, c and d tied at two, right at a cutoff of 2.
+const TEXT: &str = "d c b a a a b c d e";
+
+fn words(args: &[&str]) -> (Vec<String>, String) {
+    let output = run(args, TEXT.as_bytes());
+    assert!(output.status.success(), "{:?}: {}", args, stderr(&output));
+    let words = stdout(&output)
+        .lines()
+        .map(|line| line.split(':').next().unwrap().to_string())
+        .collect();
+    (words, stderr(&output))
+}
+
+fn json(args: &[&str]) -> Value {
+    let args = [&["--format", "json"], args].concat();
+    let output = run(&args, TEXT.as_bytes());
+    assert!(output.status.success(), "{:?}: {}", args, stderr(&output));
+    serde_json::from_str(&stdout(&output)).unwrap()
+}
+
+fn listed(document: &Value) -> Vec<&str> {
+    document["words"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|word| word["word"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn a_tie_at_the_cutoff_is_cut_alphabetically_by_default() {
+    assert_eq!(
+        words(&["--top", "2"]),
+        (vec!["a".into(), "b".into()], String::new())
+    );
+    assert_eq!(
+        words(&["--top", "2", "--ties", "truncate"]),
+        words(&["--top", "2"])
+    );
+
+    let document = json(&["--top", "2"]);
+    assert_eq!(listed(&document), ["a", "b"]);
+    assert!(document["metadata"].get("boundary_count").is_none());
+}
+
+#[test]
+fn including_ties_takes_every_word_at_the_boundary_count() {
+    let (listed_words, note) = words(&["--top", "2", "--ties", "include"]);
+    assert_eq!(listed_words, ["a", "b", "c", "d"]);
+    assert_eq!(note, "Included 2 more words tied at count 2.\n");
+
+    let document = json(&["--top", "2", "--ties", "include"]);
+    assert_eq!(listed(&document), ["a", "b", "c", "d"]);
+    assert_eq!(document["metadata"]["boundary_count"], 2);
+    // The totals still cover every word
+    assert_eq!(document["metadata"]["total_tokens"], 10);
+    assert_eq!(document["metadata"]["unique_words"], 5);
+}
+
+#[test]
+fn a_cutoff_between_counts_includes_nothing_more() {
+    let (listed_words, note) = words(&["--top", "1", "--ties", "include"]);
+    assert_eq!(listed_words, ["a"]);
+    assert_eq!(note, "");
+    assert_eq!(
+        json(&["--top", "1", "--ties", "include"])["metadata"]["boundary_count"],
+        3
+    );
+
+    // Every word at the boundary already fits
+    let (listed_words, note) = words(&["--top", "4", "--ties", "include"]);
+    assert_eq!(listed_words, ["a", "b", "c", "d"]);
+    assert_eq!(note, "");
+
+    // With no cutoff in the list there is no boundary
+    let document = json(&["--top", "9", "--ties", "include"]);
+    assert_eq!(listed(&document).len(), 5);
+    assert!(document["metadata"].get("boundary_count").is_none());
+}
@@ -0,0 +1,110 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// A word's weight in one document.
+#[derive(Clone, Debug)]
+pub struct Scored {
+    pub word: String,
+    pub count: u64,
+    pub score: f64,
+}
+
+/// Scores every word of each document, highest first (equal scores
+/// alphabetically). With `N` documents, of which `df` contain the word:
+///
+/// ```text
+/// tf     = count in the document / words in the document
+/// idf    = ln((1 + N) / (1 + df)) + 1
+/// tf-idf = tf * idf
+/// ```
+///
+/// The smoothing keeps words found in every document from scoring zero.
+pub fn score(documents: Vec<HashMap<String, u64>>) -> Vec<Vec<Scored>> {
+    let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+    for counts in &documents {
+        for word in counts.keys() {
+            *document_frequency.entry(word).or_insert(0) += 1;
+        }
+    }
+    let n = documents.len() as f64;
+    let idf: HashMap<&str, f64> = document_frequency
+        .into_iter()
+        .map(|(word, df)| (word, ((1.0 + n) / (1.0 + df as f64)).ln() + 1.0))
+        .collect();
+
+    documents
+        .iter()
+        .map(|counts| {
+            let total = counts.values().sum::<u64>().max(1) as f64;
+            let mut scored: Vec<Scored> = counts
+                .iter()
+                .map(|(word, &count)| Scored {
+                    word: word.clone(),
+                    count,
+                    score: count as f64 / total * idf[word.as_str()],
+                })
+                .collect();
+            scored.sort_by(|a, b| {
+                b.score
+                    .partial_cmp(&a.score)
+                    .unwrap_or(Ordering::Equal)
+                    .then_with(|| a.word.cmp(&b.word))
+            });
+            scored
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(words: &[(&str, u64)]) -> HashMap<String, u64> {
+        words
+            .iter()
+            .map(|&(word, count)| (word.to_string(), count))
+            .collect()
+    }
+
+    fn scores(scored: &[Scored]) -> Vec<(&str, u64, f64)> {
+        scored
+            .iter()
+            .map(|s| (s.word.as_str(), s.count, (s.score * 1e6).round() / 1e6))
+            .collect()
+    }
+
+    #[test]
+    fn two_documents_scored_by_hand() {
+        // "the cat the" and "the dog": "the" is in both, so its idf is
+        // ln(3/3) + 1 = 1, while "cat" and "dog" have ln(3/2) + 1
+        let scored = score(vec![
+            document(&[("the", 2), ("cat", 1)]),
+            document(&[("the", 1), ("dog", 1)]),
+        ]);
+        let rare = 1.5f64.ln() + 1.0;
+        assert!((scored[0][1].score - rare / 3.0).abs() < 1e-12);
+        assert!((scored[1][0].score - rare / 2.0).abs() < 1e-12);
+        assert_eq!(
+            scores(&scored[0]),
+            [("the", 2, 0.666667), ("cat", 1, 0.468488)]
+        );
+        assert_eq!(scores(&scored[1]), [("dog", 1, 0.702733), ("the", 1, 0.5)]);
+    }
+
+    #[test]
+    fn equal_scores_are_alphabetical() {
+        let scored = score(vec![document(&[("b", 1), ("c", 1), ("a", 1)])]);
+        assert_eq!(
+            scores(&scored[0]),
+            [("a", 1, 0.333333), ("b", 1, 0.333333), ("c", 1, 0.333333)]
+        );
+    }
+
+    #[test]
+    fn an_empty_document_scores_nothing() {
+        let scored = score(vec![HashMap::new(), document(&[("word", 3)])]);
+        assert!(scored[0].is_empty());
+        // tf 1 and idf ln(3/2) + 1
+        assert_eq!(scores(&scored[1]), [("word", 3, 1.405465)]);
+    }
+}
@@ -0,0 +1,571 @@
+/// How text is split into words.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Tokenizer {
+    /// Split on whitespace and trim punctuation from both ends of each word,
+    /// keeping inner apostrophes and hyphens
+    #[default]
+    Simple,
+    /// The simple tokenizer before leading punctuation was trimmed: only
+    /// trailing punctuation goes, and empty tokens are kept (--legacy-trim)
+    #[value(skip)]
+    LegacySimple,
+    /// Split at Unicode word boundaries (UAX #29), keeping tokens that
+    /// contain a letter or digit
+    Unicode,
+    /// The Unicode tokenizer, also keeping each emoji as a token of its
+    /// own, whole with its modifiers and joined emoji (--keep-emoji)
+    #[value(skip)]
+    UnicodeEmoji,
+    /// Every character is a token (--chars)
+    #[value(skip)]
+    Chars,
+    /// Every grapheme cluster is a token (--chars --graphemes)
+    #[value(skip)]
+    Graphemes,
+}
+
+/// One step of tokenized text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Token<'a> {
+    Word(&'a str),
+    /// An emoji, whole with its modifiers and joined emoji, from
+    /// `Tokenizer::UnicodeEmoji`
+    Emoji(&'a str),
+    /// Sentence-ending punctuation (`.`, `!`, `?` and their relatives)
+    SentenceEnd,
+}
+
+fn ends_sentence(punctuation: &str) -> bool {
+    punctuation.contains([
+        '.', '!', '?', '\u{2026}', '\u{3002}', '\u{FF01}', '\u{FF1F}',
+    ])
+}
+
+impl Tokenizer {
+    /// The tokens of `text`, before any case processing.
+    pub fn tokens<'a>(self, text: &'a str) -> Box<dyn Iterator<Item = Token<'a>> + 'a> {
+        match self {
+            Tokenizer::Simple => Box::new(text.split_whitespace().flat_map(simple_tokens)),
+            Tokenizer::LegacySimple => Box::new(text.split_whitespace().flat_map(|word| {
+                // Remove punctuation from the end of the word
+                let trimmed_word = word.trim_end_matches(|c: char| !c.is_alphanumeric());
+                let end = ends_sentence(&word[trimmed_word.len()..]).then_some(Token::SentenceEnd);
+                std::iter::once(Token::Word(trimmed_word)).chain(end)
+            })),
+            Tokenizer::Unicode | Tokenizer::UnicodeEmoji => {
+                let emoji = self == Tokenizer::UnicodeEmoji;
+                let words = UnicodeWords {
+                    text,
+                    pos: 0,
+                    emoji,
+                };
+                Box::new(words.filter_map(move |segment| {
+                    if emoji && segment.starts_with(is_emoji) {
+                        Some(Token::Emoji(segment))
+                    } else if segment.chars().any(char::is_alphanumeric) {
+                        Some(Token::Word(segment))
+                    } else if ends_sentence(segment) {
+                        Some(Token::SentenceEnd)
+                    } else {
+                        None
+                    }
+                }))
+            }
+            Tokenizer::Chars => Box::new(
+                text.char_indices()
+                    .map(|(i, c)| Token::Word(&text[i..i + c.len_utf8()])),
+            ),
+            Tokenizer::Graphemes => Box::new(Graphemes { text, pos: 0 }.map(Token::Word)),
+        }
+    }
+}
+
+/// The simple tokenizer's tokens for one piece of text between spaces: the
+/// piece without its leading and trailing punctuation, if anything is left,
+/// and the end of a sentence if the trailing punctuation ends one.
+fn simple_tokens(word: &str) -> impl Iterator<Item = Token<'_>> {
+    // Combining marks stay on the letter they follow, so that a decomposed
+    // "é" keeps its accent
+    let punctuation = |c: char| !c.is_alphanumeric() && word_class(c) != WordClass::Extend;
+    let trimmed_word = word
+        .trim_start_matches(|c: char| !c.is_alphanumeric())
+        .trim_end_matches(punctuation);
+    let word_end = word.trim_end_matches(punctuation).len();
+    let end = ends_sentence(&word[word_end..]).then_some(Token::SentenceEnd);
+    let word = (!trimmed_word.is_empty()).then_some(Token::Word(trimmed_word));
+    word.into_iter().chain(end)
+}
+
+/// Characters that split words for the simple tokenizer along with, or in
+/// place of, whitespace (`--delimiters`). The text is split first and each
+/// piece then trimmed of punctuation, so "foo.bar(baz)" with ".()" gives
+/// foo, bar and baz.
+#[derive(Clone, Debug)]
+pub struct Delimiters {
+    chars: Vec<char>,
+    whitespace: bool,
+}
+
+impl Delimiters {
+    /// Splits at each of `chars`, and at whitespace too if `whitespace`.
+    /// Line breaks always split, since input is read a line at a time.
+    pub fn new(chars: &str, whitespace: bool) -> Delimiters {
+        Delimiters {
+            chars: chars.chars().collect(),
+            whitespace,
+        }
+    }
+
+    /// The tokens of `text`, as the simple tokenizer gives them for each
+    /// piece. The empty pieces between adjacent delimiters are skipped.
+    pub fn tokens<'a>(&'a self, text: &'a str) -> Box<dyn Iterator<Item = Token<'a>> + 'a> {
+        let splits = |c: char| {
+            self.chars.contains(&c)
+                || if self.whitespace {
+                    c.is_whitespace()
+                } else {
+                    matches!(c, '\n' | '\r')
+                }
+        };
+        Box::new(
+            text.split(splits)
+                .filter(|piece| !piece.is_empty())
+                .flat_map(simple_tokens),
+        )
+    }
+}
+
+/// The word-boundary classes of UAX #29 that matter for telling words apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum WordClass {
+    Letter,
+    Numeric,
+    Katakana,
+    ExtendNumLet,
+    MidLetter,
+    MidNum,
+    MidNumLet,
+    SingleQuote,
+    Extend,
+    /// Ideographs and hiragana, which form a word each on their own
+    Standalone,
+    Other,
+}
+
+fn word_class(c: char) -> WordClass {
+    match c {
+        '\'' => WordClass::SingleQuote,
+        '.' | '\u{2018}' | '\u{2019}' | '\u{2024}' | '\u{FE52}' | '\u{FF07}' | '\u{FF0E}' => {
+            WordClass::MidNumLet
+        }
+        ':' | '\u{00B7}' | '\u{0387}' | '\u{05F4}' | '\u{2027}' | '\u{FE13}' | '\u{FE55}'
+        | '\u{FF1A}' => WordClass::MidLetter,
+        ',' | ';' | '\u{037E}' | '\u{0589}' | '\u{060C}' | '\u{060D}' | '\u{066C}' | '\u{07F8}'
+        | '\u{2044}' | '\u{FE10}' | '\u{FE14}' | '\u{FE50}' | '\u{FE54}' | '\u{FF0C}'
+        | '\u{FF1B}' => WordClass::MidNum,
+        '_'
+        | '\u{202F}'
+        | '\u{203F}'
+        | '\u{2040}'
+        | '\u{2054}'
+        | '\u{FE33}'
+        | '\u{FE34}'
+        | '\u{FE4D}'..='\u{FE4F}'
+        | '\u{FF3F}' => WordClass::ExtendNumLet,
+        // Combining marks, joiners, variation selectors and emoji modifiers
+        // attach to whatever precedes them
+        '\u{0300}'..='\u{036F}'
+        | '\u{0483}'..='\u{0489}'
+        | '\u{0591}'..='\u{05BD}'
+        | '\u{064B}'..='\u{065F}'
+        | '\u{0900}'..='\u{0903}'
+        | '\u{093A}'..='\u{094F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{200C}'
+        | '\u{200D}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{3099}'
+        | '\u{309A}'
+        | '\u{FE00}'..='\u{FE0F}'
+        | '\u{FE20}'..='\u{FE2F}'
+        | '\u{1F3FB}'..='\u{1F3FF}'
+        | '\u{E0020}'..='\u{E007F}' => WordClass::Extend,
+        '\u{30A0}'..='\u{30FF}'
+        | '\u{31F0}'..='\u{31FF}'
+        | '\u{32D0}'..='\u{32FE}'
+        | '\u{FF66}'..='\u{FF9F}' => WordClass::Katakana,
+        '\u{3040}'..='\u{309F}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{3134F}' => WordClass::Standalone,
+        c if c.is_numeric() => WordClass::Numeric,
+        c if c.is_alphabetic() => WordClass::Letter,
+        _ => WordClass::Other,
+    }
+}
+
+/// Whether `next` continues a word whose last character is of class `last`.
+fn continues(last: WordClass, next: WordClass) -> bool {
+    use WordClass::*;
+    matches!(
+        (last, next),
+        (
+            Letter | Numeric | ExtendNumLet,
+            Letter | Numeric | ExtendNumLet
+        ) | (Katakana | ExtendNumLet, Katakana | ExtendNumLet)
+    )
+}
+
+/// Whether `mid` may join two characters of class `last` ("don't", "3.14").
+fn joins_across(last: WordClass, mid: WordClass) -> bool {
+    use WordClass::*;
+    match last {
+        Letter => matches!(mid, MidLetter | MidNumLet | SingleQuote),
+        Numeric => matches!(mid, MidNum | MidNumLet | SingleQuote),
+        _ => false,
+    }
+}
+
+/// Segments text at word boundaries. Every character ends up in some
+/// segment, so whitespace and punctuation come out as segments of their own.
+/// With `emoji`, an emoji is a segment the size of its grapheme cluster.
+struct UnicodeWords<'a> {
+    text: &'a str,
+    pos: usize,
+    emoji: bool,
+}
+
+impl<'a> UnicodeWords<'a> {
+    /// The end of the segment starting at `start`.
+    fn segment_end(&self, start: usize) -> usize {
+        let mut chars = self.text[start..].chars();
+        let first = chars.next().expect("segment starts before the end");
+        if self.emoji && is_emoji(first) {
+            let mut cluster = Graphemes {
+                text: self.text,
+                pos: start,
+            };
+            cluster.next();
+            return cluster.pos;
+        }
+        let mut end = start + first.len_utf8();
+        let mut last = word_class(first);
+        let word_like = matches!(
+            last,
+            WordClass::Letter | WordClass::Numeric | WordClass::Katakana | WordClass::ExtendNumLet
+        );
+
+        let mut rest = self.text[end..].chars();
+        while let Some(c) = rest.next() {
+            let class = word_class(c);
+            if class == WordClass::Extend {
+                end += c.len_utf8();
+            } else if word_like && continues(last, class) {
+                end += c.len_utf8();
+                last = class;
+            } else if word_like && joins_across(last, class) {
+                match rest.clone().next() {
+                    Some(after) if word_class(after) == last => {
+                        end += c.len_utf8() + after.len_utf8();
+                        rest.next();
+                    }
+                    _ => break,
+                }
+            } else {
+                break;
+            }
+        }
+        end
+    }
+}
+
+impl<'a> Iterator for UnicodeWords<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        if self.pos >= self.text.len() {
+            return None;
+        }
+        let start = self.pos;
+        self.pos = self.segment_end(start);
+        Some(&self.text[start..self.pos])
+    }
+}
+
+/// Splits text into approximate extended grapheme clusters: a character
+/// with its combining marks and modifiers, emoji joined by ZWJ, flag pairs
+/// of regional indicators, and CR LF.
+struct Graphemes<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+/// Whether `c` is an emoji that can start a cluster: the pictographic
+/// blocks, the symbols and dingbats commonly shown as emoji, and the
+/// regional indicators of flags. Characters such as © that are shown as
+/// text unless followed by a variation selector are left out. Approximate,
+/// like the clusters.
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c,
+        '\u{231A}'..='\u{23FF}'
+            | '\u{2600}'..='\u{27BF}'
+            | '\u{2B05}'..='\u{2B55}'
+            | '\u{1F000}'..='\u{1FAFF}'
+    )
+}
+
+impl<'a> Iterator for Graphemes<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let start = self.pos;
+        let mut chars = self.text[start..].chars().peekable();
+        let first = chars.next()?;
+        let mut end = start + first.len_utf8();
+        let mut last = first;
+        let mut pair_open = is_regional_indicator(first);
+        while let Some(&c) = chars.peek() {
+            let joins = (last == '\r' && c == '\n')
+                || (last != '\r' && last != '\n' && word_class(c) == WordClass::Extend)
+                || last == '\u{200D}'
+                || (pair_open && is_regional_indicator(c));
+            if !joins {
+                break;
+            }
+            pair_open = false;
+            end += c.len_utf8();
+            last = c;
+            chars.next();
+        }
+        self.pos = end;
+        Some(&self.text[start..end])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The words `tokenizer` finds in `text`, leaving out sentence ends.
+    fn words(tokenizer: Tokenizer, text: &str) -> Vec<&str> {
+        tokenizer
+            .tokens(text)
+            .filter_map(|token| match token {
+                Token::Word(word) | Token::Emoji(word) => Some(word),
+                Token::SentenceEnd => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn french_contractions_stay_whole_but_hyphens_split() {
+        let text = "L'homme qu’il a vu, c'est-à-dire «aujourd'hui».";
+        assert_eq!(
+            words(Tokenizer::Simple, text),
+            ["L'homme", "qu’il", "a", "vu", "c'est-à-dire", "aujourd'hui"]
+        );
+        assert_eq!(
+            words(Tokenizer::Unicode, text),
+            [
+                "L'homme",
+                "qu’il",
+                "a",
+                "vu",
+                "c'est",
+                "à",
+                "dire",
+                "aujourd'hui"
+            ]
+        );
+        // An apostrophe only joins letters on both sides
+        assert_eq!(
+            words(Tokenizer::Unicode, "les 'guillemets'"),
+            ["les", "guillemets"]
+        );
+    }
+
+    #[test]
+    fn ideographs_are_words_of_their_own_and_katakana_runs_are_one() {
+        let text = "日本語を勉強、コンピューター。";
+        assert_eq!(
+            words(Tokenizer::Simple, text),
+            ["日本語を勉強、コンピューター"]
+        );
+        assert_eq!(
+            words(Tokenizer::Unicode, text),
+            ["日", "本", "語", "を", "勉", "強", "コンピューター"]
+        );
+        let sentences = Tokenizer::Unicode
+            .tokens(text)
+            .filter(|token| *token == Token::SentenceEnd)
+            .count();
+        assert_eq!(sentences, 1);
+    }
+
+    #[test]
+    fn emoji_next_to_words_split_them_apart() {
+        let text = "hello👋world café☕ 3.14🍰";
+        assert_eq!(
+            words(Tokenizer::Simple, text),
+            ["hello👋world", "café", "3.14"]
+        );
+        assert_eq!(
+            words(Tokenizer::Unicode, text),
+            ["hello", "world", "café", "3.14"]
+        );
+        assert_eq!(
+            words(Tokenizer::UnicodeEmoji, text),
+            ["hello", "👋", "world", "café", "☕", "3.14", "🍰"]
+        );
+    }
+
+    #[test]
+    fn numbers_and_marks_keep_together() {
+        let text = "1,000.5 people; e\u{301}te\u{301} snake_case";
+        assert_eq!(
+            words(Tokenizer::Unicode, text),
+            ["1,000.5", "people", "e\u{301}te\u{301}", "snake_case"]
+        );
+        assert_eq!(
+            words(Tokenizer::Simple, text),
+            ["1,000.5", "people", "e\u{301}te\u{301}", "snake_case"]
+        );
+    }
+
+    #[test]
+    fn tricky_tokens() {
+        use Tokenizer::{LegacySimple, Simple, Unicode, UnicodeEmoji};
+        let technologist = "\u{1F469}\u{200D}\u{1F4BB}";
+        let joined = format!("hi{}there", technologist);
+        // Each token with what the simple, legacy, Unicode and Unicode
+        // emoji tokenizers make of it
+        type Case<'a> = (
+            &'a str,
+            &'a [&'a str],
+            &'a [&'a str],
+            &'a [&'a str],
+            &'a [&'a str],
+        );
+        #[rustfmt::skip]
+        let cases: &[Case] = &[
+            ("don't", &["don't"], &["don't"], &["don't"], &["don't"]),
+            ("O’Brien", &["O’Brien"], &["O’Brien"], &["O’Brien"], &["O’Brien"]),
+            ("rock'n'roll", &["rock'n'roll"], &["rock'n'roll"], &["rock'n'roll"], &["rock'n'roll"]),
+            ("'quoted'", &["quoted"], &["'quoted"], &["quoted"], &["quoted"]),
+            ("(hello),", &["hello"], &["(hello"], &["hello"], &["hello"]),
+            ("well-known", &["well-known"], &["well-known"], &["well", "known"], &["well", "known"]),
+            ("co-op's", &["co-op's"], &["co-op's"], &["co", "op's"], &["co", "op's"]),
+            ("--dash--", &["dash"], &["--dash"], &["dash"], &["dash"]),
+            ("Dr.", &["Dr"], &["Dr"], &["Dr"], &["Dr"]),
+            ("U.S.A.", &["U.S.A"], &["U.S.A"], &["U.S.A"], &["U.S.A"]),
+            ("3.14", &["3.14"], &["3.14"], &["3.14"], &["3.14"]),
+            ("-42", &["42"], &["-42"], &["42"], &["42"]),
+            ("#tag", &["tag"], &["#tag"], &["tag"], &["tag"]),
+            ("...", &[], &[""], &[], &[]),
+            // Emoji are trimmed like punctuation unless kept whole
+            (technologist, &[], &[""], &[], &[technologist]),
+            (&joined, &[&joined], &[&joined], &["hi", "there"], &["hi", technologist, "there"]),
+        ];
+        for &(text, simple, legacy, unicode, emoji) in cases {
+            assert_eq!(words(Simple, text), simple, "simple {:?}", text);
+            assert_eq!(words(LegacySimple, text), legacy, "legacy {:?}", text);
+            assert_eq!(words(Unicode, text), unicode, "unicode {:?}", text);
+            assert_eq!(words(UnicodeEmoji, text), emoji, "emoji {:?}", text);
+        }
+    }
+
+    fn graphemes(text: &str) -> Vec<&str> {
+        Graphemes { text, pos: 0 }.collect()
+    }
+
+    const FAMILY: &str = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+    const THUMBS_UP_MEDIUM: &str = "\u{1F44D}\u{1F3FD}";
+    const TECHNOLOGIST_MEDIUM: &str = "\u{1F469}\u{1F3FD}\u{200D}\u{1F4BB}";
+    const FRANCE: &str = "\u{1F1EB}\u{1F1F7}";
+    const GERMANY: &str = "\u{1F1E9}\u{1F1EA}";
+
+    #[test]
+    fn zwj_sequences_are_one_cluster() {
+        assert_eq!(graphemes(FAMILY), [FAMILY]);
+        assert_eq!(graphemes(TECHNOLOGIST_MEDIUM), [TECHNOLOGIST_MEDIUM]);
+        // A heart with its emoji variation selector
+        assert_eq!(graphemes("\u{2764}\u{FE0F}a"), ["\u{2764}\u{FE0F}", "a"]);
+    }
+
+    #[test]
+    fn regional_indicators_pair_into_flags() {
+        let text = [FRANCE, GERMANY].concat();
+        assert_eq!(graphemes(&text), [FRANCE, GERMANY]);
+        // An odd one out stands alone
+        let text = [FRANCE, "\u{1F1E9}"].concat();
+        assert_eq!(graphemes(&text), [FRANCE, "\u{1F1E9}"]);
+    }
+
+    #[test]
+    fn skin_tones_and_marks_stay_on_what_they_modify() {
+        let text = [THUMBS_UP_MEDIUM, "\u{1F44D}", "e\u{301}"].concat();
+        assert_eq!(
+            graphemes(&text),
+            [THUMBS_UP_MEDIUM, "\u{1F44D}", "e\u{301}"]
+        );
+        // CR LF is one cluster, but a line break takes no marks
+        assert_eq!(graphemes("\r\n\n\u{301}"), ["\r\n", "\n", "\u{301}"]);
+    }
+
+    #[test]
+    fn is_emoji_leaves_out_text_symbols() {
+        for c in [
+            '\u{1F44B}',
+            '\u{2615}',
+            '\u{231A}',
+            '\u{2B50}',
+            '\u{1F1EB}',
+            '\u{1FAE0}',
+        ] {
+            assert!(is_emoji(c), "{:?}", c);
+        }
+        for c in ['a', '\u{A9}', '\u{AE}', '#', '\u{2122}', '\u{200D}'] {
+            assert!(!is_emoji(c), "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn emoji_tokens_are_whole_clusters() {
+        let text = format!(
+            "hi{}{} {}x{}",
+            FAMILY, THUMBS_UP_MEDIUM, FRANCE, TECHNOLOGIST_MEDIUM
+        );
+        let tokens: Vec<Token> = Tokenizer::UnicodeEmoji.tokens(&text).collect();
+        assert_eq!(
+            tokens,
+            [
+                Token::Word("hi"),
+                Token::Emoji(FAMILY),
+                Token::Emoji(THUMBS_UP_MEDIUM),
+                Token::Emoji(FRANCE),
+                Token::Word("x"),
+                Token::Emoji(TECHNOLOGIST_MEDIUM),
+            ]
+        );
+        assert_eq!(
+            words(Tokenizer::Graphemes, &text),
+            [
+                "h",
+                "i",
+                FAMILY,
+                THUMBS_UP_MEDIUM,
+                " ",
+                FRANCE,
+                "x",
+                TECHNOLOGIST_MEDIUM
+            ]
+        );
+    }
+}
@@ -0,0 +1,43 @@
+/// A Zipf distribution fitted to ranked counts: the word at rank r is
+/// predicted to be seen `constant / r^exponent` times.
+#[derive(Clone, Copy, Debug)]
+pub struct ZipfFit {
+    pub exponent: f64,
+    pub constant: f64,
+}
+
+impl ZipfFit {
+    /// Fits a line to log(count) against log(rank) by least squares, over
+    /// the words seen at least `min_count` times. `counts` must be sorted
+    /// most frequent first, so those words are a prefix of it. There is no
+    /// fit with fewer than two of them.
+    pub fn fit(counts: &[(String, u64)], min_count: u64) -> Option<ZipfFit> {
+        let points: Vec<(f64, f64)> = counts
+            .iter()
+            .take_while(|(_, count)| *count >= min_count)
+            .enumerate()
+            .map(|(i, (_, count))| (((i + 1) as f64).ln(), (*count as f64).ln()))
+            .collect();
+        if points.len() < 2 {
+            return None;
+        }
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let covariance: f64 = points
+            .iter()
+            .map(|(x, y)| (x - mean_x) * (y - mean_y))
+            .sum();
+        let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+        let slope = covariance / variance;
+        Some(ZipfFit {
+            exponent: -slope,
+            constant: (mean_y - slope * mean_x).exp(),
+        })
+    }
+
+    /// The count predicted for the word at `rank`, counting from 1.
+    pub fn predict(&self, rank: usize) -> f64 {
+        self.constant / (rank as f64).powf(self.exponent)
+    }
+}